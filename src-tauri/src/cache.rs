@@ -0,0 +1,294 @@
+//! Caché LRU de resultados de `search_files` (`cache_enabled`, `cache_ttl_hours`): al
+//! escribir, cada tecla repite búsquedas que ya se hicieron.
+//!
+//! Cualquier escritura en el índice (`Database`) llama a `invalidate`, que cambia la
+//! generación global: la caché se vacía entera en su siguiente uso.
+
+use crate::types::SearchResults;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Búsquedas guardadas como mucho; al pasarse se descarta la usada hace más tiempo.
+const CACHE_CAPACITY: usize = 256;
+
+/// Se incrementa con cada cambio en el índice.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Marca como obsoletos todos los resultados guardados.
+pub fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Generación actual: se toma antes de buscar y se pasa a `SearchCache::put`.
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Valores calculados sobre todo el índice (p. ej. `get_extension_stats`, uno por
+/// cada `key` de opciones), válidos mientras no cambie la generación.
+pub struct IndexCached<K, T> {
+    values: Mutex<(u64, HashMap<K, T>)>,
+    /// Contador de generaciones (`GENERATION` salvo en los tests).
+    source: &'static AtomicU64,
+}
+
+impl<K: Eq + Hash, T: Clone> IndexCached<K, T> {
+    pub fn new() -> Self {
+        Self::with_source(&GENERATION)
+    }
+
+    fn with_source(source: &'static AtomicU64) -> Self {
+        Self {
+            values: Mutex::new((source.load(Ordering::Relaxed), HashMap::new())),
+            source,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<T> {
+        let values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        if values.0 != self.source.load(Ordering::Relaxed) {
+            return None;
+        }
+        values.1.get(key).cloned()
+    }
+
+    /// Como `SearchCache::put`: no se guarda si el índice cambió desde `generation`.
+    pub fn put(&self, key: K, value: T, generation: u64) {
+        if generation != self.source.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut values = self.values.lock().unwrap_or_else(|e| e.into_inner());
+        if values.0 != generation {
+            *values = (generation, HashMap::new());
+        }
+        values.1.insert(key, value);
+    }
+
+    /// Valor guardado de `key` o, si no lo hay o el índice cambió, el de `compute`
+    /// (que se guarda si el índice no cambió mientras tanto).
+    pub fn get_or_compute<E>(
+        &self,
+        key: K,
+        compute: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+        let generation = self.source.load(Ordering::Relaxed);
+        let value = compute()?;
+        self.put(key, value.clone(), generation);
+        Ok(value)
+    }
+}
+
+/// Consulta, filtros (en JSON), página y límite.
+pub type CacheKey = (String, String, usize, usize);
+
+struct Entry {
+    results: SearchResults,
+    stored_at: Instant,
+    /// Valor de `SearchCache::clock` en el último acceso (para el LRU).
+    last_used: u64,
+}
+
+struct CacheState {
+    entries: HashMap<CacheKey, Entry>,
+    generation: u64,
+    clock: u64,
+}
+
+pub struct SearchCache {
+    state: Mutex<CacheState>,
+    /// Contador de generaciones (`GENERATION` salvo en los tests).
+    source: &'static AtomicU64,
+}
+
+impl SearchCache {
+    pub fn new() -> Self {
+        Self::with_source(&GENERATION)
+    }
+
+    fn with_source(source: &'static AtomicU64) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                generation: source.load(Ordering::Relaxed),
+                clock: 0,
+            }),
+            source,
+        }
+    }
+
+    /// Resultados guardados para `key` si no han caducado (`ttl` = `None`: sin caducidad).
+    pub fn get(&self, key: &CacheKey, ttl: Option<Duration>) -> Option<SearchResults> {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+
+        let entry = state.entries.get_mut(key)?;
+        if ttl.is_some_and(|ttl| entry.stored_at.elapsed() > ttl) {
+            state.entries.remove(key);
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.results.clone())
+    }
+
+    /// Guarda `results`, salvo que el índice haya cambiado desde `generation` (la
+    /// búsqueda pudo leer datos ya obsoletos).
+    pub fn put(&self, key: CacheKey, results: SearchResults, generation: u64) {
+        let mut state = self.lock();
+        if state.generation != generation {
+            return;
+        }
+        state.clock += 1;
+        let clock = state.clock;
+
+        if state.entries.len() >= CACHE_CAPACITY && !state.entries.contains_key(&key) {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(
+            key,
+            Entry {
+                results,
+                stored_at: Instant::now(),
+                last_used: clock,
+            },
+        );
+    }
+
+    /// Vacía la caché (p. ej. al cambiar la configuración de búsqueda).
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    /// Estado con las entradas de una generación anterior ya descartadas.
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let generation = self.source.load(Ordering::Relaxed);
+        if state.generation != generation {
+            state.entries.clear();
+            state.generation = generation;
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cada test con su propio contador: los demás tests escriben en bases y llaman a
+    // `invalidate` en paralelo.
+    fn results(query: &str) -> SearchResults {
+        SearchResults {
+            query: query.to_string(),
+            results: Vec::new(),
+            total: 0,
+            page: 0,
+            limit: 50,
+            timed_out: false,
+            is_stale: false,
+        }
+    }
+
+    fn key(query: &str) -> CacheKey {
+        (query.to_string(), "{}".to_string(), 0, 50)
+    }
+
+    fn cached(cache: &SearchCache, query: &str, ttl: Option<Duration>) -> Option<String> {
+        cache.get(&key(query), ttl).map(|results| results.query)
+    }
+
+    #[test]
+    fn hit_on_the_same_key() {
+        static SOURCE: AtomicU64 = AtomicU64::new(0);
+        let cache = SearchCache::with_source(&SOURCE);
+        cache.put(key("a"), results("a"), SOURCE.load(Ordering::Relaxed));
+
+        assert_eq!(cached(&cache, "a", None).as_deref(), Some("a"));
+        assert_eq!(cached(&cache, "b", None), None);
+        // Misma consulta con otros filtros: otra clave.
+        let other = ("a".to_string(), "{\"fuzzy\":true}".to_string(), 0, 50);
+        assert!(cache.get(&other, None).is_none());
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        static SOURCE: AtomicU64 = AtomicU64::new(0);
+        let cache = SearchCache::with_source(&SOURCE);
+        cache.put(key("a"), results("a"), SOURCE.load(Ordering::Relaxed));
+
+        assert!(cached(&cache, "a", Some(Duration::from_secs(3600))).is_some());
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(cached(&cache, "a", Some(Duration::ZERO)).is_none());
+        // La entrada caducada se descarta, también para lecturas sin TTL.
+        assert!(cached(&cache, "a", None).is_none());
+    }
+
+    #[test]
+    fn invalidate_empties_the_cache() {
+        static SOURCE: AtomicU64 = AtomicU64::new(0);
+        let cache = SearchCache::with_source(&SOURCE);
+        cache.put(key("a"), results("a"), SOURCE.load(Ordering::Relaxed));
+
+        SOURCE.fetch_add(1, Ordering::Relaxed);
+        assert!(cached(&cache, "a", None).is_none());
+    }
+
+    #[test]
+    fn put_after_a_write_is_dropped() {
+        static SOURCE: AtomicU64 = AtomicU64::new(0);
+        let cache = SearchCache::with_source(&SOURCE);
+        let generation = SOURCE.load(Ordering::Relaxed);
+        // El índice cambia mientras se busca.
+        SOURCE.fetch_add(1, Ordering::Relaxed);
+        cache.put(key("a"), results("a"), generation);
+
+        assert!(cached(&cache, "a", None).is_none());
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted_at_capacity() {
+        static SOURCE: AtomicU64 = AtomicU64::new(0);
+        let cache = SearchCache::with_source(&SOURCE);
+        let generation = SOURCE.load(Ordering::Relaxed);
+        for i in 0..CACHE_CAPACITY {
+            cache.put(key(&i.to_string()), results(&i.to_string()), generation);
+        }
+        // `0` es la más antigua, pero se acaba de usar: sale `1`.
+        assert!(cached(&cache, "0", None).is_some());
+        cache.put(key("new"), results("new"), generation);
+
+        assert!(cached(&cache, "1", None).is_none());
+        assert!(cached(&cache, "0", None).is_some());
+        assert!(cached(&cache, "new", None).is_some());
+        assert_eq!(cache.lock().entries.len(), CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn index_cached_follows_the_generation() {
+        static SOURCE: AtomicU64 = AtomicU64::new(0);
+        let cached = IndexCached::with_source(&SOURCE);
+        let generation = SOURCE.load(Ordering::Relaxed);
+        cached.put("stats", 1, generation);
+        assert_eq!(cached.get(&"stats"), Some(1));
+
+        SOURCE.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(cached.get(&"stats"), None);
+        cached.put("stats", 2, generation);
+        assert_eq!(cached.get(&"stats"), None);
+        cached.put("stats", 3, SOURCE.load(Ordering::Relaxed));
+        assert_eq!(cached.get(&"stats"), Some(3));
+    }
+}
@@ -0,0 +1,183 @@
+//! Categoría de archivo (`image`, `document`, ...) derivada de la extensión, o de los
+//! primeros bytes si no tiene. Se guarda en la columna `category` al indexar.
+
+use std::io::Read;
+use std::path::Path;
+
+/// Categoría -> extensiones (sin punto, en minúsculas).
+const CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "image",
+        &[
+            "png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "svg", "ico", "heic", "heif",
+            "avif", "raw", "cr2", "nef", "psd",
+        ],
+    ),
+    (
+        "video",
+        &["mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v", "mpg", "mpeg", "3gp"],
+    ),
+    (
+        "audio",
+        &["mp3", "wav", "flac", "aac", "ogg", "oga", "m4a", "wma", "opus", "mid", "midi"],
+    ),
+    (
+        "document",
+        &[
+            "pdf", "doc", "docx", "odt", "rtf", "txt", "md", "markdown", "rst", "tex", "epub",
+            "pages",
+        ],
+    ),
+    (
+        "spreadsheet",
+        &["xls", "xlsx", "ods", "csv", "tsv", "numbers"],
+    ),
+    ("presentation", &["ppt", "pptx", "odp", "key"]),
+    (
+        "archive",
+        &["zip", "rar", "7z", "tar", "gz", "tgz", "bz2", "xz", "zst", "iso", "dmg", "cab"],
+    ),
+    (
+        "code",
+        &[
+            "rs", "ts", "tsx", "js", "jsx", "py", "go", "java", "kt", "c", "h", "cpp", "hpp", "cs",
+            "rb", "php", "swift", "sh", "ps1", "sql", "html", "css", "json", "toml", "yaml", "yml",
+            "xml",
+        ],
+    ),
+    ("executable", &["exe", "msi", "dll", "so", "dylib", "app", "apk", "deb", "rpm", "bat"]),
+    ("font", &["ttf", "otf", "woff", "woff2"]),
+];
+
+/// Firmas conocidas al inicio del archivo -> categoría.
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image"),
+    (b"\xff\xd8\xff", "image"),
+    (b"GIF87a", "image"),
+    (b"GIF89a", "image"),
+    (b"%PDF-", "document"),
+    (b"PK\x03\x04", "archive"),
+    (b"Rar!\x1a\x07", "archive"),
+    (b"7z\xbc\xaf\x27\x1c", "archive"),
+    (b"\x1f\x8b", "archive"),
+    (b"ID3", "audio"),
+    (b"fLaC", "audio"),
+    (b"OggS", "audio"),
+    (b"\x1a\x45\xdf\xa3", "video"),
+    (b"\x7fELF", "executable"),
+    (b"MZ", "executable"),
+];
+
+/// Categoría de una extensión con o sin punto inicial (`.PNG`, `png`).
+pub fn from_extension(extension: Option<&str>) -> Option<&'static str> {
+    let extension = extension?.trim_start_matches('.').to_ascii_lowercase();
+    CATEGORIES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(category, _)| *category)
+}
+
+/// Categoría por firma para archivos sin extensión reconocida.
+pub fn sniff(path: &Path) -> Option<&'static str> {
+    let mut header = Vec::with_capacity(16);
+    std::fs::File::open(path)
+        .ok()?
+        .take(16)
+        .read_to_end(&mut header)
+        .ok()?;
+    from_magic(&header)
+}
+
+fn from_magic(header: &[u8]) -> Option<&'static str> {
+    // ISO BMFF (mp4, mov, heic): `ftyp` en el desplazamiento 4.
+    if header.get(4..8) == Some(b"ftyp".as_slice()) {
+        return Some(match header.get(8..12) {
+            Some(b"heic" | b"heix" | b"mif1" | b"avif") => "image",
+            Some(b"M4A ") => "audio",
+            _ => "video",
+        });
+    }
+    MAGIC
+        .iter()
+        .find(|(signature, _)| header.starts_with(signature))
+        .map(|(_, category)| *category)
+}
+
+/// Normaliza los nombres de categoría de un filtro (minúsculas, sin vacíos).
+pub fn normalize(categories: &[String]) -> Vec<String> {
+    categories
+        .iter()
+        .map(|c| c.trim().to_ascii_lowercase())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, record, TempDir};
+    use crate::types::SearchFilters;
+
+    #[test]
+    fn extensions_map_to_categories() {
+        assert_eq!(from_extension(Some(".png")), Some("image"));
+        assert_eq!(from_extension(Some("PNG")), Some("image"));
+        assert_eq!(from_extension(Some(".Docx")), Some("document"));
+        assert_eq!(from_extension(Some(".tar")), Some("archive"));
+        assert_eq!(from_extension(Some(".rs")), Some("code"));
+        assert_eq!(from_extension(Some(".woff2")), Some("font"));
+        assert_eq!(from_extension(Some(".unknown")), None);
+        assert_eq!(from_extension(Some("")), None);
+        assert_eq!(from_extension(None), None);
+    }
+
+    #[test]
+    fn signatures_map_to_categories() {
+        assert_eq!(from_magic(b"\x89PNG\r\n\x1a\n\0\0"), Some("image"));
+        assert_eq!(from_magic(b"%PDF-1.7"), Some("document"));
+        assert_eq!(from_magic(b"\x7fELF\x02\x01"), Some("executable"));
+        assert_eq!(from_magic(b"\0\0\0\x18ftypheic"), Some("image"));
+        assert_eq!(from_magic(b"\0\0\0\x18ftypM4A "), Some("audio"));
+        assert_eq!(from_magic(b"\0\0\0\x18ftypisom"), Some("video"));
+        assert_eq!(from_magic(b"plain text"), None);
+        assert_eq!(from_magic(b""), None);
+
+        let dir = TempDir::new("sniff");
+        let file = dir.path().join("sin-extension");
+        std::fs::write(&file, b"PK\x03\x04rest").unwrap();
+        assert_eq!(sniff(&file), Some("archive"));
+        assert_eq!(sniff(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn category_filter() {
+        let dir = TempDir::new("category-filter");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<_> = ["photo.jpg", "photo.pdf", "photo.rs", "photo"]
+            .iter()
+            .map(|name| {
+                let mut file = record(&dir.join(name), false, "2024-01-01T00:00:00Z");
+                file.category = from_extension(file.extension.as_deref()).map(String::from);
+                file
+            })
+            .collect();
+        db.upsert_batch(&records).unwrap();
+
+        let search = |categories: &[&str]| {
+            let filters = SearchFilters {
+                categories: categories.iter().map(|c| c.to_string()).collect(),
+                ..Default::default()
+            };
+            let (results, _) = reader.search_files("photo", &filters, 100, None).unwrap();
+            let mut names: Vec<_> = results.into_iter().map(|result| result.name).collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(search(&["image"]), ["photo.jpg"]);
+        // Sin distinguir mayúsculas e ignorando entradas vacías.
+        assert_eq!(search(&[" Document ", "CODE", ""]), ["photo.pdf", "photo.rs"]);
+        assert_eq!(search(&[]).len(), 4);
+        assert!(search(&["video"]).is_empty());
+    }
+}
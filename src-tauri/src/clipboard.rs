@@ -0,0 +1,73 @@
+//! Escritura en el portapapeles con las herramientas del sistema, como `open_location`
+//! con el explorador: `clip` en Windows, `pbcopy` en macOS y `wl-copy`, `xclip` o
+//! `xsel` en Linux (el primero que exista).
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Copia `text` al portapapeles.
+pub fn write_text(text: &str) -> io::Result<()> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "No clipboard tool available");
+    for (program, args) in tools() {
+        match pipe_to(program, args, &encode(text)) {
+            Ok(()) => return Ok(()),
+            // No instalada: se prueba la siguiente.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => last_error = e,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_error)
+}
+
+fn pipe_to(program: &str, args: &[&str], input: &[u8]) -> io::Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} exited with {}", program, status)));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    vec![("clip", &[])]
+}
+
+#[cfg(target_os = "macos")]
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    vec![("pbcopy", &[])]
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn tools() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut tools: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        tools.push(("wl-copy", &[]));
+    }
+    tools.push(("xclip", &["-selection", "clipboard"]));
+    tools.push(("xsel", &["--clipboard", "--input"]));
+    tools
+}
+
+/// `clip` interpreta la entrada con la página de códigos de la consola salvo que
+/// empiece con el BOM de UTF-16: así no se rompen las rutas con acentos.
+#[cfg(target_os = "windows")]
+fn encode(text: &str) -> Vec<u8> {
+    std::iter::once(0xFEFF)
+        .chain(text.encode_utf16())
+        .flat_map(|unit: u16| unit.to_le_bytes())
+        .collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn encode(text: &str) -> Vec<u8> {
+    text.as_bytes().to_vec()
+}
@@ -0,0 +1,219 @@
+use crate::types::{NameCollation, SortDirection};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Nombre de la collation registrada en SQLite para el orden natural.
+pub const NATURAL: &str = "NATURAL";
+
+/// Función SQL `oxi_natural_key(name)`, igual que `natural_key`.
+pub const NATURAL_KEY_FUNCTION: &str = "oxi_natural_key";
+
+/// Cifras a las que se rellena cada número en `natural_key` (cabe cualquier `u64`).
+const NUMBER_WIDTH: usize = 20;
+
+impl NameCollation {
+    /// Nombre para `COLLATE` en SQL. `NATURAL` es palabra reservada: va entre comillas.
+    pub fn sql_name(self) -> &'static str {
+        match self {
+            NameCollation::Binary => "BINARY",
+            NameCollation::Nocase => "NOCASE",
+            NameCollation::Natural => "\"NATURAL\"",
+        }
+    }
+
+    /// Orden por nombre para `ORDER BY`. El natural usa la columna `name_sort`
+    /// (indexada) en vez de la collation, que se evaluaría fila a fila.
+    pub fn order_by(self, direction: SortDirection) -> String {
+        let direction = direction.sql();
+        match self {
+            NameCollation::Natural => format!("name_sort {}, name {}", direction, direction),
+            _ => format!("name COLLATE {} {}", self.sql_name(), direction),
+        }
+    }
+
+    /// Mismo orden que `COLLATE` en SQL, para reordenar en Rust.
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            NameCollation::Binary => a.cmp(b),
+            NameCollation::Nocase => a
+                .to_ascii_lowercase()
+                .cmp(&b.to_ascii_lowercase())
+                .then_with(|| a.cmp(b)),
+            NameCollation::Natural => natural_cmp(a, b),
+        }
+    }
+}
+
+/// Orden natural: las secuencias de dígitos se comparan por valor (`File2` < `File10`)
+/// y el resto sin distinguir mayúsculas. Empates finales por orden binario.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut left = a.chars().peekable();
+    let mut right = b.chars().peekable();
+
+    loop {
+        match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let ordering = compare_numbers(&take_digits(&mut left), &take_digits(&mut right));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                left.next();
+                right.next();
+            }
+        }
+    }
+}
+
+/// Clave de `name_sort`: en minúsculas y con cada número relleno con ceros a la
+/// izquierda, de modo que su orden binario es el de `natural_cmp` (`File2` ->
+/// `file00000000000000000002`). Los números de más de `NUMBER_WIDTH` cifras quedan
+/// sin rellenar y pueden salir desordenados.
+pub fn natural_key(name: &str) -> String {
+    let mut key = String::with_capacity(name.len() + NUMBER_WIDTH);
+    let mut chars = name.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() {
+            let digits = take_digits(&mut chars);
+            let trimmed = digits.trim_start_matches('0');
+            key.extend(std::iter::repeat_n('0', NUMBER_WIDTH.saturating_sub(trimmed.len())));
+            key.push_str(trimmed);
+        } else {
+            key.extend(c.to_lowercase());
+            chars.next();
+        }
+    }
+    key
+}
+
+/// Registra `NATURAL_KEY_FUNCTION` en `conn` (la usa la migración de `name_sort`).
+pub fn register_sql_function(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        NATURAL_KEY_FUNCTION,
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let name: String = ctx.get(0)?;
+            Ok(natural_key(&name))
+        },
+    )
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Compara dos números decimales de longitud arbitraria sin convertirlos.
+fn compare_numbers(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+        // `01` después de `1`, para que el orden sea total.
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        names.sort_by(|a, b| natural_cmp(a, b));
+        names
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("File2", "File10"), Ordering::Less);
+        assert_eq!(natural_cmp("9", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        // Más cifras de las que caben en un `u64`.
+        let huge = "n123456789012345678901234567890";
+        assert_eq!(natural_cmp("n99", huge), Ordering::Less);
+        assert_eq!(sorted(&["File10", "File1", "File2"]), ["File1", "File2", "File10"]);
+    }
+
+    #[test]
+    fn case_is_ignored_until_a_tie() {
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("FILE2", "file10"), Ordering::Less);
+        // Iguales sin mayúsculas: decide el orden binario, para que el orden sea total.
+        assert_eq!(natural_cmp("File1", "file1"), Ordering::Less);
+        assert_eq!(natural_cmp("file1", "file1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn leading_zeros() {
+        assert_eq!(natural_cmp("File02", "File3"), Ordering::Less);
+        assert_eq!(natural_cmp("File007", "File7"), Ordering::Greater);
+        assert_eq!(sorted(&["File002", "File2", "File02"]), ["File2", "File02", "File002"]);
+        assert_eq!(natural_key("File007"), natural_key("file7"));
+    }
+
+    #[test]
+    fn text_after_the_number() {
+        assert_eq!(natural_cmp("File1", "File1.txt"), Ordering::Less);
+        assert_eq!(natural_cmp("File1.txt", "File1a"), Ordering::Less);
+        assert_eq!(natural_cmp("File10b", "File10a"), Ordering::Greater);
+        assert_eq!(natural_cmp("File2b", "File10a"), Ordering::Less);
+    }
+
+    const NAMES: &[&str] = &[
+        "File10", "file2", "File1", "File02", "file1.txt", "File1a", "a", "B", "10", "9", "é",
+    ];
+
+    #[test]
+    fn natural_key_follows_natural_cmp() {
+        for a in NAMES {
+            for b in NAMES {
+                if natural_key(a) < natural_key(b) {
+                    assert_eq!(natural_cmp(a, b), Ordering::Less, "{} < {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sql_order_matches_rust() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.create_collation(NATURAL, natural_cmp).unwrap();
+        register_sql_function(&conn).unwrap();
+        conn.execute("CREATE TABLE t (name TEXT)", []).unwrap();
+        for name in NAMES {
+            conn.execute("INSERT INTO t VALUES (?1)", [name]).unwrap();
+        }
+        let query = |order: &str| -> Vec<String> {
+            let sql = format!("SELECT name FROM t ORDER BY {}", order);
+            let mut stmt = conn.prepare(&sql).unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.map(|row| row.unwrap()).collect()
+        };
+
+        let expected = sorted(NAMES);
+        let collate = format!("name COLLATE {}", NameCollation::Natural.sql_name());
+        assert_eq!(query(&collate), expected);
+        // Lo que hace `name_sort`: la clave y, en empate, el nombre.
+        let by_key = query(&format!("{}(name), name", NATURAL_KEY_FUNCTION));
+        let mut expected_by_key = expected.clone();
+        expected_by_key.sort_by_key(|name| (natural_key(name), name.clone()));
+        assert_eq!(by_key, expected_by_key);
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::Read;
+use std::path::Path;
+
+/// Delimitadores del término resaltado en `ContentMatch::snippet` (STX/ETX): no aparecen
+/// en texto normal y la UI no tiene que interpretar HTML venido de los archivos.
+pub const SNIPPET_START: &str = "\u{2}";
+pub const SNIPPET_END: &str = "\u{3}";
+
+/// Extensiones cuyo contenido se indexa por defecto con `index_content`.
+pub fn default_content_extensions() -> Vec<String> {
+    [
+        ".txt", ".md", ".markdown", ".rst", ".log", ".csv", ".json", ".toml", ".yaml", ".yml",
+        ".xml", ".html", ".css", ".rs", ".ts", ".tsx", ".js", ".jsx", ".py", ".go", ".java",
+        ".c", ".h", ".cpp", ".hpp", ".cs", ".sh", ".sql",
+    ]
+    .iter()
+    .map(|ext| ext.to_string())
+    .collect()
+}
+
+/// Texto de `path` si es UTF-8 válido y no parece binario (sin bytes NUL).
+/// Lee como mucho `max_bytes + 1` para detectar archivos demasiado grandes.
+pub fn read_text(path: &Path, max_bytes: u64) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bytes = Vec::new();
+    file.take(max_bytes + 1).read_to_end(&mut bytes).ok()?;
+    if bytes.len() as u64 > max_bytes || bytes.contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Consulta FTS5 para `query`: cada palabra entre comillas (sin operadores), todas requeridas.
+pub fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
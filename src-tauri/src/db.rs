@@ -1,224 +1,531 @@
-use rusqlite::{Connection, Result};
-use std::path::PathBuf;
-use tracing::info;
+use rusqlite::{Connection, ErrorCode, OptionalExtension, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 
-use crate::types::FileRecord;
+use crate::cache;
+use crate::category;
+use crate::interpreter;
+use crate::mime;
+use crate::collation;
+use crate::content;
+use crate::exclude;
+use crate::fuzzy;
+use crate::migrations;
+use crate::path_match;
+use crate::query::{self, BooleanQuery};
+use crate::shard;
+use crate::types::{
+    ContentMatch, DuplicateGroup, EmptyKind, ExtensionStat, FileRecord, HistoryEntry, MergePolicy, MimeCount, SavedSearch, SearchFilters, SearchResult, SortBy, SortDirection, SyncMode,
+};
 
+/// Espera máxima por un bloqueo de otra conexión antes de fallar con `SQLITE_BUSY`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `modified_time`, `file_size` e `is_dir` de una entrada indexada (ver
+/// `Database::indexed_entries`).
+pub type IndexedEntry = (String, Option<i64>, bool);
+
+/// Conexión de escritura (única): índices, watcher, historial y mantenimiento.
 pub struct Database {
     conn: Connection,
+    /// Nivel de `synchronous` fuera del modo rápido (nunca `Off`).
+    synchronous: SyncMode,
+    bulk_mode: bool,
+}
+
+/// Conexión de solo lectura para búsquedas y consultas de estado (ver `pool::ReadPool`).
+/// Se puede interrumpir por timeout sin afectar a lo que esté haciendo el escritor.
+pub struct Reader {
+    conn: Connection,
+    /// `main` y los shards adjuntos (ver `shard`); las consultas recorren todos.
+    schemas: Vec<String>,
+}
+
+/// Cómo fue la consulta SQL de `search_files` (para el log de consultas lentas).
+#[derive(Debug, Clone)]
+pub struct QueryStats {
+    /// SQL generado; los valores van como parámetros `?`, nunca en el texto.
+    pub sql: String,
+    pub rows: usize,
+    pub elapsed: Duration,
+    /// Se interrumpió por timeout y `rows` es un resultado parcial.
+    pub timed_out: bool,
+}
+
+/// SQL de `search_files`: las condiciones se repiten en el `SELECT` de cada base, así
+/// que sus `condition_params` primeros parámetros se pasan una vez por base.
+struct SearchSql {
+    sql: String,
+    params: Vec<Box<dyn rusqlite::ToSql>>,
+    condition_params: usize,
+}
+
+/// Parámetros de `SearchSql` en el orden de sus `?` con `schemas` bases.
+fn bound_params(
+    params: &[Box<dyn rusqlite::ToSql>],
+    condition_params: usize,
+    schemas: usize,
+) -> Vec<&dyn rusqlite::ToSql> {
+    let mut bound: Vec<&dyn rusqlite::ToSql> = Vec::new();
+    for _ in 0..schemas {
+        bound.extend(params[..condition_params].iter().map(|p| p.as_ref()));
+    }
+    bound.extend(params[condition_params..].iter().map(|p| p.as_ref()));
+    bound
+}
+
+/// Qué hizo `upsert_file` con un registro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Inserted,
+    Updated,
+    /// Ya estaba igual en el índice: solo se renueva `last_indexed`.
+    Skipped,
+}
+
+/// Totales de `upsert_batch` (y acumulados de un índice completo).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UpsertStats {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+impl UpsertStats {
+    pub fn record(&mut self, outcome: UpsertOutcome) {
+        match outcome {
+            UpsertOutcome::Inserted => self.inserted += 1,
+            UpsertOutcome::Updated => self.updated += 1,
+            UpsertOutcome::Skipped => self.skipped += 1,
+        }
+    }
+
+    pub fn add(&mut self, other: UpsertStats) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.skipped += other.skipped;
+    }
+
+    pub fn total(&self) -> usize {
+        self.inserted + self.updated + self.skipped
+    }
 }
 
 impl Database {
     pub fn new(db_path: PathBuf) -> Result<Self> {
-        let conn = Connection::open(&db_path)?;
+        let mut conn = Connection::open(&db_path)?;
         
         // --- OPTIMIZACIONES DE VELOCIDAD EXTREMA ---
 
-        // 1. Desactiva la espera de escritura en disco (synchronous).
-        // SQLite no esperará a que el disco físico confirme que guardó el dato.
-        // Riesgo: Si se va la luz AHORA MISMO, el índice se corrompe (no importa, se reindexa luego).
-        // Beneficio: Escritura casi a la velocidad de la RAM.
-        conn.pragma_update(None, "synchronous", &0)?;
+        // 1. synchronous=NORMAL: con WAL solo espera al disco en los checkpoints. La base
+        // guarda estado real (contenido, metadatos), así que `OFF` queda para los
+        // reíndices completos (`set_bulk_mode`).
+        let synchronous = SyncMode::Normal;
+        conn.pragma_update(None, "synchronous", synchronous.pragma_value())?;
 
         // 2. Usa Write-Ahead Logging solo en producción. En desarrollo usa DELETE para evitar problemas de watch.
         if cfg!(not(debug_assertions)) {
-            conn.pragma_update(None, "journal_mode", &"WAL")?;
+            conn.pragma_update(None, "journal_mode", "WAL")?;
         }
 
         // 3. Aumenta la memoria caché que usa SQLite (aprox 50MB).
-        conn.pragma_update(None, "cache_size", &-50000)?;
+        conn.pragma_update(None, "cache_size", -50000)?;
 
         // 4. Guarda archivos temporales en RAM, no en disco.
-        conn.pragma_update(None, "temp_store", &2)?;
+        conn.pragma_update(None, "temp_store", 2)?;
+
+        // Orden natural de nombres (`File2` < `File10`), usable con `COLLATE "NATURAL"`.
+        conn.create_collation(collation::NATURAL, collation::natural_cmp)?;
+        collation::register_sql_function(&conn)?;
 
         // -------------------------------------------
 
-        let db = Self { conn };
-        db.init_schema()?;
-        Ok(db)
+        // Los lectores del pool pueden tener la base bloqueada un momento (sobre todo sin WAL).
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+
+        init_schema(&mut conn)?;
+        Ok(Self {
+            conn,
+            synchronous,
+            bulk_mode: false,
+        })
     }
 
-    fn init_schema(&self) -> Result<()> {
-        info!("Initializing database schema");
+    /// Nivel de `synchronous` a usar fuera del modo rápido; `Off` se guarda como
+    /// `Normal` (solo se permite dentro de `set_bulk_mode`).
+    pub fn set_synchronous(&mut self, mode: SyncMode) -> Result<()> {
+        self.synchronous = match mode {
+            SyncMode::Off => SyncMode::Normal,
+            mode => mode,
+        };
+        self.apply_synchronous()
+    }
 
-        self.conn.execute(
-            "CREATE TABLE IF NOT EXISTS search_index (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                path TEXT UNIQUE NOT NULL,
-                name TEXT NOT NULL,
-                extension TEXT,
-                file_size INTEGER,
-                is_dir INTEGER NOT NULL DEFAULT 0,
-                modified_time TEXT NOT NULL,
-                last_indexed TEXT NOT NULL
-            )",
-            [],
-        )?;
+    /// Modo rápido para reíndices completos: `synchronous=OFF` hasta desactivarlo, que
+    /// restaura el nivel configurado. Un corte de luz en medio puede corromper la base.
+    pub fn set_bulk_mode(&mut self, enabled: bool) -> Result<()> {
+        self.bulk_mode = enabled;
+        self.apply_synchronous()
+    }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_search_name ON search_index(name)",
-            [],
-        )?;
+    fn apply_synchronous(&self) -> Result<()> {
+        let mode = if self.bulk_mode {
+            SyncMode::Off
+        } else {
+            self.synchronous
+        };
+        self.conn.pragma_update(None, "synchronous", mode.pragma_value())
+    }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_search_extension ON search_index(extension)",
-            [],
+    /// Reconstruye `search_index_fts` y `search_path_fts` desde `search_index`
+    /// (comando `rebuild` de FTS5).
+    pub fn rebuild_fts(&self) -> Result<()> {
+        info!("Rebuilding FTS index");
+        self.conn.execute_batch(
+            "INSERT INTO search_index_fts(search_index_fts) VALUES('rebuild');
+             INSERT INTO search_path_fts(search_path_fts) VALUES('rebuild');",
         )?;
+        Ok(())
+    }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_search_size ON search_index(file_size)",
-            [],
-        )?;
+    pub fn upsert_file(&self, file: &FileRecord) -> Result<UpsertOutcome> {
+        let outcome = upsert_row(&self.conn, file)?;
+        if outcome != UpsertOutcome::Skipped {
+            cache::invalidate();
+        }
+        Ok(outcome)
+    }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_search_modified ON search_index(modified_time)",
-            [],
-        )?;
+    /// Inserta/actualiza muchos registros en **una sola** transacción (mucho más rápido).
+    pub fn upsert_batch(&mut self, files: &[FileRecord]) -> Result<UpsertStats> {
+        let mut stats = UpsertStats::default();
+        if files.is_empty() {
+            return Ok(stats);
+        }
 
-        self.conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_search_is_dir ON search_index(is_dir)",
-            [],
-        )?;
+        let tx = self.conn.transaction()?;
+        for file in files {
+            stats.record(upsert_row(&tx, file)?);
+        }
+        tx.commit()?;
+        if stats.inserted + stats.updated > 0 {
+            cache::invalidate();
+        }
+        Ok(stats)
+    }
 
-        info!("Database schema initialized");
-        Ok(())
+    /// Escribe el tamaño recursivo de cada directorio en su `file_size`.
+    pub fn set_dir_sizes(&mut self, sizes: &HashMap<String, i64>) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut updated = 0;
+        {
+            let mut stmt =
+                tx.prepare_cached("UPDATE search_index SET file_size = ?1 WHERE path = ?2 AND is_dir = 1")?;
+            for (path, size) in sizes {
+                updated += stmt.execute(rusqlite::params![size, path])?;
+            }
+        }
+        tx.commit()?;
+        cache::invalidate();
+        Ok(updated)
     }
 
-    pub fn upsert_file(
-        &self,
-        path: &str,
-        name: &str,
-        extension: Option<&str>,
-        file_size: Option<i64>,
-        is_dir: bool,
-        modified_time: &str,
-        last_indexed: &str,
-    ) -> Result<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO search_index (path, name, extension, file_size, is_dir, modified_time, last_indexed)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            rusqlite::params![path, name, extension, file_size, is_dir as i64, modified_time, last_indexed],
+    /// Entradas indexadas bajo `root` (incluida), para comparar en un recorrido
+    /// incremental sin una consulta por archivo.
+    pub fn indexed_entries(&self, root: &str) -> Result<HashMap<String, IndexedEntry>> {
+        let root = root.trim_end_matches(['/', '\\']);
+        let (lower, upper) = subtree_range(root);
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT path, modified_time, file_size, is_dir FROM search_index
+             WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
         )?;
-        Ok(())
+        let rows = stmt.query_map(rusqlite::params![root, lower, upper], |row| {
+            Ok((row.get(0)?, (row.get(1)?, row.get(2)?, row.get(3)?)))
+        })?;
+        rows.collect()
     }
 
-    /// Inserta/actualiza muchos registros en **una sola** transacción (mucho más rápido).
-    pub fn upsert_batch(&mut self, files: &[FileRecord]) -> Result<()> {
-        if files.is_empty() {
-            return Ok(());
+    /// Marca `paths` como vistos en este recorrido (`last_indexed`) sin tocar nada más,
+    /// para que `delete_stale_under` no los borre.
+    pub fn touch_paths(&mut self, paths: &[String], last_indexed: &str) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut touched = 0;
+        {
+            let mut stmt = tx.prepare_cached("UPDATE search_index SET last_indexed = ?1 WHERE path = ?2")?;
+            for path in paths {
+                touched += stmt.execute([last_indexed, path.as_str()])?;
+            }
         }
+        tx.commit()?;
+        Ok(touched)
+    }
+
+    /// Tamaños de hasta `max_size` bytes que comparten al menos dos archivos y en los
+    /// que a alguno le falta `content_hash` (ver `duplicates`). Los vacíos no cuentan.
+    pub fn unhashed_duplicate_sizes(&self, max_size: u64) -> Result<Vec<u64>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT file_size FROM search_index
+             WHERE is_dir = 0 AND file_size > 0 AND file_size <= ?1
+             GROUP BY file_size
+             HAVING COUNT(*) > 1 AND COUNT(content_hash) < COUNT(*)",
+        )?;
+        let rows = stmt.query_map([max_size as i64], |row| Ok(row.get::<_, i64>(0)? as u64))?;
+        rows.collect()
+    }
+
+    /// Archivos de `size` bytes con su `content_hash`.
+    pub fn files_with_size(&self, size: u64) -> Result<Vec<(String, Option<String>)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT path, content_hash FROM search_index WHERE is_dir = 0 AND file_size = ?1",
+        )?;
+        let rows = stmt.query_map([size as i64], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
 
+    /// Guarda los `content_hash` calculados (ruta, hash).
+    pub fn set_content_hashes(&mut self, hashes: &[(String, String)]) -> Result<usize> {
         let tx = self.conn.transaction()?;
+        let mut updated = 0;
+        {
+            let mut stmt = tx.prepare_cached("UPDATE search_index SET content_hash = ?1 WHERE path = ?2")?;
+            for (path, hash) in hashes {
+                updated += stmt.execute(rusqlite::params![hash, path])?;
+            }
+        }
+        tx.commit()?;
+        Ok(updated)
+    }
 
+    /// Recalcula a partir del índice el tamaño recursivo de los directorios bajo `root`
+    /// (incluido): cada archivo suma en todos sus ancestros. Devuelve los actualizados.
+    pub fn recompute_dir_sizes(&mut self, root: &str, dedupe_hardlinks: bool) -> Result<usize> {
+        let mut sizes: HashMap<String, i64> = HashMap::new();
+        let mut seen_links = dedupe_hardlinks.then(HashSet::new);
         {
-            let mut stmt = tx.prepare(
-                "INSERT OR REPLACE INTO search_index (path, name, extension, file_size, is_dir, modified_time, last_indexed)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            let (lower, upper) = subtree_range(root);
+            let mut stmt = self.conn.prepare(
+                "SELECT path, is_dir, file_size, file_id FROM search_index
+                 WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
             )?;
-
-            for file in files {
-                stmt.execute(rusqlite::params![
-                    file.path.as_str(),
-                    file.name.as_str(),
-                    file.extension.as_deref(),
-                    file.file_size,
-                    file.is_dir as i64,
-                    file.modified_time.as_str(),
-                    file.last_indexed.as_str()
-                ])?;
+            let mut rows = stmt.query(rusqlite::params![root, lower, upper])?;
+            while let Some(row) = rows.next()? {
+                let path: String = row.get(0)?;
+                if row.get::<_, bool>(1)? {
+                    sizes.entry(path).or_insert(0);
+                    continue;
+                }
+                let Some(size) = row.get::<_, Option<i64>>(2)? else {
+                    continue;
+                };
+                let file_id: Option<String> = row.get(3)?;
+                if let (Some(seen), Some(file_id)) = (seen_links.as_mut(), file_id) {
+                    if !seen.insert(file_id) {
+                        continue;
+                    }
+                }
+                for ancestor in Path::new(&path).ancestors().skip(1) {
+                    if !ancestor.starts_with(root) {
+                        break;
+                    }
+                    *sizes
+                        .entry(ancestor.to_string_lossy().into_owned())
+                        .or_insert(0) += size;
+                }
             }
         }
+        self.set_dir_sizes(&sizes)
+    }
+
+    /// Borra una sola entrada. Devuelve `false` si no estaba indexada.
+    pub fn delete_file(&self, path: &str) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM search_index WHERE path = ?1", [path])?;
+        if deleted > 0 {
+            cache::invalidate();
+        }
+        Ok(deleted > 0)
+    }
 
+    /// Borra estas entradas (solo ellas, no lo que tengan debajo). Devuelve las filas
+    /// eliminadas.
+    pub fn delete_paths(&mut self, paths: &[String]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut deleted = 0;
+        {
+            let mut stmt = tx.prepare_cached("DELETE FROM search_index WHERE path = ?1")?;
+            for path in paths {
+                deleted += stmt.execute([path])?;
+            }
+        }
         tx.commit()?;
-        Ok(())
+        if deleted > 0 {
+            cache::invalidate();
+        }
+        Ok(deleted)
     }
 
-    #[allow(dead_code)]
-    pub fn delete_file(&self, path: &str) -> Result<()> {
-        self.conn
-            .execute("DELETE FROM search_index WHERE path = ?1", [path])?;
-        Ok(())
+    /// Borra `path` y todo lo indexado por debajo. Devuelve las filas eliminadas.
+    pub fn delete_tree(&self, path: &str) -> Result<usize> {
+        let (lower, upper) = subtree_range(path);
+        let deleted = self.conn.execute(
+            "DELETE FROM search_index WHERE path = ?1 OR (path >= ?2 AND path < ?3)",
+            rusqlite::params![path, lower, upper],
+        )?;
+        if deleted > 0 {
+            cache::invalidate();
+        }
+        Ok(deleted)
     }
 
-    pub fn get_file_count(&self) -> Result<usize> {
-        let count: i64 = self
+    /// Borra la entrada de `path` y, si es un directorio, lo indexado por debajo. Para
+    /// rutas que ya no están en disco: se mira `is_dir` en el índice. Devuelve las filas
+    /// eliminadas.
+    pub fn delete_entry(&self, path: &str) -> Result<usize> {
+        let is_dir = self
             .conn
-            .query_row("SELECT COUNT(*) FROM search_index", [], |row| row.get(0))?;
-        Ok(count as usize)
+            .query_row(
+                "SELECT is_dir FROM search_index WHERE path = ?1",
+                [path],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()?;
+        match is_dir {
+            Some(true) => self.delete_tree(path),
+            Some(false) => Ok(self.delete_file(path)? as usize),
+            None => Ok(0),
+        }
     }
 
-    pub fn get_database_size(&self) -> Result<u64> {
-        let size: i64 = self
+    /// Bytes originales (base64) guardados para una ruta indexada con pérdida.
+    pub fn get_raw_path(&self, path: &str) -> Result<Option<String>> {
+        let raw = self
             .conn
-            .query_row("PRAGMA page_count", [], |row| row.get(0))?;
-        let page_size: i64 = self
-            .conn
-            .query_row("PRAGMA page_size", [], |row| row.get(0))?;
-        Ok((size * page_size) as u64)
+            .query_row(
+                "SELECT raw_path FROM search_index WHERE path = ?1",
+                [path],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        Ok(raw.flatten())
     }
 
-    pub fn search_files(
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_meta(&self, key: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM meta WHERE key = ?1", [key])?;
+        Ok(())
+    }
+
+    /// Guarda una búsqueda en el historial y poda lo que exceda `max_entries` o sea más
+    /// antiguo que `ttl_days`. `max_entries` = 0 desactiva el historial (no se guarda
+    /// nada) y `ttl_days` = 0, la caducidad. Repetir la última búsqueda (misma consulta
+    /// y filtros) solo actualiza su fecha y recuento.
+    pub fn record_search(
         &self,
         query: &str,
-        extensions: Option<Vec<String>>,
-        min_size: Option<i64>,
-        max_size: Option<i64>,
-        limit: usize,
-    ) -> Result<Vec<(String, String, Option<String>, Option<i64>, bool, String)>> {
-        let mut sql = "SELECT path, name, extension, file_size, is_dir, modified_time FROM search_index WHERE name LIKE ?1".to_string();
-        let query_pattern = format!("%{}%", query);
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(query_pattern)];
-
-        if let Some(exts) = extensions {
-            if !exts.is_empty() {
-                let placeholders: Vec<String> = exts.iter().map(|_| "?".to_string()).collect();
-                sql.push_str(&format!(" AND extension IN ({})", placeholders.join(", ")));
-                for ext in exts {
-                    params.push(Box::new(ext));
-                }
-            }
+        filters: Option<&str>,
+        result_count: usize,
+        max_entries: usize,
+        ttl_days: u64,
+    ) -> Result<()> {
+        if max_entries == 0 {
+            return Ok(());
         }
 
-        if let Some(min) = min_size {
-            sql.push_str(" AND file_size >= ?");
-            params.push(Box::new(min));
+        let now = chrono::Utc::now();
+        let repeated: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM (
+                    SELECT id, query, filters FROM search_history
+                    ORDER BY searched_at DESC, id DESC LIMIT 1
+                 ) WHERE query = ?1 AND filters IS ?2",
+                rusqlite::params![query, filters],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match repeated {
+            Some(id) => {
+                self.conn.execute(
+                    "UPDATE search_history SET result_count = ?1, searched_at = ?2 WHERE id = ?3",
+                    rusqlite::params![result_count as i64, now.to_rfc3339(), id],
+                )?;
+            }
+            None => {
+                self.conn.execute(
+                    "INSERT INTO search_history (query, filters, result_count, searched_at)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![query, filters, result_count as i64, now.to_rfc3339()],
+                )?;
+            }
         }
 
-        if let Some(max) = max_size {
-            sql.push_str(" AND file_size <= ?");
-            params.push(Box::new(max));
+        if ttl_days > 0 {
+            let cutoff = now - chrono::Duration::days(ttl_days as i64);
+            self.conn.execute(
+                "DELETE FROM search_history WHERE searched_at < ?1",
+                [cutoff.to_rfc3339()],
+            )?;
         }
 
-        sql.push_str(" ORDER BY is_dir DESC, name ASC LIMIT ?");
-        params.push(Box::new(limit as i64));
-
-        let mut stmt = self.conn.prepare(&sql)?;
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        let mut rows = stmt.query(params_refs.as_slice())?;
+        self.conn.execute(
+            "DELETE FROM search_history WHERE id NOT IN (
+                SELECT id FROM search_history ORDER BY searched_at DESC, id DESC LIMIT ?1
+             )",
+            [max_entries as i64],
+        )?;
 
-        let mut results = Vec::new();
-        while let Some(row) = rows.next()? {
-            results.push((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-                row.get(5)?,
-            ));
-        }
+        Ok(())
+    }
 
-        Ok(results)
+    /// Guarda la búsqueda `name`; si ya existía la sustituye (conservando su fecha de
+    /// creación) y devuelve `true`.
+    pub fn save_search(&self, name: &str, query: &str, filters: &str) -> Result<bool> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let existed = self
+            .conn
+            .prepare_cached("SELECT 1 FROM saved_searches WHERE name = ?1")?
+            .exists([name])?;
+        self.conn.execute(
+            "INSERT INTO saved_searches (name, query, filters, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT(name) DO UPDATE SET
+                query = excluded.query,
+                filters = excluded.filters,
+                updated_at = excluded.updated_at",
+            rusqlite::params![name, query, filters, now],
+        )?;
+        Ok(existed)
     }
 
-    pub fn get_last_indexed_time(&self) -> Result<Option<String>> {
-        let result: Option<String> = self
+    /// `false` si no había ninguna búsqueda guardada con ese nombre.
+    pub fn delete_saved_search(&self, name: &str) -> Result<bool> {
+        let removed = self
             .conn
-            .query_row("SELECT MAX(last_indexed) FROM search_index", [], |row| {
-                row.get(0)
-            })
-            .ok();
-        Ok(result)
+            .execute("DELETE FROM saved_searches WHERE name = ?1", [name])?;
+        Ok(removed > 0)
+    }
+
+    /// Vacía el historial de búsquedas; devuelve cuántas entradas había.
+    pub fn clear_search_history(&self) -> Result<usize> {
+        self.conn.execute("DELETE FROM search_history", [])
     }
 
     #[allow(dead_code)]
@@ -230,19 +537,1963 @@ impl Database {
             "DELETE FROM search_index WHERE last_indexed < ?1",
             [&cutoff_str],
         )?;
+        if result > 0 {
+            cache::invalidate();
+        }
 
-        Ok(result as usize)
+        Ok(result)
+    }
+
+    /// Borra lo indexado bajo `root` con `last_indexed` anterior a `indexed_before`
+    /// (RFC3339 UTC): entradas que ya no aparecieron en el último recorrido.
+    pub fn delete_stale_under(&self, root: &str, indexed_before: &str) -> Result<usize> {
+        let root = root.trim_end_matches(['/', '\\']);
+        let (lower, upper) = subtree_range(root);
+        let deleted = self.conn.execute(
+            "DELETE FROM search_index
+             WHERE last_indexed < ?1 AND (path = ?2 OR (path >= ?3 AND path < ?4))",
+            rusqlite::params![indexed_before, root, lower, upper],
+        )?;
+        if deleted > 0 {
+            cache::invalidate();
+        }
+        Ok(deleted)
+    }
+
+    /// Vuelca el WAL al fichero principal y lo trunca (no-op si no hay WAL).
+    pub fn checkpoint(&self) -> Result<()> {
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Copia en este índice las entradas de otra base de OxI. Devuelve las filas
+    /// insertadas o actualizadas. La otra base debe tener el mismo esquema.
+    pub fn merge_from(&mut self, other: &Path, policy: MergePolicy) -> Result<usize> {
+        let other_str = other.to_string_lossy();
+        self.conn.execute("ATTACH DATABASE ?1 AS other", [other_str.as_ref()])?;
+
+        let result = (|| -> Result<usize> {
+            let version: u32 = self
+                .conn
+                .query_row("PRAGMA other.user_version", [], |row| row.get(0))?;
+            if version != migrations::latest_version() {
+                return Err(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_MISMATCH),
+                    Some(format!(
+                        "{} has schema version {}, expected {}",
+                        other_str,
+                        version,
+                        migrations::latest_version()
+                    )),
+                ));
+            }
+
+            let on_conflict = match policy {
+                MergePolicy::Skip => "DO NOTHING".to_string(),
+                MergePolicy::Overwrite => MERGE_UPDATE.to_string(),
+                MergePolicy::Newest => format!(
+                    "{} WHERE excluded.last_indexed > search_index.last_indexed",
+                    MERGE_UPDATE
+                ),
+            };
+
+            // `WHERE true` evita que SQLite lea `ON CONFLICT` como parte de un JOIN.
+            let tx = self.conn.transaction()?;
+            let merged = tx.execute(
+                &format!(
+                    "INSERT INTO search_index ({cols}) SELECT {cols} FROM other.search_index WHERE true
+                     ON CONFLICT(path) {on_conflict}",
+                    cols = MERGE_COLUMNS,
+                    on_conflict = on_conflict
+                ),
+                [],
+            )?;
+            tx.commit()?;
+            Ok(merged)
+        })();
+
+        self.conn.execute("DETACH DATABASE other", [])?;
+        let merged = result?;
+        cache::invalidate();
+        info!("Merged {} entries from {} ({:?})", merged, other_str, policy);
+        Ok(merged)
     }
 
-    #[allow(dead_code)]
     pub fn vacuum(&self) -> Result<()> {
         self.conn.execute("VACUUM", [])?;
         Ok(())
     }
 
+    /// Vuelca y trunca el WAL y, con `vacuum`, compacta la base. Devuelve los bytes
+    /// liberados (ver `disk_size`).
+    pub fn maintain(&self, vacuum: bool) -> Result<u64> {
+        let before = self.disk_size()?;
+        self.checkpoint()?;
+        if vacuum {
+            // En WAL, VACUUM escribe la copia compactada en el WAL: volcarlo otra vez.
+            self.vacuum()?;
+            self.checkpoint()?;
+        }
+        Ok(before.saturating_sub(self.disk_size()?))
+    }
+
+    /// Bytes que ocupa la base en disco: páginas del archivo principal más el WAL.
+    pub fn disk_size(&self) -> Result<u64> {
+        let pages: i64 = self
+            .conn
+            .query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = self
+            .conn
+            .query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        let wal_size = self
+            .conn
+            .path()
+            .and_then(|path| std::fs::metadata(format!("{}-wal", path)).ok())
+            .map_or(0, |metadata| metadata.len());
+        Ok((pages * page_size) as u64 + wal_size)
+    }
+
     #[allow(dead_code)]
     pub fn get_connection(&self) -> &Connection {
         &self.conn
     }
     
 }
+
+impl Reader {
+    /// Abre una conexión de solo lectura a una base ya migrada por `Database::new`.
+    pub fn open(db_path: &Path) -> Result<Self> {
+        let conn = Connection::open(db_path)?;
+        conn.pragma_update(None, "query_only", true)?;
+        conn.pragma_update(None, "cache_size", -50000)?;
+        conn.pragma_update(None, "temp_store", 2)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.create_collation(collation::NATURAL, collation::natural_cmp)?;
+        exclude::register_sql_function(&conn)?;
+
+        let mut schemas = vec!["main".to_string()];
+        for (key, path) in shard::shard_files(db_path) {
+            if schemas.len() >= shard::MAX_ATTACHED {
+                warn!("Too many index shards, not attaching {}", path.display());
+                continue;
+            }
+            let schema = shard::schema_name(&key);
+            conn.execute(
+                "ATTACH DATABASE ?1 AS ?2",
+                rusqlite::params![path.to_string_lossy(), schema],
+            )?;
+            schemas.push(schema);
+        }
+        Ok(Self { conn, schemas })
+    }
+
+    /// Permite cortar desde otro hilo la consulta en curso (ver `search_epoch`).
+    pub fn interrupt_handle(&self) -> rusqlite::InterruptHandle {
+        self.conn.get_interrupt_handle()
+    }
+
+    /// `SELECT` de `arm` (con `{schema}` como marcador) unidos por `UNION ALL`.
+    fn union_all(&self, arm: &str) -> String {
+        self.schemas
+            .iter()
+            .map(|schema| arm.replace(SCHEMA_MARKER, schema))
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ")
+    }
+
+    /// Recorre las entradas indexadas (ruta y `IndexedEntry`), todas o `sample` al azar,
+    /// hasta que `f` devuelva `false`.
+    pub fn for_each_entry(
+        &self,
+        sample: Option<usize>,
+        mut f: impl FnMut(String, IndexedEntry) -> bool,
+    ) -> Result<()> {
+        let mut sql = self.union_all(
+            "SELECT path, modified_time, file_size, is_dir FROM {schema}.search_index",
+        );
+        if let Some(sample) = sample {
+            sql = format!("SELECT * FROM ({}) ORDER BY random() LIMIT {}", sql, sample);
+        }
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            if !f(row.get(0)?, (row.get(1)?, row.get(2)?, row.get(3)?)) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Resultado de `PRAGMA integrity_check` de cada base; vacío si todas están bien.
+    /// Los problemas de un shard llevan delante su esquema.
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        // FTS5 valida su índice invertido con una sentencia de escritura (que no cambia
+        // nada): con `query_only` cada tabla FTS salía como error.
+        self.conn.pragma_update(None, "query_only", false)?;
+        let problems = self.schema_problems();
+        self.conn.pragma_update(None, "query_only", true)?;
+        problems
+    }
+
+    fn schema_problems(&self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        for schema in &self.schemas {
+            let mut stmt = self.conn.prepare(&format!("PRAGMA {}.integrity_check", schema))?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            for message in rows {
+                let message = message?;
+                if message == "ok" {
+                    continue;
+                }
+                problems.push(if schema == "main" {
+                    message
+                } else {
+                    format!("{}: {}", schema, message)
+                });
+            }
+        }
+        Ok(problems)
+    }
+
+    pub fn get_file_count(&self) -> Result<usize> {
+        let sql = format!(
+            "SELECT SUM(n) FROM ({})",
+            self.union_all("SELECT COUNT(*) AS n FROM {schema}.search_index")
+        );
+        let count: i64 = self.conn.query_row(&sql, [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    pub fn get_database_size(&self) -> Result<u64> {
+        let mut total = 0;
+        for schema in &self.schemas {
+            let size: i64 = self.conn.query_row(
+                &format!("PRAGMA {}.page_count", schema),
+                [],
+                |row| row.get(0),
+            )?;
+            let page_size: i64 = self.conn.query_row(
+                &format!("PRAGMA {}.page_size", schema),
+                [],
+                |row| row.get(0),
+            )?;
+            total += (size * page_size) as u64;
+        }
+        Ok(total)
+    }
+
+    /// SQL de `search_files` para `query` y sus parámetros.
+    fn search_sql(&self, query: &str, filters: &SearchFilters, limit: usize) -> Result<SearchSql> {
+        // Condiciones comunes a todas las bases; se montan en un `SELECT` por base al final.
+        let mut sql = String::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+        // `factura -borrador`, `"frase exacta"`, `a OR b` (ver `query`).
+        let boolean = if filters.fuzzy || filters.component_match {
+            None
+        } else {
+            query::parse(query)
+        };
+        let prefix = if filters.fuzzy || filters.component_match || filters.search_in_path {
+            None
+        } else {
+            query::prefix(query)
+        };
+
+        if filters.component_match {
+            // Prefiltro: la ruta contiene todos los términos. El orden y que sean
+            // componentes completos se comprueba en `path_match`.
+            let terms = path_match::terms(query);
+            if terms.is_empty() {
+                sql.push_str("1 = 1");
+            } else {
+                let clauses: Vec<&str> = terms.iter().map(|_| "path LIKE ?").collect();
+                sql.push_str(&format!("({})", clauses.join(" AND ")));
+                for term in terms {
+                    params.push(Box::new(format!("%{}%", term)));
+                }
+            }
+        } else if filters.fuzzy {
+            // Superconjunto barato: cualquier trigrama de la consulta (o su prefijo si es corta).
+            // El ranking real por similitud se hace en Rust.
+            let grams = fuzzy::trigrams(query);
+            if grams.is_empty() {
+                sql.push_str("name LIKE ?");
+                params.push(Box::new(format!("{}%", query)));
+            } else {
+                let alternatives: Vec<String> = grams.iter().map(|gram| fts_phrase(gram)).collect();
+                sql.push_str("id IN (SELECT rowid FROM {schema}.search_index_fts(?))");
+                params.push(Box::new(alternatives.join(" OR ")));
+            }
+        } else if let Some(boolean) = &boolean {
+            sql.push_str(&boolean_conditions(boolean, filters, &mut params));
+        } else if filters.search_in_path {
+            sql.push_str(&path_condition(query, &mut params));
+        } else if let Some(prefix) = prefix {
+            // Sin comodín al principio: SQLite lo resuelve como rango sobre el índice NOCASE.
+            sql.push_str("name LIKE ?");
+            params.push(Box::new(format!("{}%", prefix)));
+        } else if query.chars().count() >= 3 {
+            // El tokenizer trigram necesita al menos 3 caracteres.
+            sql.push_str("id IN (SELECT rowid FROM {schema}.search_index_fts(?))");
+            params.push(Box::new(fts_phrase(query)));
+        } else {
+            sql.push_str("name LIKE ? ESCAPE '\\'");
+            params.push(Box::new(format!("%{}%", like_escape(query))));
+        }
+        if filters.case_sensitive && !filters.fuzzy && !filters.component_match && boolean.is_none() {
+            // Tanto el trigram como LIKE ignoran mayúsculas: siguen siendo el prefiltro
+            // por índice e `instr` (binario) descarta el resto.
+            if let Some(prefix) = prefix {
+                sql.push_str(" AND instr(name, ?) = 1");
+                params.push(Box::new(prefix.to_string()));
+            } else if filters.search_in_path {
+                sql.push_str(&format!(" AND instr({}, ?) > 0", SLASH_PATH));
+                params.push(Box::new(query.replace('\\', "/")));
+            } else {
+                sql.push_str(" AND instr(name, ?) > 0");
+                params.push(Box::new(query.to_string()));
+            }
+        }
+
+        if let Some(exts) = &filters.extensions {
+            if !exts.is_empty() {
+                let placeholders: Vec<String> = exts.iter().map(|_| "?".to_string()).collect();
+                sql.push_str(&format!(" AND extension IN ({})", placeholders.join(", ")));
+                for ext in exts {
+                    params.push(Box::new(ext.clone()));
+                }
+            }
+        }
+
+        let hide_patterns: Vec<&str> = filters
+            .hide_patterns
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect();
+        if !hide_patterns.is_empty() {
+            sql.push_str(&format!(" AND NOT {}(?, path)", exclude::SQL_FUNCTION));
+            params.push(Box::new(hide_patterns.join("\n")));
+        }
+
+        let categories = category::normalize(&filters.categories);
+        if !categories.is_empty() {
+            let placeholders: Vec<&str> = categories.iter().map(|_| "?").collect();
+            sql.push_str(&format!(" AND category IN ({})", placeholders.join(", ")));
+            for category in categories {
+                params.push(Box::new(category));
+            }
+        }
+
+        let mime_types = mime::normalize(&filters.mime_types);
+        if !mime_types.is_empty() {
+            let clauses: Vec<&str> = mime_types
+                .iter()
+                .map(|mime| if mime.ends_with('%') { "mime LIKE ?" } else { "mime = ?" })
+                .collect();
+            sql.push_str(&format!(" AND ({})", clauses.join(" OR ")));
+            for mime in mime_types {
+                params.push(Box::new(mime));
+            }
+        }
+
+        let interpreters = interpreter::normalize(&filters.interpreters);
+        if !interpreters.is_empty() {
+            let placeholders: Vec<&str> = interpreters.iter().map(|_| "?").collect();
+            sql.push_str(&format!(" AND interpreter IN ({})", placeholders.join(", ")));
+            for interpreter in interpreters {
+                params.push(Box::new(interpreter));
+            }
+        }
+
+        if let Some(uid) = filters.owner_uid {
+            sql.push_str(" AND owner_uid = ?");
+            params.push(Box::new(uid));
+        }
+
+        if let Some(mask) = filters.mode_mask.filter(|mask| *mask != 0) {
+            sql.push_str(" AND (mode & ?) = ?");
+            params.push(Box::new(mask));
+            params.push(Box::new(mask));
+        }
+
+        if filters.multiple_links {
+            sql.push_str(" AND is_dir = 0 AND link_count > 1");
+        }
+
+        if let Some(roots) = &filters.roots {
+            // Rango de `subtree_range` en vez de LIKE 'raíz%': usa el índice UNIQUE
+            // de `path` y no necesita escapar comodines. Una raíz que se queda vacía al
+            // quitar el separador final (`/`) lo abarca todo, así que no filtra.
+            let roots: Vec<&str> = roots
+                .iter()
+                .map(|r| r.trim_end_matches(['/', '\\']))
+                .collect();
+            if !roots.is_empty() && !roots.contains(&"") {
+                let clauses: Vec<&str> = roots
+                    .iter()
+                    .map(|_| "(path = ? OR (path >= ? AND path < ?))")
+                    .collect();
+                sql.push_str(&format!(" AND ({})", clauses.join(" OR ")));
+                for root in roots {
+                    let (lower, upper) = subtree_range(root);
+                    params.push(Box::new(root.to_string()));
+                    params.push(Box::new(lower));
+                    params.push(Box::new(upper));
+                }
+            }
+        }
+
+        if let Some(tags) = &filters.tags {
+            for tag in tags {
+                sql.push_str(" AND tags LIKE ? ESCAPE '\\'");
+                params.push(Box::new(format!("%,{},%", like_escape(tag))));
+            }
+        }
+
+        // `modified_time` se guarda como RFC3339 en UTC, así que la comparación
+        // lexicográfica equivale a la cronológica si los límites también están en UTC.
+        if let Some(min_date) = &filters.min_date {
+            sql.push_str(" AND modified_time >= ?");
+            params.push(Box::new(normalize_rfc3339(min_date)?));
+        }
+
+        if let Some(max_date) = &filters.max_date {
+            sql.push_str(" AND modified_time <= ?");
+            params.push(Box::new(normalize_rfc3339(max_date)?));
+        }
+
+        if let Some(min) = filters.min_size {
+            sql.push_str(" AND file_size >= ?");
+            params.push(Box::new(min as i64));
+        }
+
+        if let Some(max) = filters.max_size {
+            sql.push_str(" AND file_size <= ?");
+            params.push(Box::new(max as i64));
+        }
+
+        // Un `SELECT` por base, cada uno con los parámetros de las condiciones; el orden
+        // y el límite se aplican a la unión.
+        let conditions = std::mem::take(&mut sql);
+        let condition_params = params.len();
+        sql = format!(
+            "SELECT * FROM ({})",
+            self.union_all(&format!(
+                "SELECT {}, name_sort FROM {{schema}}.search_index WHERE {}",
+                SEARCH_COLUMNS, conditions
+            ))
+        );
+
+        // Desempate por nombre siempre ascendente.
+        let name_order = filters.collation.order_by(SortDirection::Asc);
+        let direction = filters.sort_direction().sql();
+        let rank_query = match (&boolean, prefix) {
+            (_, Some(prefix)) => prefix.to_string(),
+            (Some(_), None) => query::rank_text(query),
+            (None, None) => query.to_string(),
+        };
+        sql.push_str(" ORDER BY ");
+        if filters.dirs_first {
+            sql.push_str("is_dir DESC, ");
+        }
+        // Los órdenes salen de enums (nunca de texto del cliente): nada que escapar.
+        match filters.sort_by {
+            // Preordena para que las mejores coincidencias (exacta, prefijo, nombres cortos)
+            // entren en la ventana de candidatos; el score fino se calcula en `ranking`.
+            SortBy::Relevance => {
+                sql.push_str(&format!(
+                    "(name = ? COLLATE NOCASE) DESC, (name LIKE ?) DESC, length(name) ASC, {}",
+                    name_order
+                ));
+                params.push(Box::new(rank_query.clone()));
+                params.push(Box::new(format!("{}%", rank_query)));
+            }
+            // Sin fecha de creación, al final en ambos sentidos.
+            SortBy::CreatedDesc => {
+                sql.push_str(&format!("created_time IS NULL, created_time DESC, {}", name_order));
+            }
+            SortBy::CreatedAsc => {
+                sql.push_str(&format!("created_time IS NULL, created_time ASC, {}", name_order));
+            }
+            SortBy::Name => {
+                sql.push_str(&filters.collation.order_by(filters.sort_direction()));
+            }
+            // Sin tamaño (directorios sin `compute_dir_sizes`), al final.
+            SortBy::Size => {
+                sql.push_str(&format!("file_size IS NULL, file_size {}, {}", direction, name_order));
+            }
+            SortBy::Modified => {
+                sql.push_str(&format!("modified_time {}, {}", direction, name_order));
+            }
+            SortBy::Path => {
+                sql.push_str(&format!("path {}", direction));
+            }
+        }
+        sql.push_str(" LIMIT ?");
+        params.push(Box::new(limit as i64));
+
+        Ok(SearchSql {
+            sql,
+            params,
+            condition_params,
+        })
+    }
+
+    /// Candidatos para `query`. Con `timeout`, la consulta se interrumpe al vencer y se
+    /// devuelve lo leído hasta entonces con `timed_out` (resultado parcial).
+    pub fn search_files(
+        &self,
+        query: &str,
+        filters: &SearchFilters,
+        limit: usize,
+        timeout: Option<Duration>,
+    ) -> Result<(Vec<SearchResult>, QueryStats)> {
+        let mut results = Vec::new();
+        let stats = self.search_files_batched(query, filters, limit, timeout, usize::MAX, |batch| {
+            results.extend(batch);
+            true
+        })?;
+        Ok((results, stats))
+    }
+
+    /// Como `search_files`, pero entrega las filas en lotes de `batch_size` según salen
+    /// del cursor, en el orden del SQL. Si `on_batch` devuelve `false` se deja de leer.
+    pub fn search_files_batched(
+        &self,
+        query: &str,
+        filters: &SearchFilters,
+        limit: usize,
+        timeout: Option<Duration>,
+        batch_size: usize,
+        mut on_batch: impl FnMut(Vec<SearchResult>) -> bool,
+    ) -> Result<QueryStats> {
+        let SearchSql { sql, params, condition_params } = self.search_sql(query, filters, limit)?;
+
+        // Vigilante: si la consulta no avisa de que terminó antes del timeout, la interrumpe.
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let watchdog = timeout.map(|timeout| {
+            let handle = self.conn.get_interrupt_handle();
+            std::thread::spawn(move || {
+                if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(timeout) {
+                    handle.interrupt();
+                }
+            })
+        });
+
+        let started = Instant::now();
+        let batch_size = batch_size.max(1);
+        let mut batch = Vec::new();
+        let mut rows_read = 0;
+        let mut stopped = false;
+        let outcome = (|| -> Result<()> {
+            let mut stmt = self.conn.prepare(&sql)?;
+            let params_refs = bound_params(&params, condition_params, self.schemas.len());
+            let mut rows = stmt.query(params_refs.as_slice())?;
+
+            while let Some(row) = rows.next()? {
+                batch.push(search_result(row)?);
+                rows_read += 1;
+                if batch.len() >= batch_size && !on_batch(std::mem::take(&mut batch)) {
+                    stopped = true;
+                    break;
+                }
+            }
+            Ok(())
+        })();
+
+        // La sentencia ya terminó: un `interrupt` a partir de aquí no afecta a nada.
+        drop(done_tx);
+        if let Some(watchdog) = watchdog {
+            let _ = watchdog.join();
+        }
+
+        let timed_out = match outcome {
+            Ok(()) => false,
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::OperationInterrupted => {
+                // Por timeout o porque otra búsqueda la superó (`search_epoch`).
+                warn!(
+                    "Search for {:?} interrupted after {:?} (timeout {:?}) with {} candidates",
+                    query,
+                    started.elapsed(),
+                    timeout,
+                    rows_read
+                );
+                true
+            }
+            Err(e) => return Err(e),
+        };
+        // También tras un timeout: lo leído hasta el corte se entrega.
+        if !stopped && !batch.is_empty() {
+            on_batch(batch);
+        }
+
+        Ok(QueryStats {
+            sql,
+            rows: rows_read,
+            elapsed: started.elapsed(),
+            timed_out,
+        })
+    }
+
+    /// Archivos cuyo texto indexado contiene todas las palabras de `query`, por relevancia
+    /// (bm25), con un fragmento alrededor de la coincidencia.
+    pub fn search_content(&self, query: &str, limit: usize) -> Result<Vec<ContentMatch>> {
+        let fts_query = content::fts_query(query);
+        if fts_query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let sql = format!(
+            "SELECT * FROM ({}) ORDER BY rank LIMIT ?4",
+            self.union_all(
+                "SELECT s.path, s.name, s.extension, s.file_size, s.is_dir, s.modified_time,
+                        s.created_time, s.is_reparse, s.link_count, s.mode, s.owner_uid, s.owner_gid,
+                        snippet(content_fts, 1, ?2, ?3, '…', 16), content_fts.rank AS rank
+                 FROM {schema}.content_fts(?1)
+                 JOIN {schema}.search_index s ON s.id = content_fts.rowid"
+            )
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map(
+            rusqlite::params![
+                fts_query,
+                content::SNIPPET_START,
+                content::SNIPPET_END,
+                limit as i64
+            ],
+            |row| {
+                Ok(ContentMatch {
+                    result: search_result(row)?,
+                    snippet: row.get(12)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// Fila indexada de `path` (`None` si no está en el índice).
+    pub fn file_by_path(&self, path: &str) -> Result<Option<SearchResult>> {
+        let sql = format!(
+            "SELECT * FROM ({}) LIMIT 1",
+            self.union_all(&format!(
+                "SELECT {} FROM {{schema}}.search_index WHERE path = ?1",
+                SEARCH_COLUMNS
+            ))
+        );
+        let mut result = self.conn.query_row(&sql, [path], search_result).optional()?;
+        if let Some(result) = result.as_mut() {
+            self.fill_child_counts(std::slice::from_mut(result))?;
+        }
+        Ok(result)
+    }
+
+    /// Archivos modificados como mucho `window` antes o después que `reference`, del
+    /// más cercano en el tiempo al más lejano (score 1.0 = mismo instante).
+    /// `None` si `reference` no está indexado.
+    pub fn modified_near(
+        &self,
+        reference: &str,
+        window: Duration,
+        limit: usize,
+    ) -> Result<Option<Vec<SearchResult>>> {
+        let sql = format!(
+            "SELECT modified_time FROM ({}) LIMIT 1",
+            self.union_all("SELECT modified_time FROM {schema}.search_index WHERE path = ?1")
+        );
+        let Some(modified_time) = self
+            .conn
+            .query_row(&sql, [reference], |row| row.get::<_, String>(0))
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        let center = chrono::DateTime::parse_from_rfc3339(&modified_time)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?
+            .with_timezone(&chrono::Utc);
+        let window = chrono::Duration::from_std(window)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        // Rango sobre el texto RFC3339 en UTC (usa `idx_search_modified`); la distancia
+        // exacta para ordenar se calcula con `julianday`.
+        let lower = center
+            .checked_sub_signed(window)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC);
+        let upper = center
+            .checked_add_signed(window)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC);
+
+        let sql = format!(
+            "SELECT * FROM ({}) ORDER BY distance, path LIMIT ?5",
+            self.union_all(&format!(
+                "SELECT {}, abs(julianday(modified_time) - julianday(?4)) * 86400.0 AS distance
+                 FROM {{schema}}.search_index
+                 WHERE is_dir = 0 AND modified_time >= ?1 AND modified_time <= ?2 AND path <> ?3",
+                SEARCH_COLUMNS
+            ))
+        );
+        let window_secs = window.num_milliseconds().max(1) as f64 / 1000.0;
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            rusqlite::params![
+                lower.to_rfc3339(),
+                upper.to_rfc3339(),
+                reference,
+                modified_time,
+                limit as i64
+            ],
+            |row| {
+                let mut result = search_result(row)?;
+                let distance: Option<f64> = row.get(12)?;
+                result.score = distance.map_or(0.0, |d| (1.0 - d / window_secs).clamp(0.0, 1.0));
+                Ok(result)
+            },
+        )?;
+        rows.collect::<Result<Vec<_>>>().map(Some)
+    }
+
+    /// Archivos de 0 bytes o directorios sin hijos, por ruta. Un directorio solo cuenta
+    /// como vacío según el índice: con raíces sin indexar del todo, o con hijos
+    /// excluidos u ocultos, aparecen directorios que en disco no lo están.
+    pub fn find_empty(&self, kind: EmptyKind, limit: usize) -> Result<Vec<SearchResult>> {
+        let condition = match kind {
+            EmptyKind::Files => "s.is_dir = 0 AND s.file_size = 0",
+            // Los hijos están en la misma base que el padre (los shards son por unidad).
+            EmptyKind::Directories => {
+                "s.is_dir = 1 AND NOT EXISTS
+                 (SELECT 1 FROM {schema}.search_index c WHERE c.parent_path = s.path)"
+            }
+        };
+        let sql = format!(
+            "SELECT * FROM ({}) ORDER BY path LIMIT ?1",
+            self.union_all(&format!(
+                "SELECT {} FROM {{schema}}.search_index s WHERE {}",
+                SEARCH_COLUMNS, condition
+            ))
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map([limit as i64], search_result)?;
+        rows.collect()
+    }
+
+    /// Grupos de archivos con el mismo contenido (`hash_duplicates`), de más a menos
+    /// espacio desperdiciado (tamaño × copias sobrantes).
+    pub fn find_duplicates(&self, limit: usize) -> Result<Vec<DuplicateGroup>> {
+        let sql = format!(
+            "WITH files AS ({}),
+                  groups AS (
+                      SELECT content_hash, MAX(file_size) AS size,
+                             COUNT(DISTINCT COALESCE(file_id, path)) AS copies FROM files
+                      GROUP BY content_hash HAVING copies > 1
+                      ORDER BY size * (copies - 1) DESC, content_hash LIMIT ?1
+                  )
+             SELECT g.content_hash, g.size, f.path, g.copies FROM groups g
+             JOIN files f ON f.content_hash = g.content_hash
+             ORDER BY g.size * (g.copies - 1) DESC, g.content_hash, f.path",
+            self.union_all(
+                "SELECT path, file_size, content_hash, file_id FROM {schema}.search_index
+                 WHERE is_dir = 0 AND content_hash LIKE 'f%'"
+            )
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let mut rows = stmt.query([limit as i64])?;
+
+        let mut groups: Vec<DuplicateGroup> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let hash: String = row.get(0)?;
+            let file_size = row.get::<_, i64>(1)? as u64;
+            let path: String = row.get(2)?;
+            // Copias distintas: los enlaces duros al mismo archivo cuentan una vez.
+            let copies = row.get::<_, i64>(3)? as u64;
+            match groups.last_mut() {
+                Some(group) if group.hash == hash => group.paths.push(path),
+                _ => groups.push(DuplicateGroup {
+                    hash,
+                    file_size,
+                    paths: vec![path],
+                    wasted_bytes: file_size * (copies - 1),
+                }),
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Tipos MIME indexados con su número de archivos, de más a menos frecuente.
+    pub fn mime_summary(&self, limit: usize) -> Result<Vec<MimeCount>> {
+        let sql = format!(
+            "SELECT mime, SUM(n) AS total FROM ({}) GROUP BY mime ORDER BY total DESC, mime LIMIT ?1",
+            self.union_all(
+                "SELECT mime, COUNT(*) AS n FROM {schema}.search_index
+                 WHERE mime IS NOT NULL GROUP BY mime"
+            )
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map([limit as i64], |row| {
+            Ok(MimeCount {
+                mime: row.get(0)?,
+                count: row.get::<_, i64>(1)? as u64,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Entradas indexadas por extensión (sin distinguir mayúsculas) con su tamaño total,
+    /// de más a menos frecuente. Los archivos sin extensión y los directorios van en
+    /// grupos aparte; los directorios no suman bytes (con `compute_dir_sizes` se
+    /// contarían dos veces). Con `dedupe_hardlinks` las rutas de un mismo archivo
+    /// (`file_id`) cuentan una sola vez, en el grupo de la primera que se indexó.
+    pub fn extension_stats(&self, dedupe_hardlinks: bool) -> Result<Vec<ExtensionStat>> {
+        let dedupe = if dedupe_hardlinks {
+            "WHERE s.file_id IS NULL OR s.id = (SELECT MIN(l.id) FROM {schema}.search_index l
+                                                WHERE l.file_id = s.file_id)"
+        } else {
+            ""
+        };
+        let sql = format!(
+            "SELECT extension, is_dir, SUM(n) AS total, COALESCE(SUM(bytes), 0) FROM ({})
+             GROUP BY extension, is_dir ORDER BY total DESC, extension",
+            self.union_all(&format!(
+                "SELECT lower(s.extension) AS extension, s.is_dir, COUNT(*) AS n,
+                        SUM(CASE WHEN s.is_dir = 0 THEN s.file_size END) AS bytes
+                 FROM {{schema}}.search_index s {} GROUP BY lower(s.extension), s.is_dir",
+                dedupe
+            ))
+        );
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ExtensionStat {
+                extension: row.get(0)?,
+                is_dir: row.get(1)?,
+                count: row.get::<_, i64>(2)? as u64,
+                bytes: row.get::<_, i64>(3)?.max(0) as u64,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Últimas `limit` búsquedas del historial, de la más reciente a la más antigua.
+    /// El historial solo está en la base principal, no en los shards.
+    pub fn search_history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, query, filters, result_count, searched_at FROM main.search_history
+             ORDER BY searched_at DESC, id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit as i64], |row| {
+            let filters: Option<String> = row.get(2)?;
+            Ok(HistoryEntry {
+                id: row.get(0)?,
+                query: row.get(1)?,
+                // Filtros de una versión anterior que ya no se pueden leer: sin filtros.
+                filters: filters.and_then(|json| serde_json::from_str(&json).ok()),
+                result_count: row.get::<_, i64>(3)? as u64,
+                searched_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Búsquedas guardadas por nombre.
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "{} ORDER BY name COLLATE NOCASE",
+            SAVED_SEARCH_SELECT
+        ))?;
+        let rows = stmt.query_map([], saved_search)?;
+        rows.collect()
+    }
+
+    pub fn saved_search(&self, name: &str) -> Result<Option<SavedSearch>> {
+        self.conn
+            .prepare_cached(&format!("{} WHERE name = ?1", SAVED_SEARCH_SELECT))?
+            .query_row([name], saved_search)
+            .optional()
+    }
+
+    /// Número de entradas indexadas directamente bajo `path`.
+    pub fn get_child_count(&self, path: &str) -> Result<u64> {
+        let sql = format!(
+            "SELECT SUM(n) FROM ({})",
+            self.union_all("SELECT COUNT(*) AS n FROM {schema}.search_index WHERE parent_path = ?1")
+        );
+        let count: i64 = self.conn.prepare_cached(&sql)?.query_row([path], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+
+    /// Rellena `child_count` en los resultados que son directorios.
+    pub fn fill_child_counts(&self, results: &mut [SearchResult]) -> Result<()> {
+        for result in results.iter_mut().filter(|r| r.is_dir) {
+            result.child_count = Some(self.get_child_count(&result.path)?);
+        }
+        Ok(())
+    }
+
+    /// Valor de `key` en `meta`; si está en varias bases, el mayor.
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        let sql = format!(
+            "SELECT MAX(value) FROM ({})",
+            self.union_all("SELECT value FROM {schema}.meta WHERE key = ?1")
+        );
+        self.conn.query_row(&sql, [key], |row| row.get(0))
+    }
+
+    /// Entradas del índice en `root` y por debajo.
+    pub fn count_under(&self, root: &str) -> Result<u64> {
+        let root = root.trim_end_matches(['/', '\\']);
+        if root.is_empty() {
+            return self.get_file_count().map(|count| count as u64);
+        }
+        let (lower, upper) = subtree_range(root);
+        let sql = format!(
+            "SELECT SUM(n) FROM ({})",
+            self.union_all(
+                "SELECT COUNT(*) AS n FROM {schema}.search_index
+                 WHERE path = ?1 OR (path >= ?2 AND path < ?3)"
+            )
+        );
+        let count: i64 = self
+            .conn
+            .query_row(&sql, rusqlite::params![root, lower, upper], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+
+    pub fn get_last_indexed_time(&self) -> Result<Option<String>> {
+        let sql = format!(
+            "SELECT MAX(m) FROM ({})",
+            self.union_all("SELECT MAX(last_indexed) AS m FROM {schema}.search_index")
+        );
+        let result: Option<String> = self
+            .conn
+            .query_row(&sql, [], |row| row.get(0))
+            .ok();
+        Ok(result)
+    }
+}
+
+/// Marcador de la base (`main` o un shard) en las consultas de `Reader::union_all`.
+const SCHEMA_MARKER: &str = "{schema}";
+
+/// Columnas de `Reader::search_files`.
+const SEARCH_COLUMNS: &str = "path, name, extension, file_size, is_dir, modified_time, \
+    created_time, is_reparse, link_count, mode, owner_uid, owner_gid";
+
+/// `path` con `/` como separador, para `search_in_path`.
+const SLASH_PATH: &str = r"replace(path, '\', '/')";
+
+/// Condiciones de una consulta con operadores: un OR por grupo, los grupos con AND y
+/// los términos excluidos con `NOT LIKE` (o `instr` si distingue mayúsculas).
+fn boolean_conditions(
+    boolean: &BooleanQuery,
+    filters: &SearchFilters,
+    params: &mut Vec<Box<dyn rusqlite::ToSql>>,
+) -> String {
+    let column = if filters.search_in_path { SLASH_PATH } else { "name" };
+    let mut clauses = Vec::new();
+
+    for group in &boolean.groups {
+        let alternatives: Vec<String> = group
+            .iter()
+            .map(|term| term_condition(term, filters, params))
+            .collect();
+        clauses.push(format!("({})", alternatives.join(" OR ")));
+    }
+    for term in &boolean.excluded {
+        let term = if filters.search_in_path { term.replace('\\', "/") } else { term.clone() };
+        if filters.case_sensitive {
+            clauses.push(format!("instr({}, ?) = 0", column));
+            params.push(Box::new(term));
+        } else {
+            clauses.push(format!("{} NOT LIKE ? ESCAPE '\\'", column));
+            params.push(Box::new(format!("%{}%", like_escape(&term))));
+        }
+    }
+
+    if clauses.is_empty() {
+        "1 = 1".to_string()
+    } else {
+        clauses.join(" AND ")
+    }
+}
+
+/// Condición de un término (o frase) que debe aparecer, igual que una consulta plana.
+fn term_condition(term: &str, filters: &SearchFilters, params: &mut Vec<Box<dyn rusqlite::ToSql>>) -> String {
+    let (mut condition, column, needle) = if filters.search_in_path {
+        (path_condition(term, params), SLASH_PATH, term.replace('\\', "/"))
+    } else if term.chars().count() >= 3 {
+        params.push(Box::new(fts_phrase(term)));
+        (
+            "id IN (SELECT rowid FROM {schema}.search_index_fts(?))".to_string(),
+            "name",
+            term.to_string(),
+        )
+    } else {
+        params.push(Box::new(format!("%{}%", like_escape(term))));
+        ("name LIKE ? ESCAPE '\\'".to_string(), "name", term.to_string())
+    };
+    if filters.case_sensitive {
+        condition.push_str(&format!(" AND instr({}, ?) > 0", column));
+        params.push(Box::new(needle));
+    }
+    format!("({})", condition)
+}
+
+/// `needle` entre comillas como frase de FTS5: se busca tal cual, sin operadores.
+fn fts_phrase(needle: &str) -> String {
+    format!("\"{}\"", needle.replace('"', "\"\""))
+}
+
+/// `needle` en la ruta (con `/` o `\` como separador). El índice trigram de `path`
+/// descarta primero las filas sin los tramos de 3 o más caracteres entre separadores
+/// y comodines de LIKE; `LIKE` sobre la ruta normalizada confirma el resto, así que
+/// el resultado es el mismo que sin prefiltro.
+fn path_condition(needle: &str, params: &mut Vec<Box<dyn rusqlite::ToSql>>) -> String {
+    let needle = needle.replace('\\', "/");
+    let segments: Vec<String> = needle
+        .split(['/', '%', '_'])
+        .filter(|segment| segment.chars().count() >= 3)
+        .map(fts_phrase)
+        .collect();
+
+    let mut condition = String::new();
+    if !segments.is_empty() {
+        condition.push_str("id IN (SELECT rowid FROM {schema}.search_path_fts(?)) AND ");
+        params.push(Box::new(segments.join(" AND ")));
+    }
+    condition.push_str(&format!("{} LIKE ?", SLASH_PATH));
+    params.push(Box::new(format!("%{}%", needle)));
+    condition
+}
+
+/// Escapa los comodines de LIKE (`%`, `_`) y el propio escape para usar `value`
+/// literal con `ESCAPE '\'`.
+fn like_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Las búsquedas guardadas solo están en la base principal, no en los shards.
+const SAVED_SEARCH_SELECT: &str =
+    "SELECT name, query, filters, created_at, updated_at FROM main.saved_searches";
+
+fn saved_search(row: &rusqlite::Row) -> Result<SavedSearch> {
+    let filters: String = row.get(2)?;
+    Ok(SavedSearch {
+        name: row.get(0)?,
+        query: row.get(1)?,
+        // Filtros de una versión anterior que ya no se pueden leer: los de por defecto.
+        filters: serde_json::from_str(&filters).unwrap_or_default(),
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+/// `SearchResult` de una fila que empieza por `SEARCH_COLUMNS` (score neutro).
+fn search_result(row: &rusqlite::Row) -> Result<SearchResult> {
+    let file_size: Option<i64> = row.get(3)?;
+    Ok(SearchResult {
+        path: row.get(0)?,
+        name: row.get(1)?,
+        extension: row.get(2)?,
+        file_size: file_size.map(|s| s as u64),
+        is_dir: row.get(4)?,
+        modified_time: row.get(5)?,
+        created_time: row.get(6)?,
+        is_reparse: row.get(7)?,
+        link_count: row.get::<_, i64>(8)?.max(1) as u64,
+        mode: row.get(9)?,
+        owner_uid: row.get(10)?,
+        owner_gid: row.get(11)?,
+        score: 1.0,
+        child_count: None,
+        matched_in_path: false,
+    })
+}
+
+/// Columnas de `search_index` que se copian al fusionar (todas salvo `id`).
+const MERGE_COLUMNS: &str = "path, name, extension, file_size, is_dir, modified_time, last_indexed, \
+    parent_path, path_lossy, raw_path, tags, created_time, is_reparse, link_count, category, \
+    interpreter, name_sort, mime, mode, owner_uid, owner_gid, content_hash, file_id";
+
+const MERGE_UPDATE: &str = "DO UPDATE SET name = excluded.name, extension = excluded.extension, \
+    file_size = excluded.file_size, is_dir = excluded.is_dir, \
+    modified_time = excluded.modified_time, last_indexed = excluded.last_indexed, \
+    parent_path = excluded.parent_path, path_lossy = excluded.path_lossy, \
+    raw_path = excluded.raw_path, tags = excluded.tags, \
+    created_time = excluded.created_time, is_reparse = excluded.is_reparse, \
+    link_count = excluded.link_count, category = excluded.category, \
+    interpreter = excluded.interpreter, name_sort = excluded.name_sort, mime = excluded.mime, \
+    mode = excluded.mode, owner_uid = excluded.owner_uid, owner_gid = excluded.owner_gid, \
+    content_hash = excluded.content_hash, file_id = excluded.file_id";
+
+fn init_schema(conn: &mut Connection) -> Result<()> {
+    info!("Initializing database schema");
+    migrations::migrate(conn)?;
+    info!(
+        "Database schema initialized (version {})",
+        migrations::latest_version()
+    );
+    Ok(())
+}
+
+/// Campos de una fila que deciden si un registro cambió:
+/// (modified_time, file_size, is_dir, tags, created_time, is_reparse, link_count, category,
+/// interpreter, mime, mode, owner_uid, owner_gid, file_id).
+type IndexedState = (
+    String,
+    Option<i64>,
+    bool,
+    Option<String>,
+    Option<String>,
+    bool,
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    Option<String>,
+);
+
+/// Inserta o actualiza `file` según lo que ya haya en el índice para su ruta.
+/// `ON CONFLICT DO UPDATE` conserva el `id` de la fila (a diferencia de `REPLACE`),
+/// así que el índice FTS solo se toca cuando cambia algo.
+fn upsert_row(conn: &Connection, file: &FileRecord) -> Result<UpsertOutcome> {
+    let existing: Option<(i64, IndexedState)> = conn
+        .prepare_cached(
+            "SELECT id, modified_time, file_size, is_dir, tags, created_time, is_reparse, link_count,
+                    category, interpreter, mime, mode, owner_uid, owner_gid, file_id
+             FROM search_index WHERE path = ?1",
+        )?
+        .query_row([file.path.as_str()], |row| {
+            Ok((
+                row.get(0)?,
+                (
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                    row.get(8)?,
+                    row.get(9)?,
+                    row.get(10)?,
+                    row.get(11)?,
+                    row.get(12)?,
+                    row.get(13)?,
+                    row.get(14)?,
+                ),
+            ))
+        })
+        .optional()?;
+
+    let unchanged_id = existing
+        .as_ref()
+        .filter(|(_, (modified_time, file_size, is_dir, tags, created_time, is_reparse, link_count, category, interpreter, mime, mode, owner_uid, owner_gid, file_id))| {
+            *modified_time == file.modified_time
+                // El tamaño de un directorio lo pone `set_dir_sizes`, no el registro.
+                && (*file_size == file.file_size || (*is_dir && file.is_dir))
+                && *is_dir == file.is_dir
+                && *tags == file.tags
+                && *created_time == file.created_time
+                && *is_reparse == file.is_reparse
+                && *link_count == file.link_count
+                && *category == file.category
+                && *interpreter == file.interpreter
+                && *mime == file.mime
+                && *mode == file.mode
+                && *owner_uid == file.owner_uid
+                && *owner_gid == file.owner_gid
+                && *file_id == file.file_id
+        })
+        .map(|(id, _)| *id);
+
+    if let Some(id) = unchanged_id {
+        conn.prepare_cached("UPDATE search_index SET last_indexed = ?1 WHERE path = ?2")?
+            .execute([file.last_indexed.as_str(), file.path.as_str()])?;
+        // Sin cambios pero quizá indexado antes de activar `index_content`.
+        if let Some(content) = file.content.as_deref().filter(|c| !c.is_empty()) {
+            let indexed = conn
+                .prepare_cached("SELECT 1 FROM content_fts WHERE rowid = ?1")?
+                .exists([id])?;
+            if !indexed {
+                write_content(conn, id, &file.path, content)?;
+            }
+        }
+        return Ok(UpsertOutcome::Skipped);
+    }
+
+    let id: i64 = conn.prepare_cached(
+        "INSERT INTO search_index (path, name, extension, file_size, is_dir, modified_time, last_indexed, parent_path, path_lossy, raw_path, tags, created_time, is_reparse, link_count, category, interpreter, name_sort, mime, mode, owner_uid, owner_gid, file_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)
+         ON CONFLICT(path) DO UPDATE SET
+            name = excluded.name,
+            extension = excluded.extension,
+            file_size = CASE WHEN excluded.is_dir AND search_index.is_dir
+                THEN search_index.file_size ELSE excluded.file_size END,
+            is_dir = excluded.is_dir,
+            modified_time = excluded.modified_time,
+            last_indexed = excluded.last_indexed,
+            parent_path = excluded.parent_path,
+            path_lossy = excluded.path_lossy,
+            raw_path = excluded.raw_path,
+            tags = excluded.tags,
+            created_time = excluded.created_time,
+            is_reparse = excluded.is_reparse,
+            link_count = excluded.link_count,
+            category = excluded.category,
+            interpreter = excluded.interpreter,
+            mime = excluded.mime,
+            mode = excluded.mode,
+            owner_uid = excluded.owner_uid,
+            owner_gid = excluded.owner_gid,
+            file_id = excluded.file_id,
+            -- El hash del contenido solo sigue valiendo si el archivo no cambió.
+            content_hash = CASE WHEN search_index.modified_time = excluded.modified_time
+                AND search_index.file_size IS excluded.file_size
+                THEN search_index.content_hash END,
+            name_sort = excluded.name_sort
+         RETURNING id",
+    )?
+    .query_row(rusqlite::params![
+        file.path.as_str(),
+        file.name.as_str(),
+        file.extension.as_deref(),
+        file.file_size,
+        file.is_dir as i64,
+        file.modified_time.as_str(),
+        file.last_indexed.as_str(),
+        parent_of(&file.path),
+        file.path_lossy as i64,
+        file.raw_path.as_deref(),
+        file.tags.as_deref(),
+        file.created_time.as_deref(),
+        file.is_reparse as i64,
+        file.link_count,
+        file.category.as_deref(),
+        file.interpreter.as_deref(),
+        collation::natural_key(&file.name),
+        file.mime.as_deref(),
+        file.mode,
+        file.owner_uid,
+        file.owner_gid,
+        file.file_id.as_deref()
+    ], |row| row.get(0))?;
+
+    if let Some(content) = file.content.as_deref() {
+        write_content(conn, id, &file.path, content)?;
+    }
+
+    Ok(if existing.is_some() {
+        UpsertOutcome::Updated
+    } else {
+        UpsertOutcome::Inserted
+    })
+}
+
+/// Sustituye el texto indexado de la entrada `id` (vacío = solo borrarlo).
+fn write_content(conn: &Connection, id: i64, path: &str, content: &str) -> Result<()> {
+    conn.prepare_cached("DELETE FROM content_fts WHERE rowid = ?1")?
+        .execute([id])?;
+    if !content.is_empty() {
+        conn.prepare_cached("INSERT INTO content_fts (rowid, path, body) VALUES (?1, ?2, ?3)")?
+            .execute(rusqlite::params![id, path, content])?;
+    }
+    Ok(())
+}
+
+/// Directorio padre de `path` tal como se guarda en `parent_path`.
+fn parent_of(path: &str) -> Option<String> {
+    Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+}
+
+/// Valida una fecha RFC3339 y la convierte a UTC con el mismo formato que `modified_time`.
+fn normalize_rfc3339(value: &str) -> Result<String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&chrono::Utc).to_rfc3339())
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Rango `[path + separador, path + separador + U+10FFFF)` que contiene, en orden
+/// binario, todas las rutas por debajo de `path`.
+fn subtree_range(path: &str) -> (String, String) {
+    let lower = format!(
+        "{}{}",
+        path.trim_end_matches(['/', '\\']),
+        std::path::MAIN_SEPARATOR
+    );
+    let upper = format!("{}{}", lower, char::MAX);
+    (lower, upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, record, TempDir};
+    use std::collections::BTreeSet;
+
+    const MODIFIED: &str = "2024-01-01T00:00:00+00:00";
+
+    fn names(results: &[SearchResult]) -> Vec<&str> {
+        let mut names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    fn search(reader: &Reader, query: &str, filters: &SearchFilters) -> Vec<SearchResult> {
+        reader.search_files(query, filters, 100, None).unwrap().0
+    }
+
+    fn paths(results: &[SearchResult]) -> BTreeSet<String> {
+        results.iter().map(|r| r.path.clone()).collect()
+    }
+
+    /// Rutas con `condition` (un `LIKE` con un parámetro) sin pasar por los índices FTS.
+    fn like_scan(reader: &Reader, condition: &str, pattern: &str) -> BTreeSet<String> {
+        let sql = format!("SELECT path FROM search_index WHERE {}", condition);
+        let mut stmt = reader.conn.prepare(&sql).unwrap();
+        let rows = stmt.query_map([pattern], |row| row.get(0)).unwrap();
+        rows.map(|row| row.unwrap()).collect()
+    }
+
+    const LIKE_FIXTURE: &[&str] = &[
+        "/home/ana/Downloads/invoice-2023.pdf",
+        "/home/ana/Downloads/Informe anual.docx",
+        "/home/ana/photos/photo_01.jpg",
+        "/home/ana/README.md",
+        "/home/ana/notes/ab.txt",
+        "/home/ana/notes/xab",
+        "/srv/reports/report.txt",
+        "/srv/reports/Annual Report 2023.xlsx",
+        "/srv/a",
+        r"C:\Users\ana\Downloads\inventario.csv",
+    ];
+
+    #[test]
+    fn trigram_prefilter_matches_a_like_scan() {
+        let dir = TempDir::new("trigram-like");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> =
+            LIKE_FIXTURE.iter().map(|path| record(path, false, MODIFIED)).collect();
+        db.upsert_batch(&records).unwrap();
+
+        // Nombres: trigram con 3 o más caracteres, `LIKE` con menos.
+        let plain = SearchFilters::default();
+        for query in ["rep", "REPORT", "port 2", "2023", "phot", "readme", "ab", "a", "zzz"] {
+            let expected = like_scan(&reader, "name LIKE ?", &format!("%{}%", query));
+            let found = paths(&search(&reader, query, &plain));
+            assert_eq!(found, expected, "name query {:?}", query);
+        }
+
+        // Rutas, con cualquier separador en la consulta.
+        let in_path = SearchFilters { search_in_path: true, ..Default::default() };
+        let slash_path = format!("{} LIKE ?", SLASH_PATH);
+        let path_queries =
+            ["downloads", "Downloads/inv", r"Downloads\inv", "ana/no", "/srv/a", "s/", "zzz"];
+        for query in path_queries {
+            let pattern = format!("%{}%", query.replace('\\', "/"));
+            let expected = like_scan(&reader, &slash_path, &pattern);
+            let found = paths(&search(&reader, query, &in_path));
+            assert_eq!(found, expected, "path query {:?}", query);
+        }
+    }
+
+    #[test]
+    fn path_mode_matches_across_separators() {
+        let dir = TempDir::new("path-mode");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> =
+            LIKE_FIXTURE.iter().map(|path| record(path, false, MODIFIED)).collect();
+        db.upsert_batch(&records).unwrap();
+        let in_path = SearchFilters { search_in_path: true, ..Default::default() };
+
+        // Con `/` o `\` en la consulta, en rutas de Unix y de Windows.
+        let expected = [
+            "/home/ana/Downloads/invoice-2023.pdf",
+            r"C:\Users\ana\Downloads\inventario.csv",
+        ];
+        for query in ["Downloads/inv", r"Downloads\inv", "downloads/INV"] {
+            let found = paths(&search(&reader, query, &in_path));
+            assert_eq!(found, BTreeSet::from(expected.map(String::from)), "{:?}", query);
+        }
+        // El separador cuenta: sin él no hay coincidencia.
+        assert!(search(&reader, "Downloadsinv", &in_path).is_empty());
+        // Sin `search_in_path` solo se mira el nombre.
+        assert!(search(&reader, "Downloads/inv", &SearchFilters::default()).is_empty());
+    }
+
+    /// Tiempos del prefiltro trigram frente a un `LIKE` sin índice sobre 200k nombres:
+    /// `cargo test trigram_vs_like_timing -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn trigram_vs_like_timing() {
+        let dir = TempDir::new("trigram-bench");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> = (0..200_000)
+            .map(|i| record(&format!("/bench/dir{}/file_{:06}.txt", i % 500, i), false, MODIFIED))
+            .collect();
+        for chunk in records.chunks(10_000) {
+            db.upsert_batch(chunk).unwrap();
+        }
+
+        for query in ["file_0421", "123", "99.txt"] {
+            let started = Instant::now();
+            let found = paths(&search(&reader, query, &SearchFilters::default()));
+            let trigram = started.elapsed();
+            let started = Instant::now();
+            let expected = like_scan(&reader, "name LIKE ?", &format!("%{}%", query));
+            let like = started.elapsed();
+            assert!(found.is_subset(&expected));
+            println!(
+                "{:>10}: trigram {:?}, like {:?} ({} rows)",
+                query,
+                trigram,
+                like,
+                expected.len()
+            );
+        }
+    }
+
+    #[test]
+    fn watchdog_interrupts_a_slow_search() {
+        let dir = TempDir::new("watchdog");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> = (0..5_000)
+            .map(|i| record(&format!("/big/dir{}/file_{:05}.txt", i % 50, i), false, MODIFIED))
+            .collect();
+        db.upsert_batch(&records).unwrap();
+        let filters = SearchFilters::default();
+
+        let (all, stats) = reader.search_files("file_", &filters, 10_000, None).unwrap();
+        assert_eq!((all.len(), stats.timed_out), (5_000, false));
+
+        // Un consumidor lento mantiene la sentencia abierta: el vigilante la corta a
+        // mitad y se entrega lo leído hasta entonces.
+        let started = Instant::now();
+        let mut delivered = 0;
+        let stats = reader
+            .search_files_batched(
+                "file_",
+                &filters,
+                10_000,
+                Some(Duration::from_millis(20)),
+                1,
+                |batch| {
+                    delivered += batch.len();
+                    std::thread::sleep(Duration::from_millis(5));
+                    true
+                },
+            )
+            .unwrap();
+        assert!(stats.timed_out);
+        assert_eq!(stats.rows, delivered);
+        assert!(delivered > 0 && delivered < 5_000, "{} rows", delivered);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    fn history(reader: &Reader) -> Vec<String> {
+        let entries = reader.search_history(100).unwrap();
+        entries.into_iter().map(|entry| entry.query).collect()
+    }
+
+    #[test]
+    fn history_is_most_recent_first_and_repeats_update() {
+        let dir = TempDir::new("history");
+        let (db, reader) = open_index(&dir);
+        for query in ["first", "second", "third"] {
+            db.record_search(query, None, 1, 100, 0).unwrap();
+        }
+        assert_eq!(history(&reader), ["third", "second", "first"]);
+
+        // Repetir la última solo actualiza su recuento y fecha.
+        let before = reader.search_history(1).unwrap().remove(0);
+        db.record_search("third", None, 7, 100, 0).unwrap();
+        let after = reader.search_history(1).unwrap().remove(0);
+        assert_eq!(history(&reader), ["third", "second", "first"]);
+        assert_eq!((after.id, after.result_count), (before.id, 7));
+        assert!(after.searched_at >= before.searched_at);
+
+        // Con otros filtros, o si no es la última, es una búsqueda nueva.
+        db.record_search("third", Some(r#"{"fuzzy":true}"#), 1, 100, 0).unwrap();
+        db.record_search("first", None, 1, 100, 0).unwrap();
+        assert_eq!(history(&reader), ["first", "third", "third", "second", "first"]);
+        assert!(reader.search_history(100).unwrap()[1].filters.as_ref().unwrap().fuzzy);
+    }
+
+    #[test]
+    fn saved_searches_round_trip() {
+        let dir = TempDir::new("saved");
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&[
+            record("/docs/report.pdf", false, MODIFIED),
+            record("/docs/report.txt", false, MODIFIED),
+            record("/docs/reports", true, MODIFIED),
+        ])
+        .unwrap();
+        let pdfs = SearchFilters { extensions: Some(vec![".pdf".into()]), ..Default::default() };
+        let json = serde_json::to_string(&pdfs).unwrap();
+
+        assert!(!db.save_search("pdfs", "report", &json).unwrap());
+        assert!(!db.save_search("Everything", "report", "{}").unwrap());
+        let listed: Vec<String> =
+            reader.saved_searches().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(listed, ["Everything", "pdfs"]);
+
+        // Ejecutarla es buscar con su consulta y sus filtros.
+        let saved = reader.saved_search("pdfs").unwrap().unwrap();
+        assert_eq!(names(&search(&reader, &saved.query, &saved.filters)), ["report.pdf"]);
+        let saved = reader.saved_search("Everything").unwrap().unwrap();
+        assert_eq!(search(&reader, &saved.query, &saved.filters).len(), 3);
+
+        // Guardar con el mismo nombre sustituye y conserva la fecha de creación.
+        let before = reader.saved_search("pdfs").unwrap().unwrap();
+        assert!(db.save_search("pdfs", "reports", &json).unwrap());
+        let after = reader.saved_search("pdfs").unwrap().unwrap();
+        assert_eq!((after.query.as_str(), &after.created_at), ("reports", &before.created_at));
+        assert_eq!(reader.saved_searches().unwrap().len(), 2);
+
+        assert!(db.delete_saved_search("pdfs").unwrap());
+        assert!(!db.delete_saved_search("pdfs").unwrap());
+        assert!(reader.saved_search("pdfs").unwrap().is_none());
+        assert_eq!(reader.saved_searches().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn history_limits() {
+        let dir = TempDir::new("history-limits");
+        let (db, reader) = open_index(&dir);
+
+        // Sin entradas permitidas no se guarda nada.
+        db.record_search("off", None, 1, 0, 0).unwrap();
+        assert!(history(&reader).is_empty());
+
+        for query in ["a", "b", "c", "d"] {
+            db.record_search(query, None, 1, 3, 0).unwrap();
+        }
+        assert_eq!(history(&reader), ["d", "c", "b"]);
+
+        // `ttl_days` = 0 no caduca; con 30 días se va lo anterior.
+        let old = (chrono::Utc::now() - chrono::Duration::days(60)).to_rfc3339();
+        db.conn
+            .execute("UPDATE search_history SET searched_at = ?1 WHERE query = 'b'", [old])
+            .unwrap();
+        db.record_search("e", None, 1, 10, 0).unwrap();
+        assert_eq!(history(&reader), ["e", "d", "c", "b"]);
+        db.record_search("f", None, 1, 10, 30).unwrap();
+        assert_eq!(history(&reader), ["f", "e", "d", "c"]);
+    }
+
+    #[test]
+    fn content_search_marks_the_matches() {
+        let dir = TempDir::new("content");
+        let (mut db, reader) = open_index(&dir);
+        let with_content = |path: &str, text: &str| FileRecord {
+            content: Some(text.to_string()),
+            ..record(path, false, MODIFIED)
+        };
+        db.upsert_batch(&[
+            with_content("/notes/fox.txt", "The quick brown fox jumps over the lazy dog."),
+            with_content("/notes/cat.txt", "A quick cat naps all day."),
+            with_content("/notes/ops.txt", "Use AND or NOT between words."),
+            record("/notes/plain.txt", false, MODIFIED),
+        ])
+        .unwrap();
+
+        // Todas las palabras son obligatorias, sin distinguir mayúsculas.
+        let matches = reader.search_content("QUICK fox", 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].result.path, "/notes/fox.txt");
+        let snippet = &matches[0].snippet;
+        assert!(snippet.contains("\u{2}quick\u{3}"), "{:?}", snippet);
+        assert!(snippet.contains("\u{2}fox\u{3}"), "{:?}", snippet);
+        assert!(!snippet.contains("\u{2}brown"), "{:?}", snippet);
+
+        assert_eq!(reader.search_content("quick", 10).unwrap().len(), 2);
+
+        // Los operadores de FTS5 se buscan como palabras.
+        let ops = reader.search_content("NOT", 10).unwrap();
+        assert_eq!(ops.len(), 1);
+        assert!(ops[0].snippet.contains("\u{2}NOT\u{3}"), "{:?}", ops[0].snippet);
+        assert!(reader.search_content("   ", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn case_sensitive_name_and_path_queries() {
+        let dir = TempDir::new("case");
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&[
+            record("/docs/Readme.md", false, MODIFIED),
+            record("/docs/readme.txt", false, MODIFIED),
+            record("/docs/OldReadme.txt", false, MODIFIED),
+            record("/x/README", false, MODIFIED),
+            record("/Readme", true, MODIFIED),
+            record("/Readme/notes.txt", false, MODIFIED),
+        ])
+        .unwrap();
+        let exact = SearchFilters { case_sensitive: true, ..Default::default() };
+        let any_case = SearchFilters::default();
+
+        // Trigram (3 o más caracteres), LIKE (menos) y prefijo.
+        let found = search(&reader, "Readme", &exact);
+        assert_eq!(names(&found), ["OldReadme.txt", "Readme", "Readme.md"]);
+        assert_eq!(names(&search(&reader, "readme", &exact)), ["readme.txt"]);
+        assert_eq!(search(&reader, "readme", &any_case).len(), 5);
+        assert_eq!(names(&search(&reader, "ME", &exact)), ["README"]);
+        assert_eq!(names(&search(&reader, "Read*", &exact)), ["Readme", "Readme.md"]);
+        assert_eq!(names(&search(&reader, "Readme -md", &exact)), ["OldReadme.txt", "Readme"]);
+
+        let exact_path = SearchFilters { search_in_path: true, ..exact };
+        let expected = ["/Readme", "/Readme/notes.txt", "/docs/OldReadme.txt", "/docs/Readme.md"];
+        let found = paths(&search(&reader, "Readme", &exact_path));
+        assert_eq!(found, BTreeSet::from(expected.map(String::from)));
+        assert_eq!(names(&search(&reader, r"docs\Readme", &exact_path)), ["Readme.md"]);
+        assert_eq!(names(&search(&reader, "docs/readme", &exact_path)), ["readme.txt"]);
+        let any_path = SearchFilters { search_in_path: true, ..Default::default() };
+        assert_eq!(search(&reader, "readme", &any_path).len(), 6);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mode_mask_and_owner_filters() {
+        use crate::indexer::IndexOptions;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let dir = TempDir::new("mode-mask");
+        let files = [("file-a", 0o600), ("file-b", 0o644), ("file-c", 0o666), ("file-d", 0o777)];
+        let mut records = Vec::new();
+        for (name, mode) in files {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"").unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            records.push(IndexOptions::default().path_record(&path).unwrap());
+        }
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&records).unwrap();
+
+        let with_mask = |mask| SearchFilters { mode_mask: Some(mask), ..Default::default() };
+        // Escribibles por todos, ejecutables por el propietario, ambos a la vez.
+        assert_eq!(names(&search(&reader, "file", &with_mask(0o002))), ["file-c", "file-d"]);
+        assert_eq!(names(&search(&reader, "file", &with_mask(0o100))), ["file-d"]);
+        assert_eq!(names(&search(&reader, "file", &with_mask(0o102))), ["file-d"]);
+        // `0` no filtra.
+        assert_eq!(search(&reader, "file", &with_mask(0)).len(), files.len());
+
+        let uid = std::fs::metadata(dir.path()).unwrap().uid();
+        let owned_by = |uid| SearchFilters { owner_uid: Some(uid), ..Default::default() };
+        assert_eq!(search(&reader, "file", &owned_by(uid)).len(), files.len());
+        assert!(search(&reader, "file", &owned_by(uid.wrapping_add(1))).is_empty());
+    }
+
+    #[test]
+    fn extension_stats_count_and_cache() {
+        use crate::cache::IndexCached;
+
+        let dir = TempDir::new("extension-stats");
+        let (mut db, reader) = open_index(&dir);
+        let file = |path: &str, size, file_id: Option<&str>| FileRecord {
+            file_size: Some(size),
+            file_id: file_id.map(String::from),
+            link_count: if file_id.is_some() { 2 } else { 1 },
+            ..record(path, false, MODIFIED)
+        };
+        db.upsert_batch(&[
+            record("/d", true, MODIFIED),
+            record("/d/sub", true, MODIFIED),
+            file("/d/a.TXT", 10, Some("1:42")),
+            file("/d/b.txt", 5, None),
+            file("/d/link.txt", 10, Some("1:42")),
+            file("/d/main.rs", 7, None),
+            file("/d/README", 3, None),
+        ])
+        .unwrap();
+        let stats = |dedupe| -> Vec<(Option<String>, bool, u64, u64)> {
+            let stats = reader.extension_stats(dedupe).unwrap();
+            stats.into_iter().map(|s| (s.extension, s.is_dir, s.count, s.bytes)).collect()
+        };
+        let txt = Some(".txt".to_string());
+        let rs = Some(".rs".to_string());
+
+        // Sin distinguir mayúsculas; los directorios aparte y sin bytes.
+        let expected = [
+            (txt.clone(), false, 3, 25),
+            (None, true, 2, 0),
+            (None, false, 1, 3),
+            (rs.clone(), false, 1, 7),
+        ];
+        assert_eq!(stats(false), expected);
+        // Con `dedupe_hardlinks`, `link.txt` cuenta como `a.TXT`, que se indexó antes.
+        let deduped = [
+            (None, true, 2, 0),
+            (txt, false, 2, 15),
+            (None, false, 1, 3),
+            (rs, false, 1, 7),
+        ];
+        assert_eq!(stats(true), deduped);
+
+        // Una escritura en el índice invalida lo calculado.
+        let cached: IndexCached<bool, Vec<ExtensionStat>> = IndexCached::new();
+        let count_rs = |stats: Vec<ExtensionStat>| {
+            stats.iter().find(|s| s.extension.as_deref() == Some(".rs")).unwrap().count
+        };
+        let compute = || reader.extension_stats(false);
+        assert_eq!(count_rs(cached.get_or_compute(false, compute).unwrap()), 1);
+        db.upsert_batch(&[file("/d/lib.rs", 1, None)]).unwrap();
+        assert!(cached.get(&false).is_none());
+        assert_eq!(count_rs(cached.get_or_compute(false, compute).unwrap()), 2);
+    }
+
+    #[test]
+    fn query_plan_uses_the_trigram_and_prefix_indexes() {
+        let dir = TempDir::new("query-plan");
+        let (_db, reader) = open_index(&dir);
+        // Con los parámetros enlazados: el de `LIKE` decide si se usa el índice.
+        let plan = |query: &str| -> Vec<String> {
+            let search = reader.search_sql(query, &SearchFilters::default(), 10).unwrap();
+            let schemas = reader.schemas.len();
+            let params = bound_params(&search.params, search.condition_params, schemas);
+            let sql = format!("EXPLAIN QUERY PLAN {}", search.sql);
+            let mut stmt = reader.conn.prepare(&sql).unwrap();
+            let details = stmt.query_map(params.as_slice(), |row| row.get(3)).unwrap();
+            details.map(|detail| detail.unwrap()).collect()
+        };
+        let has_step = |plan: &[String], text: &str| plan.iter().any(|step| step.contains(text));
+        let scans_table = |plan: &[String]| plan.iter().any(|s| s == "SCAN main.search_index");
+
+        // Tres caracteres o más: el índice trigram, sin recorrer la tabla.
+        let trigram = plan("port");
+        assert!(has_step(&trigram, "VIRTUAL TABLE INDEX"), "{:?}", trigram);
+        assert!(!scans_table(&trigram), "{:?}", trigram);
+        // Prefijo: rango sobre el índice NOCASE del nombre.
+        let prefix = plan("rep*");
+        let by_index = has_step(&prefix, "USING INDEX idx_search_name_nocase");
+        assert!(by_index && !scans_table(&prefix), "{:?}", prefix);
+        // Menos de tres: `LIKE '%re%'` recorre la tabla (por eso `min_query_length`).
+        assert!(scans_table(&plan("re")));
+    }
+
+    #[test]
+    fn maintenance_reclaims_deleted_rows() {
+        let dir = TempDir::new("maintain");
+        let (mut db, _reader) = open_index(&dir);
+        let records: Vec<FileRecord> = (0..10)
+            .map(|i| record(&format!("/big/dir{}", i), true, MODIFIED))
+            .chain((0..5_000).map(|i| {
+                record(&format!("/big/dir{}/file_{:05}.txt", i % 10, i), false, MODIFIED)
+            }))
+            .collect();
+        db.upsert_batch(&records).unwrap();
+        db.maintain(false).unwrap();
+        let full = db.disk_size().unwrap();
+
+        for i in 1..10 {
+            assert_eq!(db.delete_entry(&format!("/big/dir{}", i)).unwrap(), 501);
+        }
+        // Sin VACUUM las páginas libres se quedan en el archivo.
+        db.maintain(false).unwrap();
+        let deleted = db.disk_size().unwrap();
+        assert!(deleted >= full / 2, "{} of {} bytes", deleted, full);
+
+        let reclaimed = db.maintain(true).unwrap();
+        let compacted = db.disk_size().unwrap();
+        assert!(reclaimed > 0);
+        assert_eq!(compacted, deleted - reclaimed);
+        assert!(compacted < full / 2, "{} of {} bytes", compacted, full);
+        assert_eq!(db.maintain(true).unwrap(), 0);
+    }
+
+    #[test]
+    fn synchronous_pragma_round_trip() {
+        let dir = TempDir::new("synchronous");
+        let (mut db, _reader) = open_index(&dir);
+        let current = |db: &Database| -> i64 {
+            db.conn.query_row("PRAGMA synchronous", [], |row| row.get(0)).unwrap()
+        };
+        // 0 = OFF, 1 = NORMAL, 2 = FULL.
+        assert_eq!(current(&db), 1);
+
+        db.set_synchronous(SyncMode::Full).unwrap();
+        assert_eq!(current(&db), 2);
+        db.set_bulk_mode(true).unwrap();
+        assert_eq!(current(&db), 0);
+        db.set_bulk_mode(false).unwrap();
+        assert_eq!(current(&db), 2);
+
+        // `Off` fuera del modo rápido se queda en `Normal`, también al salir de él.
+        db.set_synchronous(SyncMode::Off).unwrap();
+        assert_eq!(current(&db), 1);
+        db.set_bulk_mode(true).unwrap();
+        db.set_bulk_mode(false).unwrap();
+        assert_eq!(current(&db), 1);
+    }
+
+    #[test]
+    fn boolean_operators() {
+        let dir = TempDir::new("boolean");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> = [
+            "alpha.txt",
+            "beta.txt",
+            "alpha beta.txt",
+            "gamma.log",
+            "my_file.txt",
+            "myXfile.txt",
+        ]
+        .iter()
+        .map(|name| record(&format!("/b/{}", name), false, MODIFIED))
+        .collect();
+        db.upsert_batch(&records).unwrap();
+        let plain = SearchFilters::default();
+        let found = |query: &str| names(&search(&reader, query, &plain)).join(", ");
+
+        assert_eq!(found("alpha beta"), "alpha beta.txt");
+        assert_eq!(found("beta alpha"), "alpha beta.txt");
+        assert_eq!(found("alpha | gamma"), "alpha beta.txt, alpha.txt, gamma.log");
+        assert_eq!(found("alpha OR gamma"), "alpha beta.txt, alpha.txt, gamma.log");
+        assert_eq!(found("txt -alpha"), "beta.txt, myXfile.txt, my_file.txt");
+        assert_eq!(found("txt NOT alpha"), "beta.txt, myXfile.txt, my_file.txt");
+        assert_eq!(found("\"alpha beta\""), "alpha beta.txt");
+        assert_eq!(found("\"beta alpha\""), "");
+        // Los comodines de LIKE son literales, también en términos cortos y excluidos.
+        assert_eq!(found("file -my_file"), "myXfile.txt");
+        assert_eq!(found("txt -y_"), "alpha beta.txt, alpha.txt, beta.txt, myXfile.txt");
+        assert_eq!(found("_f"), "my_file.txt");
+        assert_eq!(found("y_ | ga"), "gamma.log, my_file.txt");
+    }
+
+    #[test]
+    fn roots_filter_treats_filesystem_root_as_everything() {
+        let dir = TempDir::new("roots");
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&[
+            record("/work/a.txt", false, MODIFIED),
+            record("/home/x/b.txt", false, MODIFIED),
+        ])
+        .unwrap();
+
+        let with_roots = |roots: &[&str]| SearchFilters {
+            roots: Some(roots.iter().map(|r| r.to_string()).collect()),
+            ..Default::default()
+        };
+
+        assert_eq!(names(&search(&reader, "txt", &with_roots(&["/home/x"]))), ["b.txt"]);
+        assert_eq!(names(&search(&reader, "txt", &with_roots(&["/home/x/"]))), ["b.txt"]);
+        assert_eq!(names(&search(&reader, "txt", &with_roots(&["/"]))), ["a.txt", "b.txt"]);
+        assert_eq!(
+            names(&search(&reader, "txt", &with_roots(&["/", "/home/x"]))),
+            ["a.txt", "b.txt"]
+        );
+        // `/home/xy` no está bajo `/home/x`.
+        assert!(search(&reader, "txt", &with_roots(&["/home/xy"])).is_empty());
+    }
+
+    #[test]
+    fn delete_entry_takes_a_directory_with_its_contents() {
+        let dir = TempDir::new("delete-entry");
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&[
+            record("/w/gone", true, MODIFIED),
+            record("/w/gone/a.txt", false, MODIFIED),
+            record("/w/gone/sub", true, MODIFIED),
+            record("/w/gone/sub/b.txt", false, MODIFIED),
+            record("/w/gone2.txt", false, MODIFIED),
+            record("/w/kept.txt", false, MODIFIED),
+        ])
+        .unwrap();
+
+        assert_eq!(db.delete_entry("/w/gone").unwrap(), 4);
+        assert_eq!(db.delete_entry("/w/kept.txt").unwrap(), 1);
+        assert_eq!(db.delete_entry("/w/missing").unwrap(), 0);
+        // `gone2.txt` comparte prefijo con `gone` pero no está debajo.
+        let left = search(&reader, "", &SearchFilters::default());
+        assert_eq!(names(&left), ["gone2.txt"]);
+    }
+
+    #[test]
+    fn tag_filter_matches_tags_literally() {
+        let dir = TempDir::new("tags");
+        let (mut db, reader) = open_index(&dir);
+        let tagged = |path: &str, tags: &str| FileRecord {
+            tags: Some(tags.to_string()),
+            ..record(path, false, MODIFIED)
+        };
+        db.upsert_batch(&[
+            tagged("/t/percent.txt", ",50%,"),
+            tagged("/t/digits.txt", ",500,"),
+            tagged("/t/under.txt", ",a_b,"),
+            tagged("/t/plain.txt", ",axb,"),
+        ])
+        .unwrap();
+
+        let with_tag = |tag: &str| SearchFilters {
+            tags: Some(vec![tag.to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(names(&search(&reader, "txt", &with_tag("50%"))), ["percent.txt"]);
+        assert_eq!(names(&search(&reader, "txt", &with_tag("a_b"))), ["under.txt"]);
+        assert!(search(&reader, "txt", &with_tag("%")).is_empty());
+        assert_eq!(like_escape(r"a\b%_"), r"a\\b\%\_");
+    }
+
+    #[test]
+    fn date_range_bounds_are_inclusive() {
+        let dir = TempDir::new("dates");
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&[
+            record("/d/jan.txt", false, "2024-01-01T00:00:00+00:00"),
+            record("/d/feb.txt", false, "2024-02-01T00:00:00+00:00"),
+            record("/d/mar.txt", false, "2024-03-01T00:00:00+00:00"),
+        ])
+        .unwrap();
+
+        let between = |min: Option<&str>, max: Option<&str>| SearchFilters {
+            min_date: min.map(String::from),
+            max_date: max.map(String::from),
+            ..Default::default()
+        };
+
+        // Un límite igual a la fecha de un archivo lo incluye.
+        let filters = between(Some("2024-01-01T00:00:00Z"), Some("2024-02-01T00:00:00Z"));
+        assert_eq!(names(&search(&reader, "txt", &filters)), ["feb.txt", "jan.txt"]);
+
+        // Un segundo después del archivo ya lo excluye.
+        let filters = between(Some("2024-02-01T00:00:01Z"), None);
+        assert_eq!(names(&search(&reader, "txt", &filters)), ["mar.txt"]);
+        let filters = between(None, Some("2024-01-31T23:59:59Z"));
+        assert_eq!(names(&search(&reader, "txt", &filters)), ["jan.txt"]);
+
+        // Los límites con otra zona horaria se pasan a UTC antes de comparar.
+        let filters = between(Some("2024-02-01T01:00:00+01:00"), Some("2024-02-01T01:00:00+01:00"));
+        assert_eq!(names(&search(&reader, "txt", &filters)), ["feb.txt"]);
+    }
+
+    #[test]
+    fn empty_or_invalid_date_range() {
+        let dir = TempDir::new("dates-empty");
+        let (mut db, reader) = open_index(&dir);
+        db.upsert_batch(&[record("/d/feb.txt", false, "2024-02-01T00:00:00+00:00")])
+            .unwrap();
+
+        let filters = SearchFilters {
+            min_date: Some("2024-03-01T00:00:00Z".into()),
+            max_date: Some("2024-01-01T00:00:00Z".into()),
+            ..Default::default()
+        };
+        assert!(search(&reader, "txt", &filters).is_empty());
+
+        let filters = SearchFilters {
+            min_date: Some("last tuesday".into()),
+            ..Default::default()
+        };
+        assert!(reader.search_files("txt", &filters, 100, None).is_err());
+    }
+}
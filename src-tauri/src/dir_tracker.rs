@@ -0,0 +1,142 @@
+//! Detecta cuándo el walker paralelo termina un directorio (evento `directory-indexed`).
+//!
+//! `ignore` visita un directorio, lo lee y encola sus hijos pasando cada uno por
+//! `filter_entry`; los hijos se visitan después, desde cualquier hilo. Cada directorio
+//! lleva la cuenta de lo que le falta: los hijos encolados sin visitar más una "lectura
+//! en curso" que se suelta cuando el hilo que lo leyó visita su siguiente entrada (o
+//! termina). Al llegar a cero ya se vieron todos sus hijos directos.
+
+use crate::types::{DirectoryIndexed, FileRecord};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex};
+
+/// Lo que los hilos del walker mandan al escritor. Un directorio terminado va por el
+/// mismo canal que los registros para llegar después de los de sus hijos.
+#[allow(clippy::large_enum_variant)]
+pub enum WalkItem {
+    Record(FileRecord),
+    /// Ruta ya indexada y sin cambios en disco (`incremental_reindex`).
+    Unchanged(String),
+    Directory(DirectoryIndexed),
+}
+
+#[derive(Default)]
+struct Pending {
+    /// Hijos encolados sin visitar, más uno mientras se lee el directorio.
+    outstanding: usize,
+    children: usize,
+}
+
+#[derive(Default)]
+pub struct DirectoryTracker {
+    pending: Mutex<HashMap<PathBuf, Pending>>,
+}
+
+impl DirectoryTracker {
+    /// Desde `filter_entry`: el walker va a encolar `path`.
+    pub fn queued(&self, path: &Path) {
+        let Some(parent) = path.parent() else {
+            return;
+        };
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dir) = pending.get_mut(parent) {
+            dir.outstanding += 1;
+        }
+    }
+
+    /// Se visitó `path`: cuenta para su padre y, si es un directorio, empieza su lectura.
+    fn visited(&self, path: &Path, is_dir: bool) -> Option<DirectoryIndexed> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if is_dir {
+            pending.insert(
+                path.to_path_buf(),
+                Pending {
+                    outstanding: 1,
+                    children: 0,
+                },
+            );
+        }
+        let parent = path.parent()?;
+        let dir = pending.get_mut(parent)?;
+        dir.children += 1;
+        Self::settle(&mut pending, parent)
+    }
+
+    /// Terminó la lectura de `dir`.
+    fn read_done(&self, dir: &Path) -> Option<DirectoryIndexed> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        Self::settle(&mut pending, dir)
+    }
+
+    fn settle(pending: &mut HashMap<PathBuf, Pending>, dir: &Path) -> Option<DirectoryIndexed> {
+        let entry = pending.get_mut(dir)?;
+        entry.outstanding = entry.outstanding.saturating_sub(1);
+        if entry.outstanding > 0 {
+            return None;
+        }
+        let done = pending.remove(dir)?;
+        Some(DirectoryIndexed {
+            path: dir.to_string_lossy().into_owned(),
+            child_count: done.children,
+        })
+    }
+}
+
+/// Emisor de un hilo del walker hacia el escritor; con `tracker`, además lleva la
+/// lectura en curso de ese hilo.
+pub struct WalkSender {
+    tx: SyncSender<WalkItem>,
+    tracker: Option<Arc<DirectoryTracker>>,
+    reading: Option<PathBuf>,
+}
+
+impl WalkSender {
+    pub fn new(tx: SyncSender<WalkItem>, tracker: Option<Arc<DirectoryTracker>>) -> Self {
+        Self {
+            tx,
+            tracker,
+            reading: None,
+        }
+    }
+
+    /// `false` si el escritor ya terminó.
+    pub fn send(&self, record: FileRecord) -> bool {
+        self.tx.send(WalkItem::Record(record)).is_ok()
+    }
+
+    /// `false` si el escritor ya terminó.
+    pub fn unchanged(&self, path: String) -> bool {
+        self.tx.send(WalkItem::Unchanged(path)).is_ok()
+    }
+
+    /// Al empezar a procesar una entrada: el hilo ya no está leyendo el directorio
+    /// anterior. No llamar con los errores, que pueden llegar en mitad de una lectura.
+    pub fn finish_read(&mut self) {
+        if let (Some(tracker), Some(dir)) = (&self.tracker, self.reading.take()) {
+            if let Some(done) = tracker.read_done(&dir) {
+                let _ = self.tx.send(WalkItem::Directory(done));
+            }
+        }
+    }
+
+    /// Tras mandar (o descartar) el registro de `path`.
+    pub fn visited(&mut self, path: &Path, is_dir: bool) {
+        let Some(tracker) = &self.tracker else {
+            return;
+        };
+        if let Some(done) = tracker.visited(path, is_dir) {
+            let _ = self.tx.send(WalkItem::Directory(done));
+        }
+        if is_dir {
+            self.reading = Some(path.to_path_buf());
+        }
+    }
+}
+
+impl Drop for WalkSender {
+    fn drop(&mut self) {
+        self.finish_read();
+    }
+}
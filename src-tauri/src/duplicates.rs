@@ -0,0 +1,101 @@
+//! Hash del contenido para `find_duplicates` (`hash_duplicates`). Solo se calcula para
+//! archivos que comparten tamaño con otro: primero un hash rápido del inicio y el final,
+//! y el del contenido completo solo si ese coincide.
+//!
+//! `content_hash` guarda `p` + hash parcial o `f` + hash completo; solo los completos
+//! cuentan como duplicados.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::debug;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+/// Bytes leídos del inicio y del final para el hash rápido.
+const EDGE_BYTES: u64 = 64 * 1024;
+const READ_BUFFER_SIZE: usize = 256 * 1024;
+
+const PARTIAL_PREFIX: char = 'p';
+const FULL_PREFIX: char = 'f';
+
+/// Hash de los primeros y últimos `EDGE_BYTES` de `path` (con `size` > 2 × `EDGE_BYTES`).
+fn partial_hash(path: &Path, size: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = Vec::with_capacity((EDGE_BYTES * 2) as usize);
+    (&mut file).take(EDGE_BYTES).read_to_end(&mut buffer)?;
+    file.seek(SeekFrom::Start(size - EDGE_BYTES))?;
+    file.take(EDGE_BYTES).read_to_end(&mut buffer)?;
+    Ok(format!("{}{:016x}", PARTIAL_PREFIX, xxh3_64(&buffer)))
+}
+
+/// Hash de todo el contenido de `path`.
+fn full_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{}{:032x}", FULL_PREFIX, hasher.digest128()))
+}
+
+fn is_full(hash: &str) -> bool {
+    hash.starts_with(FULL_PREFIX)
+}
+
+/// Hashes de un grupo de archivos de `size` bytes a partir de los que ya había
+/// (`None` = sin calcular o archivo cambiado). Devuelve solo los que cambian; los
+/// archivos que no se pueden leer se quedan sin hash.
+pub fn hash_group(size: u64, files: &[(String, Option<String>)]) -> Vec<(String, String)> {
+    let mut updates = Vec::new();
+
+    // Los pequeños se leen enteros: el hash rápido ya sería el del contenido.
+    if size <= EDGE_BYTES * 2 {
+        for (path, _) in files.iter().filter(|(_, hash)| !hash.as_deref().is_some_and(is_full)) {
+            match full_hash(Path::new(path)) {
+                Ok(full) => updates.push((path.clone(), full)),
+                Err(e) => debug!("Failed to hash {}: {}", path, e),
+            }
+        }
+        return updates;
+    }
+
+    // Los que ya tienen hash completo también se agrupan por el rápido (leerlo es
+    // barato), para saber si un archivo nuevo coincide con ellos.
+    let mut by_partial: HashMap<String, Vec<(&String, Option<&str>)>> = HashMap::new();
+    for (path, hash) in files {
+        let partial = match hash.as_deref().filter(|h| !is_full(h)) {
+            Some(partial) => partial.to_string(),
+            None => match partial_hash(Path::new(path), size) {
+                Ok(partial) => partial,
+                Err(e) => {
+                    debug!("Failed to hash {}: {}", path, e);
+                    continue;
+                }
+            },
+        };
+        by_partial.entry(partial).or_default().push((path, hash.as_deref()));
+    }
+
+    for (partial, members) in by_partial {
+        if members.len() == 1 {
+            let (path, hash) = members[0];
+            if hash.is_none() {
+                updates.push((path.clone(), partial));
+            }
+            continue;
+        }
+        for (path, _) in members.into_iter().filter(|(_, hash)| !hash.is_some_and(is_full)) {
+            match full_hash(Path::new(path)) {
+                Ok(full) => updates.push((path.clone(), full)),
+                Err(e) => debug!("Failed to hash {}: {}", path, e),
+            }
+        }
+    }
+    updates
+}
@@ -0,0 +1,86 @@
+//! Si el proceso se ejecuta como administrador: leer la MFT (`MftIndexer`) y el USN
+//! journal exige abrir el volumen, y sin elevación Windows lo deniega.
+
+/// `true` si el proceso tiene el token elevado (Windows). En el resto de sistemas la
+/// MFT no aplica y siempre es `false`.
+pub fn is_elevated() -> bool {
+    sys::is_elevated()
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+
+    const TOKEN_QUERY: u32 = 0x0008;
+    /// `TOKEN_INFORMATION_CLASS::TokenElevation`.
+    const TOKEN_ELEVATION: u32 = 20;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn OpenProcessToken(process: *mut c_void, access: u32, token: *mut *mut c_void) -> i32;
+        fn GetTokenInformation(
+            token: *mut c_void,
+            class: u32,
+            info: *mut c_void,
+            info_size: u32,
+            returned: *mut u32,
+        ) -> i32;
+    }
+
+    pub fn is_elevated() -> bool {
+        let mut token = std::ptr::null_mut();
+        // SAFETY: `GetCurrentProcess` devuelve un pseudo-handle que no hace falta cerrar;
+        // `token` solo se usa (y se cierra) si `OpenProcessToken` lo abrió.
+        unsafe {
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+                return false;
+            }
+            // `TOKEN_ELEVATION` es un único DWORD.
+            let mut elevation = 0u32;
+            let mut returned = 0u32;
+            let ok = GetTokenInformation(
+                token,
+                TOKEN_ELEVATION,
+                &mut elevation as *mut u32 as *mut c_void,
+                std::mem::size_of::<u32>() as u32,
+                &mut returned,
+            );
+            CloseHandle(token);
+            ok != 0 && elevation != 0
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod sys {
+    pub fn is_elevated() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Abrir el volumen del sistema para leerlo (lo que hace `MftIndexer`) solo se
+    /// permite con el token elevado: las dos respuestas tienen que coincidir.
+    #[cfg(windows)]
+    #[test]
+    fn elevation_matches_raw_volume_access() {
+        let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+        let volume = std::fs::File::open(format!(r"\\.\{}", drive));
+        assert_eq!(is_elevated(), volume.is_ok(), "{:?}", volume.err());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn never_elevated_outside_windows() {
+        assert!(!is_elevated());
+    }
+}
@@ -0,0 +1,188 @@
+//! Error de los comandos de Tauri. Llega al frontend como `{ "kind": ..., "message": ... }`
+//! para que distinga "no existe" de "base ocupada" o "sin permisos" sin mirar el texto.
+//! `kind` es estable: no renombrar variantes publicadas.
+
+use serde::Serialize;
+use std::sync::PoisonError;
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum OxiError {
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    Database(String),
+    /// Sin permisos sobre un archivo o la unidad (MFT sin administrador).
+    #[error("{0}")]
+    PermissionDenied(String),
+    #[error("{0}")]
+    NotFound(String),
+    /// Interrumpido: consulta superada por otra o índice cancelado.
+    #[error("{0}")]
+    Cancelled(String),
+    /// Otra operación tiene el índice o la base ocupados; se puede reintentar.
+    #[error("{0}")]
+    Busy(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    /// Demasiadas búsquedas seguidas (`search_rate_per_sec`).
+    #[error("{0}")]
+    RateLimited(String),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl OxiError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound(message.into())
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput(message.into())
+    }
+
+    pub fn busy(message: impl Into<String>) -> Self {
+        Self::Busy(message.into())
+    }
+
+    /// Como `From<io::Error>` (mismo `kind`) pero con `context` delante del mensaje.
+    pub fn io_at(context: impl std::fmt::Display, e: std::io::Error) -> Self {
+        let message = format!("{}: {}", context, e);
+        match Self::from(e) {
+            Self::NotFound(_) => Self::NotFound(message),
+            Self::PermissionDenied(_) => Self::PermissionDenied(message),
+            _ => Self::Io(message),
+        }
+    }
+}
+
+impl From<std::io::Error> for OxiError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(e.to_string()),
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied(e.to_string()),
+            _ => Self::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for OxiError {
+    fn from(e: rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode;
+        match e.sqlite_error_code() {
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => Self::Busy(e.to_string()),
+            Some(ErrorCode::OperationInterrupted) => Self::Cancelled(e.to_string()),
+            Some(ErrorCode::PermissionDenied | ErrorCode::ReadOnly) => Self::PermissionDenied(e.to_string()),
+            _ if matches!(e, rusqlite::Error::QueryReturnedNoRows) => Self::NotFound(e.to_string()),
+            _ => Self::Database(e.to_string()),
+        }
+    }
+}
+
+/// Errores de los indexadores (`Box<dyn Error>`): se conserva el tipo si es conocido.
+impl From<Box<dyn std::error::Error>> for OxiError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<rusqlite::Error>() {
+            Ok(e) => return (*e).into(),
+            Err(e) => e,
+        };
+        match e.downcast_ref::<crate::mft_indexer::MftError>() {
+            Some(mft) => Self::PermissionDenied(mft.to_string()),
+            None => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for OxiError {
+    fn from(e: PoisonError<T>) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for OxiError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for OxiError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::InvalidInput(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for OxiError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_as_kind_and_message() {
+        // La UI decide por `kind`: estos nombres son parte de la interfaz.
+        let cases = [
+            (OxiError::Io("e".into()), "io"),
+            (OxiError::Database("e".into()), "database"),
+            (OxiError::PermissionDenied("e".into()), "permission_denied"),
+            (OxiError::not_found("e"), "not_found"),
+            (OxiError::Cancelled("e".into()), "cancelled"),
+            (OxiError::busy("e"), "busy"),
+            (OxiError::invalid_input("e"), "invalid_input"),
+            (OxiError::RateLimited("e".into()), "rate_limited"),
+            (OxiError::Internal("e".into()), "internal"),
+        ];
+        for (error, kind) in cases {
+            let value = serde_json::to_value(&error).unwrap();
+            assert_eq!(value, json!({ "kind": kind, "message": "e" }));
+        }
+    }
+
+    fn kind(error: OxiError) -> String {
+        serde_json::to_value(error).unwrap()["kind"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn io_errors_keep_their_kind() {
+        use std::io::{Error, ErrorKind};
+        assert_eq!(kind(Error::from(ErrorKind::NotFound).into()), "not_found");
+        assert_eq!(kind(Error::from(ErrorKind::PermissionDenied).into()), "permission_denied");
+        assert_eq!(kind(Error::from(ErrorKind::AddrInUse).into()), "io");
+
+        let error = OxiError::io_at("/a/b", Error::new(ErrorKind::NotFound, "gone"));
+        assert_eq!(error.to_string(), "/a/b: gone");
+        assert_eq!(kind(error), "not_found");
+    }
+
+    #[test]
+    fn database_errors_map_by_sqlite_code() {
+        let sqlite = |code| {
+            rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None).into()
+        };
+        assert_eq!(kind(sqlite(rusqlite::ffi::SQLITE_BUSY)), "busy");
+        assert_eq!(kind(sqlite(rusqlite::ffi::SQLITE_INTERRUPT)), "cancelled");
+        assert_eq!(kind(sqlite(rusqlite::ffi::SQLITE_READONLY)), "permission_denied");
+        assert_eq!(kind(sqlite(rusqlite::ffi::SQLITE_CORRUPT)), "database");
+        assert_eq!(kind(rusqlite::Error::QueryReturnedNoRows.into()), "not_found");
+    }
+
+    #[test]
+    fn boxed_indexer_errors_are_downcast() {
+        let io: Box<dyn std::error::Error> =
+            Box::new(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert_eq!(kind(io.into()), "permission_denied");
+
+        let other: Box<dyn std::error::Error> = "walker failed".into();
+        let error = OxiError::from(other);
+        assert_eq!(error.to_string(), "walker failed");
+        assert_eq!(kind(error), "internal");
+    }
+}
@@ -0,0 +1,168 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::FromSqlError;
+use rusqlite::Connection;
+use std::path::Path;
+use tracing::warn;
+
+/// Función SQL `oxi_excluded(patrones, path)`: `patrones` separados por saltos de línea,
+/// con la misma sintaxis que las exclusiones del índice. Se usa para ocultar
+/// resultados en la consulta sin reindexar.
+pub const SQL_FUNCTION: &str = "oxi_excluded";
+
+/// Prefijo que marca un patrón de exclusión como expresión regular.
+const REGEX_PREFIX: &str = "re:";
+
+/// Patrones de exclusión compilados. Cada patrón puede ser:
+/// - un nombre simple (`node_modules`): excluye ese componente de ruta y todo lo que cuelga
+///   de él, equivalente a `**/node_modules` + `**/node_modules/**`;
+/// - un glob (`**/*.tmp`, `/home/*/cache/**`) evaluado contra la ruta completa;
+/// - una regex con prefijo `re:` (`re:\.bak$`) evaluada contra la ruta completa.
+#[derive(Clone)]
+pub struct ExcludeMatcher {
+    globs: GlobSet,
+    regexes: Vec<Regex>,
+}
+
+impl ExcludeMatcher {
+    /// Compila los patrones. Los inválidos se descartan con un aviso.
+    pub fn new(patterns: &[String]) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut regexes = Vec::new();
+
+        for pattern in patterns {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                continue;
+            }
+
+            if let Some(expr) = pattern.strip_prefix(REGEX_PREFIX) {
+                match Regex::new(expr) {
+                    Ok(re) => regexes.push(re),
+                    Err(e) => warn!("Invalid exclude regex {:?}: {}", pattern, e),
+                }
+                continue;
+            }
+
+            let globs = if is_glob(pattern) {
+                vec![pattern.to_string()]
+            } else {
+                let name = pattern.trim_matches(['/', '\\']);
+                vec![format!("**/{}", name), format!("**/{}/**", name)]
+            };
+
+            for glob in globs {
+                match Glob::new(&glob) {
+                    Ok(glob) => {
+                        builder.add(glob);
+                    }
+                    Err(e) => warn!("Invalid exclude pattern {:?}: {}", pattern, e),
+                }
+            }
+        }
+
+        let globs = builder.build().unwrap_or_else(|e| {
+            warn!("Failed to build exclude patterns: {}", e);
+            GlobSet::empty()
+        });
+
+        Self { globs, regexes }
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if self.globs.is_match(path) {
+            return true;
+        }
+        if self.regexes.is_empty() {
+            return false;
+        }
+        let path_str = path.to_string_lossy();
+        self.regexes.iter().any(|re| re.is_match(&path_str))
+    }
+}
+
+/// Registra `SQL_FUNCTION` en `conn`. El matcher se compila una vez por sentencia
+/// (los patrones son el mismo parámetro en todas las filas).
+pub fn register_sql_function(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        SQL_FUNCTION,
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let matcher = ctx.get_or_create_aux(0, |patterns| -> Result<_, FromSqlError> {
+                let patterns: Vec<String> = patterns.as_str()?.lines().map(str::to_string).collect();
+                Ok(ExcludeMatcher::new(&patterns))
+            })?;
+            let path: String = ctx.get(1)?;
+            Ok(matcher.is_excluded(Path::new(&path)))
+        },
+    )
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(patterns: &[&str]) -> ExcludeMatcher {
+        ExcludeMatcher::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+    }
+
+    fn excluded(matcher: &ExcludeMatcher, path: &str) -> bool {
+        matcher.is_excluded(Path::new(path))
+    }
+
+    #[test]
+    fn bare_name_matches_whole_components_only() {
+        let m = matcher(&["node_modules"]);
+        assert!(excluded(&m, "/proj/node_modules"));
+        assert!(excluded(&m, "/proj/node_modules/lodash/index.js"));
+        assert!(!excluded(&m, "/proj/my_node_modules_notes.txt"));
+        assert!(!excluded(&m, "/proj/node_modules_backup/a.js"));
+
+        // Las barras alrededor del nombre no cambian nada.
+        let m = matcher(&["/target/"]);
+        assert!(excluded(&m, "/proj/target/debug/app"));
+        assert!(!excluded(&m, "/proj/targets/a"));
+    }
+
+    #[test]
+    fn globs_match_the_full_path() {
+        let m = matcher(&["**/*.tmp", "/home/*/cache/**"]);
+        assert!(excluded(&m, "/a/b/file.tmp"));
+        assert!(!excluded(&m, "/a/b/file.tmpl"));
+        assert!(excluded(&m, "/home/ana/cache/x/y"));
+        assert!(!excluded(&m, "/home/ana/docs/cache.txt"));
+    }
+
+    #[test]
+    fn regex_patterns_and_invalid_ones() {
+        let m = matcher(&[r"re:\.bak$", "re:(", "[", "  "]);
+        assert!(excluded(&m, "/a/old.bak"));
+        assert!(!excluded(&m, "/a/old.bak.txt"));
+        // Los patrones inválidos o vacíos se descartan sin excluir nada.
+        assert!(!excluded(&m, "/a/[/b"));
+        assert!(!excluded(&m, "/a/b"));
+    }
+
+    #[test]
+    fn sql_function_uses_the_same_rules() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_sql_function(&conn).unwrap();
+        let check = |path: &str| -> bool {
+            conn.query_row(
+                &format!("SELECT {}(?1, ?2)", SQL_FUNCTION),
+                ["node_modules\n**/*.log", path],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert!(check("/p/node_modules/x.js"));
+        assert!(check("/p/debug.log"));
+        assert!(!check("/p/src/main.rs"));
+    }
+}
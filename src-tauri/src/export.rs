@@ -0,0 +1,161 @@
+//! Exportación de resultados de búsqueda a CSV o JSON (`export_results`).
+
+use crate::types::SearchResult;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+const CSV_HEADER: &str = "path,name,extension,size,modified_time";
+
+/// Escribe `results` en `path` fila a fila. Devuelve las filas escritas.
+pub fn write_results(
+    path: &Path,
+    format: ExportFormat,
+    results: &[SearchResult],
+) -> std::io::Result<usize> {
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ExportFormat::Csv => write_csv(&mut out, results)?,
+        ExportFormat::Json => write_json(&mut out, results)?,
+    }
+    out.flush()?;
+    Ok(results.len())
+}
+
+fn write_csv(out: &mut impl Write, results: &[SearchResult]) -> std::io::Result<()> {
+    writeln!(out, "{}", CSV_HEADER)?;
+    for result in results {
+        let size = result.file_size.map(|s| s.to_string()).unwrap_or_default();
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            csv_field(&result.path),
+            csv_field(&result.name),
+            csv_field(result.extension.as_deref().unwrap_or("")),
+            size,
+            csv_field(&result.modified_time)
+        )?;
+    }
+    Ok(())
+}
+
+/// Array JSON escrito elemento a elemento, sin serializar todo en memoria.
+fn write_json(out: &mut impl Write, results: &[SearchResult]) -> std::io::Result<()> {
+    out.write_all(b"[")?;
+    for (i, result) in results.iter().enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        out.write_all(b"\n  ")?;
+        serde_json::to_writer(&mut *out, result)?;
+    }
+    out.write_all(b"\n]\n")?;
+    Ok(())
+}
+
+/// Campo CSV (RFC 4180): entre comillas si contiene separadores, comillas o saltos de línea.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{result, TempDir};
+
+    /// Lector mínimo de CSV (RFC 4180) para comprobar lo exportado.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => row.push(std::mem::take(&mut field)),
+                '\n' if !quoted => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                c => field.push(c),
+            }
+        }
+        rows
+    }
+
+    fn awkward_results() -> Vec<SearchResult> {
+        let mut results = vec![
+            result("/docs/plain.txt", false),
+            result("/docs/a,b.txt", false),
+            result("/docs/say \"hi\".md", false),
+            result("/docs/two\nlines\r\n.txt", false),
+            result("/docs/folder", true),
+        ];
+        results[0].file_size = Some(1234);
+        results
+    }
+
+    fn export(format: ExportFormat, results: &[SearchResult]) -> String {
+        let dir = TempDir::new("export");
+        let path = dir.path().join("out");
+        assert_eq!(write_results(&path, format, results).unwrap(), results.len());
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let results = awkward_results();
+        let rows = parse_csv(&export(ExportFormat::Csv, &results));
+
+        assert_eq!(rows[0].join(","), CSV_HEADER);
+        assert_eq!(rows.len(), results.len() + 1);
+        for (row, result) in rows[1..].iter().zip(&results) {
+            let size = result.file_size.map(|s| s.to_string()).unwrap_or_default();
+            let expected = [
+                result.path.as_str(),
+                result.name.as_str(),
+                result.extension.as_deref().unwrap_or(""),
+                size.as_str(),
+                result.modified_time.as_str(),
+            ];
+            assert_eq!(row, &expected);
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let results = awkward_results();
+        let parsed: Vec<SearchResult> =
+            serde_json::from_str(&export(ExportFormat::Json, &results)).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&results).unwrap()
+        );
+    }
+
+    #[test]
+    fn empty_export() {
+        assert_eq!(parse_csv(&export(ExportFormat::Csv, &[])).len(), 1);
+        let parsed: Vec<SearchResult> =
+            serde_json::from_str(&export(ExportFormat::Json, &[])).unwrap();
+        assert!(parsed.is_empty());
+    }
+}
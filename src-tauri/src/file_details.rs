@@ -0,0 +1,68 @@
+//! Detalle de una ruta (`get_file_details`): completa la fila del índice con la metadata
+//! actual del disco y marca las entradas que ya no existen.
+
+use crate::indexer;
+use crate::types::{FileDetails, LiveMetadata, SearchResult};
+use chrono::{DateTime, Utc};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Junta `indexed` (la fila de `path`, si la hay) con una lectura nueva de la metadata.
+/// Sin fila y sin nada en disco devuelve `NotFound`.
+pub fn details(path: &str, indexed: Option<SearchResult>) -> std::io::Result<FileDetails> {
+    let live = match live_metadata(Path::new(path)) {
+        Ok(live) => Some(live),
+        Err(e) if e.kind() == ErrorKind::NotFound && indexed.is_some() => None,
+        Err(e) => return Err(e),
+    };
+    let changed = match (&indexed, &live) {
+        (Some(indexed), Some(live)) => {
+            // Los directorios no guardan mtime ni (sin `compute_dir_sizes`) tamaño.
+            !indexed.is_dir
+                && (indexed.file_size != Some(live.file_size)
+                    || live.modified_time.as_ref() != Some(&indexed.modified_time))
+        }
+        _ => false,
+    };
+    Ok(FileDetails {
+        path: path.to_string(),
+        stale: indexed.is_some() && live.is_none(),
+        changed,
+        indexed,
+        live,
+    })
+}
+
+fn live_metadata(path: &Path) -> std::io::Result<LiveMetadata> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    // En Windows también las junctions (como `is_reparse` al indexar).
+    let is_symlink = indexer::is_reparse_point(&metadata);
+    let symlink_target = if is_symlink {
+        std::fs::read_link(path)
+            .ok()
+            .map(|target| target.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+    let (owner_uid, owner_gid) = indexer::owner(&metadata);
+    Ok(LiveMetadata {
+        is_dir: metadata.is_dir(),
+        file_size: metadata.len(),
+        modified_time: metadata.modified().ok().map(rfc3339),
+        created_time: indexer::created_rfc3339(&metadata),
+        accessed_time: metadata.accessed().ok().map(rfc3339),
+        mode: indexer::mode_bits(&metadata),
+        readonly: metadata.permissions().readonly(),
+        owner_uid,
+        owner_gid,
+        link_count: indexer::link_count(&metadata).max(1) as u64,
+        is_symlink,
+        symlink_target,
+        // `exists` sigue el enlace.
+        dangling: is_symlink && !path.exists(),
+    })
+}
+
+fn rfc3339(time: std::time::SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
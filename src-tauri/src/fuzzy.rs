@@ -0,0 +1,96 @@
+use crate::ranking;
+use crate::types::SearchResult;
+
+/// Trigramas únicos de la consulta, usados como prefiltro SQL barato.
+/// Devuelve vacío si la consulta tiene menos de 3 caracteres.
+pub fn trigrams(query: &str) -> Vec<String> {
+    let chars: Vec<char> = query.to_lowercase().chars().collect();
+    let mut grams: Vec<String> = Vec::new();
+
+    for window in chars.windows(3) {
+        let gram: String = window.iter().collect();
+        if !grams.contains(&gram) {
+            grams.push(gram);
+        }
+    }
+
+    grams
+}
+
+/// Similitud Jaro-Winkler (0.0..=1.0) entre la consulta y el nombre.
+/// Compara tanto con el nombre completo como sin extensión y se queda con la mejor.
+pub fn similarity(query: &str, name: &str) -> f64 {
+    let query = query.to_lowercase();
+    let name = name.to_lowercase();
+
+    let full = strsim::jaro_winkler(&query, &name);
+    let stem = match name.rfind('.') {
+        Some(idx) if idx > 0 => strsim::jaro_winkler(&query, &name[..idx]),
+        _ => full,
+    };
+
+    full.max(stem)
+}
+
+/// Puntúa los candidatos, descarta los que no alcanzan `threshold` y ordena por score.
+pub fn rank(candidates: Vec<SearchResult>, query: &str, threshold: f64, limit: usize) -> Vec<SearchResult> {
+    let mut ranked: Vec<SearchResult> = candidates
+        .into_iter()
+        .map(|mut result| {
+            result.score = similarity(query, &result.name);
+            result
+        })
+        .filter(|result| result.score >= threshold)
+        .collect();
+
+    ranking::sort_by_score(&mut ranked);
+    ranked.truncate(limit);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str) -> SearchResult {
+        SearchResult {
+            path: format!("/docs/{}", name),
+            name: name.to_string(),
+            extension: None,
+            file_size: None,
+            is_dir: false,
+            modified_time: String::new(),
+            created_time: None,
+            is_reparse: false,
+            link_count: 1,
+            mode: None,
+            owner_uid: None,
+            owner_gid: None,
+            score: 0.0,
+            child_count: None,
+            matched_in_path: false,
+        }
+    }
+
+    #[test]
+    fn typo_matches_and_unrelated_query_does_not() {
+        let candidates = vec![result("document.txt"), result("notes.md")];
+
+        let ranked = rank(candidates.clone(), "documnt", 0.7, 10);
+        let names: Vec<&str> = ranked.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["document.txt"]);
+
+        assert!(rank(candidates, "xyz", 0.7, 10).is_empty());
+    }
+
+    #[test]
+    fn similarity_ignores_extension_and_case() {
+        assert!(similarity("DOCUMENT", "document.txt") > 0.99);
+    }
+
+    #[test]
+    fn trigrams_are_unique_and_need_three_chars() {
+        assert_eq!(trigrams("aaaa"), ["aaa"]);
+        assert!(trigrams("ab").is_empty());
+    }
+}
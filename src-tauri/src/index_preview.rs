@@ -0,0 +1,174 @@
+//! Simulacro de `reindex_path` (`preview_index`): recorre las raíces con los mismos
+//! filtros que el índice real y cuenta lo que se guardaría, sin tocar la base de datos.
+
+use crate::indexer::IndexOptions;
+use crate::types::{ExtensionStats, IndexPreview, IndexRoot};
+use ignore::WalkState;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Extensiones que se devuelven en el desglose, de más a menos bytes.
+const MAX_EXTENSIONS: usize = 100;
+
+#[derive(Default)]
+struct Tally {
+    files: u64,
+    directories: u64,
+    total_bytes: u64,
+    /// Extensión (`.rs`, `None` sin extensión) -> (archivos, bytes).
+    extensions: HashMap<Option<String>, (u64, u64)>,
+}
+
+impl Tally {
+    fn merge(&mut self, other: Tally) {
+        self.files += other.files;
+        self.directories += other.directories;
+        self.total_bytes += other.total_bytes;
+        for (extension, (count, bytes)) in other.extensions {
+            let entry = self.extensions.entry(extension).or_default();
+            entry.0 += count;
+            entry.1 += bytes;
+        }
+    }
+}
+
+/// Cuenta de un hilo del walker; se suma al total cuando el hilo termina.
+struct ThreadTally<'a> {
+    local: Tally,
+    total: &'a Mutex<Tally>,
+}
+
+impl Drop for ThreadTally<'_> {
+    fn drop(&mut self) {
+        let local = std::mem::take(&mut self.local);
+        self.total.lock().unwrap_or_else(|e| e.into_inner()).merge(local);
+    }
+}
+
+/// Recorre `roots` como lo haría el índice (exclusiones, ocultos, archivos de ignore)
+/// y devuelve los totales. Con la MFT el índice real no recorre el disco, pero guarda
+/// las mismas entradas.
+pub fn preview(roots: &[IndexRoot], options: &IndexOptions) -> IndexPreview {
+    let started = Instant::now();
+    let total = Mutex::new(Tally::default());
+
+    for root in roots {
+        let path = Path::new(&root.path);
+        if !path.exists() {
+            continue;
+        }
+        options
+            .walk_builder(path, &root.exclude_patterns, None)
+            .build_parallel()
+            .run(|| {
+                let mut tally = ThreadTally {
+                    local: Tally::default(),
+                    total: &total,
+                };
+                Box::new(move |result| {
+                    let Ok(entry) = result else {
+                        return WalkState::Continue;
+                    };
+                    match entry.file_type() {
+                        Some(file_type) if file_type.is_dir() => tally.local.directories += 1,
+                        Some(file_type) if file_type.is_file() => {
+                            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                            let extension = entry
+                                .path()
+                                .extension()
+                                .map(|e| format!(".{}", e.to_string_lossy()));
+                            tally.local.files += 1;
+                            tally.local.total_bytes += size;
+                            let stats = tally.local.extensions.entry(extension).or_default();
+                            stats.0 += 1;
+                            stats.1 += size;
+                        }
+                        _ => {}
+                    }
+                    WalkState::Continue
+                })
+            });
+    }
+
+    let total = total.into_inner().unwrap_or_else(|e| e.into_inner());
+    let mut extensions: Vec<ExtensionStats> = total
+        .extensions
+        .into_iter()
+        .map(|(extension, (count, bytes))| ExtensionStats {
+            extension,
+            count,
+            bytes,
+        })
+        .collect();
+    extensions.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(b.count.cmp(&a.count)));
+    let extension_count = extensions.len();
+    extensions.truncate(MAX_EXTENSIONS);
+
+    IndexPreview {
+        paths: roots.iter().map(|root| root.path.clone()).collect(),
+        entries: total.files + total.directories,
+        files: total.files,
+        directories: total.directories,
+        total_bytes: total.total_bytes,
+        extensions,
+        extension_count,
+        walk_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::{IndexControl, Indexer};
+    use crate::test_support::{open_index, TempDir};
+    use std::sync::Arc;
+
+    #[test]
+    fn preview_counts_what_the_index_would_store_without_writing() {
+        let dir = TempDir::new("preview");
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        let files = [("src/main.rs", 30), ("src/lib.rs", 70), ("notes", 5), ("target/big.o", 999)];
+        for (file, size) in files {
+            std::fs::write(root.join(file), vec![b'.'; size]).unwrap();
+        }
+        let root_path = root.to_string_lossy().into_owned();
+        let excludes = vec!["target".to_string()];
+        let roots = [IndexRoot {
+            path: root_path.clone(),
+            exclude_patterns: excludes.clone(),
+        }];
+
+        let (db, reader) = open_index(&dir);
+        let options = IndexOptions::default();
+        let estimate = preview(&roots, &options);
+        assert_eq!(reader.get_file_count().unwrap(), 0);
+
+        // La raíz y `src`; sin `target` ni lo que contiene.
+        assert_eq!((estimate.files, estimate.directories, estimate.total_bytes), (3, 2, 105));
+        let extensions: Vec<(Option<&str>, u64, u64)> = estimate
+            .extensions
+            .iter()
+            .map(|stats| (stats.extension.as_deref(), stats.count, stats.bytes))
+            .collect();
+        assert_eq!(extensions, [(Some(".rs"), 2, 100), (None, 1, 5)]);
+
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+            .with_options(options);
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime
+            .block_on(indexer.index_multiple_paths(vec![root_path], excludes, Arc::new(|_| {})))
+            .unwrap();
+        let indexed = reader.get_file_count().unwrap();
+        assert_eq!(indexed as u64, estimate.entries);
+
+        // Con el índice ya lleno, lo nuevo en disco sale en la vista previa pero no se
+        // guarda.
+        std::fs::write(root.join("src/new.rs"), b"").unwrap();
+        assert_eq!(preview(&roots, &IndexOptions::default()).files, 4);
+        assert_eq!(reader.get_file_count().unwrap(), indexed);
+    }
+}
@@ -1,37 +1,717 @@
-use crate::db::Database;
-use crate::mft_indexer::MftIndexer;
-use crate::types::{FileRecord, IndexingProgress};
+use crate::category;
+use crate::interpreter;
+use crate::content;
+use crate::db::{Database, IndexedEntry, UpsertStats};
+use crate::duplicates;
+use crate::dir_tracker::{DirectoryTracker, WalkItem, WalkSender};
+use crate::exclude::ExcludeMatcher;
+use crate::locate_indexer::{self, LocateIndexer};
+use crate::mft_indexer::{MftError, MftIndexer, MftScan};
+use crate::mime::MimeResolver;
+use crate::spotlight_indexer::{self, SpotlightIndexer};
+use crate::progress::ProgressThrottle;
+use crate::rate_limit::RateLimiter;
+use crate::paths;
+use crate::shard::{self, Shards};
+use crate::tags;
+use crate::usn_journal;
+use crate::types::{
+    DirectoryIndexed, FileRecord, IndexLimit, IndexRoot, IndexingProgress, IndexingWarnings,
+    SearchConfig, SyncMode,
+};
 use chrono::{DateTime, Utc};
-use ignore::WalkBuilder;
-use std::path::{Path};
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
 use std::sync::Arc;
-use std::time::Instant;
-use std::sync::Mutex;
-use tracing::{info, warn};
+use std::time::{Duration, Instant, SystemTime};
+use std::sync::{Condvar, Mutex};
+use tracing::{debug, info, warn};
+
+/// Claves de `meta` con el inicio del último índice completo / refresco rápido terminados.
+pub const META_LAST_FULL_INDEX: &str = "last_full_index";
+pub const META_LAST_REFRESH: &str = "last_refresh";
+/// Inicio de la última ejecución cortada por `max_files` / `max_duration`; se borra
+/// al completar un índice completo.
+pub const META_INCOMPLETE_RUN: &str = "incomplete_run";
+/// Prefijos de `meta` (seguidos de la ruta) con el inicio del último recorrido completo
+/// de cada raíz y del último que terminó; si difieren, el último se interrumpió.
+pub const META_ROOT_STARTED: &str = "root_started:";
+pub const META_ROOT_COMPLETED: &str = "root_completed:";
+
+/// Cada cuánto mira el escritor si hay una pausa cuando no le llegan registros.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Estado compartido entre los índices en curso y el resto de la app:
+/// permite pedir que se detengan o pausen y esperar a que hayan guardado su último lote.
+pub struct IndexControl {
+    stop: AtomicBool,
+    cancel: AtomicBool,
+    running: Mutex<usize>,
+    idle: Condvar,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+impl IndexControl {
+    pub fn new() -> Self {
+        Self {
+            stop: AtomicBool::new(false),
+            cancel: AtomicBool::new(false),
+            running: Mutex::new(0),
+            idle: Condvar::new(),
+            paused: Mutex::new(false),
+            resumed: Condvar::new(),
+        }
+    }
+
+    /// Pide a los índices en curso que paren. Devuelve `true` si ya se había pedido.
+    /// A diferencia de `cancel`, es definitivo (se usa al cerrar la app).
+    pub fn request_stop(&self) -> bool {
+        let already = self.stop.swap(true, Ordering::SeqCst);
+        self.wake_paused();
+        already
+    }
+
+    /// Cancela los índices en curso; el siguiente índice que empiece lo ignora.
+    /// Devuelve `false` si no había nada que cancelar.
+    pub fn cancel(&self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        self.cancel.store(true, Ordering::SeqCst);
+        self.wake_paused();
+        true
+    }
+
+    /// Pausa los índices en curso: los recorridos se quedan esperando en `wait_if_paused`
+    /// y el escritor guarda el lote pendiente. Al reanudar sigue el mismo recorrido en
+    /// memoria (no hay cursor persistente: si la app se cierra, el siguiente índice empieza
+    /// de cero). El tiempo en pausa cuenta para `index_max_duration_secs`.
+    /// Devuelve `false` si no había índice activo o ya estaba en pausa.
+    pub fn pause(&self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        let mut paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        !std::mem::replace(&mut *paused, true)
+    }
+
+    /// Reanuda tras `pause`. Devuelve `false` si no estaba en pausa.
+    pub fn resume(&self) -> bool {
+        let mut paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        let was_paused = std::mem::replace(&mut *paused, false);
+        self.resumed.notify_all();
+        was_paused
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Bloquea mientras haya una pausa, salvo que se pida parar o cancelar.
+    pub fn wait_if_paused(&self) {
+        let paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        let _paused = self
+            .resumed
+            .wait_while(paused, |paused| *paused && !self.should_stop())
+            .unwrap_or_else(|e| e.into_inner());
+    }
+
+    /// Despierta a los hilos en pausa para que vean `stop` / `cancel`. Se toma el mutex
+    /// para no perder el aviso entre su comprobación y su espera.
+    fn wake_paused(&self) {
+        let _paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+        self.resumed.notify_all();
+    }
+
+    /// El último índice terminó por una cancelación.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    pub fn should_stop(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Hay al menos un índice en curso.
+    pub fn is_running(&self) -> bool {
+        *self.running.lock().unwrap_or_else(|e| e.into_inner()) > 0
+    }
+
+    /// Marca un índice como activo hasta que se suelte el guard devuelto
+    /// (también si el índice termina con error o panic).
+    pub fn begin(self: &Arc<Self>) -> RunGuard {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        self.enter(&mut running)
+    }
+
+    /// Como `begin`, pero solo si no hay nada en curso: la comprobación y la marca van
+    /// bajo el mismo bloqueo, así que de dos llamadas simultáneas solo una lo consigue.
+    /// Los comandos lo toman antes de lanzar su tarea y la mueven dentro.
+    pub fn try_begin(self: &Arc<Self>) -> Option<RunGuard> {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        if *running > 0 {
+            return None;
+        }
+        Some(self.enter(&mut running))
+    }
+
+    fn enter(self: &Arc<Self>, running: &mut usize) -> RunGuard {
+        if *running == 0 {
+            self.cancel.store(false, Ordering::SeqCst);
+            *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = false;
+        }
+        *running += 1;
+        RunGuard {
+            control: Arc::clone(self),
+        }
+    }
+
+    /// Espera a que no quede ningún índice activo. Devuelve `false` si vence el timeout.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        let (running, _) = self
+            .idle
+            .wait_timeout_while(running, timeout, |count| *count > 0)
+            .unwrap_or_else(|e| e.into_inner());
+        *running == 0
+    }
+}
+
+pub struct RunGuard {
+    control: Arc<IndexControl>,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        let mut running = self.control.running.lock().unwrap_or_else(|e| e.into_inner());
+        *running = running.saturating_sub(1);
+        self.control.idle.notify_all();
+    }
+}
+
+/// Límites de seguridad de una ejecución (todas sus rutas), compartidos con el lector MFT.
+pub struct RunLimits {
+    max_files: Option<usize>,
+    max_duration: Option<Duration>,
+    started: Mutex<Instant>,
+    files: AtomicUsize,
+    hit: AtomicBool,
+    reached: Mutex<Option<IndexLimit>>,
+    /// `max_depth` dejó directorios sin recorrer (no detiene la ejecución).
+    depth_cut: AtomicBool,
+}
+
+impl RunLimits {
+    pub fn new(max_files: Option<usize>, max_duration: Option<Duration>) -> Self {
+        Self {
+            max_files,
+            max_duration,
+            started: Mutex::new(Instant::now()),
+            files: AtomicUsize::new(0),
+            hit: AtomicBool::new(false),
+            reached: Mutex::new(None),
+            depth_cut: AtomicBool::new(false),
+        }
+    }
+
+    /// Vuelve a contar desde cero (al empezar una ejecución).
+    pub fn restart(&self) {
+        *self.started.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        self.files.store(0, Ordering::SeqCst);
+        self.hit.store(false, Ordering::SeqCst);
+        *self.reached.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.depth_cut.store(false, Ordering::SeqCst);
+    }
+
+    /// Suma `count` entradas. Devuelve `true` si la ejecución debe parar.
+    pub fn count(&self, count: usize) -> bool {
+        if self.is_reached() {
+            return true;
+        }
+        let files = self.files.fetch_add(count, Ordering::Relaxed) + count;
+        let limit = if self.max_files.is_some_and(|max| files >= max) {
+            IndexLimit::MaxFiles
+        } else if self.duration_exceeded() {
+            IndexLimit::MaxDuration
+        } else {
+            return false;
+        };
+        self.reach(limit, files);
+        true
+    }
+
+    /// Como `count(1)` pero antes de guardar la entrada: `false` si ya no cabe y no hay
+    /// que guardarla. Con varios hilos del walker, nunca se admiten más de `max_files`.
+    pub fn admit(&self) -> bool {
+        if self.is_reached() {
+            return false;
+        }
+        let files = self.files.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.max_files {
+            Some(max) if files > max => {
+                self.reach(IndexLimit::MaxFiles, max);
+                return false;
+            }
+            // La última que cabe entra y detiene la ejecución.
+            Some(max) if files == max => self.reach(IndexLimit::MaxFiles, files),
+            _ if self.duration_exceeded() => self.reach(IndexLimit::MaxDuration, files),
+            _ => {}
+        }
+        true
+    }
+
+    fn duration_exceeded(&self) -> bool {
+        self.max_duration.is_some_and(|max| {
+            self.started.lock().unwrap_or_else(|e| e.into_inner()).elapsed() >= max
+        })
+    }
+
+    fn reach(&self, limit: IndexLimit, files: usize) {
+        let mut reached = self.reached.lock().unwrap_or_else(|e| e.into_inner());
+        if reached.is_none() {
+            warn!("Indexing limit {:?} reached after {} entries", limit, files);
+            *reached = Some(limit);
+        }
+        self.hit.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.hit.load(Ordering::Relaxed)
+    }
+
+    /// `max_depth` dejó fuera el contenido de algún directorio.
+    pub fn depth_cut(&self) {
+        self.depth_cut.store(true, Ordering::Relaxed);
+    }
+
+    fn is_depth_cut(&self) -> bool {
+        self.depth_cut.load(Ordering::Relaxed)
+    }
+
+    /// Límite que detuvo o recortó la ejecución, si alguno.
+    pub fn reached(&self) -> Option<IndexLimit> {
+        let reached = *self.reached.lock().unwrap_or_else(|e| e.into_inner());
+        reached.or(self.is_depth_cut().then_some(IndexLimit::MaxDepth))
+    }
+}
+
+/// Entradas por transacción si no se configura otra (`index_batch_size`).
+pub const DEFAULT_BATCH_SIZE: usize = 5_000;
+
+/// Cuándo guardar lo acumulado: al llenarse el lote o, con `interval`, si pasó ese
+/// tiempo desde el último guardado (un árbol pequeño y lento se ve antes en la base).
+pub struct FlushPolicy {
+    batch_size: usize,
+    interval: Option<Duration>,
+    last_flush: Instant,
+}
+
+impl FlushPolicy {
+    pub fn new(batch_size: usize, interval: Option<Duration>) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            interval,
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Toca guardar las `pending` entradas acumuladas.
+    pub fn is_due(&self, pending: usize) -> bool {
+        let overdue = self
+            .interval
+            .is_some_and(|interval| self.last_flush.elapsed() >= interval);
+        pending >= self.batch_size || (pending > 0 && overdue)
+    }
+
+    /// Se acaba de guardar: el plazo vuelve a empezar.
+    pub fn flushed(&mut self) {
+        self.last_flush = Instant::now();
+    }
+}
+
+/// Opciones del indexador derivadas de `SearchConfig`.
+#[derive(Debug, Clone, Default)]
+pub struct IndexOptions {
+    /// Atributos extendidos que se guardan como etiquetas (vacío = no leerlos).
+    pub tag_attributes: Vec<String>,
+    /// Borra las entradas que no aparecieron al terminar un recorrido completo.
+    pub prune_after_reindex: bool,
+    /// Solo guarda entradas modificadas después de este instante (refresco rápido).
+    /// El recorrido no es completo, así que no se poda ni cuenta como índice completo.
+    pub modified_since: Option<SystemTime>,
+    /// Clave de `meta` donde guardar el inicio de la ejecución si termina completa
+    /// (solo para recorridos de todas las raíces configuradas).
+    pub record_run_as: Option<&'static str>,
+    /// Límite de registros al leer la MFT (`None` = sin límite).
+    pub mft_max_records: Option<usize>,
+    /// Entradas por ejecución antes de parar (`None` = sin límite).
+    pub max_files: Option<usize>,
+    /// Duración máxima de una ejecución (`None` = sin límite).
+    pub max_duration: Option<Duration>,
+    /// Extensiones cuyo texto se indexa (vacío = no indexar contenido).
+    pub content_extensions: Vec<String>,
+    pub content_max_bytes: u64,
+    /// Guarda en `file_size` de cada directorio la suma de sus archivos (recorrido completo).
+    pub compute_dir_sizes: bool,
+    /// Los enlaces duros de un archivo suman su tamaño una sola vez (ver `file_id`).
+    pub dedupe_hardlinks: bool,
+    /// Las unidades se indexan en sus shards (ver `Indexer::with_shards`).
+    pub shard_by_drive: bool,
+    /// Cuenta las entradas antes del recorrido para dar `total_files` en el progreso.
+    pub precount_entries: bool,
+    pub index_hidden: bool,
+    pub respect_gitignore: bool,
+    pub respect_ignore_files: bool,
+    /// Tamaño máximo para leer el shebang (`None` = no leerlo).
+    pub interpreter_max_bytes: Option<u64>,
+    pub follow_symlinks: bool,
+    pub use_spotlight: bool,
+    pub use_locate_db: bool,
+    pub locate_refresh_walk: bool,
+    /// Avisa de los directorios terminados (ver `Indexer::with_directory_callback`).
+    pub directory_events: bool,
+    /// `Off` activa el modo rápido de la base en los recorridos completos.
+    pub synchronous: SyncMode,
+    /// Resolución de tipos MIME compartida por todo el índice (`None` = no guardarlos).
+    pub mime: Option<Arc<MimeResolver>>,
+    pub follow_usn_journal: bool,
+    /// Tamaño máximo de los archivos cuyo contenido se compara al terminar (`None` = no
+    /// calcular hashes; ver `duplicates`).
+    pub duplicate_max_bytes: Option<u64>,
+    /// Salta los archivos ya indexados con el mismo mtime y tamaño.
+    pub incremental: bool,
+    /// Profundidad máxima del recorrido bajo cada raíz (`Some(1)`: solo sus hijos
+    /// directos). Un recorrido limitado no es completo: no se poda ni usa la MFT,
+    /// Spotlight o locate, que no saben limitarse.
+    pub max_depth: Option<usize>,
+    /// Emite el progreso cada tantas entradas (0 = sin límite por cantidad; ver `progress`).
+    pub progress_every_files: u64,
+    /// Emite el progreso si pasó este tiempo desde el último aviso.
+    pub progress_interval: Option<Duration>,
+    /// Entradas por transacción (`None` = `DEFAULT_BATCH_SIZE`).
+    pub batch_size: Option<usize>,
+    /// Guarda lo acumulado al menos con esta frecuencia (`None` = solo con el lote lleno).
+    pub flush_interval: Option<Duration>,
+    /// Hilos del walker (0 = los que elija `ignore`).
+    pub threads: usize,
+    /// Entradas por segundo que acepta el escritor del recorrido; con el canal lleno,
+    /// los hilos del walker esperan.
+    pub max_files_per_sec: Option<f64>,
+}
+
+impl IndexOptions {
+    /// El recorrido ve todo lo que hay bajo cada raíz: puede podar lo que no aparezca
+    /// y contar como cobertura.
+    pub fn full_walk(&self) -> bool {
+        self.modified_since.is_none() && self.max_depth.is_none()
+    }
+
+    /// Opciones de "indexar esta carpeta ahora": sin `recursive`, solo los hijos
+    /// directos (y, como con cualquier `max_depth`, sin podar).
+    pub fn single_directory(self, recursive: bool) -> Self {
+        Self {
+            max_depth: (!recursive).then_some(1),
+            ..self
+        }
+    }
+
+    pub fn flush_policy(&self) -> FlushPolicy {
+        FlushPolicy::new(self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE), self.flush_interval)
+    }
+
+    pub fn from_config(config: &SearchConfig) -> Self {
+        Self {
+            tag_attributes: if config.index_tags {
+                config.tag_attributes.clone()
+            } else {
+                Vec::new()
+            },
+            prune_after_reindex: config.prune_after_reindex,
+            modified_since: None,
+            record_run_as: None,
+            mft_max_records: (config.mft_max_records > 0).then_some(config.mft_max_records),
+            max_files: (config.index_max_files > 0).then_some(config.index_max_files),
+            max_duration: (config.index_max_duration_secs > 0)
+                .then(|| Duration::from_secs(config.index_max_duration_secs)),
+            content_extensions: if config.index_content {
+                config.content_extensions.clone()
+            } else {
+                Vec::new()
+            },
+            content_max_bytes: config.content_max_bytes,
+            compute_dir_sizes: config.compute_dir_sizes,
+            dedupe_hardlinks: config.dedupe_hardlinks,
+            shard_by_drive: config.shard_by_drive,
+            precount_entries: config.precount_entries,
+            index_hidden: config.index_hidden,
+            respect_gitignore: config.respect_gitignore,
+            respect_ignore_files: config.respect_ignore_files,
+            interpreter_max_bytes: config
+                .index_interpreters
+                .then_some(config.interpreter_max_bytes),
+            follow_symlinks: config.follow_symlinks,
+            use_spotlight: config.use_spotlight,
+            use_locate_db: config.use_locate_db,
+            locate_refresh_walk: config.locate_refresh_walk,
+            directory_events: config.emit_directory_events,
+            synchronous: config.db_synchronous,
+            mime: config
+                .index_mime_types
+                .then(|| Arc::new(MimeResolver::default())),
+            follow_usn_journal: config.follow_usn_journal,
+            duplicate_max_bytes: config.hash_duplicates.then_some(config.duplicate_max_bytes),
+            incremental: config.incremental_reindex,
+            max_depth: (config.index_max_depth > 0).then_some(config.index_max_depth),
+            progress_every_files: config.progress_every_files,
+            progress_interval: (config.progress_interval_ms > 0)
+                .then(|| Duration::from_millis(config.progress_interval_ms)),
+            batch_size: (config.index_batch_size > 0).then_some(config.index_batch_size),
+            flush_interval: (config.index_flush_interval_secs > 0)
+                .then(|| Duration::from_secs(config.index_flush_interval_secs)),
+            threads: config.index_threads,
+            max_files_per_sec: (config.index_max_files_per_sec > 0.0)
+                .then_some(config.index_max_files_per_sec),
+        }
+    }
+
+    /// Recorrido de `path` con los filtros del índice: ocultos, archivos de ignore,
+    /// enlaces simbólicos y `exclude_patterns`. `tracker` recibe cada entrada que se
+    /// va a visitar.
+    pub fn walk_builder(
+        &self,
+        path: &Path,
+        exclude_patterns: &[String],
+        tracker: Option<Arc<DirectoryTracker>>,
+    ) -> WalkBuilder {
+        let mut walk = WalkBuilder::new(path);
+        walk.hidden(!self.index_hidden)
+            .git_ignore(self.respect_gitignore)
+            .git_global(self.respect_gitignore)
+            .git_exclude(self.respect_gitignore)
+            .ignore(self.respect_ignore_files)
+            .follow_links(self.follow_symlinks)
+            .max_depth(self.max_depth)
+            .threads(self.threads);
+
+        // `filter_entry` solo conserva el último closure que se le pasa: todos los
+        // patrones (y la comprobación de ciclos) tienen que ir en un único closure.
+        let excludes = ExcludeMatcher::new(exclude_patterns);
+        let visited = self.follow_symlinks.then(|| Mutex::new(HashSet::new()));
+        walk.filter_entry(move |entry| {
+            let keep = !excludes.is_excluded(entry.path())
+                && visited.as_ref().is_none_or(|visited| first_visit(visited, entry));
+            if let Some(tracker) = tracker.as_ref().filter(|_| keep) {
+                tracker.queued(entry.path());
+            }
+            keep
+        });
+        walk
+    }
+
+    /// Completa los campos opcionales de `record` que dependen de estas opciones.
+    pub fn enrich(&self, record: &mut FileRecord, path: &Path) {
+        record.tags = tags::read_tags(path, &self.tag_attributes);
+        if record.category.is_none() && !record.is_dir {
+            record.category = category::sniff(path).map(String::from);
+        }
+        if let Some(max_bytes) = self.interpreter_max_bytes {
+            let candidate = !record.is_dir
+                && record.file_size.is_some_and(|size| size as u64 <= max_bytes)
+                && interpreter::is_script_candidate(record.extension.as_deref());
+            if candidate {
+                record.interpreter = interpreter::read_interpreter(path);
+            }
+        }
+        if let Some(resolver) = self.mime.as_ref().filter(|_| !record.is_dir) {
+            record.mime = record.extension.as_deref().and_then(|ext| resolver.resolve(ext));
+        }
+        if !self.content_extensions.is_empty() {
+            record.content = Some(self.read_content(record, path).unwrap_or_default());
+        }
+    }
+
+    /// Registro de `path` como lo haría el walker, para los índices que parten de una
+    /// lista de rutas (Spotlight, locate); `None` si ya no existe o no entra en un
+    /// refresco rápido.
+    pub fn path_record(&self, path: &Path) -> Option<FileRecord> {
+        let metadata = std::fs::symlink_metadata(path).ok()?;
+        if let Some(since) = self.modified_since {
+            if metadata.modified().is_ok_and(|modified| modified <= since) {
+                return None;
+            }
+        }
+        let mut record = Indexer::file_record(path, &metadata);
+        self.enrich(&mut record, path);
+        Some(record)
+    }
+
+    /// `true` si el walker saltaría `path` por oculto: algún componente bajo `root`
+    /// empieza por `.`.
+    pub fn skips_hidden(&self, path: &Path, root: &Path) -> bool {
+        !self.index_hidden
+            && path.strip_prefix(root).unwrap_or(path).components().any(|component| {
+                matches!(component, Component::Normal(name) if name.to_string_lossy().starts_with('.'))
+            })
+    }
+
+    /// Texto de `record` si su extensión y tamaño lo permiten.
+    fn read_content(&self, record: &FileRecord, path: &Path) -> Option<String> {
+        let extension = record.extension.as_deref()?;
+        let wanted = self
+            .content_extensions
+            .iter()
+            .any(|ext| ext.eq_ignore_ascii_case(extension));
+        let size = record.file_size? as u64;
+        if record.is_dir || !wanted || size > self.content_max_bytes {
+            return None;
+        }
+        content::read_text(path, self.content_max_bytes)
+    }
+}
+
+/// Rutas de ejemplo que se guardan en `IndexingWarnings::sample_paths`.
+const WARNING_SAMPLE_PATHS: usize = 20;
+
+/// Lo que el recorrido no pudo leer en una ejecución (todas sus rutas). Sin esto los
+/// directorios sin permiso se saltaban en silencio.
+#[derive(Default)]
+pub struct AccessLog {
+    warnings: Mutex<IndexingWarnings>,
+}
+
+impl AccessLog {
+    fn lock(&self) -> std::sync::MutexGuard<'_, IndexingWarnings> {
+        self.warnings.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn restart(&self) {
+        *self.lock() = IndexingWarnings::default();
+    }
+
+    /// Error del walker: una entrada que no se pudo leer (y, si es un directorio,
+    /// todo lo que contiene).
+    fn record(&self, err: &ignore::Error) {
+        let denied = err
+            .io_error()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied);
+        let mut warnings = self.lock();
+        if denied {
+            warnings.permission_denied += 1;
+        } else {
+            warnings.other_errors += 1;
+        }
+        if warnings.sample_paths.len() < WARNING_SAMPLE_PATHS {
+            if let Some(path) = error_path(err) {
+                warnings.sample_paths.push(path.to_string_lossy().into_owned());
+            }
+        }
+        debug!("Walk error: {}", err);
+    }
+
+    /// No se pudo abrir el volumen de `drive` para leer su MFT.
+    fn mft_denied(&self, drive: &str, elevated: bool) {
+        let mut warnings = self.lock();
+        warnings.mft_access_denied.push(drive.to_string());
+        warnings.requires_elevation |= !elevated;
+    }
+
+    /// `None` si no hubo nada que avisar.
+    pub fn summary(&self) -> Option<IndexingWarnings> {
+        let warnings = self.lock();
+        let empty = warnings.permission_denied == 0
+            && warnings.other_errors == 0
+            && warnings.mft_access_denied.is_empty();
+        (!empty).then(|| warnings.clone())
+    }
+}
+
+/// Ruta a la que se refiere un error del walker, si la lleva.
+fn error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => error_path(err),
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::Partial(errors) => errors.iter().find_map(error_path),
+        _ => None,
+    }
+}
+
+/// Raíces a indexar en cada shard, por unidad.
+type ShardedPaths = BTreeMap<String, Vec<IndexRoot>>;
+
+/// Recibe los directorios terminados, agrupados, después de guardarlos.
+pub type DirectoryCallback = Arc<dyn Fn(Vec<DirectoryIndexed>) + Send + Sync>;
 
 pub struct Indexer {
     db: Arc<Mutex<Database>>,
+    control: Arc<IndexControl>,
+    options: IndexOptions,
+    limits: Arc<RunLimits>,
+    access: Arc<AccessLog>,
+    shards: Option<Arc<Shards>>,
+    directory_callback: Option<DirectoryCallback>,
 }
 
 impl Indexer {
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Mutex<Database>>, control: Arc<IndexControl>) -> Self {
+        Self {
+            db,
+            control,
+            options: IndexOptions::default(),
+            limits: Arc::new(RunLimits::new(None, None)),
+            access: Arc::new(AccessLog::default()),
+            shards: None,
+            directory_callback: None,
+        }
+    }
+
+    /// Shards por unidad: con `shard_by_drive` cada unidad se indexa en el suyo.
+    pub fn with_shards(mut self, shards: Arc<Shards>) -> Self {
+        self.shards = Some(shards);
+        self
+    }
+
+    /// Con `directory_events`, el recorrido avisa a `callback` de cada directorio cuyos
+    /// hijos directos ya están guardados (no aplica a la MFT ni a Spotlight).
+    pub fn with_directory_callback(mut self, callback: DirectoryCallback) -> Self {
+        self.directory_callback = Some(callback);
+        self
+    }
+
+    pub fn with_options(mut self, options: IndexOptions) -> Self {
+        self.limits = Arc::new(RunLimits::new(options.max_files, options.max_duration));
+        self.options = options;
+        self
+    }
+
+    /// Límite que cortó la última ejecución de `index_multiple_paths`, si alguno.
+    pub fn limit_reached(&self) -> Option<IndexLimit> {
+        self.limits.reached()
+    }
+
+    /// Entradas que la última ejecución no pudo leer, si alguna.
+    pub fn access_warnings(&self) -> Option<IndexingWarnings> {
+        self.access.summary()
     }
 
     fn is_windows_drive(path: &str) -> bool {
-        #[cfg(windows)]
-        {
-            let path_upper = path.to_uppercase();
-            path_upper.len() == 3 && path_upper.chars().nth(1) == Some(':')
-                && path_upper.chars().nth(2) == Some('\\')
-        }
-        #[cfg(not(windows))]
-        {
-            false
-        }
+        let path_upper = path.to_uppercase();
+        cfg!(windows)
+            && path_upper.len() == 3
+            && path_upper.chars().nth(1) == Some(':')
+            && path_upper.chars().nth(2) == Some('\\')
     }
 
-    fn can_use_mft(path: &str) -> bool {
+    pub(crate) fn can_use_mft(path: &str) -> bool {
         if !Self::is_windows_drive(path) {
             return false;
         }
@@ -55,27 +735,91 @@ impl Indexer {
         progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
     ) -> Result<usize, Box<dyn std::error::Error>> {
         info!("Starting indexing of path: {}", path);
+        // Todo lo que se toque en este recorrido tendrá `last_indexed` >= este instante.
+        let run_start = Utc::now().to_rfc3339();
 
-        if Self::is_windows_drive(path) && Self::can_use_mft(path) {
+        let depth_limited = self.options.max_depth.is_some();
+        if Self::is_windows_drive(path) && !depth_limited {
             info!("Attempting MFT indexing for drive: {}", path);
             let drive = path.chars().next().unwrap();
-            let mft_indexer = MftIndexer::new(Arc::clone(&self.db));
+            let mft_indexer = MftIndexer::new(Arc::clone(&self.db), Arc::clone(&self.control))
+                .with_max_records(self.options.mft_max_records)
+                .with_batching(
+                    self.options.batch_size.unwrap_or(DEFAULT_BATCH_SIZE),
+                    self.options.flush_interval,
+                )
+                .with_limits(Arc::clone(&self.limits));
             match mft_indexer
                 .index_drive(&drive.to_string(), progress_callback.clone())
                 .await
             {
+                Ok(scan) => return self.finish_mft_scan(path, &run_start, scan),
+                Err(e) => {
+                    warn!("MFT indexing failed: {}. Falling back to filesystem walk.", e);
+                    if let Some(MftError::AccessDenied { elevated, .. }) = e.downcast_ref::<MftError>() {
+                        self.access.mft_denied(path, *elevated);
+                    }
+                }
+            }
+        }
+
+        if self.options.use_spotlight && !depth_limited && spotlight_indexer::is_available(path) {
+            info!("Attempting Spotlight indexing for path: {}", path);
+            let spotlight = SpotlightIndexer::new(
+                Arc::clone(&self.db),
+                Arc::clone(&self.control),
+                Arc::clone(&self.limits),
+                self.options.clone(),
+            );
+            match spotlight.index_path(path, &exclude_patterns, progress_callback.clone()) {
+                // Spotlight no ve todo lo que hay en disco: no se poda con su listado.
                 Ok(count) => {
-                    info!("MFT indexing successful: {} files", count);
+                    if self.options.compute_dir_sizes {
+                        self.store_dir_sizes(path, None)?;
+                    }
                     return Ok(count);
                 }
                 Err(e) => {
-                    warn!("MFT indexing failed: {}. Falling back to filesystem walk.", e);
+                    warn!("Spotlight indexing failed: {}. Falling back to filesystem walk.", e);
+                }
+            }
+        }
+
+        let use_locate_db = self.options.use_locate_db && !depth_limited;
+        if let Some(database) = use_locate_db.then(locate_indexer::find_database).flatten() {
+            info!("Attempting locate indexing for path: {}", path);
+            let locate = LocateIndexer::new(
+                Arc::clone(&self.db),
+                Arc::clone(&self.control),
+                Arc::clone(&self.limits),
+                self.options.clone(),
+            );
+            match locate.index_path(&database, path, &exclude_patterns, progress_callback.clone()) {
+                // La base refleja el último `updatedb`, no el disco: no se poda con ella.
+                Ok(count) if !self.options.locate_refresh_walk => {
+                    if self.options.compute_dir_sizes {
+                        self.store_dir_sizes(path, None)?;
+                    }
+                    return Ok(count);
+                }
+                // El índice ya sirve para buscar; el recorrido añade lo que falte y poda.
+                Ok(count) => {
+                    info!("Seeded {} entries from locate database. Refreshing with filesystem walk.", count);
+                    if self.control.should_stop() || self.limits.is_reached() {
+                        return Ok(count);
+                    }
+                }
+                Err(e) => {
+                    warn!("Locate indexing failed: {}. Falling back to filesystem walk.", e);
                 }
             }
         }
 
         info!("Using filesystem walk for path: {}", path);
         let start = Instant::now();
+        // Lo sembrado desde locate ya tiene `last_indexed` de esta ejecución: la poda
+        // solo respeta lo que vea el recorrido.
+        let run_start = Utc::now().to_rfc3339();
 
         let path_obj = Path::new(path);
 
@@ -83,29 +827,34 @@ impl Indexer {
             return Err(format!("Path does not exist: {}", path).into());
         }
 
-        let mut walk = WalkBuilder::new(path_obj);
-        walk.hidden(true);
-
-        for pattern in &exclude_patterns {
-            let pattern = pattern.clone();
-            walk.filter_entry(move |entry| {
-                let path_str = entry.path().to_string_lossy();
-                !path_str.contains(&pattern)
-            });
-        }
-
-        let walker = walk.build();
+        let total_files = if self.options.precount_entries {
+            self.count_entries(path_obj, &exclude_patterns)
+        } else {
+            None
+        };
+        let tracker = self
+            .directory_callback
+            .as_ref()
+            .filter(|_| self.options.directory_events)
+            .map(|_| Arc::new(DirectoryTracker::default()));
+        let walker = self
+            .options
+            .walk_builder(path_obj, &exclude_patterns, tracker.clone())
+            .build_parallel();
 
-        const BATCH_SIZE: usize = 5_000;
-        let mut batch_buffer: Vec<FileRecord> = Vec::with_capacity(BATCH_SIZE);
+        let mut flush = self.options.flush_policy();
+        let mut batch_buffer: Vec<FileRecord> = Vec::with_capacity(flush.batch_size());
 
         // "Procesados" (para progreso) vs "persistidos" (para retorno).
-        let mut processed = 0usize;
-        let mut persisted = 0usize;
+        // Los hilos del walker incrementan los contadores; este hilo es el único escritor.
+        let processed = AtomicUsize::new(0);
+        let mut stats = UpsertStats::default();
+        // Rutas que no son UTF-8 válido y se guardaron con `to_string_lossy`.
+        let lossy_paths = AtomicUsize::new(0);
 
-        let flush_batch = |batch: &mut Vec<FileRecord>| -> Result<usize, Box<dyn std::error::Error>> {
+        let flush_batch = |batch: &mut Vec<FileRecord>| -> Result<UpsertStats, Box<dyn std::error::Error>> {
             if batch.is_empty() {
-                return Ok(0);
+                return Ok(UpsertStats::default());
             }
 
             let mut db_guard = self
@@ -116,162 +865,713 @@ impl Indexer {
             let batch_len = batch.len();
 
             match db_guard.upsert_batch(batch.as_slice()) {
-                Ok(()) => {
+                Ok(stats) => {
                     batch.clear();
-                    Ok(batch_len)
+                    Ok(stats)
                 }
                 Err(e) => {
                     warn!("Batch upsert falló ({} items): {}. Haciendo fallback item-por-item.", batch_len, e);
 
-                    let mut ok_count = 0usize;
+                    let mut stats = UpsertStats::default();
                     for r in batch.iter() {
-                        if let Err(item_err) = db_guard.upsert_file(
-                            r.path.as_str(),
-                            r.name.as_str(),
-                            r.extension.as_deref(),
-                            r.file_size,
-                            r.is_dir,
-                            r.modified_time.as_str(),
-                            r.last_indexed.as_str(),
-                        ) {
-                            warn!("Failed to upsert {}: {}", r.path, item_err);
-                        } else {
-                            ok_count += 1;
+                        match db_guard.upsert_file(r) {
+                            Ok(outcome) => stats.record(outcome),
+                            Err(item_err) => warn!("Failed to upsert {}: {}", r.path, item_err),
                         }
                     }
 
                     batch.clear();
-                    Ok(ok_count)
+                    Ok(stats)
                 }
             }
         };
 
-        for result in walker {
-            if let Ok(entry) = result {
-                if entry.file_type().map_or(false, |ft| ft.is_dir()) {
-                    if let Some(path_str) = entry.path().to_str() {
-                        if let Some(name) = entry.file_name().to_str() {
-                            let modified_time: DateTime<Utc> = Utc::now();
-                            let modified_time_str = modified_time.to_rfc3339();
-                            let last_indexed_str = Utc::now().to_rfc3339();
-
-                            batch_buffer.push(FileRecord {
-                                path: path_str.to_string(),
-                                name: name.to_string(),
-                                extension: None,
-                                file_size: None,
-                                is_dir: true,
-                                modified_time: modified_time_str,
-                                last_indexed: last_indexed_str,
-                            });
-
-                            processed += 1;
-                            progress_callback(IndexingProgress {
-                                current_path: path_str.to_string(),
-                                files_processed: processed,
-                                total_files: None,
-                                status: "indexing".to_string(),
-                            });
-
-                            if batch_buffer.len() >= BATCH_SIZE {
-                                persisted += flush_batch(&mut batch_buffer)?;
+        // Lo ya indexado, para saltar lo que no cambió (una sola consulta para todo).
+        let known: Option<HashMap<String, IndexedEntry>> =
+            if self.options.incremental && self.options.modified_since.is_none() {
+                let known = self
+                    .db
+                    .lock()
+                    .map_err(|e| format!("Failed to lock database: {}", e))?
+                    .indexed_entries(path)?;
+                info!("Loaded {} indexed entries under {} for incremental reindex", known.len(), path);
+                Some(known)
+            } else {
+                None
+            };
+        // Rutas sin cambios pendientes de marcar como vistas.
+        let mut unchanged: Vec<String> = Vec::new();
+        let touch_unchanged = |paths: &mut Vec<String>| -> Result<usize, Box<dyn std::error::Error>> {
+            if paths.is_empty() {
+                return Ok(0);
+            }
+            let touched = self
+                .db
+                .lock()
+                .map_err(|e| format!("Failed to lock database: {}", e))?
+                .touch_paths(paths, &Utc::now().to_rfc3339())?;
+            paths.clear();
+            Ok(touched)
+        };
+
+        // Tamaño acumulado por directorio; cada archivo suma en todos sus ancestros
+        // hasta `path`, así que basta una pasada (O(archivos × profundidad)). En modo
+        // incremental faltarían los que no cambiaron: se recalcula desde el índice.
+        let mut dir_sizes: Option<HashMap<String, i64>> =
+            (self.options.compute_dir_sizes && known.is_none()).then(HashMap::new);
+        // Archivos con varios enlaces duros que ya sumaron (`dedupe_hardlinks`).
+        let mut seen_links: Option<HashSet<String>> =
+            self.options.dedupe_hardlinks.then(HashSet::new);
+
+        // Directorios terminados cuyos hijos aún no se han guardado.
+        let mut completed: Vec<DirectoryIndexed> = Vec::new();
+
+        let (tx, rx) = mpsc::sync_channel::<WalkItem>(flush.batch_size() * 2);
+        // `index_max_files_per_sec`: el escritor espera su turno por cada entrada y el
+        // canal lleno frena a los hilos del walker. Ráfaga de como mucho un segundo.
+        let throttle = self.options.max_files_per_sec.map(|rate| (RateLimiter::new(), rate));
+        let wait_turn = || {
+            if let Some((limiter, rate)) = &throttle {
+                limiter.acquire(*rate, rate.ceil() as u32, || self.control.should_stop());
+            }
+        };
+        let report_progress = |current_path: String, stats: &UpsertStats| {
+            progress_callback(IndexingProgress {
+                current_path,
+                files_processed: processed.load(Ordering::Relaxed),
+                total_files,
+                status: "indexing".to_string(),
+                operation_id: None,
+                inserted: stats.inserted,
+                updated: stats.updated,
+                skipped: stats.skipped,
+            });
+        };
+
+        std::thread::scope(|scope| -> Result<(), Box<dyn std::error::Error>> {
+            scope.spawn(|| {
+                walker.run(|| {
+                    let mut sender = WalkSender::new(tx.clone(), tracker.clone());
+                    let processed = &processed;
+                    let lossy_paths = &lossy_paths;
+                    let known = &known;
+                    Box::new(move |result| {
+                        self.control.wait_if_paused();
+                        if self.control.should_stop() || self.limits.is_reached() {
+                            return WalkState::Quit;
+                        }
+
+                        let entry = match result {
+                            Ok(entry) => entry,
+                            Err(err) => {
+                                self.access.record(&err);
+                                return WalkState::Continue;
                             }
+                        };
+                        sender.finish_read();
+                        if let Some(path) = known.as_ref().and_then(|known| unchanged_path(known, &entry)) {
+                            if !self.limits.admit() {
+                                return WalkState::Quit;
+                            }
+                            processed.fetch_add(1, Ordering::Relaxed);
+                            if !sender.unchanged(path) || self.limits.is_reached() {
+                                return WalkState::Quit;
+                            }
+                        } else if let Some(record) = self.entry_record(&entry) {
+                            if !self.limits.admit() {
+                                return WalkState::Quit;
+                            }
+                            if record.path_lossy {
+                                lossy_paths.fetch_add(1, Ordering::Relaxed);
+                            }
+                            processed.fetch_add(1, Ordering::Relaxed);
+
+                            // Si el escritor terminó (error de BD) no tiene sentido seguir.
+                            if !sender.send(record) || self.limits.is_reached() {
+                                return WalkState::Quit;
+                            }
+                        }
+                        let is_dir = entry.file_type().is_some_and(|file_type| file_type.is_dir());
+                        if is_dir && self.options.max_depth == Some(entry.depth()) {
+                            self.note_depth_cut(entry.path());
+                        }
+                        sender.visited(entry.path(), is_dir);
+                        WalkState::Continue
+                    })
+                });
+                // Suelta el último emisor: el escritor sale del bucle al vaciar el canal.
+                drop(tx);
+            });
+
+            loop {
+                let record = match rx.recv_timeout(PAUSE_POLL_INTERVAL) {
+                    Ok(WalkItem::Record(record)) => record,
+                    Ok(WalkItem::Unchanged(unchanged_path)) => {
+                        wait_turn();
+                        report_progress(unchanged_path.clone(), &stats);
+                        unchanged.push(unchanged_path);
+                        if unchanged.len() >= flush.batch_size() {
+                            stats.skipped += touch_unchanged(&mut unchanged)?;
                         }
+                        continue;
                     }
-                } else if entry.file_type().map_or(false, |ft| ft.is_file()) {
-                    if let Ok(metadata) = entry.metadata() {
-                        if let Some(path_str) = entry.path().to_str() {
-                            if let Some(name) = entry.file_name().to_str() {
-                                let extension = entry
-                                    .path()
-                                    .extension()
-                                    .and_then(|e| e.to_str())
-                                    .map(|s| format!(".{}", s));
-
-                                let modified_time: DateTime<Utc> = metadata
-                                    .modified()
-                                    .ok()
-                                    .map(|t| DateTime::<Utc>::from(t))
-                                    .unwrap_or_else(Utc::now);
-
-                                let file_size = Some(metadata.len() as i64);
-                                let modified_time_str = modified_time.to_rfc3339();
-                                let last_indexed_str = Utc::now().to_rfc3339();
-
-                                batch_buffer.push(FileRecord {
-                                    path: path_str.to_string(),
-                                    name: name.to_string(),
-                                    extension,
-                                    file_size,
-                                    is_dir: false,
-                                    modified_time: modified_time_str,
-                                    last_indexed: last_indexed_str,
-                                });
-
-                                processed += 1;
-                                progress_callback(IndexingProgress {
-                                    current_path: path_str.to_string(),
-                                    files_processed: processed,
-                                    total_files: None,
-                                    status: "indexing".to_string(),
-                                });
-
-                                if batch_buffer.len() >= BATCH_SIZE {
-                                    persisted += flush_batch(&mut batch_buffer)?;
-                                }
-                            }
+                    Ok(WalkItem::Directory(directory)) => {
+                        completed.push(directory);
+                        continue;
+                    }
+                    // Sin registros nuevos (pausa o directorios lentos): guarda lo pendiente
+                    // si alguien lo espera o venció el plazo.
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        if self.control.is_paused()
+                            || !completed.is_empty()
+                            || flush.is_due(batch_buffer.len() + unchanged.len())
+                        {
+                            stats.add(flush_batch(&mut batch_buffer)?);
+                            flush.flushed();
+                            stats.skipped += touch_unchanged(&mut unchanged)?;
+                            self.report_directories(&mut completed);
                         }
+                        continue;
                     }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                };
+                wait_turn();
+                report_progress(record.path.clone(), &stats);
+                if let Some(dir_sizes) = dir_sizes.as_mut() {
+                    accumulate_dir_size(dir_sizes, &record, path_obj, seen_links.as_mut());
+                }
+                batch_buffer.push(record);
+
+                if flush.is_due(batch_buffer.len()) {
+                    stats.add(flush_batch(&mut batch_buffer)?);
+                    flush.flushed();
+                    self.report_directories(&mut completed);
                 }
             }
+            Ok(())
+        })?;
+
+        if self.control.should_stop() {
+            info!("Stop requested, finishing indexing of {} early", path);
+        } else if self.limits.is_reached() {
+            info!("Indexing limit reached, finishing indexing of {} early", path);
         }
 
         // Guardar el remanente final.
-        persisted += flush_batch(&mut batch_buffer)?;
+        stats.add(flush_batch(&mut batch_buffer)?);
+        stats.skipped += touch_unchanged(&mut unchanged)?;
+        // Último progreso con los totales ya guardados (insertados, actualizados, sin cambios).
+        report_progress(path.to_string(), &stats);
+        self.report_directories(&mut completed);
+        if self.options.compute_dir_sizes {
+            self.store_dir_sizes(path, dir_sizes)?;
+        }
+        self.prune_stale(path, &run_start)?;
+
+        let processed = processed.into_inner();
+        let persisted = stats.total();
+        let lossy_paths = lossy_paths.into_inner();
 
         let elapsed = start.elapsed();
         info!(
-            "Indexing completed: processed={} persisted={} in {:?}",
+            "Indexing completed: processed={} persisted={} ({:?}) in {:?}",
             processed,
             persisted,
+            stats,
             elapsed
         );
+        if lossy_paths > 0 {
+            warn!(
+                "{} paths under {} are not valid UTF-8 and were indexed lossily",
+                lossy_paths, path
+            );
+        }
 
         Ok(persisted)
     }
 
-    pub async fn index_multiple_paths(
-        &self,
-        paths: Vec<String>,
-        exclude_patterns: Vec<String>,
-        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
-    ) -> Result<usize, Box<dyn std::error::Error>> {
-        let mut total_count = 0;
-
-        for (idx, path) in paths.iter().enumerate() {
-            info!("Indexing path {}/{}: {}", idx + 1, paths.len(), path);
-            let count = self
-                .index_path(path, exclude_patterns.clone(), progress_callback.clone())
-                .await?;
-            total_count += count;
+    /// `dir` está en `max_depth`: si tiene algo dentro, el recorrido lo deja fuera.
+    fn note_depth_cut(&self, dir: &Path) {
+        if self.limits.is_depth_cut() {
+            return;
+        }
+        let has_children = std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some());
+        if has_children {
+            self.limits.depth_cut();
         }
+    }
 
-        Ok(total_count)
+    /// Entradas (archivos y directorios) que visitará el recorrido de `path`.
+    /// `None` si se pidió parar durante el recuento.
+    fn count_entries(&self, path: &Path, exclude_patterns: &[String]) -> Option<usize> {
+        let start = Instant::now();
+        let count = AtomicUsize::new(0);
+        self.options
+            .walk_builder(path, exclude_patterns, None)
+            .build_parallel()
+            .run(|| {
+                let count = &count;
+                Box::new(move |result| {
+                    self.control.wait_if_paused();
+                    if self.control.should_stop() {
+                        return WalkState::Quit;
+                    }
+                    let counted = result
+                        .ok()
+                        .and_then(|entry| entry.file_type())
+                        .is_some_and(|file_type| file_type.is_file() || file_type.is_dir());
+                    if counted {
+                        count.fetch_add(1, Ordering::Relaxed);
+                    }
+                    WalkState::Continue
+                })
+            });
+        if self.control.should_stop() {
+            return None;
+        }
+        let count = count.into_inner();
+        info!("Counted {} entries under {} in {:?}", count, path.display(), start.elapsed());
+        Some(count)
     }
 
-    pub fn get_default_indexing_paths() -> Vec<String> {
-        let mut paths = Vec::new();
+    /// Registro para una entrada del walker (solo archivos y directorios).
+    fn entry_record(&self, entry: &DirEntry) -> Option<FileRecord> {
+        let file_type = entry.file_type()?;
+        let metadata = entry.metadata().ok();
 
-        #[cfg(unix)]
-        {
-            if let Ok(home) = std::env::var("HOME") {
-                paths.push(home.clone());
+        if let Some(since) = self.options.modified_since {
+            // Sin mtime no se sabe si cambió: se incluye por si acaso.
+            let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+            if matches!(modified, Some(modified) if modified <= since) {
+                return None;
             }
+        }
 
-            let mount_file = "/proc/mounts";
-            if let Ok(content) = std::fs::read_to_string(mount_file) {
-                for line in content.lines() {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
+        let mut record = if file_type.is_dir() {
+            let (path_str, name, path_lossy) = utf8_or_lossy(entry.path(), entry.file_name());
+            let now = Utc::now().to_rfc3339();
+            // Con el mtime real (y no la hora del recorrido) un directorio sin cambios no
+            // se reescribe en cada reindexado.
+            let modified_time = metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339())
+                .unwrap_or_else(|| now.clone());
+            let created_time = metadata.as_ref().and_then(created_rfc3339);
+            let (owner_uid, owner_gid) = metadata.as_ref().map_or((None, None), owner);
+            FileRecord {
+                path: path_str,
+                name,
+                extension: None,
+                file_size: None,
+                is_dir: true,
+                modified_time,
+                created_time,
+                last_indexed: now,
+                path_lossy,
+                raw_path: path_lossy.then(|| paths::encode_raw(entry.path())),
+                tags: None,
+                is_reparse: metadata.as_ref().is_some_and(is_reparse_point),
+                link_count: metadata.as_ref().map_or(1, link_count),
+                file_id: None,
+                category: None,
+                interpreter: None,
+                mime: None,
+                mode: metadata.as_ref().and_then(mode_bits),
+                owner_uid,
+                owner_gid,
+                content: None,
+            }
+        } else if file_type.is_file() {
+            Self::file_record(entry.path(), &metadata?)
+        } else {
+            return None;
+        };
+        // Con `follow_symlinks` la metadata es la del destino: el enlace se ve en la entrada.
+        record.is_reparse |= entry.path_is_symlink();
+
+        self.options.enrich(&mut record, entry.path());
+        Some(record)
+    }
+
+    /// Pasa a `directory_callback` los directorios terminados (ya guardados).
+    fn report_directories(&self, completed: &mut Vec<DirectoryIndexed>) {
+        if completed.is_empty() {
+            return;
+        }
+        let completed = std::mem::take(completed);
+        if let Some(callback) = &self.directory_callback {
+            callback(completed);
+        }
+    }
+
+    /// Guarda los tamaños de `compute_dir_sizes`. Nada si el recorrido se cortó: las
+    /// sumas se quedarían cortas. En un refresco rápido `dir_sizes` solo cubre lo
+    /// modificado y con Spotlight no hay recorrido (`None`), así que se recalculan
+    /// desde el índice.
+    fn store_dir_sizes(
+        &self,
+        path: &str,
+        dir_sizes: Option<HashMap<String, i64>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.control.should_stop() || self.limits.is_reached() {
+            return Ok(());
+        }
+
+        let mut db_guard = self
+            .db
+            .lock()
+            .map_err(|e| format!("Failed to lock database: {}", e))?;
+        // Sin tamaños acumulados del recorrido completo, se recalculan desde el índice.
+        let updated = match dir_sizes.filter(|_| self.options.full_walk()) {
+            Some(dir_sizes) => db_guard.set_dir_sizes(&dir_sizes)?,
+            None => db_guard.recompute_dir_sizes(path, self.options.dedupe_hardlinks)?,
+        };
+        info!("Stored sizes of {} directories under {}", updated, path);
+        Ok(())
+    }
+
+    /// Calcula el `content_hash` de los archivos de hasta `max_size` bytes que comparten
+    /// tamaño con otro y aún no lo tienen (nuevos o modificados). Lee los archivos sin
+    /// bloquear la base.
+    fn hash_duplicates(&self, max_size: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let sizes = self
+            .db
+            .lock()
+            .map_err(|e| format!("Failed to lock database: {}", e))?
+            .unhashed_duplicate_sizes(max_size)?;
+        if sizes.is_empty() {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let mut hashed = 0;
+        for size in &sizes {
+            self.control.wait_if_paused();
+            if self.control.should_stop() {
+                break;
+            }
+            let files = self
+                .db
+                .lock()
+                .map_err(|e| format!("Failed to lock database: {}", e))?
+                .files_with_size(*size)?;
+            let hashes = duplicates::hash_group(*size, &files);
+            if !hashes.is_empty() {
+                hashed += self
+                    .db
+                    .lock()
+                    .map_err(|e| format!("Failed to lock database: {}", e))?
+                    .set_content_hashes(&hashes)?;
+            }
+        }
+        info!(
+            "Hashed {} files in {} size groups in {:?}",
+            hashed,
+            sizes.len(),
+            start.elapsed()
+        );
+        Ok(())
+    }
+
+    /// Tras leer la MFT de `path` (`C:\`): poda y sigue el USN journal solo si se
+    /// leyó entera; si se cortó en el límite faltan entradas válidas.
+    fn finish_mft_scan(
+        &self,
+        path: &str,
+        run_start: &str,
+        scan: MftScan,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        info!("MFT indexing successful: {} files", scan.files_found);
+        if !scan.truncated {
+            self.prune_stale(path, run_start)?;
+            if self.options.follow_usn_journal && !self.control.should_stop() {
+                if let Some(drive) = path.chars().next() {
+                    usn_journal::start_monitor(drive, Arc::clone(&self.db));
+                }
+            }
+        }
+        Ok(scan.files_found)
+    }
+
+    /// Con `prune_after_reindex`, borra bajo `path` lo que no se vio desde `run_start`.
+    /// No hace nada si el recorrido se interrumpió, porque faltarían entradas válidas.
+    fn prune_stale(&self, path: &str, run_start: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.options.prune_after_reindex
+            || !self.options.full_walk()
+            || self.control.should_stop()
+            || self.limits.is_reached()
+        {
+            return Ok(());
+        }
+
+        let db_guard = self
+            .db
+            .lock()
+            .map_err(|e| format!("Failed to lock database: {}", e))?;
+        let removed = db_guard.delete_stale_under(path, run_start)?;
+        if removed > 0 {
+            info!("Pruned {} stale entries under {}", removed, path);
+        }
+        Ok(())
+    }
+
+    /// Indexa `paths` con las mismas exclusiones en todas.
+    pub async fn index_multiple_paths(
+        &self,
+        paths: Vec<String>,
+        exclude_patterns: Vec<String>,
+        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let roots = paths
+            .into_iter()
+            .map(|path| IndexRoot {
+                path,
+                exclude_patterns: exclude_patterns.clone(),
+            })
+            .collect();
+        self.index_multiple_roots(roots, progress_callback).await
+    }
+
+    /// Indexa cada raíz con sus propias exclusiones.
+    pub async fn index_multiple_roots(
+        &self,
+        roots: Vec<IndexRoot>,
+        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let _running = self.control.begin();
+        let run_start = Utc::now().to_rfc3339();
+        self.limits.restart();
+        self.access.restart();
+
+        // Los contadores de cada aviso son exactos; solo se agrupan los eventos.
+        let throttle = Arc::new(ProgressThrottle::new(
+            self.options.progress_every_files,
+            self.options.progress_interval,
+            progress_callback,
+        ));
+        let progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync> = {
+            let throttle = Arc::clone(&throttle);
+            Arc::new(move |progress| throttle.report(progress))
+        };
+
+        let (local, sharded) = self.route_paths(roots)?;
+        let total_count = match &self.shards {
+            Some(shards) if !sharded.is_empty() => {
+                self.index_sharded(shards, local, sharded, &progress_callback)
+            }
+            _ => self.index_paths(&local, &progress_callback).await,
+        };
+        // El estado final llega siempre, también si la ejecución falló.
+        throttle.flush();
+        let total_count = total_count?;
+
+        if self.limits.is_reached() {
+            let db_guard = self
+                .db
+                .lock()
+                .map_err(|e| format!("Failed to lock database: {}", e))?;
+            db_guard.set_meta(META_INCOMPLETE_RUN, &run_start)?;
+        } else if let Some(key) = self.options.record_run_as {
+            if !self.control.should_stop() {
+                let db_guard = self
+                    .db
+                    .lock()
+                    .map_err(|e| format!("Failed to lock database: {}", e))?;
+                db_guard.set_meta(key, &run_start)?;
+                if key == META_LAST_FULL_INDEX {
+                    db_guard.delete_meta(META_INCOMPLETE_RUN)?;
+                }
+            }
+        }
+
+        Ok(total_count)
+    }
+
+    /// Indexa `roots` en la base de este indexador, en modo rápido si está configurado
+    /// y el recorrido es completo (se restaura al terminar, también si falla).
+    async fn index_paths(
+        &self,
+        roots: &[IndexRoot],
+        progress_callback: &Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let bulk = self.options.synchronous == SyncMode::Off && self.options.full_walk();
+        {
+            let mut db_guard = self
+                .db
+                .lock()
+                .map_err(|e| format!("Failed to lock database: {}", e))?;
+            db_guard.set_synchronous(self.options.synchronous)?;
+            db_guard.set_bulk_mode(bulk)?;
+        }
+
+        let mut result = self.index_roots(roots, progress_callback).await;
+        if let (Ok(_), Some(max_size)) = (&result, self.options.duplicate_max_bytes) {
+            if let Err(e) = self.hash_duplicates(max_size) {
+                result = Err(e);
+            }
+        }
+
+        if bulk {
+            self.db
+                .lock()
+                .map_err(|e| format!("Failed to lock database: {}", e))?
+                .set_bulk_mode(false)?;
+        }
+        result
+    }
+
+    async fn index_roots(
+        &self,
+        roots: &[IndexRoot],
+        progress_callback: &Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut total_count = 0;
+        for (idx, root) in roots.iter().enumerate() {
+            if self.control.should_stop() || self.limits.is_reached() {
+                break;
+            }
+
+            let path = &root.path;
+            info!("Indexing path {}/{}: {}", idx + 1, roots.len(), path);
+            // Los refrescos rápidos y los recorridos con `max_depth` no recorren todo: no
+            // cuentan para la cobertura.
+            let full_scan = self.options.full_walk();
+            let started = Utc::now().to_rfc3339();
+            if full_scan {
+                self.set_meta(&format!("{}{}", META_ROOT_STARTED, path), &started)?;
+            }
+            let count = self
+                .index_path(path, root.exclude_patterns.clone(), progress_callback.clone())
+                .await?;
+            if full_scan && !self.control.should_stop() && !self.limits.is_reached() {
+                self.set_meta(&format!("{}{}", META_ROOT_COMPLETED, path), &started)?;
+            }
+            total_count += count;
+        }
+        Ok(total_count)
+    }
+
+    /// Separa las raíces que van a la base principal de las que van al shard de su
+    /// unidad. En un recorrido completo borra cada raíz de la base que ya no le toca,
+    /// para que una unidad nunca esté en las dos.
+    fn route_paths(
+        &self,
+        roots: Vec<IndexRoot>,
+    ) -> Result<(Vec<IndexRoot>, ShardedPaths), Box<dyn std::error::Error>> {
+        let mut sharded = ShardedPaths::new();
+        let Some(shards) = &self.shards else {
+            return Ok((roots, sharded));
+        };
+        let full_run = self.options.full_walk();
+
+        let mut local = Vec::new();
+        for root in roots {
+            let path = &root.path;
+            let key = shard::drive_key(path).filter(|_| self.options.shard_by_drive);
+            let previous = match &key {
+                Some(_) => Some(Arc::clone(&self.db)),
+                None => shards.existing_writer(path)?,
+            };
+            if let Some(previous) = previous.filter(|_| full_run) {
+                let removed = previous
+                    .lock()
+                    .map_err(|e| format!("Failed to lock database: {}", e))?
+                    .delete_tree(path)?;
+                if removed > 0 {
+                    info!("Moved {} entries under {} out of their previous index", removed, path);
+                }
+            }
+            match key {
+                Some(key) => sharded.entry(key).or_default().push(root),
+                None => local.push(root),
+            }
+        }
+        Ok((local, sharded))
+    }
+
+    /// Indexa cada unidad de `sharded` en su shard desde un hilo propio, a la vez
+    /// que `local` en la base principal.
+    fn index_sharded(
+        &self,
+        shards: &Shards,
+        local: Vec<IndexRoot>,
+        sharded: ShardedPaths,
+        progress_callback: &Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut groups = vec![(None, self.db_for(Arc::clone(&self.db)), local)];
+        for (key, roots) in sharded {
+            let writer = shards.writer(&key)?;
+            groups.push((Some(key), self.db_for(writer), roots));
+        }
+
+        let runtime = tokio::runtime::Handle::current();
+        let results: Vec<Result<usize, String>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = groups
+                .into_iter()
+                .filter(|(_, _, roots)| !roots.is_empty())
+                .map(|(key, indexer, roots)| {
+                    let runtime = runtime.clone();
+                    scope.spawn(move || {
+                        if let Some(key) = &key {
+                            info!("Indexing drive {} into its shard", key);
+                        }
+                        runtime
+                            .block_on(indexer.index_paths(&roots, progress_callback))
+                            .map_err(|e| match key {
+                                Some(key) => format!("Drive {}: {}", key, e),
+                                None => e.to_string(),
+                            })
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err("Indexing thread panicked".into())))
+                .collect()
+        });
+
+        let mut total_count = 0;
+        for result in results {
+            total_count += result?;
+        }
+        Ok(total_count)
+    }
+
+    fn set_meta(&self, key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.db
+            .lock()
+            .map_err(|e| format!("Failed to lock database: {}", e))?
+            .set_meta(key, value)?;
+        Ok(())
+    }
+
+    /// Copia de este indexador que escribe en `db` (mismo control, opciones y límites).
+    fn db_for(&self, db: Arc<Mutex<Database>>) -> Indexer {
+        Indexer {
+            db,
+            control: Arc::clone(&self.control),
+            options: self.options.clone(),
+            limits: Arc::clone(&self.limits),
+            access: Arc::clone(&self.access),
+            shards: None,
+            directory_callback: self.directory_callback.clone(),
+        }
+    }
+
+    pub fn get_default_indexing_paths() -> Vec<String> {
+        let mut paths = Vec::new();
+
+        #[cfg(unix)]
+        {
+            if let Ok(home) = std::env::var("HOME") {
+                paths.push(home.clone());
+            }
+
+            let mount_file = "/proc/mounts";
+            if let Ok(content) = std::fs::read_to_string(mount_file) {
+                for line in content.lines() {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
                     if parts.len() >= 2 {
                         let mount_point = parts[1];
                         let path = Path::new(mount_point);
@@ -325,6 +1625,51 @@ impl Indexer {
         paths
     }
 
+    /// Construye el registro de `path` a partir de sus metadatos.
+    pub fn file_record(path: &Path, metadata: &std::fs::Metadata) -> FileRecord {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        let (path_str, name, path_lossy) = utf8_or_lossy(path, name);
+        let is_dir = metadata.is_dir();
+
+        let extension = if is_dir {
+            None
+        } else {
+            path.extension().map(|e| format!(".{}", e.to_string_lossy()))
+        };
+
+        let modified_time: DateTime<Utc> = metadata
+            .modified()
+            .ok()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(Utc::now);
+        let category = category::from_extension(extension.as_deref()).map(String::from);
+        let (owner_uid, owner_gid) = owner(metadata);
+
+        FileRecord {
+            path: path_str,
+            name,
+            extension,
+            file_size: if is_dir { None } else { Some(metadata.len() as i64) },
+            is_dir,
+            modified_time: modified_time.to_rfc3339(),
+            created_time: created_rfc3339(metadata),
+            last_indexed: Utc::now().to_rfc3339(),
+            path_lossy,
+            raw_path: path_lossy.then(|| paths::encode_raw(path)),
+            tags: None,
+            is_reparse: is_reparse_point(metadata),
+            link_count: link_count(metadata),
+            file_id: file_id(metadata),
+            category,
+            interpreter: None,
+            mime: None,
+            mode: mode_bits(metadata),
+            owner_uid,
+            owner_gid,
+            content: None,
+        }
+    }
+
     pub fn get_default_exclude_patterns() -> Vec<String> {
         vec![
             ".git".to_string(),
@@ -337,3 +1682,994 @@ impl Indexer {
         ]
     }
 }
+
+/// Raíces de `reindex_path`: `roots` con `exclude_patterns` añadidas a las suyas, o
+/// `path` (o las raíces por defecto) con `exclude_patterns`. Sin exclusiones globales
+/// se usan las de por defecto.
+pub fn reindex_roots(
+    path: Option<String>,
+    exclude_patterns: Vec<String>,
+    roots: Option<Vec<IndexRoot>>,
+) -> Vec<IndexRoot> {
+    let patterns = if exclude_patterns.is_empty() {
+        Indexer::get_default_exclude_patterns()
+    } else {
+        exclude_patterns
+    };
+    match roots.filter(|r| !r.is_empty()) {
+        Some(roots) => roots
+            .into_iter()
+            .map(|root| IndexRoot {
+                exclude_patterns: patterns.iter().chain(&root.exclude_patterns).cloned().collect(),
+                path: root.path,
+            })
+            .collect(),
+        None => {
+            let paths = match path {
+                Some(p) => vec![p],
+                None => Indexer::get_default_indexing_paths(),
+            };
+            with_patterns(paths, &patterns)
+        }
+    }
+}
+
+/// Las mismas exclusiones para todas las raíces.
+pub fn with_patterns(paths: Vec<String>, patterns: &[String]) -> Vec<IndexRoot> {
+    paths
+        .into_iter()
+        .map(|path| IndexRoot {
+            path,
+            exclude_patterns: patterns.to_vec(),
+        })
+        .collect()
+}
+
+/// Punto de reparse (symlink/junction) según la metadata sin seguir enlaces. Los que
+/// sigue el walker (`follow_symlinks`) se marcan en `entry_record`.
+pub(crate) fn is_reparse_point(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+    }
+    #[cfg(not(windows))]
+    {
+        metadata.file_type().is_symlink()
+    }
+}
+
+/// Con `follow_symlinks`, `false` si el directorio ya se visitó por otro camino (un
+/// ciclo de enlaces o dos enlaces al mismo sitio). Se identifica por dispositivo + inodo.
+#[cfg(unix)]
+fn first_visit(visited: &Mutex<HashSet<(u64, u64)>>, entry: &DirEntry) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    if !entry.file_type().is_some_and(|file_type| file_type.is_dir()) {
+        return true;
+    }
+    let Ok(metadata) = entry.metadata() else {
+        return true;
+    };
+    visited
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert((metadata.dev(), metadata.ino()))
+}
+
+/// Fuera de Unix no hay inodo estable en std: los ciclos los corta el propio walker,
+/// que compara cada directorio con sus ancestros.
+#[cfg(not(unix))]
+fn first_visit(_visited: &Mutex<HashSet<(u64, u64)>>, _entry: &DirEntry) -> bool {
+    true
+}
+
+/// Enlaces duros de la entrada. Fuera de Unix std no lo expone de forma estable: 1.
+pub(crate) fn link_count(metadata: &std::fs::Metadata) -> i64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.nlink() as i64
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        1
+    }
+}
+
+/// `dispositivo:inodo` de un archivo con más de un enlace duro (solo Unix). Al resto no
+/// le hace falta: sus datos solo tienen una ruta.
+pub(crate) fn file_id(metadata: &std::fs::Metadata) -> Option<String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.is_file() && metadata.nlink() > 1)
+            .then(|| format!("{}:{}", metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Atributos de Windows que se guardan en `mode`: solo lectura, oculto y de sistema
+/// (el resto, como `ARCHIVE`, cambia sin que cambie el archivo).
+pub(crate) const WINDOWS_ATTRIBUTE_MASK: u32 = 0x1 | 0x2 | 0x4;
+
+/// `st_mode` en Unix; en Windows, los atributos de `WINDOWS_ATTRIBUTE_MASK`.
+pub(crate) fn mode_bits(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode())
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        Some(metadata.file_attributes() & WINDOWS_ATTRIBUTE_MASK)
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// uid y gid del propietario (solo Unix).
+pub(crate) fn owner(metadata: &std::fs::Metadata) -> (Option<u32>, Option<u32>) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (Some(metadata.uid()), Some(metadata.gid()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        (None, None)
+    }
+}
+
+/// Suma el tamaño de `record` a todos sus directorios ancestros dentro de `root`
+/// (y registra los directorios vacíos con 0). Con `seen_links`, un archivo con varios
+/// enlaces duros solo suma en los ancestros de la primera ruta que llega (como `du`).
+fn accumulate_dir_size(
+    dir_sizes: &mut HashMap<String, i64>,
+    record: &FileRecord,
+    root: &Path,
+    seen_links: Option<&mut HashSet<String>>,
+) {
+    if record.is_dir {
+        dir_sizes.entry(record.path.clone()).or_insert(0);
+        return;
+    }
+    let Some(size) = record.file_size else {
+        return;
+    };
+    if let (Some(seen), Some(file_id)) = (seen_links, &record.file_id) {
+        if !seen.insert(file_id.clone()) {
+            return;
+        }
+    }
+    for ancestor in Path::new(&record.path).ancestors().skip(1) {
+        if !ancestor.starts_with(root) {
+            break;
+        }
+        *dir_sizes
+            .entry(ancestor.to_string_lossy().into_owned())
+            .or_insert(0) += size;
+    }
+}
+
+/// Fecha de creación en RFC3339 UTC (no disponible en todos los sistemas de archivos).
+pub(crate) fn created_rfc3339(metadata: &std::fs::Metadata) -> Option<String> {
+    metadata
+        .created()
+        .ok()
+        .map(|created| DateTime::<Utc>::from(created).to_rfc3339())
+}
+
+/// Ruta de `entry` si ya está indexada y no cambió: mismo mtime y tamaño para los
+/// archivos; los directorios no guardan mtime y basta con que sigan siéndolo.
+fn unchanged_path(known: &HashMap<String, IndexedEntry>, entry: &DirEntry) -> Option<String> {
+    let path = entry.path().to_str()?;
+    let (modified_time, file_size, is_dir) = known.get(path)?;
+    let metadata = entry.metadata().ok()?;
+    if metadata.is_dir() {
+        return is_dir.then(|| path.to_string());
+    }
+    let modified = DateTime::<Utc>::from(metadata.modified().ok()?).to_rfc3339();
+    (!is_dir && *modified_time == modified && *file_size == Some(metadata.len() as i64))
+        .then(|| path.to_string())
+}
+
+/// Ruta y nombre como `String`. Si alguno no es UTF-8 válido se usa
+/// `to_string_lossy` y el tercer valor indica que la ruta no es reversible.
+fn utf8_or_lossy(path: &Path, name: &OsStr) -> (String, String, bool) {
+    match (path.to_str(), name.to_str()) {
+        (Some(path), Some(name)) => (path.to_string(), name.to_string(), false),
+        _ => (
+            path.to_string_lossy().into_owned(),
+            name.to_string_lossy().into_owned(),
+            true,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{indexed_paths, open_index, record, TempDir};
+    use std::sync::Barrier;
+
+    fn block_on<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// `count` archivos vacíos en `dir/tree`; devuelve la ruta de `tree`.
+    fn make_tree(dir: &TempDir, count: usize) -> String {
+        let tree = dir.path().join("tree");
+        std::fs::create_dir(&tree).unwrap();
+        for i in 0..count {
+            std::fs::write(tree.join(format!("file{:04}.txt", i)), b"").unwrap();
+        }
+        tree.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn cancel_mid_walk_keeps_the_flushed_batches() {
+        let dir = TempDir::new("cancel");
+        let tree = make_tree(&dir, 500);
+        let (db, reader) = open_index(&dir);
+        let control = Arc::new(IndexControl::new());
+        let options = IndexOptions {
+            batch_size: Some(10),
+            threads: 1,
+            ..Default::default()
+        };
+        let indexer =
+            Indexer::new(Arc::new(Mutex::new(db)), Arc::clone(&control)).with_options(options);
+
+        let progress: Arc<dyn Fn(IndexingProgress) + Send + Sync> = {
+            let control = Arc::clone(&control);
+            Arc::new(move |progress: IndexingProgress| {
+                if progress.files_processed >= 50 {
+                    control.cancel();
+                }
+            })
+        };
+        let count =
+            block_on(indexer.index_multiple_paths(vec![tree], Vec::new(), progress)).unwrap();
+
+        assert!(control.is_cancelled());
+        assert!(count > 0 && count < 500, "indexed {} of 500", count);
+        // Todo lo que se cuenta como guardado está en la base.
+        assert_eq!(reader.get_file_count().unwrap(), count);
+    }
+
+    #[test]
+    fn pause_flushes_the_pending_batch_and_writes_nothing_more() {
+        let dir = TempDir::new("pause");
+        let tree = make_tree(&dir, 3000);
+        let (db, reader) = open_index(&dir);
+        let control = Arc::new(IndexControl::new());
+        // Un lote que no se llena: solo la pausa puede guardarlo.
+        let options = IndexOptions {
+            batch_size: Some(100_000),
+            flush_interval: None,
+            threads: 1,
+            ..Default::default()
+        };
+        let indexer =
+            Indexer::new(Arc::new(Mutex::new(db)), Arc::clone(&control)).with_options(options);
+
+        let processed = Arc::new(AtomicUsize::new(0));
+        let (paused_tx, paused_rx) = mpsc::channel();
+        let progress: Arc<dyn Fn(IndexingProgress) + Send + Sync> = {
+            let control = Arc::clone(&control);
+            let processed = Arc::clone(&processed);
+            let once = AtomicBool::new(false);
+            Arc::new(move |progress: IndexingProgress| {
+                processed.fetch_max(progress.files_processed, Ordering::SeqCst);
+                if progress.files_processed >= 100 && !once.swap(true, Ordering::SeqCst) {
+                    assert!(control.pause());
+                    paused_tx.send(()).unwrap();
+                }
+            })
+        };
+        let run = std::thread::spawn(move || {
+            block_on(indexer.index_multiple_paths(vec![tree], Vec::new(), progress)).unwrap()
+        });
+
+        paused_rx.recv().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while reader.get_file_count().unwrap() == 0 {
+            assert!(Instant::now() < deadline, "pending batch was not flushed on pause");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        // Lo que quedaba en el canal ya se guardó con el lote.
+        std::thread::sleep(PAUSE_POLL_INTERVAL * 2);
+        let while_paused = reader.get_file_count().unwrap();
+        assert_eq!(while_paused, processed.load(Ordering::SeqCst));
+        assert!(while_paused < 3001);
+
+        std::thread::sleep(PAUSE_POLL_INTERVAL * 2);
+        assert_eq!(reader.get_file_count().unwrap(), while_paused);
+        assert!(!run.is_finished());
+
+        assert!(control.resume());
+        assert_eq!(run.join().unwrap(), 3001);
+        assert_eq!(reader.get_file_count().unwrap(), 3001);
+    }
+
+    /// Suma de los tamaños de los archivos bajo `dir`, leída del disco.
+    fn disk_size(dir: &Path) -> u64 {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let metadata = entry.metadata().unwrap();
+                if metadata.is_dir() {
+                    disk_size(&entry.path())
+                } else {
+                    metadata.len()
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn directory_size_is_the_sum_of_its_descendants() {
+        let dir = TempDir::new("dir-sizes");
+        let root = dir.path().join("root");
+        for sub in ["a/b/c", "a/d", "empty"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        let files = [("top", 1), ("a/x", 10), ("a/b/y", 100), ("a/b/c/z", 1000), ("a/d/w", 7)];
+        for (file, size) in files {
+            std::fs::write(root.join(file), vec![b'.'; size]).unwrap();
+        }
+        let (db, reader) = open_index(&dir);
+        let db = Arc::new(Mutex::new(db));
+        let root_path = root.to_string_lossy().into_owned();
+        let dirs = ["", "/a", "/a/b", "/a/b/c", "/a/d", "/empty"];
+        let check = |run: &str| {
+            for sub in dirs {
+                let entry = reader.file_by_path(&format!("{}{}", root_path, sub)).unwrap().unwrap();
+                let expected = disk_size(&root.join(sub.trim_start_matches('/')));
+                assert_eq!(entry.file_size, Some(expected), "{} run, {:?}", run, sub);
+            }
+        };
+
+        // Completo: sumas del recorrido. Incremental (con `a/b/y` más grande):
+        // recalculadas desde el índice.
+        for incremental in [false, true] {
+            if incremental {
+                std::fs::write(root.join("a/b/y"), vec![b'.'; 300]).unwrap();
+            }
+            let options =
+                IndexOptions { compute_dir_sizes: true, incremental, ..Default::default() };
+            let indexer = Indexer::new(Arc::clone(&db), Arc::new(IndexControl::new()))
+                .with_options(options);
+            let paths = vec![root_path.clone()];
+            block_on(indexer.index_multiple_paths(paths, Vec::new(), no_progress())).unwrap();
+            check(if incremental { "incremental" } else { "full" });
+        }
+        assert_eq!(disk_size(&root), 1 + 10 + 300 + 1000 + 7);
+    }
+
+    #[test]
+    fn progress_carries_the_precounted_total() {
+        let dir = TempDir::new("precount");
+        let tree = make_tree(&dir, 40);
+        let (db, _reader) = open_index(&dir);
+        let db = Arc::new(Mutex::new(db));
+
+        for precount_entries in [true, false] {
+            let reports = Arc::new(Mutex::new(Vec::new()));
+            let progress: Arc<dyn Fn(IndexingProgress) + Send + Sync> = {
+                let reports = Arc::clone(&reports);
+                Arc::new(move |progress: IndexingProgress| {
+                    reports.lock().unwrap().push((progress.files_processed, progress.total_files));
+                })
+            };
+            let options = IndexOptions { precount_entries, ..Default::default() };
+            let indexer = Indexer::new(Arc::clone(&db), Arc::new(IndexControl::new()))
+                .with_options(options);
+            block_on(indexer.index_multiple_paths(vec![tree.clone()], Vec::new(), progress))
+                .unwrap();
+
+            // El árbol y sus 40 archivos; sin recuento previo no hay total.
+            let reports = reports.lock().unwrap();
+            let expected = precount_entries.then_some(41);
+            assert!(!reports.is_empty());
+            assert!(reports.iter().all(|(_, total)| *total == expected), "{:?}", reports);
+            assert_eq!(reports.last().unwrap().0, 41);
+        }
+    }
+
+    #[test]
+    fn walk_applies_every_exclude_pattern() {
+        let dir = TempDir::new("excludes");
+        let root = dir.path().join("root");
+        for sub in ["node_modules/pkg", "target/debug", "src", "drafts"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        for file in [
+            "node_modules/pkg/index.js",
+            "target/debug/app",
+            "src/main.rs",
+            "src/build.log",
+            "drafts/note.tmp",
+        ] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+
+        // Cada patrón excluye algo distinto: si solo contara el último, se colarían
+        // los demás.
+        let patterns: Vec<String> = ["node_modules", "target", "*.log", "*.tmp"]
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect();
+        let options = IndexOptions { threads: 1, ..Default::default() };
+        assert_eq!(walked(&options, &root, &patterns), ["drafts", "src", "src/main.rs"]);
+    }
+
+    /// Rutas que recorre `walk_builder` bajo `root`, relativas (con `/`) y ordenadas.
+    fn walked(options: &IndexOptions, root: &Path, patterns: &[String]) -> Vec<String> {
+        let mut walked: Vec<String> = options
+            .walk_builder(root, patterns, None)
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let relative = entry.path().strip_prefix(root).ok()?;
+                Some(relative.to_string_lossy().replace('\\', "/"))
+            })
+            .filter(|relative| !relative.is_empty())
+            .collect();
+        walked.sort();
+        walked
+    }
+
+    #[test]
+    fn hidden_toggle_includes_or_skips_dotfiles() {
+        let dir = TempDir::new("hidden");
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join(".config")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        for file in [".env", ".config/app.toml", "src/.keep", "src/main.rs"] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+
+        let shown = IndexOptions { index_hidden: true, threads: 1, ..Default::default() };
+        assert_eq!(
+            walked(&shown, &root, &[]),
+            [".config", ".config/app.toml", ".env", "src", "src/.keep", "src/main.rs"]
+        );
+        let skipped = IndexOptions { index_hidden: false, ..shown };
+        assert_eq!(walked(&skipped, &root, &[]), ["src", "src/main.rs"]);
+    }
+
+    fn no_progress() -> Arc<dyn Fn(IndexingProgress) + Send + Sync> {
+        Arc::new(|_| {})
+    }
+
+    #[test]
+    fn reindex_prunes_entries_deleted_between_runs() {
+        let dir = TempDir::new("prune");
+        let tree = make_tree(&dir, 10);
+        let (db, reader) = open_index(&dir);
+        let options = IndexOptions { prune_after_reindex: true, ..Default::default() };
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+            .with_options(options);
+
+        block_on(indexer.index_multiple_paths(vec![tree.clone()], Vec::new(), no_progress()))
+            .unwrap();
+        let before = reader.get_file_count().unwrap();
+        let deleted = dir.join("tree/file0003.txt");
+        std::fs::remove_file(&deleted).unwrap();
+
+        block_on(indexer.index_multiple_paths(vec![tree], Vec::new(), no_progress())).unwrap();
+        assert_eq!(reader.get_file_count().unwrap(), before - 1);
+        assert!(reader.file_by_path(&deleted).unwrap().is_none());
+    }
+
+    #[test]
+    fn reindexing_an_unchanged_tree_upserts_nothing() {
+        for incremental in [false, true] {
+            let dir = TempDir::new("upsert-stats");
+            let tree = make_tree(&dir, 20);
+            let (db, _reader) = open_index(&dir);
+            let options = IndexOptions { incremental, threads: 1, ..Default::default() };
+            let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+                .with_options(options);
+            let last = Arc::new(Mutex::new(None));
+            let progress: Arc<dyn Fn(IndexingProgress) + Send + Sync> = {
+                let last = Arc::clone(&last);
+                Arc::new(move |p: IndexingProgress| {
+                    *last.lock().unwrap() = Some((p.inserted, p.updated, p.skipped));
+                })
+            };
+            let run = || {
+                let paths = vec![tree.clone()];
+                block_on(indexer.index_multiple_paths(paths, Vec::new(), progress.clone()))
+                    .unwrap();
+                last.lock().unwrap().take().unwrap()
+            };
+
+            // El directorio raíz y sus 20 archivos.
+            assert_eq!(run(), (21, 0, 0), "incremental: {}", incremental);
+            assert_eq!(run(), (0, 0, 21), "incremental: {}", incremental);
+
+            std::fs::write(dir.join("tree/file0007.txt"), b"cambiado").unwrap();
+            assert_eq!(run(), (0, 1, 20), "incremental: {}", incremental);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle_is_walked_once() {
+        let dir = TempDir::new("symlink-cycle");
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::create_dir_all(root.join("b")).unwrap();
+        std::fs::write(root.join("a/file.txt"), b"").unwrap();
+        std::os::unix::fs::symlink("..", root.join("a/loop")).unwrap();
+        let (db, _reader) = open_index(&dir);
+        let options = IndexOptions { follow_symlinks: true, ..Default::default() };
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+            .with_options(options);
+
+        let root = root.to_string_lossy().into_owned();
+        block_on(indexer.index_multiple_paths(vec![root.clone()], Vec::new(), no_progress()))
+            .unwrap();
+
+        // `a/loop` vuelve a la raíz: el recorrido lo detecta y no entra otra vez.
+        assert_eq!(indexed_paths(&dir, &root), ["", "/a", "/a/file.txt", "/b"]);
+    }
+
+    /// Rutas bajo `root` (relativas, con `/`) listadas con `read_dir`, sin el walker.
+    #[test]
+    fn duplicates_group_identical_files_only() {
+        let dir = TempDir::new("duplicates");
+        let root = dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        // Pequeños (hash completo directo) y grandes que solo difieren en medio, donde
+        // el hash rápido del inicio y el final coincide.
+        let large = vec![b'x'; 300 * 1024];
+        let mut large_other = large.clone();
+        large_other[150 * 1024] = b'y';
+        let files = [
+            ("small-a", b"same text".to_vec()),
+            ("small-b", b"same text".to_vec()),
+            ("small-c", b"diff text".to_vec()),
+            ("large-a", large.clone()),
+            ("large-b", large),
+            ("large-c", large_other),
+        ];
+        for (name, content) in &files {
+            std::fs::write(root.join(name), content).unwrap();
+        }
+        let (db, reader) = open_index(&dir);
+        let options = IndexOptions { duplicate_max_bytes: Some(1 << 20), ..Default::default() };
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+            .with_options(options);
+        let root_path = root.to_string_lossy().into_owned();
+        block_on(indexer.index_multiple_paths(vec![root_path], Vec::new(), no_progress()))
+            .unwrap();
+
+        let groups = reader.find_duplicates(10).unwrap();
+        let found: Vec<(u64, Vec<String>, u64)> = groups
+            .into_iter()
+            .map(|group| (group.file_size, group.paths, group.wasted_bytes))
+            .collect();
+        let size = 300 * 1024;
+        assert_eq!(
+            found,
+            [
+                (size, vec![dir.join("root/large-a"), dir.join("root/large-b")], size),
+                (9, vec![dir.join("root/small-a"), dir.join("root/small-b")], 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn root_excludes_apply_only_to_their_root() {
+        let dir = TempDir::new("root-excludes");
+        for root in ["one", "two"] {
+            for sub in ["build", "cache", "src"] {
+                std::fs::create_dir_all(dir.path().join(root).join(sub)).unwrap();
+                std::fs::write(dir.path().join(root).join(sub).join("file"), b"").unwrap();
+            }
+        }
+        let roots = vec![
+            IndexRoot { path: dir.join("one"), exclude_patterns: vec!["build".to_string()] },
+            IndexRoot { path: dir.join("two"), exclude_patterns: Vec::new() },
+        ];
+        // `cache` es global: se suma a las de cada raíz.
+        let roots = reindex_roots(None, vec!["cache".to_string()], Some(roots));
+        let (db, _reader) = open_index(&dir);
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()));
+        block_on(indexer.index_multiple_roots(roots, no_progress())).unwrap();
+
+        assert_eq!(indexed_paths(&dir, &dir.join("one")), ["", "/src", "/src/file"]);
+        let two = ["", "/build", "/build/file", "/src", "/src/file"];
+        assert_eq!(indexed_paths(&dir, &dir.join("two")), two);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directory_is_reported() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("unreadable");
+        let root = dir.path().join("root");
+        let locked = root.join("locked");
+        std::fs::create_dir_all(&locked).unwrap();
+        std::fs::write(locked.join("secret"), b"").unwrap();
+        std::fs::write(root.join("open"), b"").unwrap();
+        let set_mode = |mode| {
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(mode)).unwrap()
+        };
+        set_mode(0o000);
+        // Como root los permisos no cuentan: no hay nada que comprobar.
+        if std::fs::read_dir(&locked).is_ok() {
+            set_mode(0o755);
+            return;
+        }
+
+        let (db, _reader) = open_index(&dir);
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()));
+        let root_path = root.to_string_lossy().into_owned();
+        let outcome = block_on(indexer.index_multiple_paths(
+            vec![root_path.clone()],
+            Vec::new(),
+            no_progress(),
+        ));
+        set_mode(0o755);
+        outcome.unwrap();
+
+        let warnings = indexer.access_warnings().unwrap();
+        assert_eq!((warnings.permission_denied, warnings.other_errors), (1, 0));
+        assert_eq!(warnings.sample_paths, [locked.to_string_lossy()]);
+        // El directorio se guarda; lo que contiene no se pudo ver.
+        assert_eq!(indexed_paths(&dir, &root_path), ["", "/locked", "/open"]);
+    }
+
+    fn serial_walk(root: &Path, relative: &str, paths: &mut Vec<String>) {
+        paths.push(relative.to_string());
+        let path = root.join(relative.trim_start_matches('/'));
+        if !path.is_dir() {
+            return;
+        }
+        for entry in std::fs::read_dir(path).unwrap() {
+            let name = entry.unwrap().file_name().to_string_lossy().into_owned();
+            serial_walk(root, &format!("{}/{}", relative, name), paths);
+        }
+    }
+
+    #[test]
+    fn parallel_walk_matches_a_serial_walk() {
+        let tree = TempDir::new("parallel-tree");
+        for a in 0..6 {
+            for b in 0..5 {
+                let sub = tree.path().join(format!("d{}/e{}", a, b));
+                std::fs::create_dir_all(&sub).unwrap();
+                for f in 0..20 {
+                    std::fs::write(sub.join(format!("f{}.txt", f)), b"x").unwrap();
+                }
+            }
+        }
+        let root = tree.path().to_string_lossy().into_owned();
+        let mut expected = Vec::new();
+        serial_walk(tree.path(), "", &mut expected);
+        expected.sort();
+
+        // Un hilo frente a varios; el lote pequeño llena el canal y frena al walker.
+        for threads in [1, 8] {
+            let dir = TempDir::new("parallel-index");
+            let (db, reader) = open_index(&dir);
+            let options = IndexOptions { threads, batch_size: Some(7), ..Default::default() };
+            let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+                .with_options(options);
+            block_on(indexer.index_multiple_paths(vec![root.clone()], Vec::new(), no_progress()))
+                .unwrap();
+
+            assert_eq!(reader.get_file_count().unwrap(), expected.len(), "threads {}", threads);
+            assert_eq!(indexed_paths(&dir, &root), expected, "threads {}", threads);
+        }
+    }
+
+    #[test]
+    fn truncated_mft_scan_does_not_prune() {
+        let dir = TempDir::new("prune-mft");
+        let (db, reader) = open_index(&dir);
+        let db = Arc::new(Mutex::new(db));
+        // Cualquier raíz sirve: la poda solo mira las rutas bajo ella.
+        let root = dir.join("drive");
+        let old = record(&dir.join("drive/old.txt"), false, "2020-01-01T00:00:00+00:00");
+        db.lock().unwrap().upsert_file(&old).unwrap();
+        let options = IndexOptions { prune_after_reindex: true, ..Default::default() };
+        let indexer = Indexer::new(db, Arc::new(IndexControl::new())).with_options(options);
+        let run_start = Utc::now().to_rfc3339();
+
+        let truncated = MftScan { files_found: 3, truncated: true };
+        assert_eq!(indexer.finish_mft_scan(&root, &run_start, truncated).unwrap(), 3);
+        assert_eq!(reader.get_file_count().unwrap(), 1);
+
+        let complete = MftScan { files_found: 3, truncated: false };
+        indexer.finish_mft_scan(&root, &run_start, complete).unwrap();
+        assert_eq!(reader.get_file_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn run_cut_by_a_limit_does_not_prune() {
+        let dir = TempDir::new("prune-limit");
+        let tree = make_tree(&dir, 10);
+        let (db, reader) = open_index(&dir);
+        let db = Arc::new(Mutex::new(db));
+        let options = IndexOptions { prune_after_reindex: true, ..Default::default() };
+        let full = Indexer::new(Arc::clone(&db), Arc::new(IndexControl::new()))
+            .with_options(options.clone());
+        block_on(full.index_multiple_paths(vec![tree.clone()], Vec::new(), no_progress()))
+            .unwrap();
+        let before = reader.get_file_count().unwrap();
+        std::fs::remove_file(dir.join("tree/file0003.txt")).unwrap();
+
+        let limited = Indexer::new(db, Arc::new(IndexControl::new()))
+            .with_options(IndexOptions { max_files: Some(3), threads: 1, ..options });
+        block_on(limited.index_multiple_paths(vec![tree], Vec::new(), no_progress())).unwrap();
+        assert_eq!(limited.limit_reached(), Some(IndexLimit::MaxFiles));
+        // Las entradas que el recorrido no llegó a ver siguen en el índice.
+        assert_eq!(reader.get_file_count().unwrap(), before);
+    }
+
+    #[test]
+    fn admit_never_lets_more_than_max_files_through() {
+        let limits = Arc::new(RunLimits::new(Some(50), None));
+        let barrier = Arc::new(Barrier::new(8));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let limits = Arc::clone(&limits);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    (0..100).filter(|_| limits.admit()).count()
+                })
+            })
+            .collect();
+
+        let admitted: usize = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+        assert_eq!(admitted, 50);
+        assert_eq!(limits.reached(), Some(IndexLimit::MaxFiles));
+
+        limits.restart();
+        assert_eq!(limits.reached(), None);
+        assert!(limits.admit());
+    }
+
+    #[test]
+    fn max_files_stops_at_exactly_that_many_rows() {
+        let dir = TempDir::new("max-files");
+        let tree = make_tree(&dir, 200);
+        let (db, reader) = open_index(&dir);
+        let options = IndexOptions { max_files: Some(37), threads: 8, ..Default::default() };
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+            .with_options(options);
+
+        block_on(indexer.index_multiple_paths(vec![tree], Vec::new(), no_progress())).unwrap();
+        assert_eq!(indexer.limit_reached(), Some(IndexLimit::MaxFiles));
+        assert_eq!(reader.get_file_count().unwrap(), 37);
+    }
+
+    #[test]
+    fn max_depth_leaves_deeper_entries_out() {
+        let dir = TempDir::new("max-depth");
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        std::fs::create_dir_all(root.join("empty")).unwrap();
+        std::fs::write(root.join("top.txt"), b"").unwrap();
+        std::fs::write(root.join("a/mid.txt"), b"").unwrap();
+        std::fs::write(root.join("a/b/deep.txt"), b"").unwrap();
+        let (db, _reader) = open_index(&dir);
+        let db = Arc::new(Mutex::new(db));
+        let root = root.to_string_lossy().into_owned();
+
+        // Cada ejecución ve un nivel más; los recortes no podan lo anterior.
+        for (max_depth, expected) in [
+            (1, vec!["", "/a", "/empty", "/top.txt"]),
+            (2, vec!["", "/a", "/a/b", "/a/mid.txt", "/empty", "/top.txt"]),
+        ] {
+            let options = IndexOptions { max_depth: Some(max_depth), ..Default::default() };
+            let indexer = Indexer::new(Arc::clone(&db), Arc::new(IndexControl::new()))
+                .with_options(options);
+            block_on(indexer.index_multiple_paths(vec![root.clone()], Vec::new(), no_progress()))
+                .unwrap();
+
+            assert_eq!(indexed_paths(&dir, &root), expected, "max_depth {}", max_depth);
+            assert_eq!(indexer.limit_reached(), Some(IndexLimit::MaxDepth));
+        }
+    }
+
+    #[test]
+    fn single_directory_is_recursive_or_one_level() {
+        let dir = TempDir::new("single-directory");
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        for file in ["top.txt", "a/mid.txt", "a/b/deep.txt"] {
+            std::fs::write(root.join(file), b"").unwrap();
+        }
+        let root_path = root.to_string_lossy().into_owned();
+        let index = |db: &Arc<Mutex<Database>>, recursive| {
+            let options = IndexOptions::default().single_directory(recursive);
+            let indexer = Indexer::new(Arc::clone(db), Arc::new(IndexControl::new()))
+                .with_options(options);
+            let roots = vec![root_path.clone()];
+            block_on(indexer.index_multiple_paths(roots, Vec::new(), no_progress())).unwrap()
+        };
+
+        let (db, reader) = open_index(&dir);
+        let db = Arc::new(Mutex::new(db));
+        assert_eq!(index(&db, false), 3);
+        assert_eq!(indexed_paths(&dir, &root_path), ["", "/a", "/top.txt"]);
+
+        assert_eq!(index(&db, true), 6);
+        assert_eq!(reader.get_file_count().unwrap(), 6);
+
+        // Sin `recursive` no se poda lo que quedó por debajo.
+        std::fs::remove_file(root.join("a/b/deep.txt")).unwrap();
+        index(&db, false);
+        assert_eq!(reader.get_file_count().unwrap(), 6);
+    }
+
+    #[test]
+    fn tiny_batches_commit_every_batch_size_entries() {
+        // El progreso lleva lo ya guardado: cada valor distinto es una transacción.
+        for (batch_size, committed) in [(3, vec![0, 3, 6, 9, 11]), (100, vec![0, 11])] {
+            let dir = TempDir::new("flush-policy");
+            let tree = make_tree(&dir, 10);
+            let (db, _reader) = open_index(&dir);
+            let options = IndexOptions { batch_size: Some(batch_size), ..Default::default() };
+            let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+                .with_options(options);
+            let seen = Arc::new(Mutex::new(Vec::new()));
+            let progress = {
+                let seen = Arc::clone(&seen);
+                Arc::new(move |progress: IndexingProgress| {
+                    seen.lock().unwrap().push(progress.inserted);
+                })
+            };
+            block_on(indexer.index_multiple_paths(vec![tree], Vec::new(), progress)).unwrap();
+
+            let mut seen = seen.lock().unwrap().clone();
+            seen.dedup();
+            assert_eq!(seen, committed, "batch size {}", batch_size);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hard_links_count_once_in_sizes_and_duplicates() {
+        for dedupe_hardlinks in [true, false] {
+            let dir = TempDir::new("hard-links");
+            let root = dir.path().join("root");
+            std::fs::create_dir(&root).unwrap();
+            std::fs::write(root.join("original"), vec![b'.'; 1000]).unwrap();
+            std::fs::hard_link(root.join("original"), root.join("link")).unwrap();
+            std::fs::write(root.join("copy"), vec![b'.'; 1000]).unwrap();
+
+            let (db, reader) = open_index(&dir);
+            let db = Arc::new(Mutex::new(db));
+            let root_path = root.to_string_lossy().into_owned();
+            // Completo (sumas del recorrido) y después incremental (sumas del índice).
+            for incremental in [false, true] {
+                let options = IndexOptions {
+                    compute_dir_sizes: true,
+                    dedupe_hardlinks,
+                    incremental,
+                    duplicate_max_bytes: Some(1 << 20),
+                    ..Default::default()
+                };
+                let indexer = Indexer::new(Arc::clone(&db), Arc::new(IndexControl::new()))
+                    .with_options(options);
+                let roots = vec![root_path.clone()];
+                block_on(indexer.index_multiple_paths(roots, Vec::new(), no_progress())).unwrap();
+
+                let size = reader.file_by_path(&root_path).unwrap().unwrap().file_size;
+                let expected = if dedupe_hardlinks { 2000 } else { 3000 };
+                let run = (dedupe_hardlinks, incremental);
+                assert_eq!(size, Some(expected), "dedupe_hardlinks, incremental: {:?}", run);
+            }
+
+            // Los duplicados siempre cuentan el enlace como la misma copia.
+            let groups = reader.find_duplicates(10).unwrap();
+            assert_eq!(groups.len(), 1);
+            let expected = ["root/copy", "root/link", "root/original"].map(|path| dir.join(path));
+            assert_eq!(groups[0].paths, expected);
+            assert_eq!(groups[0].wasted_bytes, 1000);
+        }
+    }
+
+    #[test]
+    fn try_begin_admits_one_of_many_concurrent_callers() {
+        let control = Arc::new(IndexControl::new());
+        let callers = 8;
+        let barrier = Arc::new(Barrier::new(callers));
+
+        let handles: Vec<_> = (0..callers)
+            .map(|_| {
+                let control = Arc::clone(&control);
+                let barrier = Arc::clone(&barrier);
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    let guard = control.try_begin();
+                    let admitted = guard.is_some();
+                    // Retiene el guard hasta que todos lo hayan intentado.
+                    barrier.wait();
+                    admitted
+                })
+            })
+            .collect();
+
+        let admitted = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|admitted| *admitted)
+            .count();
+        assert_eq!(admitted, 1);
+        assert!(!control.is_running());
+        assert!(control.try_begin().is_some());
+    }
+
+    #[test]
+    fn run_guard_is_released_on_drop_and_on_panic() {
+        let control = Arc::new(IndexControl::new());
+        let guard = control.try_begin().expect("nothing running yet");
+        assert!(control.try_begin().is_none());
+        drop(guard);
+        assert!(!control.is_running());
+
+        let panicked = {
+            let control = Arc::clone(&control);
+            std::thread::spawn(move || {
+                let _running = control.try_begin().expect("released by drop");
+                panic!("indexing run panicked");
+            })
+            .join()
+        };
+        assert!(panicked.is_err());
+        assert!(!control.is_running());
+        assert!(control.wait_idle(Duration::from_millis(20)));
+        assert!(control.try_begin().is_some());
+    }
+
+    #[test]
+    fn status_follows_a_slow_run() {
+        let control = Arc::new(IndexControl::new());
+        let (started_tx, started_rx) = mpsc::channel();
+        let (finish_tx, finish_rx) = mpsc::channel::<()>();
+
+        let run = {
+            let guard = control.try_begin().expect("nothing running yet");
+            std::thread::spawn(move || {
+                let _running = guard;
+                started_tx.send(()).unwrap();
+                let _ = finish_rx.recv();
+            })
+        };
+
+        started_rx.recv().unwrap();
+        assert!(control.is_running());
+        assert!(control.try_begin().is_none());
+        assert!(!control.wait_idle(Duration::from_millis(20)));
+
+        finish_tx.send(()).unwrap();
+        assert!(control.wait_idle(Duration::from_secs(5)));
+        assert!(!control.is_running());
+        run.join().unwrap();
+    }
+}
@@ -0,0 +1,73 @@
+//! Intérprete de los scripts según su shebang (`#!/usr/bin/env python3` -> `python`),
+//! para buscar por intérprete (`interpreter:bash`) sea cual sea la extensión.
+
+use std::io::Read;
+use std::path::Path;
+
+/// Prefijo del operador de búsqueda por intérprete (`interpreter:bash deploy`).
+const INTERPRETER_OPERATOR: &str = "interpreter:";
+
+/// Bytes leídos de la cabecera: de sobra para cualquier línea `#!` real.
+const HEAD_BYTES: u64 = 256;
+
+/// Extensiones de script. Los archivos sin extensión también se leen: es lo habitual
+/// en los scripts ejecutables.
+const SCRIPT_EXTENSIONS: &[&str] = &[
+    ".sh", ".bash", ".zsh", ".fish", ".ksh", ".csh", ".py", ".pyw", ".pl", ".pm", ".rb",
+    ".php", ".js", ".mjs", ".cjs", ".ts", ".lua", ".tcl", ".awk", ".r", ".groovy", ".command",
+];
+
+/// `true` si merece la pena leer la cabecera de un archivo con esta extensión.
+pub fn is_script_candidate(extension: Option<&str>) -> bool {
+    match extension {
+        None => true,
+        Some(ext) => SCRIPT_EXTENSIONS
+            .iter()
+            .any(|script| script.eq_ignore_ascii_case(ext)),
+    }
+}
+
+/// Intérprete del shebang de `path`, o `None` si no empieza por `#!`.
+pub fn read_interpreter(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut head = Vec::new();
+    file.take(HEAD_BYTES).read_to_end(&mut head).ok()?;
+    parse_shebang(&head)
+}
+
+/// `#!/bin/bash` -> `bash`, `#!/usr/bin/env -S python3 -u` -> `python`. Se quitan la
+/// ruta y la versión final para que `python3.11` y `python` casen igual.
+fn parse_shebang(head: &[u8]) -> Option<String> {
+    let rest = head.strip_prefix(b"#!")?;
+    let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+    let line = std::str::from_utf8(&rest[..end]).ok()?;
+
+    let mut words = line.split_whitespace();
+    let mut program = file_name(words.next()?);
+    if program == "env" {
+        // Opciones de `env` (`-S`, `-i`) y asignaciones `VAR=valor` antes del programa.
+        program = file_name(words.find(|word| !word.starts_with('-') && !word.contains('='))?);
+    }
+
+    let name = program
+        .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+        .to_ascii_lowercase();
+    (!name.is_empty()).then_some(name)
+}
+
+fn file_name(program: &str) -> &str {
+    program.rsplit(['/', '\\']).next().unwrap_or(program)
+}
+
+/// Normaliza los valores de `SearchFilters::interpreters` como se guardan en el índice.
+pub fn normalize(interpreters: &[String]) -> Vec<String> {
+    interpreters
+        .iter()
+        .filter_map(|name| parse_shebang(format!("#!{}", name.trim()).as_bytes()))
+        .collect()
+}
+
+/// Separa los términos `interpreter:xxx` del resto de la consulta.
+pub fn extract_interpreter_terms(query: &str) -> (String, Vec<String>) {
+    crate::tags::extract_operator_terms(query, INTERPRETER_OPERATOR)
+}
@@ -1,25 +1,106 @@
+mod cache;
+mod category;
+mod clipboard;
+mod collation;
+mod content;
 mod db;
+mod dir_tracker;
+mod duplicates;
+mod elevation;
+mod error;
+mod exclude;
+mod export;
+mod file_details;
+mod fuzzy;
+mod index_preview;
 mod indexer;
+mod interpreter;
+mod locate_indexer;
 mod mft_indexer;
+mod migrations;
+mod mime;
+mod operations;
+mod path_check;
+mod path_match;
+mod paths;
+mod pool;
+mod prefetch;
+mod preview;
+mod progress;
+mod query;
+mod ranking;
+mod rate_limit;
+mod refine;
+mod search;
+mod search_epoch;
+#[cfg(feature = "http-api")]
+mod server;
+mod shard;
+mod spotlight_indexer;
+mod tags;
+#[cfg(test)]
+mod test_support;
 mod types;
+mod usn_journal;
+mod verify;
+mod watcher;
 
 use db::Database;
-use indexer::Indexer;
-use std::path::PathBuf;
+use error::OxiError;
+use pool::ReadPool;
+use shard::Shards;
+use indexer::{reindex_roots, with_patterns, IndexControl, IndexOptions, Indexer, RunGuard};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
-use dirs;
+use std::time::{Duration, Instant};
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{TrayIconBuilder},
-    Emitter, Manager, WindowEvent,
+    Emitter, Manager, RunEvent, WindowEvent,
 };
-use tracing::{error, info};
-use tracing_subscriber;
-use types::{IndexingStatus, SearchConfig, SearchFilters, SearchResults};
+use tracing::{error, info, warn};
+use operations::Operations;
+use types::{
+    ContentMatch, CoverageReport, DuplicateGroup, EmptyKind, ExtensionStat, FileDetails,
+    HistoryEntry, IndexLimit, IndexLimitReached, IndexPreview, IndexRoot, IndexingStatus,
+    IndexingWarnings, MergePolicy, MimeCount, OperationInfo, OperationStatus, PathStyle,
+    PathValidation, RefinedResults, Refinement, RootCoverage, SaveSearchOutcome, SavedSearch,
+    SearchComplete, SearchConfig, SearchFilters, SearchResult, SearchResultBatch, SearchResults,
+    TextPreview, VerifyReport,
+};
+use cache::{CacheKey, IndexCached, SearchCache};
+use search::{execute_search, stream_search, SearchTuning};
+use search_epoch::SearchEpochs;
+use rate_limit::RateLimiter;
+use watcher::IndexWatcher;
 
 static DB_PATH: &str = "oxi-search.db";
 
+/// Conexiones de lectura simultáneas (búsquedas, estado) además del escritor.
+const READ_POOL_SIZE: usize = 4;
+
+/// Resultados de `find_siblings_by_time` si no se indica `limit`.
+const SIBLINGS_DEFAULT_LIMIT: usize = 100;
+
+/// Resultados de `find_empty` si no se indica `limit`.
+const EMPTY_DEFAULT_LIMIT: usize = 1000;
+
+/// Grupos de `find_duplicates` si no se indica `limit`.
+const DUPLICATES_DEFAULT_LIMIT: usize = 100;
+
+/// Entradas de `get_search_history` si no se indica `limit`.
+const HISTORY_DEFAULT_LIMIT: usize = 50;
+
+/// Tipos de `get_mime_summary` si no se indica `limit`.
+const MIME_SUMMARY_DEFAULT_LIMIT: usize = 100;
+
+/// Tiempo máximo que se espera a que los índices guarden su último lote al salir.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Cada cuánto se comprueba si toca el refresco rápido programado.
+const QUICK_REFRESH_TICK: Duration = Duration::from_secs(60);
+
 fn get_db_path() -> PathBuf {
     if cfg!(debug_assertions) {
         // En desarrollo, usar el directorio de datos del usuario
@@ -35,125 +116,1295 @@ fn get_db_path() -> PathBuf {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_files(
     query: String,
-    filters: SearchFilters,
+    mut filters: SearchFilters,
     page: usize,
     limit: usize,
     db: tauri::State<'_, Arc<Mutex<Database>>>,
-) -> Result<SearchResults, String> {
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    limiter: tauri::State<'_, Arc<RateLimiter>>,
+    cache: tauri::State<'_, Arc<SearchCache>>,
+    epochs: tauri::State<'_, Arc<SearchEpochs>>,
+) -> Result<SearchResults, OxiError> {
+    // También la consulta vacía supera (e interrumpe) a las anteriores.
+    let epoch = epochs.begin();
     if query.is_empty() {
-        return Ok(SearchResults {
-            query,
-            results: Vec::new(),
-            total: 0,
-            page,
-            limit,
-        });
+        return Ok(empty_results(query, page, limit, false));
     }
 
-    let db_guard = db.lock().map_err(|e| e.to_string())?;
-    let results = db_guard
-        .search_files(
-            &query,
-            filters.extensions,
-            filters.min_size.map(|s| s as i64),
-            filters.max_size.map(|s| s as i64),
+    // `tag:xxx` filtra por etiqueta, `interpreter:xxx` por shebang y `mime:xxx` por
+    // tipo MIME; el resto de la consulta se busca en el nombre.
+    let (name_query, tag_terms) = tags::extract_tag_terms(&query);
+    if !tag_terms.is_empty() {
+        filters.tags.get_or_insert_with(Vec::new).extend(tag_terms);
+    }
+    let (name_query, interpreter_terms) = interpreter::extract_interpreter_terms(&name_query);
+    filters.interpreters.extend(interpreter_terms);
+    let (name_query, mime_terms) = mime::extract_mime_terms(&name_query);
+    filters.mime_types.extend(mime_terms);
+
+    let (history_max_entries, history_ttl_days, rate_per_sec, burst, tuning, debounce, cache_ttl) = {
+        let config_guard = config.lock()?;
+        (
+            config_guard.history_max_entries,
+            config_guard.history_ttl_days,
+            config_guard.search_rate_per_sec,
+            config_guard.search_burst,
+            SearchTuning::from_config(&config_guard),
+            config_guard.search_debounce(),
+            // `None`: caché desactivada; `Some(None)`: sin caducidad.
+            config_guard
+                .cache_enabled
+                .then(|| config_guard.cache_ttl()),
+        )
+    };
+    // Como la consulta vacía: ni caché, ni límite de frecuencia, ni historial.
+    if tuning.too_short(&name_query) {
+        return Ok(empty_results(query, page, limit, false));
+    }
+
+    let filters_json = serde_json::to_string(&filters).ok();
+    let cache_key: Option<CacheKey> = cache_ttl.map(|_| {
+        (
+            name_query.clone(),
+            filters_json.clone().unwrap_or_default(),
+            page,
             limit,
         )
-        .map_err(|e| e.to_string())?;
+    });
+    // Una búsqueda repetida no cuenta para el límite de frecuencia: no toca SQLite.
+    let cached = cache_key
+        .as_ref()
+        .zip(cache_ttl)
+        .and_then(|(key, ttl)| cache.get(key, ttl));
+
+    let (results, timed_out) = match cached {
+        Some(cached) => (cached.results, cached.timed_out),
+        None => {
+            // Tecleando rápido, solo la última búsqueda de la ventana llega a SQLite.
+            if let Some(debounce) = debounce {
+                tokio::time::sleep(debounce).await;
+                if epochs.is_stale(epoch) {
+                    return Ok(empty_results(query, page, limit, true));
+                }
+            }
+            if let Err(retry_after) = limiter.try_acquire(rate_per_sec, burst) {
+                return Err(OxiError::RateLimited(format!(
+                    "Retry after {}ms",
+                    retry_after.as_millis()
+                )));
+            }
 
+            let generation = cache::generation();
+            let (results, timed_out) = {
+                let reader = pool.get()?;
+                if !epochs.register(epoch, reader.interrupt_handle()) {
+                    return Ok(empty_results(query, page, limit, true));
+                }
+                let outcome = execute_search(&reader, &name_query, &filters, limit, &tuning);
+                // Antes de devolver la conexión al pool (ver `SearchEpochs::register`).
+                epochs.finish(epoch);
+                outcome?
+            };
+            // Lo cortado por timeout está incompleto: no se guarda.
+            if let Some(key) = cache_key.filter(|_| !timed_out) {
+                let stored = SearchResults {
+                    query: query.clone(),
+                    results: results.clone(),
+                    total: results.len(),
+                    page,
+                    limit,
+                    timed_out,
+                    is_stale: false,
+                };
+                cache.put(key, stored, generation);
+            }
+            (results, timed_out)
+        }
+    };
     let total = results.len();
+    // Un paso intermedio del tecleo no va al historial.
+    let is_stale = epochs.is_stale(epoch);
+    if !is_stale {
+        // El historial va por el escritor, que puede estar ocupado con un lote del índice:
+        // se guarda en segundo plano para no retrasar la respuesta.
+        let db = Arc::clone(&db);
+        let history_query = query.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = db.lock().map_err(|e| e.to_string()).and_then(|db_guard| {
+                db_guard
+                    .record_search(
+                        &history_query,
+                        filters_json.as_deref(),
+                        total,
+                        history_max_entries,
+                        history_ttl_days,
+                    )
+                    .map_err(|e| e.to_string())
+            });
+            if let Err(e) = result {
+                warn!("Failed to record search history: {}", e);
+            }
+        });
+    }
 
-    let results: Vec<types::SearchResult> = results
-        .into_iter()
-        .map(
-            |(path, name, extension, file_size, is_dir, modified_time)| types::SearchResult {
-                path,
-                name,
-                extension,
-                file_size: file_size.map(|s| s as u64),
-                is_dir,
-                modified_time,
-                score: 1.0,
+    Ok(SearchResults {
+        query,
+        results,
+        total,
+        page,
+        limit,
+        timed_out,
+        is_stale,
+    })
+}
+
+/// Busca dentro de los resultados de `search_files` con los mismos `query`, `filters`,
+/// `page` y `limit`: aplica `refinement` a esa lista (de la caché si sigue ahí; si no,
+/// repite la búsqueda) y devuelve además el recuento por extensión. No amplía la
+/// búsqueda: lo que no estaba en los resultados originales no aparece. Sin historial.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn refine_search(
+    query: String,
+    mut filters: SearchFilters,
+    page: usize,
+    limit: usize,
+    refinement: Refinement,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    limiter: tauri::State<'_, Arc<RateLimiter>>,
+    cache: tauri::State<'_, Arc<SearchCache>>,
+) -> Result<RefinedResults, OxiError> {
+    let refined = |search: SearchResults| -> Result<RefinedResults, OxiError> {
+        let (results, facets) = refine::refine(search.results, &refinement)?;
+        Ok(RefinedResults {
+            search: SearchResults {
+                total: results.len(),
+                results,
+                ..search
             },
+            facets,
+        })
+    };
+    if query.is_empty() {
+        return refined(empty_results(query, page, limit, false));
+    }
+
+    let (name_query, tag_terms) = tags::extract_tag_terms(&query);
+    if !tag_terms.is_empty() {
+        filters.tags.get_or_insert_with(Vec::new).extend(tag_terms);
+    }
+    let (name_query, interpreter_terms) = interpreter::extract_interpreter_terms(&name_query);
+    filters.interpreters.extend(interpreter_terms);
+    let (name_query, mime_terms) = mime::extract_mime_terms(&name_query);
+    filters.mime_types.extend(mime_terms);
+
+    let (rate_per_sec, burst, tuning, cache_ttl) = {
+        let config_guard = config.lock()?;
+        (
+            config_guard.search_rate_per_sec,
+            config_guard.search_burst,
+            SearchTuning::from_config(&config_guard),
+            config_guard
+                .cache_enabled
+                .then(|| config_guard.cache_ttl()),
         )
-        .collect();
+    };
+    if tuning.too_short(&name_query) {
+        return refined(empty_results(query, page, limit, false));
+    }
 
-    Ok(SearchResults {
+    // Misma clave que `search_files`: se reutiliza la lista que ya se mostró.
+    let cache_key: CacheKey = (
+        name_query.clone(),
+        serde_json::to_string(&filters).unwrap_or_default(),
+        page,
+        limit,
+    );
+    if let Some(cached) = cache_ttl.and_then(|ttl| cache.get(&cache_key, ttl)) {
+        return refined(SearchResults { query, ..cached });
+    }
+
+    if let Err(retry_after) = limiter.try_acquire(rate_per_sec, burst) {
+        return Err(OxiError::RateLimited(format!(
+            "Retry after {}ms",
+            retry_after.as_millis()
+        )));
+    }
+    let generation = cache::generation();
+    let (results, timed_out) = {
+        let reader = pool.get()?;
+        execute_search(&reader, &name_query, &filters, limit, &tuning)?
+    };
+    let search = SearchResults {
         query,
+        total: results.len(),
         results,
-        total,
         page,
         limit,
+        timed_out,
+        is_stale: false,
+    };
+    if cache_ttl.is_some() && !timed_out {
+        cache.put(cache_key, search.clone(), generation);
+    }
+    refined(search)
+}
+
+/// Como `search_files`, pero emite los resultados en eventos `search-result-batch` a
+/// medida que salen de SQLite y termina con `search-complete` (también la respuesta).
+/// Cada lote se filtra y puntúa por separado y sale en el orden del SQL: no hay orden
+/// global por relevancia ni `max_per_extension`/`dirs_first`. Una búsqueda nueva
+/// (de cualquiera de los dos comandos) corta el flujo. Sin caché ni historial.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn search_files_streaming(
+    query: String,
+    mut filters: SearchFilters,
+    limit: Option<usize>,
+    batch_size: Option<usize>,
+    app: tauri::AppHandle,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    limiter: tauri::State<'_, Arc<RateLimiter>>,
+    epochs: tauri::State<'_, Arc<SearchEpochs>>,
+) -> Result<SearchComplete, OxiError> {
+    let epoch = epochs.begin();
+    let complete = |total: usize, timed_out: bool, is_stale: bool| {
+        let complete = SearchComplete {
+            epoch,
+            query: query.clone(),
+            total,
+            timed_out,
+            is_stale,
+        };
+        let _ = app.emit("search-complete", &complete);
+        complete
+    };
+    if query.is_empty() {
+        return Ok(complete(0, false, false));
+    }
+
+    let (name_query, tag_terms) = tags::extract_tag_terms(&query);
+    if !tag_terms.is_empty() {
+        filters.tags.get_or_insert_with(Vec::new).extend(tag_terms);
+    }
+    let (name_query, interpreter_terms) = interpreter::extract_interpreter_terms(&name_query);
+    filters.interpreters.extend(interpreter_terms);
+    let (name_query, mime_terms) = mime::extract_mime_terms(&name_query);
+    filters.mime_types.extend(mime_terms);
+
+    let (limit, batch_size, rate_per_sec, burst, tuning, debounce) = {
+        let config_guard = config.lock()?;
+        (
+            limit.unwrap_or(config_guard.max_results),
+            batch_size.unwrap_or(config_guard.stream_batch_size).max(1),
+            config_guard.search_rate_per_sec,
+            config_guard.search_burst,
+            SearchTuning::from_config(&config_guard),
+            config_guard.search_debounce(),
+        )
+    };
+    if tuning.too_short(&name_query) {
+        return Ok(complete(0, false, false));
+    }
+
+    if let Some(debounce) = debounce {
+        tokio::time::sleep(debounce).await;
+        if epochs.is_stale(epoch) {
+            return Ok(complete(0, false, true));
+        }
+    }
+    if let Err(retry_after) = limiter.try_acquire(rate_per_sec, burst) {
+        return Err(OxiError::RateLimited(format!(
+            "Retry after {}ms",
+            retry_after.as_millis()
+        )));
+    }
+
+    let pool = Arc::clone(&pool);
+    let stream_epochs = Arc::clone(&epochs);
+    let batch_app = app.clone();
+    let batch_query = query.clone();
+    let (total, timed_out) = tokio::task::spawn_blocking(move || -> Result<_, OxiError> {
+        let epochs = stream_epochs;
+        let reader = pool.get()?;
+        if !epochs.register(epoch, reader.interrupt_handle()) {
+            return Ok((0, false));
+        }
+        let outcome = stream_search(
+            &reader,
+            &name_query,
+            &filters,
+            limit,
+            &tuning,
+            batch_size,
+            |offset, results| {
+                if epochs.is_stale(epoch) {
+                    return false;
+                }
+                let _ = batch_app.emit(
+                    "search-result-batch",
+                    SearchResultBatch {
+                        epoch,
+                        query: batch_query.clone(),
+                        offset,
+                        results,
+                    },
+                );
+                true
+            },
+        );
+        // Antes de devolver la conexión al pool (ver `SearchEpochs::register`).
+        epochs.finish(epoch);
+        outcome
+    })
+    .await??;
+
+    // Una búsqueda nueva también interrumpe la consulta: eso no es un timeout.
+    let is_stale = epochs.is_stale(epoch);
+    Ok(complete(total, timed_out && !is_stale, is_stale))
+}
+
+/// Resultado sin filas: consulta vacía o búsqueda superada antes de consultar.
+fn empty_results(query: String, page: usize, limit: usize, is_stale: bool) -> SearchResults {
+    SearchResults {
+        query,
+        results: Vec::new(),
+        total: 0,
+        page,
+        limit,
+        timed_out: false,
+        is_stale,
+    }
+}
+
+/// Busca dentro del texto indexado (`index_content`); devuelve fragmentos resaltados.
+#[tauri::command]
+async fn search_content(
+    query: String,
+    limit: usize,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<ContentMatch>, OxiError> {
+    let reader = pool.get()?;
+    Ok(reader.search_content(&query, limit)?)
+}
+
+/// Archivos modificados en torno a la fecha de `reference_path` (± `window_seconds`),
+/// del más cercano al más lejano: "¿qué más cambié cuando edité esto?".
+#[tauri::command]
+async fn find_siblings_by_time(
+    reference_path: String,
+    window_seconds: u64,
+    limit: Option<usize>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<SearchResult>, OxiError> {
+    let limit = limit.unwrap_or(SIBLINGS_DEFAULT_LIMIT);
+    let reader = pool.get()?;
+    reader
+        .modified_near(&reference_path, Duration::from_secs(window_seconds), limit)?
+        .ok_or_else(|| OxiError::not_found(format!("Not indexed: {}", reference_path)))
+}
+
+/// Archivos vacíos o directorios sin entradas, para revisarlos y limpiar. Los
+/// directorios dependen de que el índice cubra todo su contenido.
+#[tauri::command]
+async fn find_empty(
+    kind: EmptyKind,
+    limit: Option<usize>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<SearchResult>, OxiError> {
+    let limit = limit.unwrap_or(EMPTY_DEFAULT_LIMIT);
+    let reader = pool.get()?;
+    Ok(reader.find_empty(kind, limit)?)
+}
+
+/// Grupos de archivos duplicados por contenido, de más a menos espacio desperdiciado
+/// (requiere `hash_duplicates`).
+#[tauri::command]
+async fn find_duplicates(
+    limit: Option<usize>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<DuplicateGroup>, OxiError> {
+    let limit = limit.unwrap_or(DUPLICATES_DEFAULT_LIMIT);
+    let reader = pool.get()?;
+    Ok(reader.find_duplicates(limit)?)
+}
+
+/// Últimas búsquedas, de la más reciente a la más antigua.
+#[tauri::command]
+async fn get_search_history(
+    limit: Option<usize>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<HistoryEntry>, OxiError> {
+    let limit = limit.unwrap_or(HISTORY_DEFAULT_LIMIT);
+    let reader = pool.get()?;
+    Ok(reader.search_history(limit)?)
+}
+
+/// Vacía el historial de búsquedas; devuelve cuántas entradas se borraron.
+#[tauri::command]
+async fn clear_search_history(
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+) -> Result<usize, OxiError> {
+    let db_guard = db.lock()?;
+    let removed = db_guard.clear_search_history()?;
+    info!("Cleared {} search history entries", removed);
+    Ok(removed)
+}
+
+/// Guarda `query` y `filters` como `name`. Un nombre repetido sustituye a la búsqueda
+/// anterior y lo indica en `overwritten`.
+#[tauri::command]
+async fn save_search(
+    name: String,
+    query: String,
+    filters: SearchFilters,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<SaveSearchOutcome, OxiError> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(OxiError::invalid_input("Saved search name cannot be empty"));
+    }
+    let filters_json = serde_json::to_string(&filters)?;
+    let overwritten = db
+        .lock()?
+        .save_search(&name, &query, &filters_json)?;
+    if overwritten {
+        warn!("Saved search '{}' overwritten", name);
+    }
+    let reader = pool.get()?;
+    let search = reader
+        .saved_search(&name)?
+        .ok_or_else(|| OxiError::not_found(format!("Saved search '{}' not found", name)))?;
+    Ok(SaveSearchOutcome { search, overwritten })
+}
+
+#[tauri::command]
+async fn list_saved_searches(
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<SavedSearch>, OxiError> {
+    let reader = pool.get()?;
+    Ok(reader.saved_searches()?)
+}
+
+/// Lanza la búsqueda guardada `name` como un `search_files` normal.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn run_saved_search(
+    name: String,
+    page: usize,
+    limit: usize,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    limiter: tauri::State<'_, Arc<RateLimiter>>,
+    cache: tauri::State<'_, Arc<SearchCache>>,
+    epochs: tauri::State<'_, Arc<SearchEpochs>>,
+) -> Result<SearchResults, OxiError> {
+    let saved = {
+        let reader = pool.get()?;
+        reader.saved_search(&name)?
+    };
+    let saved =
+        saved.ok_or_else(|| OxiError::not_found(format!("Saved search '{}' not found", name)))?;
+    search_files(saved.query, saved.filters, page, limit, db, pool, config, limiter, cache, epochs).await
+}
+
+/// `false` si no había ninguna búsqueda guardada con ese nombre.
+#[tauri::command]
+async fn delete_saved_search(
+    name: String,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+) -> Result<bool, OxiError> {
+    Ok(db.lock()?.delete_saved_search(&name)?)
+}
+
+/// Tipos MIME indexados con su número de archivos (requiere `index_mime_types`).
+#[tauri::command]
+async fn get_mime_summary(
+    limit: Option<usize>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<Vec<MimeCount>, OxiError> {
+    let limit = limit.unwrap_or(MIME_SUMMARY_DEFAULT_LIMIT);
+    let reader = pool.get()?;
+    Ok(reader.mime_summary(limit)?)
+}
+
+/// Entradas indexadas por extensión, con grupos para los archivos sin extensión y los
+/// directorios. `dedupe_hardlinks` (por defecto el de la configuración) cuenta una vez
+/// los enlaces duros de un mismo archivo. Se calcula una vez por cada versión del índice.
+#[tauri::command]
+async fn get_extension_stats(
+    dedupe_hardlinks: Option<bool>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    stats: tauri::State<'_, Arc<IndexCached<bool, Vec<ExtensionStat>>>>,
+) -> Result<Vec<ExtensionStat>, OxiError> {
+    let dedupe_hardlinks = match dedupe_hardlinks {
+        Some(dedupe_hardlinks) => dedupe_hardlinks,
+        None => config.lock()?.dedupe_hardlinks,
+    };
+    if let Some(cached) = stats.get(&dedupe_hardlinks) {
+        return Ok(cached);
+    }
+    let pool = Arc::clone(&pool);
+    let stats = Arc::clone(&stats);
+    tokio::task::spawn_blocking(move || {
+        stats.get_or_compute(dedupe_hardlinks, || {
+            Ok(pool.get()?.extension_stats(dedupe_hardlinks)?)
+        })
+    })
+    .await?
+}
+
+/// Inicio de `path` decodificado para la vista previa (como mucho
+/// `preview::MAX_PREVIEW_BYTES`).
+#[tauri::command]
+async fn preview_text(path: String, max_bytes: Option<u64>) -> Result<TextPreview, OxiError> {
+    let max_bytes = max_bytes.unwrap_or(preview::MAX_PREVIEW_BYTES);
+    tokio::task::spawn_blocking(move || {
+        preview::preview_text(Path::new(&path), max_bytes)
+            .map_err(|e| OxiError::io_at(format!("Failed to read {}", path), e))
     })
+    .await?
 }
 
+/// Fila indexada de `path` con la metadata actual del disco (`stale` si ya no existe).
 #[tauri::command]
+async fn get_file_details(
+    path: String,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+) -> Result<FileDetails, OxiError> {
+    let pool = Arc::clone(&pool);
+    tokio::task::spawn_blocking(move || {
+        let indexed = pool.get()?.file_by_path(&path)?;
+        file_details::details(&path, indexed)
+            .map_err(|e| OxiError::io_at(format!("Failed to read metadata of {}", path), e))
+    })
+    .await?
+}
+
+/// Reindexa `path`, las `roots` indicadas (cada una con sus exclusiones además de
+/// `exclude_patterns`) o, sin ninguna de las dos, las raíces por defecto.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn reindex_path(
     path: Option<String>,
     exclude_patterns: Vec<String>,
+    roots: Option<Vec<IndexRoot>>,
     db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
     app_handle: tauri::AppHandle,
-) -> Result<String, String> {
+) -> Result<u64, OxiError> {
+    let mut options = {
+        let config_guard = config.lock()?;
+        IndexOptions::from_config(&config_guard)
+    };
+
+    let full_run = path.is_none() && roots.as_ref().is_none_or(|r| r.is_empty());
+    if full_run {
+        options.record_run_as = Some(indexer::META_LAST_FULL_INDEX);
+    }
+    let roots_to_index = reindex_roots(path, exclude_patterns, roots);
+    let running = begin_exclusive(&control)?;
+
     let db_clone = Arc::clone(&db);
-    let indexer = Indexer::new(db_clone);
+    let indexer = Indexer::new(db_clone, Arc::clone(&control))
+        .with_options(options)
+        .with_shards(Arc::clone(&shards));
 
-    let paths_to_index = if let Some(p) = path {
-        vec![p]
-    } else {
-        Indexer::get_default_indexing_paths()
+    info!("Starting reindex of {:?}", roots_to_index);
+    let operation_id = spawn_index_run(
+        "reindex",
+        indexer,
+        roots_to_index,
+        Arc::clone(&control),
+        running,
+        app_handle,
+    );
+
+    Ok(operation_id)
+}
+
+/// Simulacro de `reindex_path` con los mismos argumentos: recorre y cuenta sin
+/// escribir nada en el índice.
+#[tauri::command]
+async fn preview_index(
+    path: Option<String>,
+    exclude_patterns: Vec<String>,
+    roots: Option<Vec<IndexRoot>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+) -> Result<IndexPreview, OxiError> {
+    let options = {
+        let config_guard = config.lock()?;
+        IndexOptions::from_config(&config_guard)
+    };
+    let roots = reindex_roots(path, exclude_patterns, roots);
+    Ok(tokio::task::spawn_blocking(move || index_preview::preview(&roots, &options)).await?)
+}
+
+/// "Indexar esta carpeta ahora": indexa solo `path`, con las exclusiones de la
+/// configuración pero sin tocarla, y espera a que termine (pensado para carpetas
+/// pequeñas). Sin `recursive` solo entran sus hijos directos y no se poda nada.
+/// Devuelve las entradas guardadas.
+#[tauri::command]
+async fn index_single_directory(
+    path: String,
+    recursive: bool,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+) -> Result<usize, OxiError> {
+    let (options, patterns, allowed_roots) = {
+        let config_guard = config.lock()?;
+        let (_, patterns) = configured_roots(&config_guard);
+        (
+            IndexOptions::from_config(&config_guard),
+            patterns,
+            config_guard.allowed_index_roots.clone(),
+        )
     };
+    let metadata = std::fs::metadata(&path).map_err(|e| OxiError::io_at(&path, e))?;
+    if !metadata.is_dir() {
+        return Err(OxiError::invalid_input(format!("Not a directory: {}", path)));
+    }
+    if !allowed_roots.is_empty() && !is_within_roots(Path::new(&path), &allowed_roots) {
+        return Err(OxiError::PermissionDenied(format!(
+            "{} is outside the allowed index roots",
+            path
+        )));
+    }
+    let _running = begin_exclusive(&control)?;
+
+    let indexer = Indexer::new(Arc::clone(&db), Arc::clone(&control))
+        .with_options(options.single_directory(recursive))
+        .with_shards(Arc::clone(&shards));
+    info!("Indexing single directory {} (recursive: {})", path, recursive);
+    let count = indexer
+        .index_multiple_roots(with_patterns(vec![path], &patterns), Arc::new(|_| {}))
+        .await?;
+    Ok(count)
+}
+
+/// `path` es una de `roots` o está dentro. Se comparan las rutas canónicas para que
+/// `..` o un enlace no saquen de la lista.
+fn is_within_roots(path: &Path, roots: &[String]) -> bool {
+    let Ok(path) = std::fs::canonicalize(path) else {
+        return false;
+    };
+    roots
+        .iter()
+        .filter_map(|root| std::fs::canonicalize(root).ok())
+        .any(|root| path.starts_with(root))
+}
+
+/// Comprueba una raíz candidata antes de guardarla en la configuración: si existe y
+/// se puede leer, si es una unidad o un recurso de red y cuántas entradas tiene.
+#[tauri::command]
+async fn validate_indexing_path(
+    path: String,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+) -> Result<PathValidation, OxiError> {
+    let (roots, patterns, options) = {
+        let config_guard = config.lock()?;
+        let (roots, patterns) = configured_roots(&config_guard);
+        (roots, patterns, IndexOptions::from_config(&config_guard))
+    };
+    let validation =
+        tokio::task::spawn_blocking(move || path_check::validate(&path, &roots, &patterns, &options))
+            .await?;
+    Ok(validation)
+}
+
+/// Refresco rápido: recorre las raíces pero solo guarda lo modificado después de
+/// `since` (RFC3339). Sin `since`, parte del último índice completo o refresco terminado.
+#[tauri::command]
+async fn index_since(
+    paths: Option<Vec<String>>,
+    since: Option<String>,
+    app_handle: tauri::AppHandle,
+) -> Result<u64, OxiError> {
+    start_index_since(&app_handle, paths, since)
+}
+
+/// Lanza el refresco rápido y devuelve el id de la operación.
+fn start_index_since(
+    app: &tauri::AppHandle,
+    paths: Option<Vec<String>>,
+    since: Option<String>,
+) -> Result<u64, OxiError> {
+    let db = app.state::<Arc<Mutex<Database>>>();
+    let control = app.state::<Arc<IndexControl>>();
+    let config = app.state::<Arc<Mutex<SearchConfig>>>();
+
+    let since = match since {
+        Some(since) => since,
+        None => last_index_time(&db)?
+            .ok_or_else(|| OxiError::not_found("No previous index to refresh from"))?,
+    };
+    let since_time = chrono::DateTime::parse_from_rfc3339(&since)
+        .map_err(|e| OxiError::invalid_input(format!("Invalid timestamp {:?}: {}", since, e)))?;
+
+    let (mut options, roots, patterns) = {
+        let config_guard = config.lock()?;
+        let (roots, patterns) = configured_roots(&config_guard);
+        (IndexOptions::from_config(&config_guard), roots, patterns)
+    };
+    options.modified_since = Some(since_time.into());
+
+    let paths = match paths.filter(|p| !p.is_empty()) {
+        Some(paths) => paths,
+        None => {
+            options.record_run_as = Some(indexer::META_LAST_REFRESH);
+            roots
+        }
+    };
+    let running = begin_exclusive(&control)?;
+    info!("Starting quick refresh of {:?} since {}", paths, since);
+
+    let indexer = Indexer::new(Arc::clone(&db), Arc::clone(&control))
+        .with_options(options)
+        .with_shards(Arc::clone(&app.state::<Arc<Shards>>()));
+    Ok(spawn_index_run(
+        "quick_refresh",
+        indexer,
+        with_patterns(paths, &patterns),
+        Arc::clone(&control),
+        running,
+        app.clone(),
+    ))
+}
+
+/// Inicio del último índice completo o refresco rápido terminado, el más reciente.
+fn last_index_time(db: &Mutex<Database>) -> Result<Option<String>, OxiError> {
+    let db_guard = db.lock()?;
+    let full = db_guard
+        .get_meta(indexer::META_LAST_FULL_INDEX)?;
+    let refresh = db_guard
+        .get_meta(indexer::META_LAST_REFRESH)?;
+    // Ambos son RFC3339 UTC generados por nosotros: el orden de texto es el cronológico.
+    Ok(full.max(refresh))
+}
 
-    let patterns = if exclude_patterns.is_empty() {
+/// Raíces y exclusiones de la config, o las de por defecto si están vacías.
+fn configured_roots(config: &SearchConfig) -> (Vec<String>, Vec<String>) {
+    let paths = if config.indexing_paths.is_empty() {
+        Indexer::get_default_indexing_paths()
+    } else {
+        config.indexing_paths.clone()
+    };
+    let patterns = if config.exclude_patterns.is_empty() {
         Indexer::get_default_exclude_patterns()
     } else {
-        exclude_patterns
+        config.exclude_patterns.clone()
     };
+    (paths, patterns)
+}
 
-    info!("Starting reindex of {:?} paths", paths_to_index);
-
+/// Lanza el índice en segundo plano como operación `kind` y devuelve su id.
+/// Emite `indexing-progress` y, al terminar, `indexing-completed`,
+/// `indexing-cancelled` o `indexing-error`, además de `operation-updated`.
+/// `running` (de `begin_exclusive`) se suelta cuando termina la tarea.
+fn spawn_index_run(
+    kind: &str,
+    indexer: Indexer,
+    roots: Vec<IndexRoot>,
+    control: Arc<IndexControl>,
+    running: RunGuard,
+    app_handle: tauri::AppHandle,
+) -> u64 {
+    let operation = start_operation(&app_handle, kind);
+    let operation_id = operation.id;
+    let indexer = indexer.with_directory_callback(directory_callback(&app_handle));
     let app = Arc::new(app_handle);
 
     tokio::spawn(async move {
+        let _running = running;
         let app_clone = app.clone();
-        let progress_callback = Arc::new(move |progress: types::IndexingProgress| {
+        let progress_callback = Arc::new(move |mut progress: types::IndexingProgress| {
+            progress.operation_id = Some(operation_id);
             info!("Indexing progress: {:?}", progress);
             let _ = app_clone.emit("indexing-progress", progress);
         });
 
         let result = indexer
-            .index_multiple_paths(paths_to_index, patterns, progress_callback)
+            .index_multiple_roots(roots, progress_callback)
             .await;
 
+        finish_index_run(
+            &app,
+            operation_id,
+            &control,
+            indexer.limit_reached(),
+            indexer.access_warnings(),
+            result,
+        );
+    });
+
+    operation_id
+}
+
+/// Marca el inicio de un índice u operación exclusiva sobre la base, o `busy` si ya
+/// hay uno en curso. Comprobar y marcar es atómico (ver `IndexControl::try_begin`).
+fn begin_exclusive(control: &Arc<IndexControl>) -> Result<RunGuard, OxiError> {
+    control
+        .try_begin()
+        .ok_or_else(|| OxiError::busy("Indexing in progress"))
+}
+
+/// Emite `directory-indexed` con cada grupo de directorios terminados.
+fn directory_callback(app_handle: &tauri::AppHandle) -> indexer::DirectoryCallback {
+    let app = app_handle.clone();
+    Arc::new(move |directories: Vec<types::DirectoryIndexed>| {
+        let _ = app.emit("directory-indexed", directories);
+    })
+}
+
+/// Emite el evento final de un índice y cierra su operación.
+fn finish_index_run(
+    app: &tauri::AppHandle,
+    operation_id: u64,
+    control: &IndexControl,
+    limit: Option<IndexLimit>,
+    warnings: Option<IndexingWarnings>,
+    result: Result<usize, Box<dyn std::error::Error>>,
+) {
+    if let Some(warnings) = warnings {
+        warn!(
+            "Indexing could not read {} entries ({} permission denied); MFT denied on {:?} (requires elevation: {})",
+            warnings.permission_denied + warnings.other_errors,
+            warnings.permission_denied,
+            warnings.mft_access_denied,
+            warnings.requires_elevation
+        );
+        let _ = app.emit("indexing-warnings", &warnings);
+        app.state::<Arc<Operations>>().set_warnings(operation_id, warnings);
+    }
+    let (status, message) = match (result, limit) {
+        (Ok(count), _) if control.is_cancelled() => {
+            info!("Indexing cancelled after {} files", count);
+            let _ = app.emit("indexing-cancelled", count);
+            (OperationStatus::Cancelled, format!("{} files", count))
+        }
+        (Ok(count), Some(limit)) => {
+            warn!("Indexing limited by {:?} after {} files", limit, count);
+            let _ = app.emit("indexing-limit-reached", IndexLimitReached { limit, files: count });
+            let _ = app.emit("indexing-completed", count);
+            (OperationStatus::Completed, format!("{} files (limit reached)", count))
+        }
+        (Ok(count), None) => {
+            info!("Indexing completed: {} files", count);
+            let _ = app.emit("indexing-completed", count);
+            (OperationStatus::Completed, format!("{} files", count))
+        }
+        (Err(e), _) => {
+            error!("Indexing failed: {}", e);
+            let _ = app.emit("indexing-error", e.to_string());
+            (OperationStatus::Failed, e.to_string())
+        }
+    };
+    finish_operation(app, operation_id, status, Some(message));
+}
+
+fn start_operation(app: &tauri::AppHandle, kind: &str) -> OperationInfo {
+    let operation = app.state::<Arc<Operations>>().start(kind);
+    let _ = app.emit("operation-updated", &operation);
+    operation
+}
+
+fn finish_operation(
+    app: &tauri::AppHandle,
+    id: u64,
+    status: OperationStatus,
+    message: Option<String>,
+) {
+    if let Some(operation) = app.state::<Arc<Operations>>().finish(id, status, message) {
+        let _ = app.emit("operation-updated", &operation);
+    }
+}
+
+/// Operaciones largas en curso y las últimas terminadas.
+#[tauri::command]
+async fn get_operations(
+    operations: tauri::State<'_, Arc<Operations>>,
+) -> Result<Vec<OperationInfo>, OxiError> {
+    Ok(operations.list())
+}
+
+/// Lanza `index_since` cada `quick_refresh_minutes` si no hay otro índice en curso.
+async fn quick_refresh_loop(app: tauri::AppHandle) {
+    let mut last_run = Instant::now();
+    loop {
+        tokio::time::sleep(QUICK_REFRESH_TICK).await;
+
+        let minutes = app
+            .state::<Arc<Mutex<SearchConfig>>>()
+            .lock()
+            .map(|c| c.quick_refresh_minutes)
+            .unwrap_or(0);
+        if minutes == 0 || last_run.elapsed() < Duration::from_secs(minutes * 60) {
+            continue;
+        }
+        if app.state::<Arc<IndexControl>>().is_running() {
+            continue;
+        }
+
+        last_run = Instant::now();
+        if let Err(e) = start_index_since(&app, None, None) {
+            warn!("Scheduled quick refresh skipped: {}", e);
+        }
+    }
+}
+
+/// Cancela el índice en curso: guarda el lote actual y termina con lo ya procesado.
+/// Devuelve `false` si no había ningún índice activo.
+#[tauri::command]
+async fn cancel_indexing(control: tauri::State<'_, Arc<IndexControl>>) -> Result<bool, OxiError> {
+    let cancelled = control.cancel();
+    if cancelled {
+        info!("Indexing cancellation requested");
+    }
+    Ok(cancelled)
+}
+
+/// Pausa el índice en curso tras guardar el lote actual. Emite `indexing-paused`.
+/// Devuelve `false` si no había índice activo o ya estaba en pausa.
+#[tauri::command]
+async fn pause_indexing(
+    control: tauri::State<'_, Arc<IndexControl>>,
+    app_handle: tauri::AppHandle,
+) -> Result<bool, OxiError> {
+    let paused = control.pause();
+    if paused {
+        info!("Indexing paused");
+        let _ = app_handle.emit("indexing-paused", ());
+    }
+    Ok(paused)
+}
+
+/// Reanuda el índice pausado donde estaba. Emite `indexing-resumed`.
+#[tauri::command]
+async fn resume_indexing(
+    control: tauri::State<'_, Arc<IndexControl>>,
+    app_handle: tauri::AppHandle,
+) -> Result<bool, OxiError> {
+    let resumed = control.resume();
+    if resumed {
+        info!("Indexing resumed");
+        let _ = app_handle.emit("indexing-resumed", ());
+    }
+    Ok(resumed)
+}
+
+/// Reconstruye solo el índice FTS a partir de `search_index`, sin volver a recorrer
+/// el disco. Emite `fts-rebuilt` al terminar o `fts-rebuild-error` si falla.
+#[tauri::command]
+async fn rebuild_fts(
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+    app_handle: tauri::AppHandle,
+) -> Result<u64, OxiError> {
+    let running = begin_exclusive(&control)?;
+
+    let operation_id = start_operation(&app_handle, "rebuild_fts").id;
+    let db_clone = Arc::clone(&db);
+    tokio::task::spawn_blocking(move || {
+        let _running = running;
+        let result = db_clone
+            .lock()
+            .map_err(|e| e.to_string())
+            .and_then(|db_guard| db_guard.rebuild_fts().map_err(|e| e.to_string()));
+
         match result {
-            Ok(count) => {
-                info!("Indexing completed: {} files", count);
-                let _ = app.emit("indexing-completed", count);
+            Ok(()) => {
+                info!("FTS index rebuilt");
+                let _ = app_handle.emit("fts-rebuilt", ());
+                finish_operation(&app_handle, operation_id, OperationStatus::Completed, None);
             }
             Err(e) => {
-                error!("Indexing failed: {}", e);
-                let _ = app.emit("indexing-error", e.to_string());
+                error!("FTS rebuild failed: {}", e);
+                let _ = app_handle.emit("fts-rebuild-error", &e);
+                finish_operation(&app_handle, operation_id, OperationStatus::Failed, Some(e));
             }
         }
     });
 
-    Ok("Indexing started".to_string())
+    Ok(operation_id)
 }
 
+/// Exporta todos los resultados de `query` (sin el límite de página) a `path`
+/// en CSV o JSON. Devuelve el número de filas escritas.
 #[tauri::command]
-async fn get_indexing_status(
+async fn export_results(
+    query: String,
+    mut filters: SearchFilters,
+    path: String,
+    format: export::ExportFormat,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+) -> Result<usize, OxiError> {
+    let (name_query, tag_terms) = tags::extract_tag_terms(&query);
+    if !tag_terms.is_empty() {
+        filters.tags.get_or_insert_with(Vec::new).extend(tag_terms);
+    }
+    let (name_query, interpreter_terms) = interpreter::extract_interpreter_terms(&name_query);
+    filters.interpreters.extend(interpreter_terms);
+    let (name_query, mime_terms) = mime::extract_mime_terms(&name_query);
+    filters.mime_types.extend(mime_terms);
+    // Sin timeout: una exportación puede tardar lo que haga falta.
+    let tuning = SearchTuning {
+        timeout: None,
+        min_query_length: 0,
+        ..SearchTuning::from_config(&*config.lock()?)
+    };
+
+    let pool = Arc::clone(&pool);
+    tokio::task::spawn_blocking(move || {
+        let results = {
+            let reader = pool.get()?;
+            // `usize::MAX` llega a SQLite como `LIMIT -1`, es decir, sin límite.
+            execute_search(&reader, &name_query, &filters, usize::MAX, &tuning)?.0
+        };
+        let rows = export::write_results(Path::new(&path), format, &results)
+            .map_err(|e| OxiError::io_at(format!("Failed to write {}", path), e))?;
+        info!("Exported {} results for {:?} to {}", rows, query, path);
+        Ok(rows)
+    })
+    .await?
+}
+
+/// Fusiona en el índice las entradas de otra base de OxI (otra máquina o perfil).
+/// Devuelve las filas insertadas o actualizadas según `policy`.
+#[tauri::command]
+async fn merge_database(
+    other_path: String,
+    policy: MergePolicy,
     db: tauri::State<'_, Arc<Mutex<Database>>>,
-) -> Result<IndexingStatus, String> {
-    let db_guard = db.lock().map_err(|e| e.to_string())?;
-    let file_count = db_guard.get_file_count().map_err(|e| e.to_string())?;
-    let database_size = db_guard.get_database_size().map_err(|e| e.to_string())?;
-    let last_indexed = db_guard
-        .get_last_indexed_time()
-        .map_err(|e| e.to_string())?;
+    control: tauri::State<'_, Arc<IndexControl>>,
+) -> Result<usize, OxiError> {
+    let other = PathBuf::from(&other_path);
+    if !other.is_file() {
+        return Err(OxiError::not_found(format!("Database not found: {}", other_path)));
+    }
+    if other.canonicalize().ok() == get_db_path().canonicalize().ok() {
+        return Err(OxiError::invalid_input("Cannot merge the index into itself"));
+    }
+    let running = begin_exclusive(&control)?;
+
+    let db_clone = Arc::clone(&db);
+    tokio::task::spawn_blocking(move || {
+        let _running = running;
+        let mut db_guard = db_clone.lock()?;
+        Ok(db_guard.merge_from(&other, policy)?)
+    })
+    .await?
+}
+
+/// Vuelca y trunca el WAL y, con `vacuum`, compacta la base y sus shards para
+/// recuperar el espacio de las entradas borradas. Devuelve los bytes liberados.
+#[tauri::command]
+async fn maintain_database(
+    vacuum: bool,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+) -> Result<u64, OxiError> {
+    let running = begin_exclusive(&control)?;
+
+    let db_clone = Arc::clone(&db);
+    let shards = Arc::clone(&shards);
+    tokio::task::spawn_blocking(move || {
+        let _running = running;
+        let mut databases = vec![db_clone];
+        databases.extend(shards.existing()?);
+
+        let mut reclaimed = 0;
+        for database in databases {
+            reclaimed += database.lock()?.maintain(vacuum)?;
+        }
+
+        info!("Database maintenance reclaimed {} bytes (vacuum={})", reclaimed, vacuum);
+        Ok(reclaimed)
+    })
+    .await?
+}
+
+/// Comprueba que lo indexado sigue en disco (todo o `sample` entradas al azar) y, con
+/// `check_metadata`, que no cambió; incluye `PRAGMA integrity_check`. Con `prune` borra
+/// del índice lo que ya no existe. Se cancela con `cancel_indexing`.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn verify_index(
+    sample: Option<usize>,
+    check_metadata: bool,
+    prune: bool,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+    app_handle: tauri::AppHandle,
+) -> Result<VerifyReport, OxiError> {
+    // Como un índice: `cancel_indexing` lo para y nadie indexa mientras tanto.
+    let running = begin_exclusive(&control)?;
+
+    let operation_id = start_operation(&app_handle, "verify_index").id;
+    let db = Arc::clone(&db);
+    let shards = Arc::clone(&shards);
+    let pool = Arc::clone(&pool);
+    let control = Arc::clone(&control);
+    let result = tokio::task::spawn_blocking(move || -> Result<VerifyReport, OxiError> {
+        let _running = running;
+        let reader = pool.get()?;
+        let (mut report, missing) = verify::verify(&reader, sample, check_metadata, &control)?;
+        drop(reader);
+        if prune && !report.cancelled && !missing.is_empty() {
+            let mut by_database: Vec<(Arc<Mutex<Database>>, Vec<String>)> = Vec::new();
+            for path in missing {
+                let target = shards.existing_writer(&path)?.unwrap_or_else(|| Arc::clone(&db));
+                match by_database.iter_mut().find(|(db, _)| Arc::ptr_eq(db, &target)) {
+                    Some((_, paths)) => paths.push(path),
+                    None => by_database.push((target, vec![path])),
+                }
+            }
+            for (database, paths) in by_database {
+                report.pruned += database.lock()?.delete_paths(&paths)?;
+            }
+        }
+        Ok(report)
+    })
+    .await?;
+
+    match &result {
+        Ok(report) => {
+            info!(
+                "Index verification: {} checked, {} missing, {} stale, {} unreadable, {} pruned, \
+                 {} integrity errors",
+                report.checked,
+                report.missing,
+                report.stale,
+                report.unreadable,
+                report.pruned,
+                report.integrity_errors.len()
+            );
+            let status = if report.cancelled {
+                OperationStatus::Cancelled
+            } else {
+                OperationStatus::Completed
+            };
+            let message = format!("{} checked, {} missing", report.checked, report.missing);
+            finish_operation(&app_handle, operation_id, status, Some(message));
+        }
+        Err(e) => {
+            let message = Some(e.to_string());
+            finish_operation(&app_handle, operation_id, OperationStatus::Failed, message);
+        }
+    }
+    result
+}
+
+/// Quita una entrada del índice (p. ej. "quitar del índice" en el menú contextual); de
+/// un directorio, también lo indexado debajo. Emite `index-entry-removed` con la ruta si
+/// existía.
+#[tauri::command]
+async fn remove_from_index(
+    path: String,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    app_handle: tauri::AppHandle,
+) -> Result<bool, OxiError> {
+    remove_entry(&path, &db, &shards, &app_handle)
+}
+
+/// Borra la fila de `path` (de su shard si lo tiene), con lo que tenga debajo si es un
+/// directorio, y emite `index-entry-removed`.
+fn remove_entry(
+    path: &str,
+    db: &Arc<Mutex<Database>>,
+    shards: &Shards,
+    app_handle: &tauri::AppHandle,
+) -> Result<bool, OxiError> {
+    let db = match shards.existing_writer(path)? {
+        Some(shard) => shard,
+        None => Arc::clone(db),
+    };
+    let removed = {
+        let db_guard = db.lock()?;
+        db_guard.delete_entry(path)? > 0
+    };
+
+    if removed {
+        info!("Removed {} from index", path);
+        let _ = app_handle.emit("index-entry-removed", path);
+    }
+    Ok(removed)
+}
+
+/// Cobertura de cada raíz configurada: si existe y se puede leer, cuándo se recorrió
+/// entera por última vez y si el último recorrido quedó a medias.
+#[tauri::command]
+async fn get_coverage_report(
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+) -> Result<CoverageReport, OxiError> {
+    let (roots, _) = {
+        let config_guard = config.lock()?;
+        configured_roots(&config_guard)
+    };
+    let is_indexing = control.is_running();
+
+    let pool = Arc::clone(&pool);
+    tokio::task::spawn_blocking(move || {
+        let reader = pool.get()?;
+        let meta = |key: &str| reader.get_meta(key);
+
+        let mut report = CoverageReport {
+            roots: Vec::with_capacity(roots.len()),
+            last_full_index: meta(indexer::META_LAST_FULL_INDEX)?,
+            last_refresh: meta(indexer::META_LAST_REFRESH)?,
+            is_indexing,
+        };
+        for root in roots {
+            let metadata = std::fs::metadata(&root).ok();
+            let last_started = meta(&format!("{}{}", indexer::META_ROOT_STARTED, root))?;
+            let last_completed = meta(&format!("{}{}", indexer::META_ROOT_COMPLETED, root))?;
+            report.roots.push(RootCoverage {
+                exists: metadata.is_some(),
+                readable: metadata.is_some_and(|m| m.is_dir()) && std::fs::read_dir(&root).is_ok(),
+                incomplete: last_started.is_some() && last_started != last_completed,
+                indexed_entries: reader.count_under(&root)?,
+                last_started,
+                last_completed,
+                path: root,
+            });
+        }
+        Ok(report)
+    })
+    .await?
+}
+
+#[tauri::command]
+async fn get_indexing_status(
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    control: tauri::State<'_, Arc<IndexControl>>,
+) -> Result<IndexingStatus, OxiError> {
+    let reader = pool.get()?;
+    let file_count = reader.get_file_count()?;
+    let database_size = reader.get_database_size()?;
+    let last_indexed = reader
+        .get_last_indexed_time()?;
 
     Ok(IndexingStatus {
-        is_indexing: false,
+        is_indexing: control.is_running(),
         last_indexed,
         total_files: file_count,
         database_size,
@@ -161,122 +1412,301 @@ async fn get_indexing_status(
 }
 
 #[tauri::command]
-async fn get_config() -> Result<SearchConfig, String> {
-    Ok(SearchConfig::default())
+async fn start_watching(
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    watcher: tauri::State<'_, Arc<IndexWatcher>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), OxiError> {
+    let ((paths, patterns), options) = {
+        let config_guard = config.lock()?;
+        (
+            configured_roots(&config_guard),
+            IndexOptions::from_config(&config_guard),
+        )
+    };
+
+    let on_change = Arc::new(move |event: types::WatchEvent| {
+        let _ = app_handle.emit("watch-event", event);
+    });
+
+    watcher
+        .start(Arc::clone(&db), Arc::clone(&shards), paths, patterns, options, on_change)
+        .map_err(|e| OxiError::Io(e.to_string()))
+}
+
+#[tauri::command]
+async fn stop_watching(watcher: tauri::State<'_, Arc<IndexWatcher>>) -> Result<bool, OxiError> {
+    Ok(watcher.stop())
+}
+
+/// Arranca la API HTTP con la dirección y el puerto de la config; devuelve `host:puerto`.
+#[cfg(feature = "http-api")]
+#[tauri::command]
+async fn start_api_server(
+    server: tauri::State<'_, Arc<server::ApiServer>>,
+    pool: tauri::State<'_, Arc<ReadPool>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    limiter: tauri::State<'_, Arc<RateLimiter>>,
+) -> Result<String, OxiError> {
+    let addr = {
+        let config_guard = config.lock()?;
+        format!("{}:{}", config_guard.api_bind_address, config_guard.api_port)
+    };
+    let addr: std::net::SocketAddr = addr
+        .parse()
+        .map_err(|e| OxiError::invalid_input(format!("Invalid API address {}: {}", addr, e)))?;
+
+    let state = server::ApiState {
+        pool: Arc::clone(&pool),
+        config: Arc::clone(&config),
+        limiter: Arc::clone(&limiter),
+    };
+    let bound = server.start(state, addr).await?;
+    Ok(bound.to_string())
+}
+
+#[cfg(not(feature = "http-api"))]
+#[tauri::command]
+async fn start_api_server() -> Result<String, OxiError> {
+    Err(OxiError::invalid_input("HTTP API not available: build with the `http-api` feature"))
+}
+
+/// Detiene la API HTTP. Devuelve `false` si no estaba activa.
+#[cfg(feature = "http-api")]
+#[tauri::command]
+async fn stop_api_server(
+    server: tauri::State<'_, Arc<server::ApiServer>>,
+) -> Result<bool, OxiError> {
+    Ok(server.stop())
+}
+
+#[cfg(not(feature = "http-api"))]
+#[tauri::command]
+async fn stop_api_server() -> Result<bool, OxiError> {
+    Ok(false)
+}
+
+#[tauri::command]
+async fn get_config(
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+) -> Result<SearchConfig, OxiError> {
+    let config_guard = config.lock()?;
+    Ok(config_guard.clone())
 }
 
 #[tauri::command]
-async fn update_config(config: SearchConfig) -> Result<(), String> {
+async fn update_config(
+    config: SearchConfig,
+    config_state: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    cache: tauri::State<'_, Arc<SearchCache>>,
+) -> Result<(), OxiError> {
     info!("Config updated: {:?}", config);
+    // Umbrales y tiempos de búsqueda cambian los resultados guardados.
+    cache.clear();
+    // Los shards lo toman en su siguiente índice (`Indexer::index_paths`).
+    db.lock()?
+        .set_synchronous(config.db_synchronous)?;
+    let mut config_guard = config_state.lock()?;
+    *config_guard = config;
     Ok(())
 }
 
 #[tauri::command]
-async fn minimize_window(app_handle: tauri::AppHandle) -> Result<(), String> {
+async fn minimize_window(app_handle: tauri::AppHandle) -> Result<(), OxiError> {
     if let Some(window) = app_handle.get_webview_window("main") {
-        window.hide().map_err(|e| e.to_string())?;
+        window.hide()?;
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn toggle_maximize_window(app_handle: tauri::AppHandle) -> Result<(), String> {
+async fn toggle_maximize_window(app_handle: tauri::AppHandle) -> Result<(), OxiError> {
     if let Some(window) = app_handle.get_webview_window("main") {
-        let is_maximized = window.is_maximized().map_err(|e| e.to_string())?;
+        let is_maximized = window.is_maximized()?;
         if is_maximized {
-            window.unmaximize().map_err(|e| e.to_string())?;
+            window.unmaximize()?;
         } else {
-            window.maximize().map_err(|e| e.to_string())?;
+            window.maximize()?;
         }
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn close_window(app_handle: tauri::AppHandle) -> Result<(), String> {
+async fn close_window(app_handle: tauri::AppHandle) -> Result<(), OxiError> {
     if let Some(window) = app_handle.get_webview_window("main") {
-        window.close().map_err(|e| e.to_string())?;
+        window.close()?;
     }
     Ok(())
 }
 
 #[tauri::command]
-async fn start_dragging(app_handle: tauri::AppHandle) -> Result<(), String> {
+async fn start_dragging(app_handle: tauri::AppHandle) -> Result<(), OxiError> {
     if let Some(window) = app_handle.get_webview_window("main") {
-        window.start_dragging().map_err(|e| e.to_string())?;
+        window.start_dragging()?;
     }
     Ok(())
 }
 
+/// Precarga en la caché del SO el inicio de los archivos indicados (opt-in vía
+/// `prefetch_enabled`). Devuelve cuántos se encolaron.
 #[tauri::command]
-async fn open_location(path: String) -> Result<(), String> {
+async fn prefetch_results(
+    paths: Vec<String>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    config: tauri::State<'_, Arc<Mutex<SearchConfig>>>,
+) -> Result<usize, OxiError> {
+    let (enabled, bytes_per_file, max_total_bytes) = {
+        let config_guard = config.lock()?;
+        (
+            config_guard.prefetch_enabled,
+            config_guard.prefetch_bytes_per_file,
+            config_guard.prefetch_max_total_bytes,
+        )
+    };
+    if !enabled {
+        return Ok(0);
+    }
+
+    let targets: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| paths::resolve_open_path(&db, path))
+        .collect();
+    Ok(prefetch::spawn(targets, bytes_per_file, max_total_bytes))
+}
+
+/// Muestra `path` en el explorador de archivos. Si ya no existe en disco, quita su
+/// entrada del índice (`index-entry-removed`) y devuelve `not_found`.
+#[tauri::command]
+async fn open_location(
+    path: String,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    shards: tauri::State<'_, Arc<Shards>>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), OxiError> {
+    let target = paths::existing_or_prune(&db, &path, |path| {
+        remove_entry(path, &db, &shards, &app_handle)
+    })?;
 
     #[cfg(target_os = "windows")]
     {
         std::process::Command::new("explorer")
-            .args(["/select,", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+            .arg("/select,")
+            .arg(&target)
+            .spawn()?;
     }
 
     #[cfg(target_os = "linux")]
     {
-        if std::path::Path::new(&path).is_dir() {
+        if target.is_dir() {
             std::process::Command::new("xdg-open")
-                .arg(&path)
-                .spawn()
-                .map_err(|e| e.to_string())?;
+                .arg(&target)
+                .spawn()?;
         } else {
-            let parent = std::path::Path::new(&path)
+            let parent = target
                 .parent()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|| path.clone());
+                .map(|p| p.to_path_buf())
+                .unwrap_or_else(|| target.clone());
 
             std::process::Command::new("xdg-open")
                 .arg(&parent)
-                .spawn()
-                .map_err(|e| e.to_string())?;
+                .spawn()?;
         }
     }
 
     #[cfg(target_os = "macos")]
     {
         std::process::Command::new("open")
-            .args(["-R", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+            .arg("-R")
+            .arg(&target)
+            .spawn()?;
     }
 
     Ok(())
 }
 
+/// Abre el resultado con la aplicación predeterminada del sistema (un directorio, con el
+/// explorador). `NotFound` si ya no existe.
 #[tauri::command]
-async fn open_item(path: String, _is_dir: bool) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        std::process::Command::new("cmd")
-            .args(["/C", "start", "", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
+async fn open_item(
+    path: String,
+    _is_dir: bool,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+) -> Result<(), OxiError> {
+    open_with_default_app(&db, &path, true)
+}
+
+/// Como `open_item` pero solo archivos: los directorios se abren con `open_location`.
+/// `NotFound` si ya no existe (el índice está desactualizado).
+#[tauri::command]
+async fn open_file(
+    path: String,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+) -> Result<(), OxiError> {
+    open_with_default_app(&db, &path, false)
+}
+
+fn open_with_default_app(
+    db: &Mutex<Database>,
+    path: &str,
+    allow_dirs: bool,
+) -> Result<(), OxiError> {
+    let target = paths::resolve_open_path(db, path);
+    paths::check_openable(path, &target, allow_dirs)?;
+    tauri_plugin_opener::open_path(&target, None::<&str>)
+        .map_err(|e| OxiError::Io(format!("Failed to open {}: {}", path, e)))
+}
+
+/// Copia `path` al portapapeles con los separadores de `style` (`native` por defecto).
+/// Con `trailing_separator`, a los directorios se les añade el separador final.
+/// Devuelve el texto copiado.
+#[tauri::command]
+async fn copy_path(
+    path: String,
+    style: Option<PathStyle>,
+    trailing_separator: Option<bool>,
+) -> Result<String, OxiError> {
+    let style = style.unwrap_or_default();
+    let mut text = paths::with_style(&path, style);
+    if trailing_separator.unwrap_or(false) && Path::new(&path).is_dir() {
+        text = paths::with_trailing_separator(text, style);
     }
+    let copied = text.clone();
+    tokio::task::spawn_blocking(move || clipboard::write_text(&text)).await??;
+    Ok(copied)
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        std::process::Command::new("xdg-open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| e.to_string())?;
+/// Detiene los índices en curso, espera a que guarden su último lote y vuelca el WAL.
+/// Es idempotente: la salida por la bandeja y el evento `Exit` pueden llamarlo ambos.
+fn shutdown(app: &tauri::AppHandle) {
+    let control = app.state::<Arc<IndexControl>>();
+    if control.request_stop() {
+        return;
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        std::process::Command::new("open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| e.to_string())?;
+    app.state::<Arc<IndexWatcher>>().stop();
+
+    info!("Shutting down, waiting for pending index writes");
+    if !control.wait_idle(SHUTDOWN_TIMEOUT) {
+        warn!("Indexing did not stop within {:?}", SHUTDOWN_TIMEOUT);
     }
 
-    Ok(())
+    checkpoint(app);
+}
+
+/// Vuelca el WAL de la base principal al fichero.
+fn checkpoint(app: &tauri::AppHandle) {
+    let db = app.state::<Arc<Mutex<Database>>>();
+    let result = match db.lock() {
+        Ok(db_guard) => db_guard.checkpoint().map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to checkpoint database: {}", e);
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -291,7 +1721,7 @@ pub fn run() {
     info!("OxI Search starting...");
 
     let db_path = get_db_path();
-    let db = match Database::new(db_path) {
+    let db = match Database::new(db_path.clone()) {
         Ok(db) => Arc::new(Mutex::new(db)),
         Err(e) => {
             error!("Failed to initialize database: {}", e);
@@ -300,9 +1730,23 @@ pub fn run() {
     };
 
     info!("Database initialized");
+    let read_pool = Arc::new(ReadPool::new(db_path.clone(), READ_POOL_SIZE));
 
     let db_for_tauri = Arc::clone(&db);
-    tauri::Builder::default()
+    let pool_for_tauri = Arc::clone(&read_pool);
+    let shards = Arc::new(Shards::new(db_path, Arc::clone(&read_pool)));
+    let shards_for_tauri = Arc::clone(&shards);
+    let config = Arc::new(Mutex::new(SearchConfig::default()));
+    let config_for_setup = Arc::clone(&config);
+    let control = Arc::new(IndexControl::new());
+    let control_for_tauri = Arc::clone(&control);
+    let watcher = Arc::new(IndexWatcher::new());
+    let limiter = Arc::new(RateLimiter::new());
+    let search_cache = Arc::new(SearchCache::new());
+    let extension_stats: Arc<IndexCached<bool, Vec<ExtensionStat>>> = Arc::new(IndexCached::new());
+    let search_epochs = Arc::new(SearchEpochs::new());
+    let operations = Arc::new(Operations::new());
+    let builder = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .setup(move |app| {
@@ -316,7 +1760,16 @@ pub fn run() {
                 .show_menu_on_left_click(false)
                 .on_menu_event(|app, event| match event.id.as_ref() {
                     "quit" => {
-                        app.exit(0);
+                        // `shutdown` espera al índice hasta `SHUTDOWN_TIMEOUT`: fuera del
+                        // hilo de la UI, con la ventana ya oculta.
+                        if let Some(window) = app.get_webview_window("main") {
+                            let _ = window.hide();
+                        }
+                        let app = app.clone();
+                        tauri::async_runtime::spawn_blocking(move || {
+                            shutdown(&app);
+                            app.exit(0);
+                        });
                     }
                     "show" => {
                         if let Some(window) = app.get_webview_window("main") {
@@ -331,6 +1784,9 @@ pub fn run() {
                 .build(app)?;
 
             let db_for_setup = Arc::clone(&db);
+            let pool_for_setup = Arc::clone(&read_pool);
+            let shards_for_setup = Arc::clone(&shards);
+            let control_for_setup = Arc::clone(&control);
             let app_handle = app.handle().clone();
 
             #[cfg(desktop)]
@@ -341,20 +1797,20 @@ pub fn run() {
                     tauri_plugin_global_shortcut::Builder::new()
                         .with_shortcuts(["ctrl+p"])?
                         .with_handler(move |app, shortcut, event| {
-                            if event.state == ShortcutState::Pressed {
-                                if shortcut.matches(Modifiers::CONTROL, Code::KeyP) {
-                                    if let Some(window) = app.get_webview_window("main") {
-                                        let is_visible = window.is_visible().unwrap_or(false);
-                                        let is_focused = window.is_focused().unwrap_or(false);
-
-                                        if is_visible && is_focused {
-                                            let _ = window.hide();
-                                        } else {
-                                            let _ = window.unminimize();
-                                            let _ = window.show();
-                                            let _ = window.set_focus();
-                                            let _ = window.emit("focus-search-input", ());
-                                        }
+                            if event.state == ShortcutState::Pressed
+                                && shortcut.matches(Modifiers::CONTROL, Code::KeyP)
+                            {
+                                if let Some(window) = app.get_webview_window("main") {
+                                    let is_visible = window.is_visible().unwrap_or(false);
+                                    let is_focused = window.is_focused().unwrap_or(false);
+
+                                    if is_visible && is_focused {
+                                        let _ = window.hide();
+                                    } else {
+                                        let _ = window.unminimize();
+                                        let _ = window.show();
+                                        let _ = window.set_focus();
+                                        let _ = window.emit("focus-search-input", ());
                                     }
                                 }
                             }
@@ -363,23 +1819,39 @@ pub fn run() {
                 )?;
             }
 
+            tauri::async_runtime::spawn(quick_refresh_loop(app_handle.clone()));
+
             std::thread::spawn(move || {
                 let rt = tokio::runtime::Runtime::new().unwrap();
                 rt.block_on(async move {
-                    let file_count = {
-                        let db_guard = db_for_setup.lock().unwrap();
-                        db_guard.get_file_count().unwrap_or(0)
-                    };
+                    let file_count = pool_for_setup
+                        .get()
+                        .and_then(|reader| reader.get_file_count())
+                        .unwrap_or(0);
 
                     if file_count == 0 {
+                        let Some(_running) = control_for_setup.try_begin() else {
+                            info!("Indexing already in progress, skipping auto-index");
+                            return;
+                        };
                         info!("No files indexed yet, starting automatic indexing");
-                        let indexer = Indexer::new(db_for_setup);
+                        let mut options = {
+                            let config_guard = config_for_setup.lock().unwrap();
+                            IndexOptions::from_config(&config_guard)
+                        };
+                        options.record_run_as = Some(indexer::META_LAST_FULL_INDEX);
+                        let indexer = Indexer::new(db_for_setup, Arc::clone(&control_for_setup))
+                            .with_options(options)
+                            .with_shards(shards_for_setup)
+                            .with_directory_callback(directory_callback(&app_handle));
 
                         let paths_to_index = Indexer::get_default_indexing_paths();
                         let patterns = Indexer::get_default_exclude_patterns();
 
+                        let operation_id = start_operation(&app_handle, "auto_index").id;
                         let app_clone = app_handle.clone();
-                        let progress_callback = Arc::new(move |progress: types::IndexingProgress| {
+                        let progress_callback = Arc::new(move |mut progress: types::IndexingProgress| {
+                            progress.operation_id = Some(operation_id);
                             info!("Auto-indexing progress: {:?}", progress);
                             let _ = app_clone.emit("indexing-progress", progress);
                         });
@@ -388,16 +1860,14 @@ pub fn run() {
                             .index_multiple_paths(paths_to_index, patterns, progress_callback)
                             .await;
 
-                        match result {
-                            Ok(count) => {
-                                info!("Auto-indexing completed: {} files", count);
-                                let _ = app_handle.emit("indexing-completed", count);
-                            }
-                            Err(e) => {
-                                error!("Auto-indexing failed: {}", e);
-                                let _ = app_handle.emit("indexing-error", e.to_string());
-                            }
-                        }
+                        finish_index_run(
+                            &app_handle,
+                            operation_id,
+                            &control_for_setup,
+                            indexer.limit_reached(),
+                            indexer.access_warnings(),
+                            result,
+                        );
                     } else {
                         info!("Database already contains {} files, skipping auto-index", file_count);
                     }
@@ -406,27 +1876,87 @@ pub fn run() {
 
             Ok(())
         })
-        .on_window_event(|window, event| match event {
-            WindowEvent::CloseRequested { api, .. } => {
+        .on_window_event(|window, event| {
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                // Cerrar solo oculta la ventana (la app sigue en la bandeja); se vuelca el
+                // WAL para que lo escrito no dependa de una salida limpia.
                 window.hide().unwrap();
                 api.prevent_close();
+                let app = window.app_handle().clone();
+                tauri::async_runtime::spawn_blocking(move || checkpoint(&app));
             }
-            _ => {}
         })
         .manage(db_for_tauri)
+        .manage(pool_for_tauri)
+        .manage(shards_for_tauri)
+        .manage(config)
+        .manage(control_for_tauri)
+        .manage(watcher)
+        .manage(limiter)
+        .manage(search_cache)
+        .manage(extension_stats)
+        .manage(search_epochs)
+        .manage(operations);
+    #[cfg(feature = "http-api")]
+    let builder = builder.manage(Arc::new(server::ApiServer::new()));
+
+    builder
         .invoke_handler(tauri::generate_handler![
             search_files,
+            search_files_streaming,
+            refine_search,
+            search_content,
+            find_siblings_by_time,
+            find_empty,
+            find_duplicates,
+            get_search_history,
+            clear_search_history,
+            save_search,
+            list_saved_searches,
+            run_saved_search,
+            delete_saved_search,
+            get_mime_summary,
+            get_extension_stats,
+            preview_text,
+            get_file_details,
+            export_results,
             reindex_path,
+            validate_indexing_path,
+            index_single_directory,
+            preview_index,
+            index_since,
+            cancel_indexing,
+            pause_indexing,
+            resume_indexing,
+            rebuild_fts,
+            merge_database,
+            maintain_database,
+            get_operations,
+            remove_from_index,
+            verify_index,
             get_indexing_status,
+            get_coverage_report,
+            start_watching,
+            stop_watching,
+            start_api_server,
+            stop_api_server,
             get_config,
             update_config,
             open_location,
             open_item,
+            open_file,
+            copy_path,
+            prefetch_results,
             minimize_window,
             toggle_maximize_window,
             close_window,
             start_dragging,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                shutdown(app);
+            }
+        });
 }
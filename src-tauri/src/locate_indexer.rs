@@ -0,0 +1,355 @@
+//! Índice rápido en Linux a partir de la base de `updatedb` (mlocate): lista las rutas
+//! que ya recorrió el sistema en vez de leer cada directorio, y solo se leen sus
+//! metadatos.
+//!
+//! La base refleja el último `updatedb` (normalmente diario), así que puede faltar lo
+//! más reciente: por eso se puede completar con un recorrido (`locate_refresh_walk`).
+
+use crate::db::{Database, UpsertStats};
+use crate::exclude::ExcludeMatcher;
+use crate::indexer::{IndexControl, IndexOptions, RunLimits};
+use crate::types::{FileRecord, IndexingProgress};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::info;
+
+/// Bases conocidas, en orden de preferencia.
+const DATABASE_PATHS: &[&str] = &["/var/lib/mlocate/mlocate.db", "/var/lib/plocate/plocate.db"];
+
+const MLOCATE_MAGIC: &[u8; 8] = b"\0mlocate";
+const PLOCATE_MAGIC: &[u8; 8] = b"\0plocate";
+
+/// Tipos de entrada dentro de un directorio de mlocate.
+const ENTRY_FILE: u8 = 0;
+const ENTRY_SUBDIR: u8 = 1;
+const ENTRY_END: u8 = 2;
+
+/// Primera base de `updatedb` que existe y se puede leer (suelen ser del grupo
+/// `mlocate`/`plocate`, no legibles por cualquier usuario).
+pub fn find_database() -> Option<PathBuf> {
+    DATABASE_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| File::open(path).is_ok())
+}
+
+/// Lector del formato de mlocate: cabecera (`\0mlocate`, tamaño de la configuración,
+/// versión, visibilidad, relleno, raíz y configuración) y después cada directorio con
+/// su mtime y ruta absoluta seguido de sus entradas (tipo + nombre, hasta `ENTRY_END`).
+pub struct MlocateReader<R> {
+    reader: R,
+    /// Directorio cuyas entradas se están leyendo.
+    dir: Option<PathBuf>,
+}
+
+impl<R: BufRead> MlocateReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 16];
+        reader.read_exact(&mut header)?;
+        if &header[..8] == PLOCATE_MAGIC {
+            return Err(invalid_data("plocate databases are not supported"));
+        }
+        if &header[..8] != MLOCATE_MAGIC {
+            return Err(invalid_data("not an mlocate database"));
+        }
+        let config_size = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        if header[12] != 0 {
+            return Err(invalid_data(&format!("unsupported mlocate version {}", header[12])));
+        }
+
+        // La raíz de `updatedb` no hace falta: cada directorio lleva su ruta completa.
+        read_cstr(&mut reader)?;
+        let skipped = io::copy(&mut (&mut reader).take(config_size as u64), &mut io::sink())?;
+        if skipped != config_size as u64 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(Self { reader, dir: None })
+    }
+
+    fn next_path(&mut self) -> io::Result<Option<PathBuf>> {
+        loop {
+            let Some(dir) = &self.dir else {
+                if self.reader.fill_buf()?.is_empty() {
+                    return Ok(None);
+                }
+                // Segundos (8), nanosegundos (4) y relleno (4) del mtime del directorio.
+                let mut header = [0u8; 16];
+                self.reader.read_exact(&mut header)?;
+                self.dir = Some(bytes_to_path(read_cstr(&mut self.reader)?));
+                continue;
+            };
+
+            let mut kind = [0u8; 1];
+            self.reader.read_exact(&mut kind)?;
+            match kind[0] {
+                ENTRY_FILE | ENTRY_SUBDIR => {
+                    return Ok(Some(dir.join(bytes_to_path(read_cstr(&mut self.reader)?))));
+                }
+                ENTRY_END => self.dir = None,
+                other => return Err(invalid_data(&format!("unknown mlocate entry type {}", other))),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MlocateReader<R> {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_path().transpose()
+    }
+}
+
+/// Lee hasta el siguiente NUL (sin incluirlo).
+fn read_cstr(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_until(0, &mut bytes)?;
+    if bytes.pop() != Some(0) {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub struct LocateIndexer {
+    db: Arc<Mutex<Database>>,
+    control: Arc<IndexControl>,
+    limits: Arc<RunLimits>,
+    options: IndexOptions,
+}
+
+impl LocateIndexer {
+    pub fn new(
+        db: Arc<Mutex<Database>>,
+        control: Arc<IndexControl>,
+        limits: Arc<RunLimits>,
+        options: IndexOptions,
+    ) -> Self {
+        Self {
+            db,
+            control,
+            limits,
+            options,
+        }
+    }
+
+    /// Indexa las rutas de `database` bajo `root`. Devuelve las entradas guardadas.
+    pub fn index_path(
+        &self,
+        database: &Path,
+        root: &str,
+        exclude_patterns: &[String],
+        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        info!("Starting locate indexing of {} from {}", root, database.display());
+        let start = Instant::now();
+
+        let reader = MlocateReader::new(BufReader::new(File::open(database)?))?;
+        let excludes = ExcludeMatcher::new(exclude_patterns);
+        let root_path = Path::new(root);
+        let mut flush = self.options.flush_policy();
+        let mut batch_buffer: Vec<FileRecord> = Vec::with_capacity(flush.batch_size());
+        let mut stats = UpsertStats::default();
+        let mut files_found = 0;
+
+        for path in reader {
+            if self.control.is_paused() {
+                stats.add(self.flush_batch(&mut batch_buffer)?);
+                flush.flushed();
+                self.control.wait_if_paused();
+            }
+            if self.control.should_stop() || self.limits.is_reached() {
+                info!("Stopping locate indexing of {} early", root);
+                break;
+            }
+
+            let path = path?;
+            if !path.starts_with(root_path)
+                || excludes.is_excluded(&path)
+                || self.options.skips_hidden(&path, root_path)
+            {
+                continue;
+            }
+            // Las rutas borradas desde el último `updatedb` se saltan aquí.
+            let Some(record) = self.options.path_record(&path) else {
+                continue;
+            };
+
+            files_found += 1;
+            progress_callback(IndexingProgress {
+                current_path: record.path.clone(),
+                files_processed: files_found,
+                total_files: None,
+                status: "indexing".to_string(),
+                operation_id: None,
+                inserted: stats.inserted,
+                updated: stats.updated,
+                skipped: stats.skipped,
+            });
+            batch_buffer.push(record);
+
+            if flush.is_due(batch_buffer.len()) {
+                stats.add(self.flush_batch(&mut batch_buffer)?);
+                flush.flushed();
+            }
+            if self.limits.count(1) {
+                break;
+            }
+        }
+        stats.add(self.flush_batch(&mut batch_buffer)?);
+
+        info!(
+            "Locate indexing of {} completed: {} entries in {:?} ({:?})",
+            root,
+            files_found,
+            start.elapsed(),
+            stats
+        );
+        Ok(files_found)
+    }
+
+    fn flush_batch(&self, batch: &mut Vec<FileRecord>) -> Result<UpsertStats, Box<dyn std::error::Error>> {
+        if batch.is_empty() {
+            return Ok(UpsertStats::default());
+        }
+
+        let mut db_guard = self.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let stats = db_guard.upsert_batch(batch.as_slice())?;
+        batch.clear();
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, TempDir};
+
+    /// Base de mlocate con `dirs` (ruta, entradas) y una configuración de relleno.
+    fn mlocate_db(root: &str, dirs: &[(&str, &[(u8, &str)])]) -> Vec<u8> {
+        let config = b"prune_bind_mounts\0\0";
+        let mut bytes = MLOCATE_MAGIC.to_vec();
+        bytes.extend((config.len() as u32).to_be_bytes());
+        bytes.extend([0, 1, 0, 0]); // versión, visibilidad y relleno
+        bytes.extend(root.as_bytes());
+        bytes.push(0);
+        bytes.extend(config);
+        for (dir, entries) in dirs {
+            bytes.extend([0u8; 16]); // mtime del directorio
+            bytes.extend(dir.as_bytes());
+            bytes.push(0);
+            for (kind, name) in *entries {
+                bytes.push(*kind);
+                bytes.extend(name.as_bytes());
+                bytes.push(0);
+            }
+            bytes.push(ENTRY_END);
+        }
+        bytes
+    }
+
+    fn fixture(root: &str) -> Vec<u8> {
+        let sub = format!("{}/sub", root);
+        let deep = format!("{}/sub/deep", root);
+        mlocate_db(
+            root,
+            &[
+                (root, &[(ENTRY_FILE, "a.txt"), (ENTRY_SUBDIR, "sub")]),
+                (&sub, &[(ENTRY_SUBDIR, "deep"), (ENTRY_FILE, "b.txt")]),
+                (&deep, &[(ENTRY_FILE, "c.txt")]),
+            ],
+        )
+    }
+
+    fn read_all(bytes: &[u8]) -> io::Result<Vec<PathBuf>> {
+        MlocateReader::new(bytes)?.collect()
+    }
+
+    #[test]
+    fn reads_nested_directories() {
+        let paths = read_all(&fixture("/r")).unwrap();
+        let expected = [
+            "/r/a.txt",
+            "/r/sub",
+            "/r/sub/deep",
+            "/r/sub/b.txt",
+            "/r/sub/deep/c.txt",
+        ];
+        assert_eq!(paths, expected.map(PathBuf::from));
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        let mut plocate = fixture("/r");
+        plocate[..8].copy_from_slice(PLOCATE_MAGIC);
+        assert_eq!(read_all(&plocate).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut version = fixture("/r");
+        version[12] = 1;
+        assert_eq!(read_all(&version).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        assert!(read_all(b"not a database at all").is_err());
+    }
+
+    #[test]
+    fn truncated_or_malformed_databases_fail() {
+        let bytes = fixture("/r");
+        // Cortada en la cabecera, en la configuración o a mitad de un nombre.
+        for len in [10, 20, bytes.len() - 3] {
+            let result = read_all(&bytes[..len]);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::UnexpectedEof, "len {}", len);
+        }
+
+        // El byte anterior al nombre es el tipo de entrada.
+        let mut bad_kind = fixture("/r");
+        let entry = bad_kind.windows(6).position(|w| w == b"\0a.txt").unwrap();
+        bad_kind[entry] = 7;
+        let result = read_all(&bad_kind);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn indexes_the_entries_still_on_disk() {
+        let dir = TempDir::new("locate");
+        let root = dir.path().join("r");
+        std::fs::create_dir_all(root.join("sub/deep")).unwrap();
+        std::fs::write(root.join("a.txt"), b"").unwrap();
+        std::fs::write(root.join("sub/deep/c.txt"), b"").unwrap();
+        // `sub/b.txt` se borró después del último `updatedb`.
+        let root = root.to_string_lossy().into_owned();
+        let database = dir.path().join("mlocate.db");
+        std::fs::write(&database, fixture(&root)).unwrap();
+
+        let (db, reader) = open_index(&dir);
+        let indexer = LocateIndexer::new(
+            Arc::new(Mutex::new(db)),
+            Arc::new(IndexControl::new()),
+            Arc::new(RunLimits::new(None, None)),
+            IndexOptions::default(),
+        );
+        let count = indexer.index_path(&database, &root, &[], Arc::new(|_| {})).unwrap();
+
+        assert_eq!(count, 4);
+        assert_eq!(reader.get_file_count().unwrap(), 4);
+        assert!(reader.file_by_path(&format!("{}/sub/deep/c.txt", root)).unwrap().is_some());
+        assert!(reader.file_by_path(&format!("{}/sub/b.txt", root)).unwrap().is_none());
+    }
+}
@@ -1,42 +1,124 @@
-use crate::db::Database;
+use crate::category;
+use crate::db::{Database, UpsertStats};
+use crate::elevation;
+use crate::indexer::{
+    FlushPolicy, IndexControl, RunLimits, DEFAULT_BATCH_SIZE, WINDOWS_ATTRIBUTE_MASK,
+};
 use crate::types::{FileRecord, IndexingProgress};
 use byteorder::{LittleEndian, ReadBytesExt};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::fs::File;
 use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
 const MFT_RECORD_SIZE: usize = 1024;
+const ATTR_STANDARD_INFORMATION: u32 = 0x10;
 const ATTR_FILENAME: u32 = 0x30;
+const ATTR_DATA: u32 = 0x80;
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+/// Segundos entre 1601-01-01 (época de FILETIME) y 1970-01-01.
+const FILETIME_UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+/// Un $FILE_NAME ocupa como mucho 0x42 + 255 * 2 bytes; más es un registro corrupto.
+const MAX_NON_RESIDENT_NAME_SIZE: u64 = 4096;
 const END_OF_ATTRIBUTES: u32 = 0xFFFFFFFF;
+/// Número de registro dentro de una referencia de archivo (el resto es la secuencia).
+const PARENT_RECORD_MASK: u64 = 0x0000_FFFF_FFFF_FFFF;
+
+/// Errores de `index_drive` que la UI distingue del resto.
+#[derive(Debug, thiserror::Error)]
+pub enum MftError {
+    /// Windows no deja abrir el volumen; sin `elevated`, ejecutar como administrador
+    /// lo resolvería.
+    #[error("access to drive {drive} denied (run as administrator for fast indexing)")]
+    AccessDenied { drive: String, elevated: bool },
+}
+
+/// Resultado de `index_drive`.
+#[derive(Debug, Clone, Copy)]
+pub struct MftScan {
+    pub files_found: usize,
+    /// Se alcanzó `max_records` (o un límite de la ejecución) antes de terminar de leer la MFT.
+    pub truncated: bool,
+}
 
 pub struct MftIndexer {
     db: Arc<Mutex<Database>>,
+    control: Arc<IndexControl>,
+    max_records: Option<usize>,
+    limits: Option<Arc<RunLimits>>,
+    batch_size: usize,
+    flush_interval: Option<Duration>,
 }
 
 impl MftIndexer {
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
-        Self { db }
+    pub fn new(db: Arc<Mutex<Database>>, control: Arc<IndexControl>) -> Self {
+        Self {
+            db,
+            control,
+            max_records: None,
+            limits: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: None,
+        }
+    }
+
+    /// Límite de registros MFT a leer (`None` = hasta que falle la lectura).
+    pub fn with_max_records(mut self, max_records: Option<usize>) -> Self {
+        self.max_records = max_records;
+        self
+    }
+
+    /// Entradas por transacción y frecuencia mínima de guardado (ver `FlushPolicy`).
+    pub fn with_batching(mut self, batch_size: usize, flush_interval: Option<Duration>) -> Self {
+        self.batch_size = batch_size;
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Límites de la ejecución del `Indexer` que lanza este escaneo.
+    pub fn with_limits(mut self, limits: Arc<RunLimits>) -> Self {
+        self.limits = Some(limits);
+        self
     }
 
     pub async fn index_drive(
         &self,
         drive: &str,
         progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
-    ) -> Result<usize, Box<dyn std::error::Error>> {
+    ) -> Result<MftScan, Box<dyn std::error::Error>> {
         info!("Starting MFT indexing of drive: {}", drive);
-        let start = Instant::now();
 
         let path = format!(r"\\.\{}:", drive);
-        let f = File::open(&path).map_err(|e| {
-            warn!("Failed to open drive {} for MFT access: {}", drive, e);
-            e
-        })?;
+        let f = match File::open(&path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                let elevated = elevation::is_elevated();
+                warn!("Access to drive {} denied for MFT reading (elevated: {})", drive, elevated);
+                return Err(MftError::AccessDenied {
+                    drive: drive.to_string(),
+                    elevated,
+                }
+                .into());
+            }
+            Err(e) => {
+                warn!("Failed to open drive {} for MFT access: {}", drive, e);
+                return Err(e.into());
+            }
+        };
 
-        let mut reader = SectorReader::new(f, 4096);
+        self.scan(drive, SectorReader::new(f, 4096), progress_callback)
+    }
 
+    /// Lee la MFT de `reader` (el volumen entero, desde el sector de arranque).
+    fn scan<R: Read + Seek>(
+        &self,
+        drive: &str,
+        mut reader: R,
+        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<MftScan, Box<dyn std::error::Error>> {
+        let start = Instant::now();
         let mut boot_sector = vec![0u8; 512];
         reader.seek(SeekFrom::Start(0))?;
         reader.read_exact(&mut boot_sector)?;
@@ -57,17 +139,49 @@ impl MftIndexer {
             bytes_per_sector, cluster_size, mft_cluster_lcn, mft_offset
         );
 
+        // El registro 0 es el propio $MFT: su tamaño da el número de registros, que
+        // sirve como total estimado para el progreso.
+        reader.seek(SeekFrom::Start(mft_offset))?;
+        let mut first_record = vec![0u8; MFT_RECORD_SIZE];
+        reader.read_exact(&mut first_record)?;
+        let mut total_records = apply_fixups(&mut first_record, bytes_per_sector as usize)
+            .then(|| mft_data_size(&first_record))
+            .flatten()
+            .map(|size| (size / MFT_RECORD_SIZE as u64) as usize);
+        if let Some(max) = self.max_records {
+            total_records = total_records.map(|total| total.min(max));
+        }
+        info!("MFT holds about {:?} records", total_records);
+
         reader.seek(SeekFrom::Start(mft_offset))?;
 
         let mut records_processed = 0;
         let mut files_found = 0;
+        let mut stats = UpsertStats::default();
         let mut buffer = vec![0u8; MFT_RECORD_SIZE];
-        const BATCH_SIZE: usize = 5_000;
-        let mut batch_buffer: Vec<FileRecord> = Vec::with_capacity(BATCH_SIZE);
+        let mut flush = FlushPolicy::new(self.batch_size, self.flush_interval);
+        let mut batch_buffer: Vec<FileRecord> = Vec::with_capacity(flush.batch_size());
 
-        let max_scan = 1_000_000;
+        let mut truncated = false;
+
+        for i in 0usize.. {
+            if self.max_records.is_some_and(|max| i >= max)
+                || self.limits.as_ref().is_some_and(|limits| limits.is_reached())
+            {
+                truncated = true;
+                break;
+            }
+
+            if self.control.is_paused() {
+                stats.add(self.flush_batch(&mut batch_buffer)?);
+                flush.flushed();
+                self.control.wait_if_paused();
+            }
+            if self.control.should_stop() {
+                info!("Stop requested, finishing MFT scan of {} early", drive);
+                break;
+            }
 
-        for i in 0..max_scan {
             if reader.read_exact(&mut buffer).is_err() {
                 break;
             }
@@ -81,117 +195,30 @@ impl MftIndexer {
                 continue;
             }
 
-            let mut rdr = Cursor::new(&buffer);
-            rdr.set_position(0x16);
-            let flags = rdr.read_u16::<LittleEndian>()?;
-            let in_use = (flags & 0x01) != 0;
-
-            rdr.set_position(0x14);
-            let first_attr_offset = rdr.read_u16::<LittleEndian>()? as u64;
-            rdr.set_position(first_attr_offset);
-
-            let mut filename = None;
-            let mut file_size = None;
-            let mut is_dir = false;
-
-            loop {
-                if rdr.position() >= MFT_RECORD_SIZE as u64 - 8 {
-                    break;
-                }
-                let attr_start_pos = rdr.position();
-                let attr_type = rdr.read_u32::<LittleEndian>()?;
-                if attr_type == END_OF_ATTRIBUTES {
-                    break;
-                }
-                let attr_len = rdr.read_u32::<LittleEndian>()?;
-                if attr_len == 0 {
-                    break;
-                }
-
-                if attr_type == ATTR_FILENAME && filename.is_none() {
-                    rdr.set_position(attr_start_pos + 8);
-                    let non_resident = rdr.read_u8()? != 0;
-
-                    if !non_resident {
-                        rdr.set_position(attr_start_pos + 20);
-                        let content_offset = rdr.read_u16::<LittleEndian>()? as u64;
-                        let absolute_content_pos = attr_start_pos + content_offset;
-                        rdr.set_position(absolute_content_pos);
-
-                        if rdr.seek(SeekFrom::Current(48)).is_ok() {
-                            let flags = rdr.read_u32::<LittleEndian>()?;
-                            is_dir = (flags & 0x10000000) != 0;
-                        }
-
-                        if rdr.seek(SeekFrom::Current(8)).is_ok() {
-                            let size = rdr.read_u64::<LittleEndian>()?;
-                            file_size = Some(size as i64);
-                        }
-
-                        rdr.set_position(absolute_content_pos);
-
-                        if rdr.seek(SeekFrom::Current(64)).is_err() {
-                            rdr.set_position(attr_start_pos + attr_len as u64);
-                            continue;
-                        }
-
-                        let name_len = rdr.read_u8()?;
-                        let _namespace = rdr.read_u8()?;
-
-                        let name_bytes_len = (name_len as usize) * 2;
-                        let mut name_buffer = vec![0u8; name_bytes_len];
-
-                        if rdr.read_exact(&mut name_buffer).is_ok() {
-                            let u16_vec: Vec<u16> = name_buffer
-                                .chunks_exact(2)
-                                .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-                                .collect();
-
-                            if let Ok(name) = String::from_utf16(&u16_vec) {
-                                filename = Some(name);
-                            }
-                        }
-                    }
-                }
-
-                rdr.set_position(attr_start_pos + attr_len as u64);
+            let Some(entry) = parse_record(&buffer, &mut reader, cluster_size, i)? else {
+                continue;
+            };
+            // Los ADS se indexan como entradas propias, pero no cuentan como archivos.
+            if let Some(limits) = &self.limits {
+                limits.count(1);
             }
-
-            if let Some(name) = filename {
-                if in_use && !name.is_empty() {
-                    let path = format!("{}:\\{}", drive, name);
-                    let modified_time_str = Utc::now().to_rfc3339();
-                    let last_indexed_str = Utc::now().to_rfc3339();
-
-                    let extension = if is_dir {
-                        None
-                    } else {
-                        name.rfind('.').map(|idx| format!(".{}", &name[idx..]))
-                    };
-
-                    batch_buffer.push(FileRecord {
-                        path,
-                        name,
-                        extension,
-                        file_size,
-                        is_dir,
-                        modified_time: modified_time_str,
-                        last_indexed: last_indexed_str,
-                    });
-
-                    files_found += 1;
-
-                    progress_callback(IndexingProgress {
-                        current_path: format!("{}\\...", drive),
-                        files_processed: files_found,
-                        total_files: None,
-                        status: "indexing".to_string(),
-                    });
-
-                    if batch_buffer.len() >= BATCH_SIZE {
-                        self.flush_batch(&mut batch_buffer)?;
-                    }
-                }
+            files_found += 1;
+            batch_buffer.extend(entry.into_records(drive));
+
+            progress_callback(IndexingProgress {
+                current_path: format!("{}\\...", drive),
+                files_processed: files_found,
+                total_files: total_records,
+                status: "indexing".to_string(),
+                operation_id: None,
+                inserted: stats.inserted,
+                updated: stats.updated,
+                skipped: stats.skipped,
+            });
+
+            if flush.is_due(batch_buffer.len()) {
+                stats.add(self.flush_batch(&mut batch_buffer)?);
+                flush.flushed();
             }
 
             if i % 50000 == 0 && i > 0 {
@@ -199,57 +226,452 @@ impl MftIndexer {
             }
         }
 
-        self.flush_batch(&mut batch_buffer)?;
+        stats.add(self.flush_batch(&mut batch_buffer)?);
+
+        if truncated {
+            warn!(
+                "MFT scan of {} stopped at a configured limit after {} records",
+                drive, records_processed
+            );
+            progress_callback(IndexingProgress {
+                current_path: format!("{}\\...", drive),
+                files_processed: files_found,
+                total_files: total_records,
+                status: "mft-truncated".to_string(),
+                operation_id: None,
+                inserted: stats.inserted,
+                updated: stats.updated,
+                skipped: stats.skipped,
+            });
+        }
 
         let elapsed = start.elapsed();
         info!(
-            "MFT indexing completed: processed={} files_found={} in {:?}",
-            records_processed, files_found, elapsed
+            "MFT indexing completed: processed={} files_found={} in {:?} ({:?})",
+            records_processed, files_found, elapsed, stats
         );
 
-        Ok(files_found)
+        Ok(MftScan {
+            files_found,
+            truncated,
+        })
     }
 
-    fn flush_batch(&self, batch: &mut Vec<FileRecord>) -> Result<usize, Box<dyn std::error::Error>> {
+    fn flush_batch(&self, batch: &mut Vec<FileRecord>) -> Result<UpsertStats, Box<dyn std::error::Error>> {
         if batch.is_empty() {
-            return Ok(0);
+            return Ok(UpsertStats::default());
         }
 
         let mut db_guard = self.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
         let batch_len = batch.len();
 
         match db_guard.upsert_batch(batch.as_slice()) {
-            Ok(()) => {
+            Ok(stats) => {
                 batch.clear();
-                Ok(batch_len)
+                Ok(stats)
             }
             Err(e) => {
                 warn!("Batch upsert failed ({} items): {}. Falling back to item-by-item.", batch_len, e);
 
-                let mut ok_count = 0usize;
+                let mut stats = UpsertStats::default();
                 for r in batch.iter() {
-                    if let Err(item_err) = db_guard.upsert_file(
-                        r.path.as_str(),
-                        r.name.as_str(),
-                        r.extension.as_deref(),
-                        r.file_size,
-                        r.is_dir,
-                        r.modified_time.as_str(),
-                        r.last_indexed.as_str(),
-                    ) {
-                        warn!("Failed to upsert {}: {}", r.path, item_err);
-                    } else {
-                        ok_count += 1;
+                    match db_guard.upsert_file(r) {
+                        Ok(outcome) => stats.record(outcome),
+                        Err(item_err) => warn!("Failed to upsert {}: {}", r.path, item_err),
                     }
                 }
 
                 batch.clear();
-                Ok(ok_count)
+                Ok(stats)
             }
         }
     }
 }
 
+/// Lo que interesa de un registro de la MFT en uso.
+struct MftEntry {
+    name: String,
+    /// Registro del directorio padre; aún no se usa para construir rutas completas.
+    #[allow(dead_code)]
+    parent: u64,
+    is_dir: bool,
+    file_size: Option<i64>,
+    created_time: Option<String>,
+    is_reparse: bool,
+    mode: Option<u32>,
+    link_count: i64,
+    /// Flujos de datos con nombre (ADS): (nombre, tamaño).
+    streams: Vec<(String, i64)>,
+}
+
+impl MftEntry {
+    /// Entradas del índice: una por cada ADS (`ruta:flujo`) y la del propio archivo.
+    fn into_records(self, drive: &str) -> Vec<FileRecord> {
+        let path = format!("{}:\\{}", drive, self.name);
+        let modified_time_str = Utc::now().to_rfc3339();
+        let last_indexed_str = Utc::now().to_rfc3339();
+
+        let extension = if self.is_dir {
+            None
+        } else {
+            self.name.rfind('.').map(|idx| self.name[idx..].to_string())
+        };
+        let category = category::from_extension(extension.as_deref()).map(String::from);
+
+        let mut records: Vec<FileRecord> = self
+            .streams
+            .into_iter()
+            .map(|(stream, size)| FileRecord {
+                path: format!("{}:{}", path, stream),
+                name: format!("{}:{}", self.name, stream),
+                extension: None,
+                file_size: Some(size),
+                is_dir: false,
+                modified_time: modified_time_str.clone(),
+                created_time: self.created_time.clone(),
+                last_indexed: last_indexed_str.clone(),
+                path_lossy: false,
+                raw_path: None,
+                tags: None,
+                is_reparse: false,
+                link_count: self.link_count,
+                file_id: None,
+                category: None,
+                interpreter: None,
+                mime: None,
+                mode: self.mode,
+                owner_uid: None,
+                owner_gid: None,
+                content: None,
+            })
+            .collect();
+
+        records.push(FileRecord {
+            path,
+            name: self.name,
+            extension,
+            file_size: self.file_size,
+            is_dir: self.is_dir,
+            modified_time: modified_time_str,
+            created_time: self.created_time,
+            last_indexed: last_indexed_str,
+            path_lossy: false,
+            raw_path: None,
+            tags: None,
+            is_reparse: self.is_reparse,
+            link_count: self.link_count,
+            // Solo se guarda un nombre por registro: cada archivo, una ruta.
+            file_id: None,
+            category,
+            interpreter: None,
+            mime: None,
+            mode: self.mode,
+            owner_uid: None,
+            owner_gid: None,
+            content: None,
+        });
+        records
+    }
+}
+
+/// Decodifica el registro `index` (ya con `apply_fixups`). `None` si no está en uso o
+/// no tiene nombre. Un $FILE_NAME no residente se lee de `reader`.
+fn parse_record<R: Read + Seek>(
+    buffer: &[u8],
+    reader: &mut R,
+    cluster_size: u64,
+    index: usize,
+) -> std::io::Result<Option<MftEntry>> {
+    let mut rdr = Cursor::new(buffer);
+    rdr.set_position(0x16);
+    let flags = rdr.read_u16::<LittleEndian>()?;
+    let in_use = (flags & 0x01) != 0;
+
+    rdr.set_position(0x12);
+    let link_count = rdr.read_u16::<LittleEndian>()?.max(1) as i64;
+
+    rdr.set_position(0x14);
+    let first_attr_offset = rdr.read_u16::<LittleEndian>()? as u64;
+    rdr.set_position(first_attr_offset);
+
+    let mut file_name = None;
+    let mut created_time = None;
+    let mut is_reparse = false;
+    let mut mode = None;
+    let mut streams: Vec<(String, i64)> = Vec::new();
+
+    loop {
+        if rdr.position() >= MFT_RECORD_SIZE as u64 - 8 {
+            break;
+        }
+        let attr_start_pos = rdr.position();
+        let attr_type = rdr.read_u32::<LittleEndian>()?;
+        if attr_type == END_OF_ATTRIBUTES {
+            break;
+        }
+        let attr_len = rdr.read_u32::<LittleEndian>()?;
+        if attr_len == 0 {
+            break;
+        }
+        let attr_end = (attr_start_pos + attr_len as u64).min(MFT_RECORD_SIZE as u64);
+        let attr = &buffer[attr_start_pos as usize..attr_end as usize];
+
+        if attr_type == ATTR_STANDARD_INFORMATION && created_time.is_none() {
+            // $STANDARD_INFORMATION siempre es residente: creación en 0x00 y atributos
+            // DOS/Win32 en 0x20.
+            if attr.get(8) == Some(&0) {
+                rdr.set_position(attr_start_pos + 20);
+                let content_offset = rdr.read_u16::<LittleEndian>()? as u64;
+                rdr.set_position(attr_start_pos + content_offset);
+                created_time = filetime_to_rfc3339(rdr.read_u64::<LittleEndian>()?);
+                rdr.set_position(attr_start_pos + content_offset + 0x20);
+                let attributes = rdr.read_u32::<LittleEndian>()?;
+                is_reparse = (attributes & FILE_ATTRIBUTE_REPARSE_POINT) != 0;
+                mode = Some(attributes & WINDOWS_ATTRIBUTE_MASK);
+            }
+        }
+
+        if attr_type == ATTR_FILENAME && file_name.is_none() {
+            let content = if attr.get(8) == Some(&0) {
+                resident_content(attr).map(<[u8]>::to_vec)
+            } else {
+                // Casi nunca ocurre, pero si el atributo es no residente el nombre está
+                // en los clusters a los que apuntan sus data runs.
+                match read_non_resident(reader, attr, cluster_size) {
+                    Ok(content) => Some(content),
+                    Err(e) => {
+                        warn!(
+                            "Failed to read non-resident $FILE_NAME in MFT record {}: {}",
+                            index, e
+                        );
+                        None
+                    }
+                }
+            };
+            file_name = content.as_deref().and_then(parse_file_name);
+        }
+
+        if attr_type == ATTR_DATA {
+            if let Some(stream) = named_stream(attr) {
+                streams.push(stream);
+            }
+        }
+
+        rdr.set_position(attr_start_pos + attr_len as u64);
+    }
+
+    Ok(file_name
+        .filter(|parsed| in_use && !parsed.name.is_empty())
+        .map(|parsed| MftEntry {
+            name: parsed.name,
+            parent: parsed.parent,
+            is_dir: parsed.is_dir,
+            file_size: Some(parsed.size as i64),
+            created_time,
+            is_reparse,
+            mode,
+            link_count,
+            streams,
+        }))
+}
+
+/// Campos de un atributo $FILE_NAME.
+struct FileName {
+    name: String,
+    parent: u64,
+    is_dir: bool,
+    size: u64,
+}
+
+/// Interpreta el contenido de $FILE_NAME: referencia al padre en 0x00 (número de
+/// registro en los 48 bits bajos), tamaño real en 0x30, flags en 0x38, longitud del
+/// nombre (en UTF-16) en 0x40 y el nombre desde 0x42.
+fn parse_file_name(content: &[u8]) -> Option<FileName> {
+    let parent = u64::from_le_bytes(content.get(0x00..0x08)?.try_into().ok()?);
+    let size = u64::from_le_bytes(content.get(0x30..0x38)?.try_into().ok()?);
+    let flags = u32::from_le_bytes(content.get(0x38..0x3C)?.try_into().ok()?);
+    let name_len = *content.get(0x40)? as usize;
+
+    let units: Vec<u16> = content
+        .get(0x42..0x42 + name_len * 2)?
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+
+    Some(FileName {
+        name: String::from_utf16(&units).ok()?,
+        parent: parent & PARENT_RECORD_MASK,
+        is_dir: (flags & 0x10000000) != 0,
+        size,
+    })
+}
+
+/// Nombre y tamaño de un atributo $DATA con nombre (ADS); `None` para el flujo principal.
+/// El nombre (UTF-16) está en el offset de +0x0A con la longitud de +0x09.
+fn named_stream(attr: &[u8]) -> Option<(String, i64)> {
+    let name_len = *attr.get(0x09)? as usize;
+    if name_len == 0 {
+        return None;
+    }
+    let name_offset = u16::from_le_bytes(attr.get(0x0A..0x0C)?.try_into().ok()?) as usize;
+    let units: Vec<u16> = attr
+        .get(name_offset..name_offset + name_len * 2)?
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+    let name = String::from_utf16(&units).ok()?;
+
+    let size = if *attr.get(8)? == 0 {
+        resident_content(attr)?.len() as i64
+    } else {
+        u64::from_le_bytes(attr.get(0x30..0x38)?.try_into().ok()?) as i64
+    };
+    Some((name, size))
+}
+
+/// Tamaño real del $DATA sin nombre de un registro si es no residente (en el $MFT,
+/// el tamaño de la propia tabla).
+fn mft_data_size(record: &[u8]) -> Option<u64> {
+    let mut offset = u16::from_le_bytes(record.get(0x14..0x16)?.try_into().ok()?) as usize;
+    loop {
+        let attr_type = u32::from_le_bytes(record.get(offset..offset + 4)?.try_into().ok()?);
+        if attr_type == END_OF_ATTRIBUTES {
+            return None;
+        }
+        let attr_len = u32::from_le_bytes(record.get(offset + 4..offset + 8)?.try_into().ok()?) as usize;
+        if attr_len == 0 {
+            return None;
+        }
+        let attr = record.get(offset..(offset + attr_len).min(record.len()))?;
+        if attr_type == ATTR_DATA && *attr.get(0x09)? == 0 && *attr.get(8)? != 0 {
+            return Some(u64::from_le_bytes(attr.get(0x30..0x38)?.try_into().ok()?));
+        }
+        offset += attr_len;
+    }
+}
+
+/// Contenido de un atributo residente (longitud en +0x10, offset en +0x14).
+fn resident_content(attr: &[u8]) -> Option<&[u8]> {
+    let len = u32::from_le_bytes(attr.get(0x10..0x14)?.try_into().ok()?) as usize;
+    let offset = u16::from_le_bytes(attr.get(0x14..0x16)?.try_into().ok()?) as usize;
+    attr.get(offset..offset + len)
+}
+
+/// Lee el contenido de un atributo no residente siguiendo sus data runs.
+/// Deja `reader` en la posición en la que estaba.
+fn read_non_resident<R: Read + Seek>(
+    reader: &mut R,
+    attr: &[u8],
+    cluster_size: u64,
+) -> std::io::Result<Vec<u8>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string());
+
+    let runs_offset = attr
+        .get(0x20..0x22)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| invalid("truncated attribute header"))?;
+    let real_size = attr
+        .get(0x30..0x38)
+        .and_then(|b| b.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| invalid("truncated attribute header"))?;
+    if real_size > MAX_NON_RESIDENT_NAME_SIZE {
+        return Err(invalid("attribute too large"));
+    }
+    let runs = data_runs(attr.get(runs_offset..).unwrap_or_default())
+        .ok_or_else(|| invalid("malformed data runs"))?;
+
+    let resume = reader.stream_position()?;
+    let mut content = Vec::with_capacity(real_size as usize);
+    let result = (|| -> std::io::Result<()> {
+        for (lcn, clusters) in runs {
+            let remaining = real_size as usize - content.len();
+            if remaining == 0 {
+                break;
+            }
+            let len = ((clusters * cluster_size) as usize).min(remaining);
+            match lcn {
+                Some(lcn) => {
+                    let start = content.len();
+                    content.resize(start + len, 0);
+                    reader.seek(SeekFrom::Start(lcn * cluster_size))?;
+                    reader.read_exact(&mut content[start..])?;
+                }
+                // Run disperso: ceros.
+                None => content.resize(content.len() + len, 0),
+            }
+        }
+        Ok(())
+    })();
+    reader.seek(SeekFrom::Start(resume))?;
+    result?;
+
+    if content.len() < real_size as usize {
+        return Err(invalid("data runs shorter than attribute"));
+    }
+    Ok(content)
+}
+
+/// Decodifica una lista de data runs en `(LCN, clusters)`; `None` como LCN es un run disperso.
+fn data_runs(bytes: &[u8]) -> Option<Vec<(Option<u64>, u64)>> {
+    let mut runs = Vec::new();
+    let mut pos = 0;
+    let mut lcn: i64 = 0;
+
+    loop {
+        let header = *bytes.get(pos)?;
+        if header == 0 {
+            return Some(runs);
+        }
+        let len_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        pos += 1;
+
+        if len_size == 0 || len_size > 8 || offset_size > 8 {
+            return None;
+        }
+        let clusters = le_uint(bytes.get(pos..pos + len_size)?);
+        pos += len_size;
+
+        if offset_size == 0 {
+            runs.push((None, clusters));
+            continue;
+        }
+        let delta = le_int(bytes.get(pos..pos + offset_size)?);
+        pos += offset_size;
+        lcn = lcn.checked_add(delta)?;
+        if lcn < 0 {
+            return None;
+        }
+        runs.push((Some(lcn as u64), clusters));
+    }
+}
+
+fn le_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+/// Entero con signo little-endian de 1 a 8 bytes.
+fn le_int(bytes: &[u8]) -> i64 {
+    let value = le_uint(bytes);
+    let bits = bytes.len() * 8;
+    if bits < 64 && value & (1 << (bits - 1)) != 0 {
+        (value | (!0u64 << bits)) as i64
+    } else {
+        value as i64
+    }
+}
+
+/// FILETIME (intervalos de 100 ns desde 1601) a RFC3339 UTC.
+pub(crate) fn filetime_to_rfc3339(filetime: u64) -> Option<String> {
+    if filetime == 0 {
+        return None;
+    }
+    let secs = (filetime / 10_000_000) as i64 - FILETIME_UNIX_EPOCH_SECS;
+    let nanos = ((filetime % 10_000_000) * 100) as u32;
+    DateTime::<Utc>::from_timestamp(secs, nanos).map(|dt| dt.to_rfc3339())
+}
+
 fn apply_fixups(buffer: &mut [u8], bytes_per_sector: usize) -> bool {
     if buffer.len() < 8 {
         return false;
@@ -292,10 +714,20 @@ fn apply_fixups(buffer: &mut [u8], bytes_per_sector: usize) -> bool {
     true
 }
 
+/// Reintentos al leer un sector (lectura interrumpida o incompleta).
+const MAX_SECTOR_RETRIES: usize = 4;
+
+/// Lector por sectores completos y alineados: los volúmenes abiertos sin caché
+/// (`\\.\C:`) exigen que el búfer, la posición y la longitud de cada lectura sean
+/// múltiplos del tamaño de sector.
 struct SectorReader<R> {
     inner: R,
     sector_size: usize,
+    /// `sector_size * 2` bytes: dentro siempre cabe un sector alineado.
     buffer: Vec<u8>,
+    /// Inicio del sector alineado dentro de `buffer`. El búfer no cambia de tamaño, así
+    /// que su dirección (y este desplazamiento) no cambia.
+    aligned_start: usize,
     buffer_offset: u64,
     buffer_valid: usize,
     pos: u64,
@@ -303,54 +735,83 @@ struct SectorReader<R> {
 
 impl<R: Read + Seek> SectorReader<R> {
     pub fn new(inner: R, sector_size: usize) -> Self {
-        let raw_vec = vec![0u8; sector_size * 2];
+        let sector_size = sector_size.max(1);
+        let buffer = vec![0u8; sector_size * 2];
+        let aligned_start = align_up(buffer.as_ptr() as usize, sector_size);
         Self {
             inner,
             sector_size,
-            buffer: raw_vec,
+            buffer,
+            aligned_start,
             buffer_offset: 0,
             buffer_valid: 0,
             pos: 0,
         }
     }
+
+    fn sector(&self) -> &[u8] {
+        &self.buffer[self.aligned_start..self.aligned_start + self.sector_size]
+    }
+
+    /// Carga el sector que empieza en `start` con una lectura de un sector entero. Una
+    /// lectura incompleta solo puede ser el final del volumen: se reintenta por si fue
+    /// transitoria y, si no, el sector queda parcial (`buffer_valid` dice cuánto hay).
+    fn fill_sector(&mut self, start: u64) -> std::io::Result<()> {
+        let mut retries = 0;
+        let read_bytes = loop {
+            self.inner.seek(SeekFrom::Start(start))?;
+            let sector_slice =
+                &mut self.buffer[self.aligned_start..self.aligned_start + self.sector_size];
+            match self.inner.read(sector_slice) {
+                Ok(read_bytes) if read_bytes == 0 || read_bytes == self.sector_size => {
+                    break read_bytes;
+                }
+                Ok(read_bytes) if retries == MAX_SECTOR_RETRIES => break read_bytes,
+                Ok(_) => {}
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::Interrupted
+                        && retries < MAX_SECTOR_RETRIES => {}
+                Err(e) => return Err(e),
+            }
+            retries += 1;
+        };
+        self.buffer_valid = read_bytes;
+        self.buffer_offset = start;
+        Ok(())
+    }
+}
+
+/// Bytes desde `address` hasta el siguiente múltiplo de `alignment` (0 si ya lo es).
+/// Siempre menor que `alignment`, con cualquier dirección y cualquier `alignment`.
+fn align_up(address: usize, alignment: usize) -> usize {
+    (alignment - address % alignment) % alignment
 }
 
 impl<R: Read + Seek> Read for SectorReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        if buf.len() == 0 {
+        if buf.is_empty() {
             return Ok(0);
         }
         let current_sector_start =
             (self.pos / self.sector_size as u64) * self.sector_size as u64;
         let offset_in_sector = (self.pos % self.sector_size as u64) as usize;
 
-        if self.buffer_valid == 0 || self.buffer_offset != current_sector_start {
-            self.inner.seek(SeekFrom::Start(current_sector_start))?;
-            let ptr = self.buffer.as_ptr();
-            let align_offset = ptr.align_offset(self.sector_size);
-            let sector_slice =
-                &mut self.buffer[align_offset..align_offset + self.sector_size];
-            let read_bytes = self.inner.read(sector_slice)?;
-            self.buffer_valid = read_bytes;
-            self.buffer_offset = current_sector_start;
-            if read_bytes == 0 {
-                return Ok(0);
-            }
+        // Un sector en caché que no llega a `pos` (lectura corta) se vuelve a leer una
+        // vez por si ahora hay más; sin recursión, que podía no terminar.
+        let cached = self.buffer_valid > 0 && self.buffer_offset == current_sector_start;
+        if !cached || offset_in_sector >= self.buffer_valid {
+            self.fill_sector(current_sector_start)?;
         }
 
         let available_in_sector = self.buffer_valid.saturating_sub(offset_in_sector);
-        let to_copy = std::cmp::min(buf.len(), available_in_sector);
-
-        if to_copy == 0 && available_in_sector == 0 {
-            self.buffer_valid = 0;
-            return self.read(buf);
+        if available_in_sector == 0 {
+            // Fin de los datos del dispositivo.
+            return Ok(0);
         }
+        let to_copy = std::cmp::min(buf.len(), available_in_sector);
 
-        let ptr = self.buffer.as_ptr();
-        let align_offset = ptr.align_offset(self.sector_size);
-        let sector_slice = &self.buffer[align_offset..align_offset + self.sector_size];
         buf[..to_copy].copy_from_slice(
-            &sector_slice[offset_in_sector..offset_in_sector + to_copy],
+            &self.sector()[offset_in_sector..offset_in_sector + to_copy],
         );
         self.pos += to_copy as u64;
         Ok(to_copy)
@@ -368,3 +829,368 @@ impl<R: Seek> Seek for SectorReader<R> {
         Ok(self.pos)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, TempDir};
+
+    const SECTOR: usize = 512;
+
+    /// Volumen en memoria que anota cada lectura y puede devolver lecturas cortas.
+    struct Device {
+        data: Vec<u8>,
+        pos: u64,
+        /// Bytes como mucho por lectura.
+        max_read: usize,
+        /// Lecturas iniciales que devuelven la mitad de lo pedido.
+        short_reads: usize,
+        reads: Vec<(u64, usize)>,
+    }
+
+    impl Device {
+        fn new(len: usize) -> Self {
+            Self {
+                data: (0..len).map(|i| (i % 251) as u8).collect(),
+                pos: 0,
+                max_read: usize::MAX,
+                short_reads: 0,
+                reads: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Device {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.reads.push((self.pos, buf.len()));
+            let start = (self.pos as usize).min(self.data.len());
+            let mut len = buf.len().min(self.max_read).min(self.data.len() - start);
+            if self.short_reads > 0 {
+                self.short_reads -= 1;
+                len /= 2;
+            }
+            buf[..len].copy_from_slice(&self.data[start..start + len]);
+            self.pos += len as u64;
+            Ok(len)
+        }
+    }
+
+    impl Seek for Device {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.pos = match pos {
+                SeekFrom::Start(v) => v,
+                SeekFrom::End(v) => (self.data.len() as i64 + v) as u64,
+                SeekFrom::Current(v) => (self.pos as i64 + v) as u64,
+            };
+            Ok(self.pos)
+        }
+    }
+
+    /// Registro MFT en uso con `attributes` a partir de 0x38 (sin fixups).
+    fn mft_record(attributes: &[Vec<u8>]) -> Vec<u8> {
+        let mut record = vec![0u8; MFT_RECORD_SIZE];
+        record[..4].copy_from_slice(b"FILE");
+        record[0x12..0x14].copy_from_slice(&1u16.to_le_bytes());
+        record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+        record[0x16..0x18].copy_from_slice(&1u16.to_le_bytes());
+        let mut offset = 0x38;
+        for attr in attributes {
+            record[offset..offset + attr.len()].copy_from_slice(attr);
+            offset += attr.len();
+        }
+        record[offset..offset + 4].copy_from_slice(&END_OF_ATTRIBUTES.to_le_bytes());
+        record
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// Longitud del atributo en +0x04, redondeada a 8 bytes.
+    fn finish_attribute(mut attr: Vec<u8>) -> Vec<u8> {
+        attr.resize((attr.len() + 7) & !7, 0);
+        let len = attr.len() as u32;
+        attr[4..8].copy_from_slice(&len.to_le_bytes());
+        attr
+    }
+
+    /// Atributo residente con nombre `name` (vacío: sin nombre) y contenido `content`.
+    fn resident(attr_type: u32, name: &str, content: &[u8]) -> Vec<u8> {
+        let name = utf16(name);
+        let content_offset = (0x18 + name.len() + 7) & !7;
+        let mut attr = vec![0u8; content_offset];
+        attr[0..4].copy_from_slice(&attr_type.to_le_bytes());
+        attr[9] = (name.len() / 2) as u8;
+        attr[0x0A..0x0C].copy_from_slice(&0x18u16.to_le_bytes());
+        attr[0x10..0x14].copy_from_slice(&(content.len() as u32).to_le_bytes());
+        attr[0x14..0x16].copy_from_slice(&(content_offset as u16).to_le_bytes());
+        attr[0x18..0x18 + name.len()].copy_from_slice(&name);
+        attr.extend(content);
+        finish_attribute(attr)
+    }
+
+    /// Atributo no residente sin nombre con sus data runs en 0x40.
+    fn non_resident(attr_type: u32, runs: &[u8], real_size: u64) -> Vec<u8> {
+        let mut attr = vec![0u8; 0x40];
+        attr[0..4].copy_from_slice(&attr_type.to_le_bytes());
+        attr[8] = 1;
+        attr[0x20..0x22].copy_from_slice(&0x40u16.to_le_bytes());
+        attr[0x30..0x38].copy_from_slice(&real_size.to_le_bytes());
+        attr.extend(runs);
+        finish_attribute(attr)
+    }
+
+    fn standard_information(attributes: u32) -> Vec<u8> {
+        let mut content = vec![0u8; 0x48];
+        content[0..8].copy_from_slice(&132_000_000_000_000_000u64.to_le_bytes());
+        content[0x20..0x24].copy_from_slice(&attributes.to_le_bytes());
+        resident(ATTR_STANDARD_INFORMATION, "", &content)
+    }
+
+    fn file_name_content(parent: u64, name: &str, size: u64, is_dir: bool) -> Vec<u8> {
+        let mut content = vec![0u8; 0x42];
+        content[0..8].copy_from_slice(&parent.to_le_bytes());
+        content[0x30..0x38].copy_from_slice(&size.to_le_bytes());
+        let flags: u32 = if is_dir { 0x10000000 } else { 0 };
+        content[0x38..0x3C].copy_from_slice(&flags.to_le_bytes());
+        content[0x40] = name.encode_utf16().count() as u8;
+        content.extend(utf16(name));
+        content
+    }
+
+    /// Aplica la protección de fin de sector que `apply_fixups` deshace (USA en 0x30).
+    fn protect(mut record: Vec<u8>) -> Vec<u8> {
+        let sectors = MFT_RECORD_SIZE / SECTOR;
+        record[0x04..0x06].copy_from_slice(&0x30u16.to_le_bytes());
+        record[0x06..0x08].copy_from_slice(&(sectors as u16 + 1).to_le_bytes());
+        record[0x30..0x32].copy_from_slice(&0x0101u16.to_le_bytes());
+        for sector in 1..=sectors {
+            let end = sector * SECTOR - 2;
+            let (saved, tail) = (0x30 + sector * 2, record[end..end + 2].to_vec());
+            record[saved..saved + 2].copy_from_slice(&tail);
+            record[end..end + 2].copy_from_slice(&0x0101u16.to_le_bytes());
+        }
+        record
+    }
+
+    /// Volumen con sectores y clusters de 512 bytes y la MFT en el LCN 2: el registro 0
+    /// (`$MFT`, con el tamaño de la tabla) y después `records`.
+    fn volume(records: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut volume = vec![0u8; SECTOR * 2];
+        volume[0x0B..0x0D].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+        volume[0x0D] = 1;
+        volume[0x30..0x38].copy_from_slice(&2u64.to_le_bytes());
+
+        let table_size = ((records.len() + 1) * MFT_RECORD_SIZE) as u64;
+        volume.extend(protect(mft_record(&[
+            resident(ATTR_FILENAME, "", &file_name_content(5, "$MFT", table_size, false)),
+            non_resident(ATTR_DATA, &[0], table_size),
+        ])));
+        for record in records {
+            volume.extend(protect(record));
+        }
+        volume
+    }
+
+    fn file_record(name: &str) -> Vec<u8> {
+        mft_record(&[resident(ATTR_FILENAME, "", &file_name_content(5, name, 1, false))])
+    }
+
+    /// Escanea `volume` con un límite de registros; devuelve el resultado y los estados
+    /// de progreso emitidos.
+    fn scan_volume(
+        volume: Vec<u8>,
+        max_records: Option<usize>,
+    ) -> (MftScan, Vec<(String, Option<usize>)>, usize) {
+        let dir = TempDir::new("mft-scan");
+        let (db, reader) = open_index(&dir);
+        let indexer = MftIndexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()))
+            .with_max_records(max_records);
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&statuses);
+        let progress = Arc::new(move |p: IndexingProgress| {
+            seen.lock().unwrap().push((p.status, p.total_files));
+        });
+
+        let scan = indexer.scan("C", Cursor::new(volume), progress).unwrap();
+        let statuses = statuses.lock().unwrap().clone();
+        (scan, statuses, reader.get_file_count().unwrap())
+    }
+
+    fn read_at(reader: &mut SectorReader<Device>, offset: u64, len: usize) -> Vec<u8> {
+        reader.seek(SeekFrom::Start(offset)).unwrap();
+        let mut buf = vec![0u8; len];
+        reader.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn unaligned_reads_across_sector_boundaries() {
+        let device = Device::new(SECTOR * 8);
+        let data = device.data.clone();
+        let mut reader = SectorReader::new(device, SECTOR);
+
+        assert_eq!(read_at(&mut reader, 500, 30), data[500..530]);
+        assert_eq!(read_at(&mut reader, 1000, 2000), data[1000..3000]);
+        assert_eq!(read_at(&mut reader, 3, 1), data[3..4]);
+
+        // El dispositivo solo ve lecturas de un sector entero en offsets alineados.
+        assert!(!reader.inner.reads.is_empty());
+        for &(offset, len) in &reader.inner.reads {
+            assert_eq!(offset % SECTOR as u64, 0, "unaligned offset {}", offset);
+            assert_eq!(len, SECTOR);
+        }
+    }
+
+    #[test]
+    fn transient_short_reads_are_retried() {
+        let mut device = Device::new(SECTOR * 4);
+        device.short_reads = 3;
+        let data = device.data.clone();
+        let mut reader = SectorReader::new(device, SECTOR);
+
+        assert_eq!(read_at(&mut reader, 100, SECTOR * 3), data[100..100 + SECTOR * 3]);
+    }
+
+    #[test]
+    fn reads_stop_at_the_end_of_the_device() {
+        let device = Device::new(SECTOR + 188);
+        let data = device.data.clone();
+        let mut reader = SectorReader::new(device, SECTOR);
+
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, data);
+
+        reader.seek(SeekFrom::Start(SECTOR as u64 * 3)).unwrap();
+        assert_eq!(reader.read(&mut [0u8; 16]).unwrap(), 0);
+    }
+
+    #[test]
+    fn persistent_short_reads_end_without_looping() {
+        // Un dispositivo que nunca completa un sector: se reintenta un número acotado
+        // de veces y lo leído se trata como el final de los datos.
+        let mut device = Device::new(SECTOR * 2);
+        device.max_read = 100;
+        let data = device.data.clone();
+        let mut reader = SectorReader::new(device, SECTOR);
+
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(all, data[..100]);
+        assert!(reader.inner.reads.len() <= 2 * (MAX_SECTOR_RETRIES + 1));
+    }
+
+    #[test]
+    fn align_up_is_below_the_alignment() {
+        assert_eq!(align_up(4096, 512), 0);
+        assert_eq!(align_up(4097, 512), 511);
+        assert_eq!(align_up(7, 1), 0);
+    }
+
+    #[test]
+    fn non_resident_file_name_is_read_from_its_clusters() {
+        // Referencia al padre: registro 5 (la raíz) con secuencia 3.
+        let content = file_name_content(5 | (3 << 48), "informe.txt", 42, false);
+        let mut volume = vec![0u8; SECTOR * 4];
+        volume[SECTOR * 3..SECTOR * 3 + content.len()].copy_from_slice(&content);
+        // Un run de un cluster en el LCN 3.
+        let runs = [0x11, 0x01, 0x03, 0x00];
+        let record = mft_record(&[non_resident(ATTR_FILENAME, &runs, content.len() as u64)]);
+        let mut reader = Cursor::new(volume);
+        reader.set_position(77);
+
+        let entry = parse_record(&record, &mut reader, SECTOR as u64, 40).unwrap().unwrap();
+        assert_eq!(entry.name, "informe.txt");
+        assert_eq!(entry.parent, 5);
+        assert_eq!(entry.file_size, Some(42));
+        assert!(!entry.is_dir);
+        // La lectura de los clusters no mueve el lector de la MFT.
+        assert_eq!(reader.position(), 77);
+    }
+
+    #[test]
+    fn unreadable_non_resident_file_name_is_skipped() {
+        // El run apunta fuera del volumen.
+        let runs = [0x11, 0x01, 0x40, 0x00];
+        let record = mft_record(&[non_resident(ATTR_FILENAME, &runs, 0x60)]);
+        let mut reader = Cursor::new(vec![0u8; SECTOR * 4]);
+        assert!(parse_record(&record, &mut reader, SECTOR as u64, 40).unwrap().is_none());
+
+        let content = file_name_content(5, "dir", 0, true);
+        let resident_record = mft_record(&[resident(ATTR_FILENAME, "", &content)]);
+        let entry = parse_record(&resident_record, &mut reader, SECTOR as u64, 41).unwrap();
+        assert!(entry.unwrap().is_dir);
+    }
+
+    #[test]
+    fn named_data_streams_and_reparse_points() {
+        let record = mft_record(&[
+            standard_information(FILE_ATTRIBUTE_REPARSE_POINT | 0x20),
+            resident(ATTR_FILENAME, "", &file_name_content(5, "a.txt", 3, false)),
+            resident(ATTR_DATA, "", b"abc"),
+            resident(ATTR_DATA, "Zone.Identifier", b"[ZoneTransfer]"),
+        ]);
+        let mut reader = Cursor::new(Vec::new());
+        let entry = parse_record(&record, &mut reader, SECTOR as u64, 40).unwrap().unwrap();
+
+        assert!(entry.is_reparse);
+        assert_eq!(entry.mode, Some(0x420 & WINDOWS_ATTRIBUTE_MASK));
+        assert!(entry.created_time.is_some());
+        assert_eq!(entry.streams, [("Zone.Identifier".to_string(), 14)]);
+
+        // El flujo sin nombre es el contenido del archivo; el ADS, una entrada aparte.
+        let records = entry.into_records("C");
+        let paths: Vec<_> = records.iter().map(|record| record.path.as_str()).collect();
+        assert_eq!(paths, [r"C:\a.txt:Zone.Identifier", r"C:\a.txt"]);
+        let (stream, file) = (&records[0], &records[1]);
+        assert_eq!(stream.file_size, Some(14));
+        assert!(!stream.is_reparse);
+        assert_eq!(file.file_size, Some(3));
+        assert_eq!(file.extension.as_deref(), Some(".txt"));
+        assert!(file.is_reparse);
+    }
+
+    #[test]
+    fn plain_files_have_no_streams() {
+        let record = mft_record(&[
+            standard_information(0x20),
+            resident(ATTR_FILENAME, "", &file_name_content(5, "b.bin", 0, false)),
+            resident(ATTR_DATA, "", b""),
+        ]);
+        let mut reader = Cursor::new(Vec::new());
+        let entry = parse_record(&record, &mut reader, SECTOR as u64, 40).unwrap().unwrap();
+
+        assert!(!entry.is_reparse);
+        assert!(entry.streams.is_empty());
+        assert_eq!(entry.into_records("C").len(), 1);
+    }
+
+    #[test]
+    fn scan_stops_at_the_record_cap() {
+        let records = (0..6).map(|i| file_record(&format!("f{}.txt", i))).collect();
+        let (scan, statuses, rows) = scan_volume(volume(records), Some(3));
+
+        assert!(scan.truncated);
+        assert_eq!(scan.files_found, 3);
+        assert_eq!(rows, 3);
+        // El total estimado se ajusta al límite y el último estado avisa del corte.
+        assert_eq!(statuses.last(), Some(&("mft-truncated".to_string(), Some(3))));
+    }
+
+    #[test]
+    fn scan_reads_the_whole_table_without_a_cap() {
+        let mut records: Vec<_> = (0..5).map(|i| file_record(&format!("f{}.txt", i))).collect();
+        records.push(mft_record(&[
+            resident(ATTR_FILENAME, "", &file_name_content(5, "g.txt", 1, false)),
+            resident(ATTR_DATA, "Zone.Identifier", b"x"),
+        ]));
+        let (scan, statuses, rows) = scan_volume(volume(records), None);
+
+        assert!(!scan.truncated);
+        // `$MFT` y los seis archivos; el ADS se indexa pero no cuenta como archivo.
+        assert_eq!(scan.files_found, 7);
+        assert_eq!(rows, 8);
+        assert!(statuses.iter().all(|(status, total)| status == "indexing" && *total == Some(7)));
+    }
+}
@@ -0,0 +1,419 @@
+use crate::collation;
+use rusqlite::{Connection, Result};
+use tracing::info;
+
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Pasos de esquema en orden. La versión de una base es el número de pasos aplicados
+/// (`PRAGMA user_version`). Nunca reordenar ni editar uno publicado: añadir otro al final.
+/// Los pasos son idempotentes porque las bases previas a este sistema (versión 0)
+/// pueden tener ya parte del esquema.
+const MIGRATIONS: &[Migration] = &[
+    create_search_index,
+    add_parent_path,
+    create_search_history,
+    add_raw_path,
+    add_tags,
+    create_fts_index,
+    add_created_time,
+    create_meta,
+    add_is_reparse,
+    create_content_fts,
+    add_link_count,
+    add_category,
+    add_interpreter,
+    add_name_sort,
+    add_mime,
+    add_permissions,
+    add_content_hash,
+    create_path_fts,
+    create_saved_searches,
+    add_name_nocase_index,
+    add_file_id,
+];
+
+/// Versión que tendrá la base tras `migrate`.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+/// Aplica los pasos pendientes, cada uno en su propia transacción.
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let current: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, step) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let version = index as u32 + 1;
+        info!("Applying schema migration {}", version);
+
+        let tx = conn.transaction()?;
+        step(&tx)?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn create_search_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS search_index (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT UNIQUE NOT NULL,
+            name TEXT NOT NULL,
+            extension TEXT,
+            file_size INTEGER,
+            is_dir INTEGER NOT NULL DEFAULT 0,
+            modified_time TEXT NOT NULL,
+            last_indexed TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_search_name ON search_index(name);
+        CREATE INDEX IF NOT EXISTS idx_search_extension ON search_index(extension);
+        CREATE INDEX IF NOT EXISTS idx_search_size ON search_index(file_size);
+        CREATE INDEX IF NOT EXISTS idx_search_modified ON search_index(modified_time);
+        CREATE INDEX IF NOT EXISTS idx_search_is_dir ON search_index(is_dir);",
+    )
+}
+
+fn add_parent_path(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "parent_path", "TEXT")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_search_parent ON search_index(parent_path);",
+    )
+}
+
+fn create_search_history(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS search_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            query TEXT NOT NULL,
+            filters TEXT,
+            result_count INTEGER NOT NULL DEFAULT 0,
+            searched_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_history_searched_at ON search_history(searched_at);",
+    )
+}
+
+fn add_raw_path(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "path_lossy", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "search_index", "raw_path", "TEXT")
+}
+
+fn add_tags(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "tags", "TEXT")
+}
+
+/// Índice FTS5 (tokenizer trigram) sobre `name`, sincronizado por triggers.
+/// Permite búsquedas por subcadena sin recorrer toda la tabla.
+fn create_fts_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS search_index_fts USING fts5(
+            name,
+            content = 'search_index',
+            content_rowid = 'id',
+            tokenize = 'trigram'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS search_index_fts_ai AFTER INSERT ON search_index BEGIN
+            INSERT INTO search_index_fts(rowid, name) VALUES (new.id, new.name);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS search_index_fts_ad AFTER DELETE ON search_index BEGIN
+            INSERT INTO search_index_fts(search_index_fts, rowid, name) VALUES ('delete', old.id, old.name);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS search_index_fts_au AFTER UPDATE OF name ON search_index BEGIN
+            INSERT INTO search_index_fts(search_index_fts, rowid, name) VALUES ('delete', old.id, old.name);
+            INSERT INTO search_index_fts(rowid, name) VALUES (new.id, new.name);
+        END;
+
+        -- Llena el índice con lo ya indexado (no-op en una base nueva).
+        INSERT INTO search_index_fts(search_index_fts) VALUES('rebuild');",
+    )
+}
+
+fn add_created_time(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "created_time", "TEXT")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_search_created ON search_index(created_time);",
+    )
+}
+
+/// Pares clave/valor de estado de la app (p. ej. fecha del último índice completo).
+fn create_meta(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )
+}
+
+fn add_is_reparse(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "is_reparse", "INTEGER NOT NULL DEFAULT 0")
+}
+
+/// Texto de los archivos (`index_content`). `rowid` = `search_index.id`; al borrar una
+/// entrada se borra su contenido.
+fn create_content_fts(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS content_fts USING fts5(
+            path UNINDEXED,
+            body,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS content_fts_ad AFTER DELETE ON search_index BEGIN
+            DELETE FROM content_fts WHERE rowid = old.id;
+        END;",
+    )
+}
+
+fn add_link_count(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "link_count", "INTEGER NOT NULL DEFAULT 1")
+}
+
+fn add_category(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "category", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_category ON search_index(category)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_interpreter(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "interpreter", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_interpreter ON search_index(interpreter)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Clave de orden natural precalculada (ver `collation::natural_key`). Necesita la
+/// función SQL que registra `Database::new`.
+fn add_name_sort(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "name_sort", "TEXT")?;
+    conn.execute(
+        &format!(
+            "UPDATE search_index SET name_sort = {}(name) WHERE name_sort IS NULL",
+            collation::NATURAL_KEY_FUNCTION
+        ),
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_name_sort ON search_index(name_sort)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_mime(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "mime", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_mime ON search_index(mime)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_permissions(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "mode", "INTEGER")?;
+    ensure_column(conn, "search_index", "owner_uid", "INTEGER")?;
+    ensure_column(conn, "search_index", "owner_gid", "INTEGER")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_owner ON search_index(owner_uid)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_content_hash(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "content_hash", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_content_hash ON search_index(content_hash)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Como `create_fts_index` pero sobre `path`, para `search_in_path`. La ruta no cambia
+/// en un upsert (es la clave), así que solo se toca al insertar o borrar.
+fn create_path_fts(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS search_path_fts USING fts5(
+            path,
+            content = 'search_index',
+            content_rowid = 'id',
+            tokenize = 'trigram'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS search_path_fts_ai AFTER INSERT ON search_index BEGIN
+            INSERT INTO search_path_fts(rowid, path) VALUES (new.id, new.path);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS search_path_fts_ad AFTER DELETE ON search_index BEGIN
+            INSERT INTO search_path_fts(search_path_fts, rowid, path) VALUES ('delete', old.id, old.path);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS search_path_fts_au AFTER UPDATE OF path ON search_index BEGIN
+            INSERT INTO search_path_fts(search_path_fts, rowid, path) VALUES ('delete', old.id, old.path);
+            INSERT INTO search_path_fts(rowid, path) VALUES (new.id, new.path);
+        END;
+
+        INSERT INTO search_path_fts(search_path_fts) VALUES('rebuild');",
+    )
+}
+
+/// Búsquedas con nombre (`save_search`); `filters` es el JSON de `SearchFilters`.
+fn create_saved_searches(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS saved_searches (
+            name TEXT PRIMARY KEY,
+            query TEXT NOT NULL,
+            filters TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );",
+    )
+}
+
+/// `LIKE` no distingue mayúsculas: solo aprovecha un índice con `NOCASE` para las
+/// búsquedas por prefijo (`inf*`). `idx_search_name` es binario.
+fn add_name_nocase_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_search_name_nocase
+             ON search_index(name COLLATE NOCASE);",
+    )
+}
+
+fn add_file_id(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "search_index", "file_id", "TEXT")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_search_file_id ON search_index(file_id)
+         WHERE file_id IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Añade `column` a `table` si todavía no existe.
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|c| c.ok())
+        .any(|c| c == column);
+
+    if !exists {
+        info!("Adding column {}.{}", table, column);
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Esquema de las primeras versiones, antes de `user_version`.
+    const BASELINE_SCHEMA: &str = "
+        CREATE TABLE search_index (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT UNIQUE NOT NULL,
+            name TEXT NOT NULL,
+            extension TEXT,
+            file_size INTEGER,
+            is_dir INTEGER NOT NULL DEFAULT 0,
+            modified_time TEXT NOT NULL,
+            last_indexed TEXT NOT NULL
+        );
+        CREATE INDEX idx_search_name ON search_index(name);
+        INSERT INTO search_index (path, name, extension, file_size, modified_time, last_indexed)
+        VALUES
+            ('/docs/report2.pdf', 'report2.pdf', '.pdf', 10, '2024-01-01', '2024-01-01'),
+            ('/docs/report10.pdf', 'report10.pdf', '.pdf', 20, '2024-01-01', '2024-01-01');";
+
+    fn baseline() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        collation::register_sql_function(&conn).unwrap();
+        conn.execute_batch(BASELINE_SCHEMA).unwrap();
+        conn
+    }
+
+    fn version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn schema(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY name")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|row| row.unwrap()).collect()
+    }
+
+    #[test]
+    fn migrates_a_baseline_database_keeping_its_rows() {
+        let mut conn = baseline();
+        assert_eq!(version(&conn), 0);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(version(&conn), latest_version());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM search_index"), 2);
+        // Los índices FTS se llenan con las filas que ya había.
+        let by_name = "SELECT COUNT(*) FROM search_index_fts WHERE search_index_fts MATCH 'report'";
+        assert_eq!(count(&conn, by_name), 2);
+        let by_path = "SELECT COUNT(*) FROM search_path_fts WHERE search_path_fts MATCH 'docs'";
+        assert_eq!(count(&conn, by_path), 2);
+        let unsorted = "SELECT COUNT(*) FROM search_index WHERE name_sort IS NULL";
+        assert_eq!(count(&conn, unsorted), 0);
+        let link_count = "SELECT MIN(link_count) FROM search_index";
+        assert_eq!(count(&conn, link_count), 1);
+
+        // Los triggers siguen las filas nuevas.
+        conn.execute(
+            "INSERT INTO search_index (path, name, modified_time, last_indexed)
+             VALUES ('/docs/notes.txt', 'notes.txt', '2024-01-01', '2024-01-01')",
+            [],
+        )
+        .unwrap();
+        let notes = "SELECT COUNT(*) FROM search_index_fts WHERE search_index_fts MATCH 'notes'";
+        assert_eq!(count(&conn, notes), 1);
+    }
+
+    #[test]
+    fn migrating_twice_is_a_no_op() {
+        let mut conn = baseline();
+        migrate(&mut conn).unwrap();
+        let before = schema(&conn);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(version(&conn), latest_version());
+        assert_eq!(schema(&conn), before);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM search_index"), 2);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM search_index_fts"), 2);
+    }
+
+    #[test]
+    fn partially_migrated_database_resumes() {
+        // Base de antes de `user_version` que ya tenía columnas de pasos posteriores.
+        let mut conn = baseline();
+        conn.execute_batch("ALTER TABLE search_index ADD COLUMN tags TEXT;").unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(version(&conn), latest_version());
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM search_index"), 2);
+    }
+}
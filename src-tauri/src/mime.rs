@@ -0,0 +1,139 @@
+//! Tipo MIME de cada archivo según la base de asociaciones del sistema: shared-mime-info
+//! en Linux (`mime/globs2`) y el registro en Windows (`HKCR\.ext`, `Content Type`). Más
+//! preciso que las categorías por extensión; se busca con `mime:image/png` o `mime:image`.
+//!
+//! En macOS no hay una base equivalente legible sin frameworks: no se resuelve.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Prefijo del operador de búsqueda por tipo MIME (`mime:application/pdf informe`).
+const MIME_OPERATOR: &str = "mime:";
+
+/// Resuelve extensión -> tipo MIME con caché: cada extensión se consulta una sola vez
+/// por ejecución aunque aparezca en miles de archivos.
+#[derive(Debug, Default)]
+pub struct MimeResolver {
+    /// Extensión en minúsculas y sin punto -> tipo (también las que no tienen).
+    cache: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl MimeResolver {
+    /// Tipo MIME de `extension` (`.png` o `png`), o `None` si el sistema no lo conoce.
+    pub fn resolve(&self, extension: &str) -> Option<String> {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        if extension.is_empty() {
+            return None;
+        }
+        if let Some(cached) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(&extension) {
+            return cached.clone();
+        }
+
+        // Sin el bloqueo: en Windows la consulta lanza un proceso.
+        let mime = lookup(&extension);
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(extension, mime.clone());
+        mime
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn lookup(extension: &str) -> Option<String> {
+    use std::sync::OnceLock;
+
+    static GLOBS: OnceLock<HashMap<String, String>> = OnceLock::new();
+    GLOBS.get_or_init(load_globs).get(extension).cloned()
+}
+
+/// Lee `mime/globs2` de los directorios de datos de XDG, del usuario primero. Cada
+/// línea es `peso:tipo:patrón[:opciones]`; solo sirven los patrones `*.ext`, y ante
+/// varios tipos para una extensión gana el de más peso.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn load_globs() -> HashMap<String, String> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(dirs::data_dir);
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    let mut globs: HashMap<String, (u32, String)> = HashMap::new();
+    let dirs = data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(std::path::PathBuf::from));
+    for dir in dirs {
+        let Ok(contents) = std::fs::read_to_string(dir.join("mime").join("globs2")) else {
+            continue;
+        };
+        for line in contents.lines().filter(|line| !line.starts_with('#')) {
+            let mut fields = line.split(':');
+            let (Some(weight), Some(mime), Some(pattern)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let Some(extension) = pattern.strip_prefix("*.") else {
+                continue;
+            };
+            if extension.contains(['*', '?', '[']) {
+                continue;
+            }
+            let weight = weight.parse().unwrap_or(50);
+            let extension = extension.to_ascii_lowercase();
+            if globs.get(&extension).is_none_or(|(current, _)| weight > *current) {
+                globs.insert(extension, (weight, mime.to_string()));
+            }
+        }
+    }
+
+    globs.into_iter().map(|(extension, (_, mime))| (extension, mime)).collect()
+}
+
+#[cfg(windows)]
+fn lookup(extension: &str) -> Option<String> {
+    use std::os::windows::process::CommandExt;
+
+    // Sin consola: el indexador corre en segundo plano.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let output = std::process::Command::new("reg")
+        .args(["query", &format!(r"HKCR\.{}", extension), "/v", "Content Type"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // `    Content Type    REG_SZ    image/png`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("REG_SZ"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_ascii_lowercase)
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+fn lookup(_extension: &str) -> Option<String> {
+    None
+}
+
+/// Normaliza los valores de `SearchFilters::mime_types`: en minúsculas, y `image` o
+/// `image/*` como prefijo `image/%` para `LIKE`.
+pub fn normalize(mime_types: &[String]) -> Vec<String> {
+    mime_types
+        .iter()
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .filter(|mime| !mime.is_empty())
+        .map(|mime| match mime.split_once('/') {
+            None => format!("{}/%", mime),
+            Some((top, "*")) => format!("{}/%", top),
+            Some(_) => mime,
+        })
+        .collect()
+}
+
+/// Separa los términos `mime:xxx` del resto de la consulta.
+pub fn extract_mime_terms(query: &str) -> (String, Vec<String>) {
+    crate::tags::extract_operator_terms(query, MIME_OPERATOR)
+}
@@ -0,0 +1,88 @@
+use crate::types::{IndexingWarnings, OperationInfo, OperationStatus};
+use chrono::Utc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Operaciones terminadas que se conservan para `get_operations`.
+const MAX_FINISHED: usize = 20;
+
+/// Registro de operaciones largas (índices, refrescos, reconstrucción FTS) para que
+/// la UI pueda asociar eventos y estado a un id concreto.
+pub struct Operations {
+    next_id: AtomicU64,
+    entries: Mutex<Vec<OperationInfo>>,
+}
+
+impl Operations {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Registra una operación en curso y la devuelve (con su id).
+    pub fn start(&self, kind: &str) -> OperationInfo {
+        let info = OperationInfo {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            kind: kind.to_string(),
+            status: OperationStatus::Running,
+            started_at: Utc::now().to_rfc3339(),
+            finished_at: None,
+            message: None,
+            warnings: None,
+        };
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.push(info.clone());
+        info
+    }
+
+    /// Marca la operación como terminada. Devuelve `None` si el id no existe.
+    /// Adjunta los avisos de un índice antes de terminar la operación.
+    pub fn set_warnings(&self, id: u64, warnings: IndexingWarnings) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.iter_mut().find(|op| op.id == id) {
+            entry.warnings = Some(warnings);
+        }
+    }
+
+    pub fn finish(
+        &self,
+        id: u64,
+        status: OperationStatus,
+        message: Option<String>,
+    ) -> Option<OperationInfo> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.iter_mut().find(|op| op.id == id)?;
+        entry.status = status;
+        entry.finished_at = Some(Utc::now().to_rfc3339());
+        entry.message = message;
+        let info = entry.clone();
+
+        // Poda las terminadas más antiguas (las entradas están en orden de inicio).
+        let finished = entries
+            .iter()
+            .filter(|op| op.status != OperationStatus::Running)
+            .count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED);
+        entries.retain(|op| {
+            if excess > 0 && op.status != OperationStatus::Running {
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+
+        Some(info)
+    }
+
+    /// Operaciones en curso y las últimas terminadas, por orden de inicio.
+    pub fn list(&self) -> Vec<OperationInfo> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
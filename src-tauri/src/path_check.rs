@@ -0,0 +1,161 @@
+//! Comprobaciones de una raíz candidata antes de añadirla (`validate_indexing_path`).
+
+use crate::elevation;
+use crate::indexer::{IndexOptions, Indexer};
+use crate::types::PathValidation;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// El recorrido de muestra para estimar el tamaño para en lo primero que llegue.
+const SAMPLE_MAX_ENTRIES: usize = 50_000;
+const SAMPLE_MAX_TIME: Duration = Duration::from_secs(1);
+
+/// A partir de aquí se avisa de que el índice será grande.
+const LARGE_SCOPE_ENTRIES: usize = 20_000;
+
+/// Sistemas de archivos de red en `/proc/mounts`.
+#[cfg(target_os = "linux")]
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smbfs", "smb3", "sshfs", "fuse.sshfs", "9p", "afs", "davfs",
+];
+
+/// Comprueba `path` como raíz de índice. `configured` son las raíces actuales, para
+/// avisar de solapamientos.
+pub fn validate(
+    path: &str,
+    configured: &[String],
+    exclude_patterns: &[String],
+    options: &IndexOptions,
+) -> PathValidation {
+    let mut report = PathValidation {
+        path: path.to_string(),
+        valid: false,
+        exists: false,
+        is_dir: false,
+        readable: false,
+        is_drive: false,
+        mft_available: false,
+        requires_elevation: false,
+        is_network: false,
+        estimated_entries: 0,
+        estimate_complete: false,
+        warnings: Vec::new(),
+    };
+
+    let path_obj = Path::new(path);
+    let Ok(metadata) = std::fs::metadata(path_obj) else {
+        report.warnings.push("Path does not exist".to_string());
+        return report;
+    };
+    report.exists = true;
+    report.is_dir = metadata.is_dir();
+    if !report.is_dir {
+        report.warnings.push("Path is not a directory".to_string());
+        return report;
+    }
+
+    report.readable = std::fs::read_dir(path_obj).is_ok();
+    if !report.readable {
+        report.warnings.push("Directory cannot be read (permission denied?)".to_string());
+        return report;
+    }
+    report.valid = true;
+
+    report.is_drive = path_obj.parent().is_none();
+    report.mft_available = Indexer::can_use_mft(path);
+    report.is_network = is_network_path(path_obj);
+    if report.is_network {
+        report
+            .warnings
+            .push("This is a network location, indexing may be slow".to_string());
+    }
+    if report.is_drive && !report.mft_available {
+        report
+            .warnings
+            .push("Indexing a whole drive by walking it can take a long time".to_string());
+        report.requires_elevation = cfg!(windows) && !elevation::is_elevated();
+        if report.requires_elevation {
+            report
+                .warnings
+                .push("Run as administrator to index this drive quickly from its MFT".to_string());
+        }
+    }
+
+    for root in configured {
+        let root_path = Path::new(root);
+        if path_obj == root_path {
+            report.warnings.push("Path is already an indexing root".to_string());
+        } else if path_obj.starts_with(root_path) {
+            report
+                .warnings
+                .push(format!("Path is already indexed as part of {}", root));
+        } else if root_path.starts_with(path_obj) {
+            report
+                .warnings
+                .push(format!("Path contains the existing root {}", root));
+        }
+    }
+
+    let (entries, complete) = sample_entries(path_obj, exclude_patterns, options);
+    report.estimated_entries = entries;
+    report.estimate_complete = complete;
+    if entries >= LARGE_SCOPE_ENTRIES {
+        let at_least = if complete { "" } else { "at least " };
+        report.warnings.push(format!(
+            "Path contains {}{} entries, the first index may take a while",
+            at_least, entries
+        ));
+    }
+
+    report
+}
+
+/// Entradas bajo `path` (con los mismos filtros que el índice) hasta agotar la muestra.
+/// El `bool` indica que se recorrió entero.
+fn sample_entries(path: &Path, exclude_patterns: &[String], options: &IndexOptions) -> (usize, bool) {
+    let started = Instant::now();
+    let mut entries = 0;
+    for entry in options.walk_builder(path, exclude_patterns, None).build() {
+        if entry.is_ok() {
+            entries += 1;
+        }
+        if entries >= SAMPLE_MAX_ENTRIES || started.elapsed() >= SAMPLE_MAX_TIME {
+            return (entries, false);
+        }
+    }
+    (entries, true)
+}
+
+/// Ruta UNC (`\\servidor\recurso`) o, en Linux, dentro de un montaje de red.
+fn is_network_path(path: &Path) -> bool {
+    let text = path.to_string_lossy();
+    if text.starts_with(r"\\") || text.starts_with("//") {
+        return true;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(canonical) = path.canonicalize() else {
+            return false;
+        };
+        let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+            return false;
+        };
+        // El montaje más específico que contiene la ruta decide el tipo.
+        mounts
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let mount_point = fields.nth(1)?;
+                let fs_type = fields.next()?;
+                Some((mount_point, fs_type))
+            })
+            .filter(|(mount_point, _)| canonical.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.len())
+            .is_some_and(|(_, fs_type)| NETWORK_FILESYSTEMS.contains(&fs_type))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
@@ -0,0 +1,80 @@
+use crate::ranking;
+use crate::types::SearchResult;
+
+/// Términos de la consulta en minúsculas (`src main rs` -> `["src", "main", "rs"]`).
+pub fn terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
+/// Segmentos de la ruta: componentes entre separadores, y el nombre también partido
+/// por `.` para que `main rs` encaje con `main.rs`.
+fn segments(path: &str) -> Vec<String> {
+    path.split(['/', '\\'])
+        .flat_map(|component| {
+            let mut parts = vec![component];
+            if component.contains('.') {
+                parts.extend(component.split('.'));
+            }
+            parts
+        })
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.to_lowercase())
+        .collect()
+}
+
+/// `true` si cada término coincide con un segmento completo de la ruta, en orden.
+pub fn matches(terms: &[String], path: &str) -> bool {
+    let segments = segments(path);
+    let mut remaining = segments.iter();
+    terms
+        .iter()
+        .all(|term| remaining.any(|segment| segment == term))
+}
+
+/// Filtra los candidatos que casan por componentes y los ordena: rutas más cortas
+/// (menos segmentos sobrantes) primero.
+pub fn rank(candidates: Vec<SearchResult>, query: &str, limit: usize) -> Vec<SearchResult> {
+    let terms = terms(query);
+
+    let mut ranked: Vec<SearchResult> = candidates
+        .into_iter()
+        .filter(|result| matches(&terms, &result.path))
+        .map(|mut result| {
+            let segment_count = segments(&result.path).len().max(1);
+            result.score = (terms.len() as f64 / segment_count as f64).min(1.0);
+            result
+        })
+        .collect();
+
+    ranking::sort_by_score(&mut ranked);
+    ranked.truncate(limit);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches_query(query: &str, path: &str) -> bool {
+        matches(&terms(query), path)
+    }
+
+    #[test]
+    fn terms_match_whole_components_in_order() {
+        assert!(matches_query("src main rs", "/proj/src/main.rs"));
+        assert!(matches_query("SRC Main", "C:\\proj\\src\\main.rs"));
+        // Fuera de orden o como parte de un componente no vale.
+        assert!(!matches_query("main src", "/proj/src/main.rs"));
+        assert!(!matches_query("sr main", "/proj/src/main.rs"));
+        assert!(!matches_query("proj lib", "/proj/src/main.rs"));
+    }
+
+    #[test]
+    fn each_term_consumes_one_segment() {
+        assert!(!matches_query("src src", "/proj/src/main.rs"));
+        assert!(matches_query("src src", "/proj/src/gen/src/a.rs"));
+    }
+}
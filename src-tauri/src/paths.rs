@@ -0,0 +1,334 @@
+use base64::engine::general_purpose::STANDARD;
+use crate::db::Database;
+use crate::error::OxiError;
+use crate::types::PathStyle;
+use base64::Engine;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Bytes originales de `path` en base64, para rutas que no son UTF-8 y cuya
+/// versión `to_string_lossy` no permite volver a abrir el archivo.
+/// En Unix son los bytes tal cual; en Windows las unidades UTF-16 en little-endian.
+pub fn encode_raw(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes: Vec<u8> = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+
+    #[cfg(windows)]
+    let bytes: Vec<u8> = {
+        use std::os::windows::ffi::OsStrExt;
+        path.as_os_str()
+            .encode_wide()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    };
+
+    #[cfg(not(any(unix, windows)))]
+    let bytes: Vec<u8> = path.to_string_lossy().into_owned().into_bytes();
+
+    STANDARD.encode(bytes)
+}
+
+/// Inversa de `encode_raw`. Devuelve `None` si el valor guardado no es válido.
+pub fn decode_raw(raw: &str) -> Option<PathBuf> {
+    let bytes = STANDARD.decode(raw).ok()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStringExt;
+        if bytes.len() % 2 != 0 {
+            return None;
+        }
+        let wide: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        Some(PathBuf::from(std::ffi::OsString::from_wide(&wide)))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        String::from_utf8(bytes).ok().map(PathBuf::from)
+    }
+}
+
+/// Prefijos de ruta extendida de Windows (`\\?\C:\...`, `\\?\UNC\servidor\...`).
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// `path` con los separadores de `style` (ver `copy_path`). Las unidades (`C:`) se
+/// conservan y las rutas UNC pasan de `\\servidor\recurso` a `//servidor/recurso` y al
+/// revés; el prefijo de ruta extendida (`\\?\`) se quita.
+pub fn with_style(path: &str, style: PathStyle) -> String {
+    let path = if let Some(rest) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        format!(r"\\{}", rest)
+    } else {
+        path.strip_prefix(VERBATIM_PREFIX).unwrap_or(path).to_string()
+    };
+    match style.resolve() {
+        PathStyle::Windows => path.replace('/', "\\"),
+        _ => path.replace('\\', "/"),
+    }
+}
+
+/// Añade el separador de `style` al final si no lo tiene ya.
+pub fn with_trailing_separator(mut path: String, style: PathStyle) -> String {
+    let separator = match style.resolve() {
+        PathStyle::Windows => '\\',
+        _ => '/',
+    };
+    if !path.ends_with(separator) {
+        path.push(separator);
+    }
+    path
+}
+
+/// Ruta real a abrir: si se indexó con pérdida (no UTF-8), reconstruye la original
+/// a partir de los bytes guardados; si no, usa la cadena tal cual.
+pub fn resolve_open_path(db: &Mutex<Database>, path: &str) -> PathBuf {
+    let raw = db
+        .lock()
+        .ok()
+        .and_then(|db_guard| db_guard.get_raw_path(path).ok().flatten());
+
+    raw.and_then(|raw| decode_raw(&raw))
+        .unwrap_or_else(|| PathBuf::from(path))
+}
+
+/// Ruta real de `path` (ver `resolve_open_path`) si sigue en disco; un enlace simbólico
+/// cuenta aunque su destino ya no exista. Si no existe, `prune` quita la entrada del
+/// índice (con lo que tenga debajo) para que deje de salir en los resultados y se
+/// devuelve `NotFound`.
+pub fn existing_or_prune(
+    db: &Mutex<Database>,
+    path: &str,
+    prune: impl FnOnce(&str) -> Result<bool, OxiError>,
+) -> Result<PathBuf, OxiError> {
+    let target = resolve_open_path(db, path);
+    if let Err(e) = std::fs::symlink_metadata(&target) {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            prune(path)?;
+        }
+        return Err(OxiError::io_at(path, e));
+    }
+    Ok(target)
+}
+
+/// Comprueba antes de abrirlo que `target` (la ruta real de `path`, ver `decode_raw`)
+/// sigue en disco: `NotFound` si no, y `InvalidInput` si es un directorio y no se
+/// aceptan (`allow_dirs`).
+pub fn check_openable(path: &str, target: &Path, allow_dirs: bool) -> Result<(), OxiError> {
+    let metadata = std::fs::metadata(target).map_err(|e| OxiError::io_at(path, e))?;
+    if metadata.is_dir() && !allow_dirs {
+        return Err(OxiError::invalid_input(format!("{} is a directory", path)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, record, TempDir};
+
+    const MODIFIED: &str = "2024-01-01T00:00:00+00:00";
+
+    /// Índice con `entries` (las que acaban en `/` son directorios).
+    fn index_with(dir: &TempDir, entries: &[&str]) -> (Mutex<Database>, crate::db::Reader) {
+        let (mut db, reader) = open_index(dir);
+        let records: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry.strip_suffix('/') {
+                Some(path) => record(&dir.join(path), true, MODIFIED),
+                None => record(&dir.join(entry), false, MODIFIED),
+            })
+            .collect();
+        db.upsert_batch(&records).unwrap();
+        (Mutex::new(db), reader)
+    }
+
+    fn prune_with(db: &Mutex<Database>) -> impl FnOnce(&str) -> Result<bool, OxiError> + '_ {
+        |path| Ok(db.lock().unwrap().delete_entry(path)? > 0)
+    }
+
+    #[test]
+    fn open_path_is_the_indexed_path_whether_or_not_it_exists() {
+        let dir = TempDir::new("resolve");
+        let (db, _reader) = index_with(&dir, &["here.txt", "deleted.txt"]);
+        std::fs::write(dir.join("here.txt"), b"").unwrap();
+
+        // Sin bytes guardados, la cadena tal cual; no se mira el disco.
+        for path in [dir.join("here.txt"), dir.join("deleted.txt"), dir.join("unindexed")] {
+            assert_eq!(resolve_open_path(&db, &path), Path::new(&path));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_path_of_a_symlink_is_the_link() {
+        let dir = TempDir::new("resolve-symlink");
+        let (db, _reader) = index_with(&dir, &["link"]);
+        std::fs::write(dir.join("target.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(dir.join("target.txt"), dir.join("link")).unwrap();
+
+        assert_eq!(resolve_open_path(&db, &dir.join("link")), Path::new(&dir.join("link")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_path_of_a_lossy_entry_uses_the_raw_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = TempDir::new("resolve-raw");
+        let (db, _reader) = open_index(&dir);
+        let mut real = dir.path().as_os_str().as_bytes().to_vec();
+        real.extend_from_slice(b"/caf\xe9.txt");
+        let real = PathBuf::from(std::ffi::OsStr::from_bytes(&real));
+        let lossy = real.to_string_lossy().into_owned();
+
+        let mut entry = record(&lossy, false, MODIFIED);
+        entry.path_lossy = true;
+        entry.raw_path = Some(encode_raw(&real));
+        db.upsert_file(&entry).unwrap();
+
+        let db = Mutex::new(db);
+        assert_eq!(resolve_open_path(&db, &lossy), real);
+        assert_ne!(Path::new(&lossy), real);
+    }
+
+    #[test]
+    fn missing_location_prunes_its_subtree() {
+        let dir = TempDir::new("locate-missing");
+        let (db, reader) =
+            index_with(&dir, &["gone/", "gone/a.txt", "gone/sub/", "gone/sub/b.txt", "kept.txt"]);
+        std::fs::write(dir.join("kept.txt"), b"").unwrap();
+
+        let error = existing_or_prune(&db, &dir.join("gone"), prune_with(&db)).unwrap_err();
+        assert!(matches!(error, OxiError::NotFound(_)), "{:?}", error);
+        assert_eq!(reader.get_file_count().unwrap(), 1);
+        assert!(reader.file_by_path(&dir.join("kept.txt")).unwrap().is_some());
+    }
+
+    #[test]
+    fn existing_location_is_left_alone() {
+        let dir = TempDir::new("locate-existing");
+        let (db, reader) = index_with(&dir, &["docs/", "docs/a.txt"]);
+        std::fs::create_dir(dir.path().join("docs")).unwrap();
+        std::fs::write(dir.join("docs/a.txt"), b"").unwrap();
+
+        for path in [dir.join("docs"), dir.join("docs/a.txt")] {
+            let target = existing_or_prune(&db, &path, |_| panic!("pruned {}", path)).unwrap();
+            assert_eq!(target, Path::new(&path));
+        }
+        assert_eq!(reader.get_file_count().unwrap(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_left_alone_even_if_dangling() {
+        let dir = TempDir::new("locate-symlink");
+        let (db, reader) = index_with(&dir, &["link", "dangling"]);
+        std::fs::write(dir.join("target.txt"), b"").unwrap();
+        std::os::unix::fs::symlink(dir.join("target.txt"), dir.join("link")).unwrap();
+        std::os::unix::fs::symlink(dir.join("missing.txt"), dir.join("dangling")).unwrap();
+
+        for path in [dir.join("link"), dir.join("dangling")] {
+            let target = existing_or_prune(&db, &path, |_| panic!("pruned {}", path)).unwrap();
+            // Se muestra el enlace, no su destino.
+            assert_eq!(target, Path::new(&path));
+        }
+        assert_eq!(reader.get_file_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn missing_path_is_not_found() {
+        let dir = TempDir::new("open-missing");
+        let path = dir.join("gone.txt");
+        let error = check_openable(&path, Path::new(&path), true).unwrap_err();
+        assert!(matches!(error, OxiError::NotFound(_)), "{:?}", error);
+        assert!(error.to_string().starts_with(&path));
+    }
+
+    #[test]
+    fn directories_only_when_allowed() {
+        let dir = TempDir::new("open-dir");
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, b"").unwrap();
+
+        assert!(check_openable(&file, Path::new(&file), false).is_ok());
+        assert!(check_openable("dir", dir.path(), true).is_ok());
+        let error = check_openable("dir", dir.path(), false).unwrap_err();
+        assert!(matches!(error, OxiError::InvalidInput(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn drive_paths_round_trip() {
+        let windows = r"C:\a\b c\d.txt";
+        let posix = with_style(windows, PathStyle::Posix);
+        assert_eq!(posix, "C:/a/b c/d.txt");
+        assert_eq!(with_style(&posix, PathStyle::Windows), windows);
+        // Ya en el estilo pedido (o mezclado): queda igual o se normaliza.
+        assert_eq!(with_style(windows, PathStyle::Windows), windows);
+        assert_eq!(with_style(r"C:\a/b", PathStyle::Windows), r"C:\a\b");
+    }
+
+    #[test]
+    fn unc_and_verbatim_paths() {
+        let unc = r"\\server\share\dir\f.txt";
+        let posix = with_style(unc, PathStyle::Posix);
+        assert_eq!(posix, "//server/share/dir/f.txt");
+        assert_eq!(with_style(&posix, PathStyle::Windows), unc);
+
+        // El prefijo extendido se quita en los dos estilos.
+        assert_eq!(with_style(r"\\?\C:\a\b", PathStyle::Windows), r"C:\a\b");
+        assert_eq!(with_style(r"\\?\C:\a\b", PathStyle::Posix), "C:/a/b");
+        assert_eq!(with_style(r"\\?\UNC\server\share\f", PathStyle::Windows), r"\\server\share\f");
+        assert_eq!(with_style(r"\\?\UNC\server\share\f", PathStyle::Posix), "//server/share/f");
+    }
+
+    #[test]
+    fn trailing_separator() {
+        assert_eq!(with_trailing_separator("C:".to_string(), PathStyle::Windows), r"C:\");
+        assert_eq!(with_trailing_separator(r"C:\".to_string(), PathStyle::Windows), r"C:\");
+        assert_eq!(with_trailing_separator("/home".to_string(), PathStyle::Posix), "/home/");
+        assert_eq!(with_trailing_separator("/".to_string(), PathStyle::Posix), "/");
+    }
+
+    #[test]
+    fn raw_paths_round_trip() {
+        let path = Path::new("/datos/año 2024/informe.pdf");
+        assert_eq!(decode_raw(&encode_raw(path)).as_deref(), Some(path));
+        assert_eq!(decode_raw("no es base64!"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_raw_paths_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+        // `café` en Latin-1: no es UTF-8 y `to_string_lossy` no lo conserva.
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/datos/caf\xe9.txt"));
+        assert!(path.to_str().is_none());
+
+        let raw = encode_raw(path);
+        assert_eq!(decode_raw(&raw).as_deref(), Some(path));
+        assert_ne!(Path::new(path.to_string_lossy().as_ref()), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn unpaired_surrogates_round_trip() {
+        use std::os::windows::ffi::OsStringExt;
+        let wide = [b'C' as u16, b':' as u16, b'\\' as u16, 0xD800, b'x' as u16];
+        let path = PathBuf::from(std::ffi::OsString::from_wide(&wide));
+        assert!(path.to_str().is_none());
+        assert_eq!(decode_raw(&encode_raw(&path)), Some(path));
+        // Un número impar de bytes no son unidades UTF-16.
+        assert_eq!(decode_raw(&STANDARD.encode([1u8, 2, 3])), None);
+    }
+}
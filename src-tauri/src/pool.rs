@@ -0,0 +1,113 @@
+use crate::db::Reader;
+use rusqlite::Result;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+
+/// Conexiones de solo lectura reutilizables. Las búsquedas y consultas de estado no
+/// esperan al `Mutex<Database>` del escritor: en WAL leen mientras se indexa.
+/// Se abren bajo demanda hasta `max_size`; después se espera a que se libere una.
+pub struct ReadPool {
+    db_path: PathBuf,
+    max_size: usize,
+    state: Mutex<PoolState>,
+    released: Condvar,
+}
+
+struct PoolState {
+    idle: Vec<Reader>,
+    /// Conexiones abiertas (libres + prestadas).
+    open: usize,
+    /// Sube con `reset`; las conexiones de una generación anterior se cierran al volver.
+    generation: u64,
+}
+
+impl ReadPool {
+    pub fn new(db_path: PathBuf, max_size: usize) -> Self {
+        Self {
+            db_path,
+            max_size: max_size.max(1),
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                open: 0,
+                generation: 0,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Presta una conexión; vuelve al pool al soltar el guard.
+    pub fn get(&self) -> Result<PooledReader<'_>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(reader) = state.idle.pop() {
+                return Ok(PooledReader {
+                    pool: self,
+                    reader: Some(reader),
+                    generation: state.generation,
+                });
+            }
+            if state.open < self.max_size {
+                state.open += 1;
+                break;
+            }
+            state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        let generation = state.generation;
+        drop(state);
+
+        // Abrir fuera del lock: no bloquea a quien devuelve o pide otra conexión.
+        match Reader::open(&self.db_path) {
+            Ok(reader) => Ok(PooledReader {
+                pool: self,
+                reader: Some(reader),
+                generation,
+            }),
+            Err(e) => {
+                self.state.lock().unwrap_or_else(|e| e.into_inner()).open -= 1;
+                self.released.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    /// Cierra las conexiones para que las siguientes se abran de nuevo (p. ej. para
+    /// adjuntar un shard nuevo). Las prestadas se cierran al devolverse.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.generation += 1;
+        state.open -= state.idle.len();
+        state.idle.clear();
+        drop(state);
+        self.released.notify_all();
+    }
+}
+
+pub struct PooledReader<'a> {
+    pool: &'a ReadPool,
+    reader: Option<Reader>,
+    generation: u64,
+}
+
+impl Deref for PooledReader<'_> {
+    type Target = Reader;
+
+    fn deref(&self) -> &Reader {
+        self.reader.as_ref().expect("reader taken before drop")
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            let mut state = self.pool.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.generation == self.generation {
+                state.idle.push(reader);
+            } else {
+                state.open -= 1;
+            }
+            drop(state);
+            self.pool.released.notify_one();
+        }
+    }
+}
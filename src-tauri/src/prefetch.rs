@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tracing::debug;
+
+/// Lecturas simultáneas como máximo, para no competir con la UI ni con el indexador.
+const MAX_CONCURRENT_READS: usize = 4;
+
+/// Lee los primeros `bytes_per_file` de cada archivo para que el SO los tenga en
+/// caché al abrirlos. Se detiene al alcanzar `max_total_bytes`. Devuelve los archivos
+/// que se llegaron a encolar.
+pub fn spawn(paths: Vec<PathBuf>, bytes_per_file: usize, max_total_bytes: usize) -> usize {
+    if bytes_per_file == 0 {
+        return 0;
+    }
+
+    let max_files = max_total_bytes / bytes_per_file;
+    let paths: Vec<PathBuf> = paths.into_iter().take(max_files).collect();
+    let queued = paths.len();
+
+    tokio::spawn(async move {
+        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_READS));
+        for path in paths {
+            let Ok(permit) = Arc::clone(&semaphore).acquire_owned().await else {
+                break;
+            };
+            tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                if let Err(e) = read_head(&path, bytes_per_file) {
+                    debug!("Prefetch of {} failed: {}", path.display(), e);
+                }
+            });
+        }
+    });
+
+    queued
+}
+
+fn read_head(path: &Path, bytes: usize) -> std::io::Result<()> {
+    let mut file = File::open(path)?;
+    if !file.metadata()?.is_file() {
+        return Ok(());
+    }
+    let mut buffer = vec![0u8; bytes];
+    let mut filled = 0;
+    while filled < bytes {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Vista previa de archivos de texto en cualquier codificación (`preview_text`).
+
+use crate::types::TextPreview;
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use std::io::Read;
+use std::path::Path;
+
+/// Tope de bytes leídos aunque la UI pida más.
+pub const MAX_PREVIEW_BYTES: u64 = 256 * 1024;
+
+/// Fracción de bytes de control a partir de la cual el archivo se trata como binario.
+const BINARY_CONTROL_RATIO: f64 = 0.1;
+
+/// Lee como mucho `max_bytes` del inicio de `path`, detecta la codificación y lo
+/// devuelve en UTF-8.
+pub fn preview_text(path: &Path, max_bytes: u64) -> std::io::Result<TextPreview> {
+    let max_bytes = max_bytes.clamp(1, MAX_PREVIEW_BYTES);
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)?
+        .take(max_bytes + 1)
+        .read_to_end(&mut bytes)?;
+    let truncated = bytes.len() as u64 > max_bytes;
+    bytes.truncate(max_bytes as usize);
+
+    // Con BOM la codificación es segura (y UTF-16 tiene NULs que no indican binario).
+    let (encoding, bom_len) = match Encoding::for_bom(&bytes) {
+        Some((encoding, bom_len)) => (encoding, bom_len),
+        None => {
+            if looks_binary(&bytes) {
+                return Ok(TextPreview {
+                    text: String::new(),
+                    encoding: None,
+                    is_binary: true,
+                    truncated,
+                });
+            }
+            let mut detector = EncodingDetector::new();
+            detector.feed(&bytes, !truncated);
+            (detector.guess(None, true), 0)
+        }
+    };
+
+    let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    let mut text = text.into_owned();
+    if truncated {
+        // El corte puede partir el último carácter.
+        while text.ends_with('\u{FFFD}') {
+            text.pop();
+        }
+    }
+
+    Ok(TextPreview {
+        text,
+        encoding: Some(encoding.name().to_string()),
+        is_binary: false,
+        truncated,
+    })
+}
+
+/// Binario si tiene algún NUL o demasiados bytes de control (salvo tab y saltos de línea).
+fn looks_binary(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return true;
+    }
+    let control = bytes
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c))
+        .count();
+    !bytes.is_empty() && control as f64 / bytes.len() as f64 > BINARY_CONTROL_RATIO
+}
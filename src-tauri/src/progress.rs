@@ -0,0 +1,167 @@
+//! Agrupa los avisos de progreso del índice: los indexadores avisan por cada entrada y
+//! en una unidad grande eso serían millones de eventos `indexing-progress` para la
+//! webview. Cada aviso lleva los contadores exactos, así que basta con dejar pasar uno
+//! de vez en cuando y el último al terminar.
+
+use crate::types::IndexingProgress;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type ProgressCallback = dyn Fn(IndexingProgress) + Send + Sync;
+
+#[derive(Default)]
+struct ThrottleState {
+    /// Avisos recibidos desde el último emitido.
+    skipped: u64,
+    last_emit: Option<Instant>,
+    /// Último aviso sin emitir (para `flush`).
+    pending: Option<IndexingProgress>,
+}
+
+pub struct ProgressThrottle {
+    /// Emite tras este número de avisos (0 = sin límite por cantidad).
+    every_files: u64,
+    /// Emite si pasó este tiempo desde el último (`None` = sin límite por tiempo).
+    interval: Option<Duration>,
+    emit: std::sync::Arc<ProgressCallback>,
+    state: Mutex<ThrottleState>,
+}
+
+impl ProgressThrottle {
+    /// Sin ninguno de los dos límites se emiten todos los avisos.
+    pub fn new(
+        every_files: u64,
+        interval: Option<Duration>,
+        emit: std::sync::Arc<ProgressCallback>,
+    ) -> Self {
+        Self {
+            every_files,
+            interval,
+            emit,
+            state: Mutex::new(ThrottleState::default()),
+        }
+    }
+
+    /// Emite `progress` si toca; si no, lo guarda para `flush`. El primero y los que no
+    /// son `indexing` (p. ej. `mft-truncated`) siempre se emiten.
+    pub fn report(&self, progress: IndexingProgress) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.skipped += 1;
+        let unthrottled = self.every_files == 0 && self.interval.is_none();
+        let due = unthrottled
+            || progress.status != "indexing"
+            || (self.every_files > 0 && state.skipped >= self.every_files)
+            || match (state.last_emit, self.interval) {
+                (None, _) => true,
+                (Some(last), Some(interval)) => last.elapsed() >= interval,
+                (Some(_), None) => false,
+            };
+        if !due {
+            state.pending = Some(progress);
+            return;
+        }
+        state.skipped = 0;
+        state.last_emit = Some(Instant::now());
+        state.pending = None;
+        // Con el estado bloqueado: los avisos de varios hilos salen en orden.
+        (self.emit)(progress);
+    }
+
+    /// Emite el último aviso retenido, si lo hay (al terminar la ejecución).
+    pub fn flush(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(progress) = state.pending.take() {
+            state.skipped = 0;
+            state.last_emit = Some(Instant::now());
+            (self.emit)(progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn progress(files_processed: usize, status: &str) -> IndexingProgress {
+        IndexingProgress {
+            current_path: format!("/tree/file{}", files_processed),
+            files_processed,
+            total_files: None,
+            status: status.to_string(),
+            operation_id: None,
+            inserted: 0,
+            updated: 0,
+            skipped: 0,
+        }
+    }
+
+    /// `(files_processed, status)` de cada aviso emitido.
+    type Emitted = Arc<Mutex<Vec<(usize, String)>>>;
+
+    fn throttle(every_files: u64, interval: Option<Duration>) -> (ProgressThrottle, Emitted) {
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&emitted);
+        let emit = Arc::new(move |p: IndexingProgress| {
+            sink.lock().unwrap().push((p.files_processed, p.status));
+        });
+        (ProgressThrottle::new(every_files, interval, emit), emitted)
+    }
+
+    #[test]
+    fn many_reports_stay_within_the_bound() {
+        let (by_count, emitted) = throttle(1000, None);
+        for i in 1..=100_000 {
+            by_count.report(progress(i, "indexing"));
+        }
+        // El primero y después uno cada 1000.
+        let emitted = emitted.lock().unwrap();
+        assert_eq!(emitted.len(), 100);
+        assert_eq!(emitted[0].0, 1);
+        assert_eq!(emitted[1].0, 1001);
+
+        let (by_time, emitted) = throttle(0, Some(Duration::from_secs(3600)));
+        for i in 1..=100_000 {
+            by_time.report(progress(i, "indexing"));
+        }
+        assert_eq!(emitted.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn other_statuses_always_pass() {
+        let (throttle, emitted) = throttle(1000, Some(Duration::from_secs(3600)));
+        throttle.report(progress(1, "indexing"));
+        throttle.report(progress(2, "indexing"));
+        throttle.report(progress(3, "mft-truncated"));
+        throttle.report(progress(4, "indexing"));
+        throttle.report(progress(5, "completed"));
+
+        let passed: Vec<_> = emitted.lock().unwrap().iter().map(|e| e.0).collect();
+        assert_eq!(passed, [1, 3, 5]);
+    }
+
+    #[test]
+    fn flush_emits_the_last_held_report() {
+        let (throttle, emitted) = throttle(1000, None);
+        for i in 1..=10 {
+            throttle.report(progress(i, "indexing"));
+        }
+        assert_eq!(emitted.lock().unwrap().len(), 1);
+
+        throttle.flush();
+        assert_eq!(emitted.lock().unwrap().last(), Some(&(10, "indexing".to_string())));
+        // Nada retenido: no se repite.
+        throttle.flush();
+        assert_eq!(emitted.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn without_limits_every_report_passes() {
+        let (throttle, emitted) = throttle(0, None);
+        for i in 1..=50 {
+            throttle.report(progress(i, "indexing"));
+        }
+        throttle.flush();
+        assert_eq!(emitted.lock().unwrap().len(), 50);
+    }
+}
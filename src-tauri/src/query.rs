@@ -0,0 +1,174 @@
+//! Operadores de consulta al estilo de Everything: `factura 2023 -borrador`,
+//! `"informe trimestral"`, `pdf OR docx`, `NOT tmp`.
+//!
+//! Los espacios separan términos que deben aparecer todos (AND); `OR` (o `|`) une dos
+//! términos en alternativas; `-` o `NOT` excluye; las comillas agrupan una frase que
+//! debe aparecer tal cual. Una consulta sin operadores se busca como texto plano;
+//! terminada en `*` (`inf*`), solo al inicio del nombre.
+
+/// Consulta con operadores en forma normal conjuntiva: cada grupo es un OR de términos,
+/// los grupos se combinan con AND y ningún término de `excluded` puede aparecer.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BooleanQuery {
+    pub groups: Vec<Vec<String>>,
+    pub excluded: Vec<String>,
+}
+
+enum Token {
+    Term { text: String, negated: bool },
+    And,
+    Or,
+    Not,
+}
+
+/// Interpreta los operadores de `query`. `None` si no hay ninguno (un solo término sin
+/// comillas) o no queda nada que buscar: se usa la búsqueda de texto plano.
+pub fn parse(query: &str) -> Option<BooleanQuery> {
+    let mut parsed = BooleanQuery::default();
+    let mut negate_next = false;
+    let mut join_next = false;
+    // `OR` solo une con el término anterior si este no era una exclusión.
+    let mut last_positive = false;
+
+    for token in tokenize(query) {
+        match token {
+            Token::And => join_next = false,
+            Token::Or => join_next = last_positive,
+            Token::Not => negate_next = true,
+            Token::Term { text, negated } => {
+                if negated || negate_next {
+                    parsed.excluded.push(text);
+                    last_positive = false;
+                } else {
+                    match parsed.groups.last_mut() {
+                        Some(group) if join_next => group.push(text),
+                        _ => parsed.groups.push(vec![text]),
+                    }
+                    last_positive = true;
+                }
+                negate_next = false;
+                join_next = false;
+            }
+        }
+    }
+
+    let plain = !query.contains('"')
+        && parsed.excluded.is_empty()
+        && parsed.groups.len() == 1
+        && parsed.groups[0].len() == 1
+        && parsed.groups[0][0] == query.trim();
+    if plain || (parsed.groups.is_empty() && parsed.excluded.is_empty()) {
+        return None;
+    }
+    Some(parsed)
+}
+
+/// Texto contra el que se puntúa la relevancia: los términos obligatorios en orden
+/// (`factura 2023 -borrador` -> `factura 2023`), o la consulta tal cual sin operadores.
+pub fn rank_text(query: &str) -> String {
+    if let Some(prefix) = prefix(query) {
+        return prefix.to_string();
+    }
+    let Some(parsed) = parse(query) else {
+        return query.to_string();
+    };
+    let required: Vec<&str> = parsed
+        .groups
+        .iter()
+        .filter(|group| group.len() == 1)
+        .map(|group| group[0].as_str())
+        .collect();
+    if !required.is_empty() {
+        required.join(" ")
+    } else {
+        parsed
+            .groups
+            .first()
+            .and_then(|group| group.first())
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Términos que deben aparecer en cada resultado (la consulta entera si no hay operadores).
+pub fn required_terms(query: &str) -> Vec<String> {
+    match parse(query) {
+        Some(parsed) => parsed
+            .groups
+            .into_iter()
+            .filter(|group| group.len() == 1)
+            .flatten()
+            .collect(),
+        None => vec![prefix(query).unwrap_or(query).to_string()],
+    }
+}
+
+/// Texto de una búsqueda por prefijo: un solo término terminado en `*` (`inf*`).
+/// Se busca con `name LIKE 'inf%'`, que usa `idx_search_name_nocase`.
+pub fn prefix(query: &str) -> Option<&str> {
+    let prefix = query.trim().strip_suffix('*')?;
+    let valid = !prefix.is_empty()
+        && !prefix.contains(['*', '"'])
+        && !prefix.contains(char::is_whitespace);
+    valid.then_some(prefix)
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        // `-` solo niega si va pegado al término; suelto es un término más.
+        let mut negated = false;
+        if c == '-' {
+            chars.next();
+            match chars.peek() {
+                Some(next) if !next.is_whitespace() => negated = true,
+                _ => {
+                    tokens.push(Token::Term {
+                        text: "-".to_string(),
+                        negated: false,
+                    });
+                    continue;
+                }
+            }
+        }
+
+        if chars.peek() == Some(&'"') {
+            // Frase entre comillas (sin cerrar: hasta el final).
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            if !phrase.trim().is_empty() {
+                tokens.push(Token::Term {
+                    text: phrase,
+                    negated,
+                });
+            }
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        // Los operadores van en mayúsculas: `or` puede ser parte de un nombre.
+        let token = match word.as_str() {
+            "AND" | "&&" if !negated => Token::And,
+            "OR" | "|" | "||" if !negated => Token::Or,
+            "NOT" if !negated => Token::Not,
+            _ => Token::Term { text: word, negated },
+        };
+        tokens.push(token);
+    }
+
+    tokens
+}
@@ -0,0 +1,226 @@
+use crate::types::{SearchResult, SortDirection};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Peso base según la calidad de la coincidencia. Los bonus suman como mucho 0.2,
+// así que un nivel superior siempre gana al inferior y el score queda en 0.0..=1.0.
+const EXACT_MATCH: f64 = 0.8;
+const PREFIX_MATCH: f64 = 0.6;
+const WORD_BOUNDARY_MATCH: f64 = 0.4;
+const SUBSTRING_MATCH: f64 = 0.2;
+
+const MAX_LENGTH_BOOST: f64 = 0.1;
+const MAX_RECENCY_BOOST: f64 = 0.1;
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Relevancia de `name` para `query`: exacta > prefijo > inicio de palabra > subcadena,
+/// con un pequeño bonus para nombres cortos y archivos modificados recientemente.
+pub fn score(query: &str, name: &str, modified_time: &str) -> f64 {
+    let query = query.to_lowercase();
+    let name = name.to_lowercase();
+
+    let stem = match name.rfind('.') {
+        Some(idx) if idx > 0 => &name[..idx],
+        _ => name.as_str(),
+    };
+
+    let base = if name == query || stem == query {
+        EXACT_MATCH
+    } else if name.starts_with(&query) {
+        PREFIX_MATCH
+    } else if matches_at_word_boundary(&name, &query) {
+        WORD_BOUNDARY_MATCH
+    } else if name.contains(&query) {
+        SUBSTRING_MATCH
+    } else {
+        0.0
+    };
+
+    let name_len = name.chars().count().max(1) as f64;
+    let query_len = query.chars().count() as f64;
+    let length_boost = MAX_LENGTH_BOOST * (query_len / name_len).min(1.0);
+
+    base + length_boost + recency_boost(modified_time)
+}
+
+/// Puntúa los candidatos y los ordena por score descendente (empates por nombre).
+pub fn rank(candidates: Vec<SearchResult>, query: &str, limit: usize) -> Vec<SearchResult> {
+    let mut ranked: Vec<SearchResult> = candidates
+        .into_iter()
+        .map(|mut result| {
+            result.score = score(query, &result.name, &result.modified_time);
+            result
+        })
+        .collect();
+
+    sort_by_score(&mut ranked);
+    ranked.truncate(limit);
+    ranked
+}
+
+/// Como `rank`, para `search_in_path`: los candidatos que solo casan por la ruta
+/// quedan por debajo de cualquier coincidencia en el nombre (solo cuenta la recencia)
+/// y se marcan con `matched_in_path`.
+pub fn rank_in_path(candidates: Vec<SearchResult>, query: &str, limit: usize) -> Vec<SearchResult> {
+    let needle = query.replace('\\', "/").to_lowercase();
+    let mut ranked: Vec<SearchResult> = candidates
+        .into_iter()
+        .map(|mut result| {
+            if result.name.to_lowercase().contains(&needle) {
+                result.score = score(query, &result.name, &result.modified_time);
+            } else {
+                result.matched_in_path = true;
+                result.score = recency_boost(&result.modified_time);
+            }
+            result
+        })
+        .collect();
+
+    sort_by_score(&mut ranked);
+    ranked.truncate(limit);
+    ranked
+}
+
+pub fn sort_by_score(results: &mut [SearchResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Reordena para que ninguna extensión ocupe más de `max_per_extension` puestos
+/// mientras haya otras; lo que sobre completa la página después, en su orden.
+pub fn diversify(
+    results: Vec<SearchResult>,
+    max_per_extension: usize,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let mut counts: HashMap<Option<String>, usize> = HashMap::new();
+    let mut picked = Vec::with_capacity(limit.min(results.len()));
+    let mut overflow = Vec::new();
+
+    for result in results {
+        let count = counts.entry(result.extension.clone()).or_insert(0);
+        if *count < max_per_extension && picked.len() < limit {
+            *count += 1;
+            picked.push(result);
+        } else {
+            overflow.push(result);
+        }
+    }
+
+    let missing = limit.saturating_sub(picked.len());
+    picked.extend(overflow.into_iter().take(missing));
+    picked
+}
+
+/// Ordena por fecha de creación; las entradas sin ella van al final.
+pub fn sort_by_created(results: &mut [SearchResult], newest_first: bool) {
+    results.sort_by(|a, b| match (&a.created_time, &b.created_time) {
+        (Some(x), Some(y)) if newest_first => y.cmp(x),
+        (Some(x), Some(y)) => x.cmp(y),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+/// Por tamaño; los que no tienen (directorios) al final en ambos sentidos.
+pub fn sort_by_size(results: &mut [SearchResult], direction: SortDirection) {
+    results.sort_by(|a, b| match (a.file_size, b.file_size) {
+        (Some(x), Some(y)) => direction.apply(x.cmp(&y)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
+/// `true` si `query` aparece en `name` como palabra completa: sin letras ni dígitos
+/// pegados a ningún lado (`test` casa con `my_test.rs` pero no con `latest`).
+pub fn contains_word(name: &str, query: &str, case_sensitive: bool) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+    let (name, query) = if case_sensitive {
+        (name.to_string(), query.to_string())
+    } else {
+        (name.to_lowercase(), query.to_lowercase())
+    };
+    name.match_indices(query.as_str()).any(|(idx, matched)| {
+        let before = name[..idx].chars().next_back();
+        let after = name[idx + matched.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+fn matches_at_word_boundary(name: &str, query: &str) -> bool {
+    name.match_indices(query)
+        .any(|(idx, _)| !matches!(name[..idx].chars().next_back(), Some(c) if c.is_alphanumeric()))
+}
+
+fn recency_boost(modified_time: &str) -> f64 {
+    let Ok(modified) = DateTime::parse_from_rfc3339(modified_time) else {
+        return 0.0;
+    };
+    let age_days = (Utc::now() - modified.with_timezone(&Utc)).num_seconds().max(0) as f64 / 86_400.0;
+    MAX_RECENCY_BOOST / (1.0 + age_days / RECENCY_HALF_LIFE_DAYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, modified_time: &str) -> SearchResult {
+        SearchResult {
+            path: format!("/data/{}", name),
+            name: name.to_string(),
+            extension: None,
+            file_size: None,
+            is_dir: false,
+            modified_time: modified_time.to_string(),
+            created_time: None,
+            is_reparse: false,
+            link_count: 1,
+            mode: None,
+            owner_uid: None,
+            owner_gid: None,
+            score: 0.0,
+            child_count: None,
+            matched_in_path: false,
+        }
+    }
+
+    #[test]
+    fn exact_name_ranks_above_substring() {
+        // La subcadena es más reciente y aun así queda por debajo.
+        let now = Utc::now().to_rfc3339();
+        let candidates = vec![
+            result("old_report_backup.txt", &now),
+            result("report.txt", "2000-01-01T00:00:00Z"),
+        ];
+
+        let ranked = rank(candidates, "report", 10);
+        assert_eq!(ranked[0].name, "report.txt");
+        assert!(ranked[0].score > ranked[1].score);
+    }
+
+    #[test]
+    fn match_levels_are_ordered() {
+        let old = "2000-01-01T00:00:00Z";
+        let exact = score("report", "report", old);
+        let prefix = score("report", "reports_2024", old);
+        let word = score("report", "q3-report-draft", old);
+        let substring = score("report", "myreportdraft", old);
+        assert!(exact > prefix && prefix > word && word > substring);
+    }
+
+    #[test]
+    fn contains_word_needs_boundaries() {
+        assert!(contains_word("my_test.rs", "test", false));
+        assert!(!contains_word("latest.rs", "test", false));
+        assert!(!contains_word("Test.rs", "test", true));
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Espera máxima de `acquire` entre comprobaciones de `stop`.
+const ACQUIRE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Token bucket para limitar la frecuencia de búsquedas aceptadas (y de entradas
+/// indexadas con `index_max_files_per_sec`).
+/// La tasa y la ráfaga se pasan en cada llamada para seguir la config en vivo.
+pub struct RateLimiter {
+    state: Mutex<BucketState>,
+    /// Hora actual (`Instant::now` salvo en los tests).
+    clock: fn() -> Instant,
+}
+
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::with_clock(Instant::now)
+    }
+
+    fn with_clock(clock: fn() -> Instant) -> Self {
+        Self {
+            state: Mutex::new(BucketState {
+                tokens: f64::MAX,
+                last_refill: clock(),
+            }),
+            clock,
+        }
+    }
+
+    /// Consume un token si hay disponible. Si no, devuelve cuánto falta para el siguiente.
+    /// Con `rate_per_sec <= 0` el límite está desactivado.
+    pub fn try_acquire(&self, rate_per_sec: f64, burst: u32) -> Result<(), Duration> {
+        if rate_per_sec <= 0.0 {
+            return Ok(());
+        }
+
+        let capacity = burst.max(1) as f64;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let now = (self.clock)();
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens.min(capacity) + elapsed * rate_per_sec).min(capacity);
+        state.last_refill = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - state.tokens) / rate_per_sec))
+        }
+    }
+
+    /// Como `try_acquire`, pero espera al siguiente token. Devuelve `false` sin
+    /// consumirlo si `stop` se cumple mientras espera.
+    pub fn acquire(&self, rate_per_sec: f64, burst: u32, stop: impl Fn() -> bool) -> bool {
+        loop {
+            match self.try_acquire(rate_per_sec, burst) {
+                Ok(()) => return true,
+                Err(_) if stop() => return false,
+                Err(wait) => std::thread::sleep(wait.min(ACQUIRE_POLL_INTERVAL)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::sync::OnceLock;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    thread_local! {
+        /// Tiempo simulado de cada test (cada uno corre en su hilo).
+        static ELAPSED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    }
+
+    fn fake_now() -> Instant {
+        *START.get_or_init(Instant::now) + ELAPSED.get()
+    }
+
+    fn advance(by: Duration) {
+        ELAPSED.set(ELAPSED.get() + by);
+    }
+
+    /// Intentos aceptados de `attempts` seguidos, sin avanzar el reloj.
+    fn accepted(limiter: &RateLimiter, rate: f64, burst: u32, attempts: usize) -> usize {
+        (0..attempts).filter(|_| limiter.try_acquire(rate, burst).is_ok()).count()
+    }
+
+    #[test]
+    fn burst_then_refill_at_the_rate() {
+        let limiter = RateLimiter::with_clock(fake_now);
+        assert_eq!(accepted(&limiter, 10.0, 5, 20), 5);
+        let wait = limiter.try_acquire(10.0, 5).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(100));
+
+        advance(Duration::from_millis(250));
+        assert_eq!(accepted(&limiter, 10.0, 5, 20), 2);
+        // Lo acumulado nunca pasa de la ráfaga.
+        advance(Duration::from_secs(60));
+        assert_eq!(accepted(&limiter, 10.0, 5, 20), 5);
+    }
+
+    #[test]
+    fn throughput_stays_under_the_rate() {
+        let limiter = RateLimiter::with_clock(fake_now);
+        let (rate, burst) = (50.0, 10);
+        let mut accepted_total = 0;
+        // Diez segundos intentándolo cada milisegundo.
+        for _ in 0..10_000 {
+            accepted_total += accepted(&limiter, rate, burst, 1);
+            advance(Duration::from_millis(1));
+        }
+        assert!(accepted_total <= 500 + burst as usize, "{}", accepted_total);
+        assert!(accepted_total >= 500, "{}", accepted_total);
+    }
+
+    #[test]
+    fn zero_rate_disables_the_limit() {
+        let limiter = RateLimiter::with_clock(fake_now);
+        assert_eq!(accepted(&limiter, 0.0, 1, 1000), 1000);
+    }
+
+    #[test]
+    fn acquire_gives_up_when_asked_to_stop() {
+        let limiter = RateLimiter::with_clock(fake_now);
+        assert!(limiter.acquire(1.0, 1, || false));
+        assert!(!limiter.acquire(1.0, 1, || true));
+        advance(Duration::from_secs(1));
+        assert!(limiter.acquire(1.0, 1, || true));
+    }
+}
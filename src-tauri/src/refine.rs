@@ -0,0 +1,140 @@
+//! Búsqueda dentro de resultados (`refine_search`): filtros extra sobre una búsqueda ya
+//! hecha, sin volver a consultar SQLite, y el recuento por extensión de la barra lateral.
+
+use crate::error::OxiError;
+use crate::types::{ExtensionFacet, Refinement, SearchResult};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Aplica `refinement` a `results` (se conserva su orden). Las facetas se cuentan antes
+/// del filtro de extensiones: la barra lateral sigue mostrando las demás para cambiar
+/// de una a otra.
+pub fn refine(
+    mut results: Vec<SearchResult>,
+    refinement: &Refinement,
+) -> Result<(Vec<SearchResult>, Vec<ExtensionFacet>), OxiError> {
+    // RFC3339 en UTC, como `modified_time`: se compara como texto.
+    let min_date = refinement.min_date.as_deref().map(to_utc).transpose()?;
+    let max_date = refinement.max_date.as_deref().map(to_utc).transpose()?;
+    let text = refinement.text.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    let text = text.map(str::to_lowercase);
+
+    results.retain(|result| {
+        let size = result.file_size.unwrap_or(0);
+        refinement.is_dir.is_none_or(|is_dir| result.is_dir == is_dir)
+            && refinement.min_size.is_none_or(|min| size >= min)
+            && refinement.max_size.is_none_or(|max| size <= max)
+            && min_date.as_ref().is_none_or(|min| result.modified_time >= *min)
+            && max_date.as_ref().is_none_or(|max| result.modified_time <= *max)
+            && text.as_ref().is_none_or(|text| result.name.to_lowercase().contains(text))
+    });
+    let facets = extension_facets(&results);
+
+    let extensions: Vec<String> = refinement.extensions.iter().map(|e| normalize(e)).collect();
+    if !extensions.is_empty() {
+        results.retain(|result| {
+            let extension = result.extension.as_deref().map(normalize);
+            extension.is_some_and(|extension| extensions.contains(&extension))
+        });
+    }
+    Ok((results, facets))
+}
+
+/// Resultados por extensión (en minúsculas; `None` sin extensión y directorios), de
+/// más a menos.
+fn extension_facets(results: &[SearchResult]) -> Vec<ExtensionFacet> {
+    let mut counts: HashMap<Option<String>, usize> = HashMap::new();
+    for result in results {
+        let extension = result.extension.as_deref().map(normalize);
+        *counts.entry(extension).or_default() += 1;
+    }
+    let mut facets: Vec<ExtensionFacet> = counts
+        .into_iter()
+        .map(|(extension, count)| ExtensionFacet { extension, count })
+        .collect();
+    facets.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.extension.cmp(&b.extension)));
+    facets
+}
+
+/// `.PDF`, `pdf` y `.pdf` son la misma extensión.
+fn normalize(extension: &str) -> String {
+    format!(".{}", extension.trim_start_matches('.').to_lowercase())
+}
+
+fn to_utc(value: &str) -> Result<String, OxiError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc).to_rfc3339())
+        .map_err(|e| OxiError::invalid_input(format!("Invalid date {}: {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::result;
+
+    fn results() -> Vec<SearchResult> {
+        let entries = [
+            ("/d/Report.PDF", 500, "2024-03-01T00:00:00+00:00"),
+            ("/d/report.pdf", 50, "2024-01-01T00:00:00+00:00"),
+            ("/d/report.txt", 5, "2024-03-01T00:00:00+00:00"),
+            ("/d/notes.txt", 800, "2024-03-01T00:00:00+00:00"),
+            ("/d/README", 10, "2024-03-01T00:00:00+00:00"),
+        ];
+        let mut results: Vec<SearchResult> = entries
+            .iter()
+            .map(|(path, size, modified)| SearchResult {
+                file_size: Some(*size),
+                modified_time: modified.to_string(),
+                ..result(path, false)
+            })
+            .collect();
+        results.push(result("/d/reports", true));
+        results
+    }
+
+    fn names(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|result| result.name.as_str()).collect()
+    }
+
+    fn facets(facets: &[ExtensionFacet]) -> Vec<(Option<&str>, usize)> {
+        facets.iter().map(|facet| (facet.extension.as_deref(), facet.count)).collect()
+    }
+
+    #[test]
+    fn refine_narrows_and_counts_facets_before_the_extension_filter() {
+        let refinement = Refinement {
+            text: Some(" REPORT ".to_string()),
+            is_dir: Some(false),
+            ..Default::default()
+        };
+        let (refined, found) = refine(results(), &refinement).unwrap();
+        assert_eq!(names(&refined), ["Report.PDF", "report.pdf", "report.txt"]);
+        assert_eq!(facets(&found), [(Some(".pdf"), 2), (Some(".txt"), 1)]);
+
+        // Las facetas no cambian al elegir una extensión: se cuentan antes.
+        let by_extension = Refinement { extensions: vec!["PDF".to_string()], ..refinement };
+        let (refined, found) = refine(results(), &by_extension).unwrap();
+        assert_eq!(names(&refined), ["Report.PDF", "report.pdf"]);
+        assert_eq!(facets(&found), [(Some(".pdf"), 2), (Some(".txt"), 1)]);
+
+        // Tamaño y fecha (con otra zona horaria) sobre todo lo anterior.
+        let narrowed = Refinement {
+            min_size: Some(10),
+            min_date: Some("2024-03-01T01:00:00+02:00".to_string()),
+            ..by_extension
+        };
+        let (refined, found) = refine(results(), &narrowed).unwrap();
+        assert_eq!(names(&refined), ["Report.PDF"]);
+        assert_eq!(facets(&found), [(Some(".pdf"), 1)]);
+    }
+
+    #[test]
+    fn facets_group_directories_with_extensionless_files() {
+        let (refined, found) = refine(results(), &Refinement::default()).unwrap();
+        assert_eq!(refined.len(), results().len());
+        assert_eq!(facets(&found), [(None, 2), (Some(".pdf"), 2), (Some(".txt"), 2)]);
+
+        let invalid = Refinement { max_date: Some("yesterday".to_string()), ..Default::default() };
+        assert!(refine(results(), &invalid).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Búsqueda por nombre compartida por `search_files`, `search_files_streaming` y la
+//! API HTTP: consulta a SQLite, ranking, orden y diversificación.
+
+use crate::db::Reader;
+use crate::error::OxiError;
+use crate::types::{SearchConfig, SearchFilters, SearchResult, SortBy};
+use crate::{fuzzy, path_match, query, ranking};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Cuántos candidatos por resultado pedido se traen de SQLite para rankear en Rust.
+const RANK_CANDIDATE_FACTOR: usize = 20;
+
+/// Ajustes de `execute_search` que salen de `SearchConfig`.
+pub struct SearchTuning {
+    pub fuzzy_threshold: f64,
+    /// Corta la consulta SQL al vencer (ver `SearchResults::timed_out`).
+    pub timeout: Option<Duration>,
+    /// Consultas más cortas (en caracteres, sin `tag:` y demás) no llegan a SQLite.
+    pub min_query_length: usize,
+    /// Registra en el log las consultas SQL más lentas que esto.
+    pub slow_query: Option<Duration>,
+}
+
+impl SearchTuning {
+    pub fn from_config(config: &SearchConfig) -> Self {
+        Self {
+            fuzzy_threshold: config.fuzzy_threshold,
+            timeout: config.search_timeout(),
+            min_query_length: config.min_query_length,
+            slow_query: config.slow_query_threshold(),
+        }
+    }
+
+    /// `name_query` no vacía pero por debajo de `min_query_length`: un `LIKE '%a%'`
+    /// recorrería toda la tabla. Sin texto (solo `tag:` y demás) sí se busca.
+    pub fn too_short(&self, name_query: &str) -> bool {
+        let length = name_query.trim().chars().count();
+        length > 0 && length < self.min_query_length
+    }
+}
+
+/// Busca `name_query` y aplica el ranking, el orden y la diversificación de `filters`.
+/// Compartido por el comando `search_files` y la API HTTP. El `bool` indica que la
+/// consulta venció el timeout y los resultados salen de un conjunto parcial.
+pub fn execute_search(
+    db: &Reader,
+    name_query: &str,
+    filters: &SearchFilters,
+    limit: usize,
+    tuning: &SearchTuning,
+) -> Result<(Vec<SearchResult>, bool), OxiError> {
+    if tuning.too_short(name_query) {
+        return Ok((Vec::new(), false));
+    }
+    // SQLite no calcula relevancia ni similitud: se trae un superconjunto y se rankea en Rust.
+    let candidate_limit = limit.saturating_mul(RANK_CANDIDATE_FACTOR);
+    let (candidates, stats) = db
+        .search_files(name_query, filters, candidate_limit, tuning.timeout)?;
+    if tuning.slow_query.is_some_and(|threshold| stats.elapsed >= threshold) {
+        // Forma de la consulta, no su texto: longitud y número de términos.
+        warn!(
+            "Slow search query: {:?} for {} chars / {} terms, {} rows (timed_out={}), filters={:?}",
+            stats.elapsed,
+            name_query.chars().count(),
+            name_query.split_whitespace().count(),
+            stats.rows,
+            stats.timed_out,
+            filters
+        );
+        debug!("Slow search SQL (parameters redacted): {}", stats.sql);
+    }
+    let timed_out = stats.timed_out;
+    // Con otro orden que no sea relevancia o con diversificación, los rankers solo
+    // filtran y puntúan: el recorte a `limit` se hace tras reordenar.
+    let reorders = filters.sort_by != SortBy::Relevance
+        || filters.max_per_extension.is_some()
+        || filters.dirs_first;
+    let rank_limit = if !reorders {
+        limit
+    } else {
+        candidate_limit
+    };
+    let mut results = rank_candidates(candidates, name_query, filters, tuning, rank_limit);
+    sort_results(&mut results, filters);
+    if let Some(max_per_extension) = filters.max_per_extension {
+        results = ranking::diversify(results, max_per_extension.max(1), limit);
+    }
+    if filters.dirs_first {
+        // Estable: dentro de cada grupo se mantiene el orden anterior.
+        results.sort_by_key(|result| !result.is_dir);
+    }
+    results.truncate(limit);
+
+    db.fill_child_counts(&mut results)?;
+
+    Ok((results, timed_out))
+}
+
+/// Búsqueda por lotes de `search_files_streaming`: cada lote de `batch_size` filas de
+/// SQLite se rankea, ordena y completa por separado y se entrega a `on_batch` con su
+/// posición en el flujo (los lotes que quedan vacíos no se entregan). Si `on_batch`
+/// devuelve `false` se deja de leer. Devuelve los resultados entregados y si venció el
+/// timeout.
+pub fn stream_search(
+    db: &Reader,
+    name_query: &str,
+    filters: &SearchFilters,
+    limit: usize,
+    tuning: &SearchTuning,
+    batch_size: usize,
+    mut on_batch: impl FnMut(usize, Vec<SearchResult>) -> bool,
+) -> Result<(usize, bool), OxiError> {
+    let mut total = 0;
+    let stats =
+        db.search_files_batched(name_query, filters, limit, tuning.timeout, batch_size, |batch| {
+            let mut results = rank_candidates(batch, name_query, filters, tuning, usize::MAX);
+            sort_results(&mut results, filters);
+            if let Err(e) = db.fill_child_counts(&mut results) {
+                warn!("Failed to count children for streamed results: {}", e);
+            }
+            if results.is_empty() {
+                return true;
+            }
+            let offset = total;
+            total += results.len();
+            on_batch(offset, results)
+        })?;
+    Ok((total, stats.timed_out))
+}
+
+/// Filtra (palabra completa, umbral, `min_score`) y puntúa los candidatos con el ranker
+/// de `filters`; quedan ordenados por score y recortados a `limit`.
+pub fn rank_candidates(
+    mut candidates: Vec<SearchResult>,
+    name_query: &str,
+    filters: &SearchFilters,
+    tuning: &SearchTuning,
+    limit: usize,
+) -> Vec<SearchResult> {
+    if filters.whole_word && !filters.fuzzy && !filters.component_match {
+        // Con operadores, cada término obligatorio tiene que ser una palabra completa.
+        let words = query::required_terms(name_query);
+        if filters.search_in_path {
+            let words: Vec<String> = words.iter().map(|word| word.replace('\\', "/")).collect();
+            candidates.retain(|c| {
+                let path = c.path.replace('\\', "/");
+                words
+                    .iter()
+                    .all(|word| ranking::contains_word(&path, word, filters.case_sensitive))
+            });
+        } else {
+            candidates.retain(|c| {
+                words
+                    .iter()
+                    .all(|word| ranking::contains_word(&c.name, word, filters.case_sensitive))
+            });
+        }
+    }
+    // Sin los operadores: `-borrador` o `OR` no deben contar para la relevancia.
+    let rank_query = query::rank_text(name_query);
+    let mut results = if filters.component_match {
+        path_match::rank(candidates, name_query, limit)
+    } else if filters.fuzzy {
+        fuzzy::rank(candidates, name_query, tuning.fuzzy_threshold, limit)
+    } else if filters.search_in_path {
+        ranking::rank_in_path(candidates, &rank_query, limit)
+    } else {
+        ranking::rank(candidates, &rank_query, limit)
+    };
+    // Los rankers ya ordenan por score: filtrar tras el recorte no cambia qué queda.
+    if let Some(min_score) = filters.min_score {
+        results.retain(|result| result.score >= min_score);
+    }
+    results
+}
+
+/// Aplica `sort_by`; con `Relevance` se deja el orden por score.
+pub fn sort_results(results: &mut [SearchResult], filters: &SearchFilters) {
+    let direction = filters.sort_direction();
+    match filters.sort_by {
+        SortBy::Relevance => {}
+        SortBy::CreatedDesc => ranking::sort_by_created(results, true),
+        SortBy::CreatedAsc => ranking::sort_by_created(results, false),
+        SortBy::Name => {
+            let collation = filters.collation;
+            results.sort_by(|a, b| direction.apply(collation.compare(&a.name, &b.name)));
+        }
+        SortBy::Size => ranking::sort_by_size(results, direction),
+        // RFC3339 en UTC: el orden del texto es el cronológico.
+        SortBy::Modified => {
+            results.sort_by(|a, b| direction.apply(a.modified_time.cmp(&b.modified_time)));
+        }
+        SortBy::Path => results.sort_by(|a, b| direction.apply(a.path.cmp(&b.path))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, record, result, TempDir};
+    use crate::types::{FileRecord, NameCollation, SortDirection};
+    use std::collections::BTreeSet;
+
+    const MODIFIED: &str = "2024-01-01T00:00:00+00:00";
+
+    fn tuning() -> SearchTuning {
+        SearchTuning {
+            fuzzy_threshold: 0.5,
+            timeout: None,
+            min_query_length: 1,
+            slow_query: None,
+        }
+    }
+
+    fn paths(results: &[SearchResult]) -> BTreeSet<String> {
+        results.iter().map(|result| result.path.clone()).collect()
+    }
+
+    fn paths_in_order(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|result| result.path.as_str()).collect()
+    }
+
+    #[test]
+    fn short_queries_short_circuit() {
+        let tuning = SearchTuning { min_query_length: 2, ..tuning() };
+        assert!(tuning.too_short("a"));
+        assert!(tuning.too_short(" é "));
+        assert!(!tuning.too_short("ab"));
+        assert!(!tuning.too_short("éa"));
+        // Sin texto (solo `tag:` y demás) sí se busca.
+        assert!(!tuning.too_short(""));
+        assert!(!tuning.too_short("   "));
+    }
+
+    #[test]
+    fn streamed_batches_add_up_to_the_one_shot_result() {
+        let dir = TempDir::new("stream");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> = (0..300)
+            .map(|i| {
+                let name = format!("{}-file{}.txt", ["zeta", "alpha", "Mid"][i % 3], 299 - i);
+                record(&format!("/data/d{}/{}", i % 7, name), false, MODIFIED)
+            })
+            .chain(std::iter::once(record("/data/other.txt", false, MODIFIED)))
+            .collect();
+        db.upsert_batch(&records).unwrap();
+
+        for sort_by in [SortBy::Relevance, SortBy::Name, SortBy::Path] {
+            let filters = SearchFilters { sort_by, ..Default::default() };
+            let (one_shot, _) =
+                execute_search(&reader, "file", &filters, 1_000, &tuning()).unwrap();
+            assert_eq!(one_shot.len(), 300);
+
+            let mut batches = Vec::new();
+            let (total, timed_out) =
+                stream_search(&reader, "file", &filters, 1_000, &tuning(), 32, |offset, batch| {
+                    batches.push((offset, batch));
+                    true
+                })
+                .unwrap();
+            assert_eq!((total, timed_out), (300, false));
+
+            let mut streamed = Vec::new();
+            for (offset, batch) in batches {
+                assert_eq!(offset, streamed.len());
+                // Cada lote sale ordenado, pero solo dentro de sí mismo.
+                let mut sorted = batch.clone();
+                sort_results(&mut sorted, &filters);
+                assert_eq!(paths_in_order(&batch), paths_in_order(&sorted), "{:?}", sort_by);
+                streamed.extend(batch);
+            }
+            assert_eq!(streamed.len(), total);
+            assert_eq!(paths(&streamed), paths(&one_shot), "{:?}", sort_by);
+        }
+    }
+
+    #[test]
+    fn stream_stops_when_the_consumer_says_so() {
+        let dir = TempDir::new("stream-stop");
+        let (mut db, reader) = open_index(&dir);
+        let records: Vec<FileRecord> = (0..100)
+            .map(|i| record(&format!("/data/file{}.txt", i), false, MODIFIED))
+            .collect();
+        db.upsert_batch(&records).unwrap();
+
+        let mut calls = 0;
+        let filters = SearchFilters::default();
+        let (total, _) = stream_search(&reader, "file", &filters, 1_000, &tuning(), 10, |_, _| {
+            calls += 1;
+            calls < 2
+        })
+        .unwrap();
+        assert_eq!((calls, total), (2, 20));
+    }
+
+    /// Nombres de `results` en el orden de `sort_results` con `filters`.
+    fn sorted(results: &[SearchResult], filters: SearchFilters) -> Vec<String> {
+        let mut results = results.to_vec();
+        sort_results(&mut results, &filters);
+        results.into_iter().map(|result| result.name).collect()
+    }
+
+    #[test]
+    fn sort_results_by_name_size_and_date() {
+        let by = |sort_by, sort_dir| SearchFilters { sort_by, sort_dir, ..Default::default() };
+
+        let names: Vec<SearchResult> = ["file10", "File2", "file1", "file2b"]
+            .into_iter()
+            .map(|name| result(&format!("/d/{}", name), false))
+            .collect();
+        let natural = SearchFilters {
+            collation: NameCollation::Natural,
+            ..by(SortBy::Name, None)
+        };
+        assert_eq!(sorted(&names, natural), ["file1", "File2", "file2b", "file10"]);
+        assert_eq!(
+            sorted(&names, by(SortBy::Name, None)),
+            ["File2", "file1", "file10", "file2b"]
+        );
+
+        // Los directorios (sin tamaño) van al final en los dos sentidos.
+        let mut sizes = Vec::new();
+        let entries = [("mid", Some(50)), ("dir", None), ("big", Some(900)), ("small", Some(1))];
+        for (name, size) in entries {
+            let mut entry = result(&format!("/d/{}", name), size.is_none());
+            entry.file_size = size;
+            sizes.push(entry);
+        }
+        assert_eq!(sorted(&sizes, by(SortBy::Size, None)), ["big", "mid", "small", "dir"]);
+        assert_eq!(
+            sorted(&sizes, by(SortBy::Size, Some(SortDirection::Asc))),
+            ["small", "mid", "big", "dir"]
+        );
+
+        let mut dates = Vec::new();
+        for (name, modified) in [
+            ("march", "2024-03-01T00:00:00+00:00"),
+            ("january", "2024-01-15T00:00:00+00:00"),
+            ("december", "2023-12-31T23:59:59+00:00"),
+        ] {
+            let mut entry = result(&format!("/d/{}", name), false);
+            entry.modified_time = modified.to_string();
+            dates.push(entry);
+        }
+        assert_eq!(sorted(&dates, by(SortBy::Modified, None)), ["march", "january", "december"]);
+        assert_eq!(
+            sorted(&dates, by(SortBy::Modified, Some(SortDirection::Asc))),
+            ["december", "january", "march"]
+        );
+    }
+}
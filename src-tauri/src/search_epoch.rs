@@ -0,0 +1,124 @@
+//! Épocas de `search_files`: al escribir, la búsqueda de "doc" puede terminar después
+//! de la de "docu" y pisar sus resultados. Cada búsqueda nueva supera a las anteriores,
+//! interrumpe su consulta SQL si sigue en curso y las marca como `is_stale`.
+
+use rusqlite::InterruptHandle;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+#[derive(Default)]
+pub struct SearchEpochs {
+    latest: AtomicU64,
+    /// Conexiones con una consulta en curso y la época que la lanzó.
+    running: Mutex<Vec<(u64, InterruptHandle)>>,
+}
+
+impl SearchEpochs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Época de una búsqueda nueva; interrumpe las consultas de las anteriores.
+    pub fn begin(&self) -> u64 {
+        let mut running = self.lock();
+        let epoch = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        for (_, handle) in running.drain(..) {
+            handle.interrupt();
+        }
+        epoch
+    }
+
+    /// `true` si ya se lanzó otra búsqueda después de `epoch`.
+    pub fn is_stale(&self, epoch: u64) -> bool {
+        self.latest.load(Ordering::SeqCst) != epoch
+    }
+
+    /// Registra la conexión que va a consultar para `epoch`. Devuelve `false` si la
+    /// búsqueda ya está superada (no hace falta consultar).
+    ///
+    /// Hay que llamar a `finish` antes de devolver la conexión al pool: si no, la
+    /// interrupción podría cortar la consulta de otra búsqueda.
+    pub fn register(&self, epoch: u64, handle: InterruptHandle) -> bool {
+        let mut running = self.lock();
+        if self.is_stale(epoch) {
+            return false;
+        }
+        running.push((epoch, handle));
+        true
+    }
+
+    pub fn finish(&self, epoch: u64) {
+        self.lock().retain(|(running, _)| *running != epoch);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(u64, InterruptHandle)>> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use std::sync::{Arc, Barrier};
+    use std::time::Duration;
+
+    /// Como `search_files`: consulta solo si la época sigue vigente y descarta lo que
+    /// se haya quedado viejo por el camino.
+    fn search(epochs: &SearchEpochs, epoch: u64, conn: &Connection, sql: &str) -> Option<i64> {
+        if !epochs.register(epoch, conn.get_interrupt_handle()) {
+            return None;
+        }
+        let outcome = conn.query_row(sql, [], |row| row.get(0));
+        epochs.finish(epoch);
+        outcome.ok().filter(|_| !epochs.is_stale(epoch))
+    }
+
+    #[test]
+    fn only_the_last_of_rapid_searches_is_authoritative() {
+        let epochs = Arc::new(SearchEpochs::new());
+        let started = Arc::new(Barrier::new(2));
+
+        // Una consulta lenta que solo termina si la interrumpen.
+        let first = epochs.begin();
+        let slow = {
+            let epochs = Arc::clone(&epochs);
+            let started = Arc::clone(&started);
+            std::thread::spawn(move || {
+                let conn = Connection::open_in_memory().unwrap();
+                let handle = conn.get_interrupt_handle();
+                assert!(epochs.register(first, handle));
+                started.wait();
+                let outcome: rusqlite::Result<i64> = conn.query_row(
+                    "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) \
+                     SELECT count(*) FROM n",
+                    [],
+                    |row| row.get(0),
+                );
+                epochs.finish(first);
+                outcome
+            })
+        };
+        started.wait();
+        std::thread::sleep(Duration::from_millis(50));
+
+        // Tecleo rápido: cada búsqueda supera a la anterior.
+        let middle: Vec<u64> = (0..3).map(|_| epochs.begin()).collect();
+        let last = epochs.begin();
+
+        let interrupted = slow.join().unwrap().unwrap_err();
+        assert_eq!(
+            interrupted.sqlite_error_code(),
+            Some(rusqlite::ErrorCode::OperationInterrupted)
+        );
+        assert!(epochs.is_stale(first));
+
+        let conn = Connection::open_in_memory().unwrap();
+        for epoch in middle {
+            assert!(epochs.is_stale(epoch));
+            assert_eq!(search(&epochs, epoch, &conn, "SELECT 1"), None);
+        }
+        assert!(!epochs.is_stale(last));
+        assert_eq!(search(&epochs, last, &conn, "SELECT 42"), Some(42));
+    }
+}
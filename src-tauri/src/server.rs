@@ -0,0 +1,314 @@
+//! API HTTP/JSON de solo lectura sobre el índice (feature `http-api`).
+//! Escucha en `api_bind_address:api_port` (por defecto solo 127.0.0.1) y, con
+//! `api_token`, solo responde a peticiones con ese token.
+
+use crate::error::OxiError;
+use crate::pool::ReadPool;
+use crate::rate_limit::RateLimiter;
+use crate::types::{SearchConfig, SearchFilters, SearchResults};
+use crate::search::{execute_search, SearchTuning};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Resultados por página si la petición no indica `limit`.
+const DEFAULT_LIMIT: usize = 50;
+
+/// Estado compartido con los comandos de Tauri.
+#[derive(Clone)]
+pub struct ApiState {
+    pub pool: Arc<ReadPool>,
+    pub config: Arc<Mutex<SearchConfig>>,
+    pub limiter: Arc<RateLimiter>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    q: String,
+    /// Extensiones separadas por comas (`pdf,.txt`).
+    ext: Option<String>,
+    limit: Option<usize>,
+    page: Option<usize>,
+}
+
+struct Running {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// Servidor HTTP en segundo plano; como mucho uno activo.
+pub struct ApiServer {
+    running: Mutex<Option<Running>>,
+}
+
+impl ApiServer {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(None),
+        }
+    }
+
+    /// Arranca el servidor en `addr` (reemplaza al anterior) y devuelve la dirección real.
+    pub async fn start(&self, state: ApiState, addr: SocketAddr) -> Result<SocketAddr, OxiError> {
+        self.stop();
+
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| OxiError::io_at(format_args!("Failed to bind {}", addr), e))?;
+        let addr = listener.local_addr()?;
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let shutdown_signal = async {
+                let _ = shutdown_rx.await;
+            };
+            if let Err(e) = axum::serve(listener, router(state))
+                .with_graceful_shutdown(shutdown_signal)
+                .await
+            {
+                warn!("HTTP API server failed: {}", e);
+            }
+        });
+
+        info!("HTTP API listening on {}", addr);
+        *self.running.lock().unwrap_or_else(|e| e.into_inner()) = Some(Running { addr, shutdown });
+        Ok(addr)
+    }
+
+    /// Detiene el servidor. Devuelve `false` si no había ninguno activo.
+    pub fn stop(&self) -> bool {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match running {
+            Some(running) => {
+                let _ = running.shutdown.send(());
+                info!("Stopped HTTP API on {}", running.addr);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/search", get(search))
+        .with_state(state)
+}
+
+async fn search(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResults>, (StatusCode, String)> {
+    let (rate_per_sec, burst, max_results, tuning) = {
+        let config = state.config.lock().map_err(internal)?;
+        if !authorized(&headers, &config.api_token) {
+            return Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()));
+        }
+        (
+            config.search_rate_per_sec,
+            config.search_burst,
+            config.max_results,
+            SearchTuning::from_config(&config),
+        )
+    };
+
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, max_results.max(1));
+    let page = params.page.unwrap_or(0);
+    let query = params.q;
+
+    let mut filters = SearchFilters::default();
+    if let Some(ext) = params.ext {
+        let extensions: Vec<String> = ext
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(|e| format!(".{}", e.trim_start_matches('.')))
+            .collect();
+        filters.extensions = (!extensions.is_empty()).then_some(extensions);
+    }
+
+    if query.is_empty() {
+        return Ok(Json(SearchResults {
+            query,
+            results: Vec::new(),
+            total: 0,
+            page,
+            limit,
+            timed_out: false,
+            is_stale: false,
+        }));
+    }
+
+    let (name_query, tag_terms) = crate::tags::extract_tag_terms(&query);
+    if !tag_terms.is_empty() {
+        filters.tags = Some(tag_terms);
+    }
+    let (name_query, interpreter_terms) =
+        crate::interpreter::extract_interpreter_terms(&name_query);
+    if !interpreter_terms.is_empty() {
+        filters.interpreters = interpreter_terms;
+    }
+    let (name_query, mime_terms) = crate::mime::extract_mime_terms(&name_query);
+    if !mime_terms.is_empty() {
+        filters.mime_types = mime_terms;
+    }
+
+    if let Err(retry_after) = state.limiter.try_acquire(rate_per_sec, burst) {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("RateLimited: retry after {}ms", retry_after.as_millis()),
+        ));
+    }
+
+    let reader = state.pool.get().map_err(internal)?;
+    let (results, timed_out) =
+        execute_search(&reader, &name_query, &filters, limit, &tuning).map_err(internal)?;
+
+    Ok(Json(SearchResults {
+        query,
+        total: results.len(),
+        results,
+        page,
+        limit,
+        timed_out,
+        is_stale: false,
+    }))
+}
+
+/// Sin `api_token` configurado se acepta cualquier petición.
+fn authorized(headers: &HeaderMap, token: &str) -> bool {
+    token.is_empty()
+        || headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| given == token)
+}
+
+fn internal(e: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{open_index, record, TempDir};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    /// API sobre un índice con 30 `.txt` y un `.pdf`, sin límite de frecuencia.
+    fn api(dir: &TempDir, configure: impl FnOnce(&mut SearchConfig)) -> Router {
+        let (mut db, _reader) = open_index(dir);
+        let modified = "2024-01-01T00:00:00Z";
+        let mut records: Vec<_> = (0..30)
+            .map(|i| record(&dir.join(&format!("report{:02}.txt", i)), false, modified))
+            .collect();
+        records.push(record(&dir.join("report.pdf"), false, modified));
+        db.upsert_batch(&records).unwrap();
+
+        let mut config = SearchConfig { search_rate_per_sec: 0.0, ..Default::default() };
+        configure(&mut config);
+        router(ApiState {
+            pool: Arc::new(ReadPool::new(dir.path().join("index.db"), 1)),
+            config: Arc::new(Mutex::new(config)),
+            limiter: Arc::new(RateLimiter::new()),
+        })
+    }
+
+    async fn get(app: &Router, uri: &str, token: Option<&str>) -> (StatusCode, Vec<u8>) {
+        let mut request = Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let response = app
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        (status, to_bytes(response.into_body(), usize::MAX).await.unwrap().to_vec())
+    }
+
+    async fn search_json(app: &Router, uri: &str) -> Value {
+        let (status, body) = get(app, uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_without_the_token_are_rejected() {
+        let dir = TempDir::new("api-auth");
+        let app = api(&dir, |config| config.api_token = "secreto".to_string());
+
+        assert_eq!(get(&app, "/search?q=report", None).await.0, StatusCode::UNAUTHORIZED);
+        let wrong = get(&app, "/search?q=report", Some("otro")).await;
+        assert_eq!(wrong.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(get(&app, "/search?q=report", Some("secreto")).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn search_returns_the_results_as_json() {
+        let dir = TempDir::new("api-json");
+        let app = api(&dir, |_| {});
+
+        let json = search_json(&app, "/search?q=report&ext=pdf,.txt&limit=5&page=0").await;
+        let object = json.as_object().unwrap();
+        let mut keys: Vec<_> = object.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            ["is_stale", "limit", "page", "query", "results", "timed_out", "total"]
+        );
+        assert_eq!(json["query"], "report");
+        assert_eq!(json["limit"], 5);
+        assert_eq!(json["total"], 5);
+        let first = &json["results"][0];
+        assert!(first["name"].as_str().unwrap().starts_with("report"));
+        assert!(first["path"].is_string() && first["is_dir"] == false);
+
+        let pdf = search_json(&app, "/search?q=report&ext=pdf").await;
+        assert_eq!(pdf["total"], 1);
+        assert_eq!(pdf["results"][0]["extension"], ".pdf");
+
+        // Sin consulta no se busca; sin `q` la petición no es válida.
+        assert_eq!(search_json(&app, "/search?q=").await["total"], 0);
+        assert_eq!(get(&app, "/search", None).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn limit_is_clamped_to_max_results() {
+        let dir = TempDir::new("api-limit");
+        let app = api(&dir, |config| config.max_results = 10);
+
+        let json = search_json(&app, "/search?q=report&limit=100000").await;
+        assert_eq!(json["limit"], 10);
+        assert_eq!(json["results"].as_array().unwrap().len(), 10);
+
+        let json = search_json(&app, "/search?q=report&limit=0").await;
+        assert_eq!(json["limit"], 1);
+        assert_eq!(json["results"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bursts_over_the_rate_limit_are_rejected() {
+        let dir = TempDir::new("api-rate");
+        let app = api(&dir, |config| {
+            config.search_rate_per_sec = 0.01;
+            config.search_burst = 1;
+        });
+
+        assert_eq!(get(&app, "/search?q=report", None).await.0, StatusCode::OK);
+        let (status, body) = get(&app, "/search?q=report", None).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(String::from_utf8(body).unwrap().starts_with("RateLimited"));
+    }
+}
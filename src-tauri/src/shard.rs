@@ -0,0 +1,127 @@
+//! Bases por unidad (`shard_by_drive`). Cada unidad se indexa en su propio archivo
+//! (`oxi-search.shard-c.db`) con su propio escritor, así que los índices de varias
+//! unidades escriben en paralelo sin competir por el `Mutex<Database>` principal.
+//! Las conexiones de lectura adjuntan todos los shards y consultan con `UNION ALL`.
+//!
+//! Las entradas de una unidad están en su shard o en la base principal, nunca en
+//! ambos: indexar una unidad las mueve según la opción. Cambiarla requiere reindexar.
+
+use crate::db::Database;
+use crate::pool::ReadPool;
+use rusqlite::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Bases adjuntables a una conexión (límite por defecto de SQLite), incluida la principal.
+pub const MAX_ATTACHED: usize = 10;
+
+/// Unidad de `path` en minúsculas (`C:\Users` -> `c`), o `None` si no empieza por una.
+pub fn drive_key(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+            Some(letter.to_ascii_lowercase().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Nombre con el que se adjunta el shard de `key` en las conexiones de lectura.
+pub fn schema_name(key: &str) -> String {
+    format!("shard_{}", key)
+}
+
+/// Archivo del shard de `key`, junto a la base principal.
+pub fn shard_path(main_path: &Path, key: &str) -> PathBuf {
+    let stem = main_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    main_path.with_file_name(format!("{}.shard-{}.db", stem, key))
+}
+
+/// Shards existentes junto a `main_path`, ordenados por unidad.
+pub fn shard_files(main_path: &Path) -> Vec<(String, PathBuf)> {
+    let Some(stem) = main_path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.shard-", stem);
+    let dir = match main_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut shards: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let key = name.strip_prefix(&prefix)?.strip_suffix(".db")?;
+            let valid = key.len() == 1 && key.chars().all(|c| c.is_ascii_lowercase());
+            valid.then(|| (key.to_string(), entry.path()))
+        })
+        .collect();
+    shards.sort();
+    shards
+}
+
+/// Escritores de los shards, abiertos bajo demanda.
+pub struct Shards {
+    main_path: PathBuf,
+    /// Se renueva al crear un shard para que las lecturas lo adjunten.
+    pool: Arc<ReadPool>,
+    writers: Mutex<HashMap<String, Arc<Mutex<Database>>>>,
+}
+
+impl Shards {
+    pub fn new(main_path: PathBuf, pool: Arc<ReadPool>) -> Self {
+        Self {
+            main_path,
+            pool,
+            writers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Base principal, junto a la que viven los shards.
+    pub fn main_path(&self) -> &Path {
+        &self.main_path
+    }
+
+    /// Escritor del shard de `key`; crea y migra la base si todavía no existe.
+    pub fn writer(&self, key: &str) -> Result<Arc<Mutex<Database>>> {
+        let mut writers = self.writers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(writer) = writers.get(key) {
+            return Ok(Arc::clone(writer));
+        }
+
+        let path = shard_path(&self.main_path, key);
+        let created = !path.exists();
+        let writer = Arc::new(Mutex::new(Database::new(path.clone())?));
+        writers.insert(key.to_string(), Arc::clone(&writer));
+        if created {
+            info!("Created index shard for drive {} at {}", key, path.display());
+            self.pool.reset();
+        }
+        Ok(writer)
+    }
+
+    /// Escritores de todos los shards que existen en disco.
+    pub fn existing(&self) -> Result<Vec<Arc<Mutex<Database>>>> {
+        shard_files(&self.main_path)
+            .iter()
+            .map(|(key, _)| self.writer(key))
+            .collect()
+    }
+
+    /// Escritor del shard de la unidad de `path`, solo si ese shard ya existe.
+    pub fn existing_writer(&self, path: &str) -> Result<Option<Arc<Mutex<Database>>>> {
+        match drive_key(path) {
+            Some(key) if shard_path(&self.main_path, &key).exists() => self.writer(&key).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
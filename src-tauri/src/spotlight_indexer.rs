@@ -0,0 +1,215 @@
+//! Índice rápido en macOS a partir de Spotlight (`mdfind`), el equivalente a la MFT en
+//! Windows: en vez de recorrer los directorios se piden a Spotlight las rutas que ya
+//! tiene indexadas y solo se leen sus metadatos.
+//!
+//! Spotlight no indexa todo (algunas carpetas del sistema, volúmenes excluidos en
+//! Preferencias) ni aplica `.gitignore`: por eso es opcional (`use_spotlight`).
+
+use crate::db::{Database, UpsertStats};
+use crate::exclude::ExcludeMatcher;
+use crate::indexer::{IndexControl, IndexOptions, RunLimits};
+use crate::types::{FileRecord, IndexingProgress};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::info;
+
+/// `true` si Spotlight tiene activado el índice del volumen de `path`.
+#[cfg(target_os = "macos")]
+pub fn is_available(path: &str) -> bool {
+    // `mdutil -s` responde "Indexing enabled." o "Indexing disabled." / "Spotlight
+    // server is disabled." para el volumen que contiene la ruta.
+    Command::new("mdutil")
+        .arg("-s")
+        .arg(path)
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("Indexing enabled"))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn is_available(_path: &str) -> bool {
+    false
+}
+
+pub struct SpotlightIndexer {
+    db: Arc<Mutex<Database>>,
+    control: Arc<IndexControl>,
+    limits: Arc<RunLimits>,
+    options: IndexOptions,
+}
+
+impl SpotlightIndexer {
+    pub fn new(
+        db: Arc<Mutex<Database>>,
+        control: Arc<IndexControl>,
+        limits: Arc<RunLimits>,
+        options: IndexOptions,
+    ) -> Self {
+        Self {
+            db,
+            control,
+            limits,
+            options,
+        }
+    }
+
+    /// Indexa lo que Spotlight conoce bajo `root`. Devuelve las entradas guardadas.
+    pub fn index_path(
+        &self,
+        root: &str,
+        exclude_patterns: &[String],
+        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        info!("Starting Spotlight indexing of {}", root);
+        let start = Instant::now();
+
+        // `-0` separa las rutas con NUL: los nombres pueden contener saltos de línea.
+        let mut child = Command::new("mdfind")
+            .arg("-0")
+            .arg("-onlyin")
+            .arg(root)
+            .arg("kMDItemFSName == \"*\"")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().ok_or("mdfind has no stdout")?;
+        let output = BufReader::new(stdout);
+        let outcome = self.index_output(root, output, exclude_patterns, progress_callback);
+
+        // Si se paró antes de tiempo `mdfind` sigue escribiendo: no hace falta su salida.
+        let _ = child.kill();
+        let status = child.wait()?;
+        let (files_found, stats) = outcome?;
+
+        let stopped = self.control.should_stop() || self.limits.is_reached();
+        if !stopped && !status.success() {
+            return Err(format!("mdfind exited with {}", status).into());
+        }
+
+        info!(
+            "Spotlight indexing of {} completed: {} entries in {:?} ({:?})",
+            root,
+            files_found,
+            start.elapsed(),
+            stats
+        );
+        Ok(files_found)
+    }
+
+    /// Guarda las rutas de la salida de `mdfind -0` (separadas por NUL) que siguen en
+    /// disco y no se excluyen. Devuelve las entradas encontradas y lo guardado.
+    fn index_output(
+        &self,
+        root: &str,
+        output: impl BufRead,
+        exclude_patterns: &[String],
+        progress_callback: Arc<dyn Fn(IndexingProgress) + Send + Sync>,
+    ) -> Result<(usize, UpsertStats), Box<dyn std::error::Error>> {
+        let excludes = ExcludeMatcher::new(exclude_patterns);
+        let root_path = Path::new(root);
+        let mut flush = self.options.flush_policy();
+        let mut batch_buffer: Vec<FileRecord> = Vec::with_capacity(flush.batch_size());
+        let mut stats = UpsertStats::default();
+        let mut files_found = 0;
+
+        for raw in output.split(0) {
+            if self.control.is_paused() {
+                stats.add(self.flush_batch(&mut batch_buffer)?);
+                flush.flushed();
+                self.control.wait_if_paused();
+            }
+            if self.control.should_stop() || self.limits.is_reached() {
+                info!("Stopping Spotlight indexing of {} early", root);
+                break;
+            }
+
+            let raw = raw?;
+            let path = PathBuf::from(String::from_utf8_lossy(&raw).into_owned());
+            if excludes.is_excluded(&path) || self.options.skips_hidden(&path, root_path) {
+                continue;
+            }
+            let Some(record) = self.options.path_record(&path) else {
+                continue;
+            };
+
+            files_found += 1;
+            progress_callback(IndexingProgress {
+                current_path: record.path.clone(),
+                files_processed: files_found,
+                total_files: None,
+                status: "indexing".to_string(),
+                operation_id: None,
+                inserted: stats.inserted,
+                updated: stats.updated,
+                skipped: stats.skipped,
+            });
+            batch_buffer.push(record);
+
+            if flush.is_due(batch_buffer.len()) {
+                stats.add(self.flush_batch(&mut batch_buffer)?);
+                flush.flushed();
+            }
+            if self.limits.count(1) {
+                break;
+            }
+        }
+
+        stats.add(self.flush_batch(&mut batch_buffer)?);
+        Ok((files_found, stats))
+    }
+
+    fn flush_batch(&self, batch: &mut Vec<FileRecord>) -> Result<UpsertStats, Box<dyn std::error::Error>> {
+        if batch.is_empty() {
+            return Ok(UpsertStats::default());
+        }
+
+        let mut db_guard = self.db.lock().map_err(|e| format!("Failed to lock database: {}", e))?;
+        let stats = db_guard.upsert_batch(batch.as_slice())?;
+        batch.clear();
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{indexed_paths, open_index, TempDir};
+
+    #[test]
+    fn indexes_the_paths_of_mdfind_output() {
+        let dir = TempDir::new("spotlight");
+        let root = dir.join("root");
+        std::fs::create_dir_all(dir.path().join("root/docs/.cache")).unwrap();
+        std::fs::create_dir_all(dir.path().join("root/build")).unwrap();
+        for file in ["docs/a.txt", "docs/two\nlines.txt", "docs/.cache/x", "build/out.o"] {
+            std::fs::write(dir.path().join("root").join(file), b"").unwrap();
+        }
+        let (db, _reader) = open_index(&dir);
+        let spotlight = SpotlightIndexer::new(
+            Arc::new(Mutex::new(db)),
+            Arc::new(IndexControl::new()),
+            Arc::new(RunLimits::new(None, None)),
+            IndexOptions::default(),
+        );
+
+        // Salida tal como la da `mdfind -0`: rutas terminadas en NUL, con una que ya no
+        // existe, una oculta, una excluida y un salto de línea dentro de un nombre.
+        let output: Vec<u8> = [
+            "", "/docs", "/docs/a.txt", "/docs/two\nlines.txt", "/docs/gone.txt",
+            "/docs/.cache/x", "/build", "/build/out.o",
+        ]
+        .iter()
+        .flat_map(|relative| format!("{}{}\0", root, relative).into_bytes())
+        .collect();
+        let (found, stats) = spotlight
+            .index_output(&root, output.as_slice(), &["build".to_string()], Arc::new(|_| {}))
+            .unwrap();
+
+        assert_eq!((found, stats.inserted), (4, 4));
+        let expected = ["", "/docs", "/docs/a.txt", "/docs/two\nlines.txt"];
+        assert_eq!(indexed_paths(&dir, &root), expected);
+    }
+}
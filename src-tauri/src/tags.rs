@@ -0,0 +1,284 @@
+use std::path::Path;
+
+/// Prefijo del operador de búsqueda por etiqueta (`tag:rojo factura`).
+const TAG_OPERATOR: &str = "tag:";
+
+/// Atributos extendidos leídos por defecto: etiquetas de Finder y las de xdg en Linux.
+pub fn default_tag_attributes() -> Vec<String> {
+    vec![
+        "com.apple.metadata:_kMDItemUserTags".to_string(),
+        "user.xdg.tags".to_string(),
+    ]
+}
+
+/// Lee las etiquetas de `path` a partir de `attributes` y las devuelve en el formato
+/// de la columna `tags` (`,a,b,`), o `None` si no tiene ninguna.
+pub fn read_tags(path: &Path, attributes: &[String]) -> Option<String> {
+    if attributes.is_empty() {
+        return None;
+    }
+
+    let mut tags: Vec<String> = Vec::new();
+    for attribute in attributes {
+        if let Some(value) = read_attribute(path, attribute) {
+            for tag in decode_tags(&value) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+    }
+
+    if tags.is_empty() {
+        None
+    } else {
+        Some(format!(",{},", tags.join(",")))
+    }
+}
+
+/// Separa los términos `tag:xxx` del resto de la consulta.
+pub fn extract_tag_terms(query: &str) -> (String, Vec<String>) {
+    extract_operator_terms(query, TAG_OPERATOR)
+}
+
+/// Separa los términos `operator` + valor del resto de la consulta.
+pub fn extract_operator_terms(query: &str, operator: &str) -> (String, Vec<String>) {
+    let mut rest: Vec<&str> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+
+    for term in query.split_whitespace() {
+        match term.strip_prefix(operator) {
+            Some(value) if !value.is_empty() => values.push(value.to_string()),
+            _ => rest.push(term),
+        }
+    }
+
+    if values.is_empty() {
+        (query.to_string(), values)
+    } else {
+        (rest.join(" "), values)
+    }
+}
+
+#[cfg(unix)]
+fn read_attribute(path: &Path, attribute: &str) -> Option<Vec<u8>> {
+    xattr::get(path, attribute).ok().flatten()
+}
+
+#[cfg(not(unix))]
+fn read_attribute(_path: &Path, _attribute: &str) -> Option<Vec<u8>> {
+    None
+}
+
+/// Las etiquetas de Finder son un bplist con un array de strings ("Rojo\n6", con el
+/// color tras el salto de línea); las de xdg son texto separado por comas.
+fn decode_tags(value: &[u8]) -> Vec<String> {
+    let raw = bplist_strings(value)
+        .unwrap_or_else(|| String::from_utf8_lossy(value).split(',').map(str::to_string).collect());
+
+    raw.iter()
+        .filter_map(|tag| tag.split('\n').next())
+        .map(|tag| tag.trim().replace(',', " "))
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Extrae un array de strings de la raíz de un binary plist (`bplist00`). Los bytes
+/// vienen del archivo: cualquier tamaño u offset fuera de rango da `None`.
+fn bplist_strings(data: &[u8]) -> Option<Vec<String>> {
+    if data.len() < 40 || !data.starts_with(b"bplist00") {
+        return None;
+    }
+
+    let trailer = &data[data.len() - 32..];
+    let offset_size = trailer[6] as usize;
+    let ref_size = trailer[7] as usize;
+    let num_objects = be_uint(&trailer[8..16]) as usize;
+    let top_object = be_uint(&trailer[16..24]) as usize;
+    let table_offset = be_uint(&trailer[24..32]) as usize;
+
+    let offset_of = |index: usize| -> Option<usize> {
+        if index >= num_objects {
+            return None;
+        }
+        let start = index.checked_mul(offset_size)?.checked_add(table_offset)?;
+        data.get(start..start.checked_add(offset_size)?)
+            .map(|b| be_uint(b) as usize)
+    };
+
+    let (kind, count, mut pos) = object_header(data, offset_of(top_object)?)?;
+    if kind != 0xA {
+        return None;
+    }
+
+    // Cada referencia ocupa al menos un byte del archivo.
+    let mut strings = Vec::with_capacity(count.min(data.len()));
+    for _ in 0..count {
+        let end = pos.checked_add(ref_size)?;
+        let object_ref = be_uint(data.get(pos..end)?) as usize;
+        pos = end;
+        if let Some(s) = bplist_string(data, offset_of(object_ref)?) {
+            strings.push(s);
+        }
+    }
+    Some(strings)
+}
+
+/// Tipo, longitud y posición del contenido de un objeto del bplist.
+fn object_header(data: &[u8], offset: usize) -> Option<(u8, usize, usize)> {
+    let marker = *data.get(offset)?;
+    let kind = marker >> 4;
+    let mut len = (marker & 0x0F) as usize;
+    let mut pos = offset + 1;
+
+    if len == 0x0F {
+        let int_marker = *data.get(pos)?;
+        if int_marker >> 4 != 0x1 {
+            return None;
+        }
+        let width = 1usize << (int_marker & 0x0F);
+        let end = pos.checked_add(1 + width)?;
+        len = be_uint(data.get(pos + 1..end)?) as usize;
+        pos = end;
+    }
+
+    Some((kind, len, pos))
+}
+
+fn bplist_string(data: &[u8], offset: usize) -> Option<String> {
+    let (kind, len, pos) = object_header(data, offset)?;
+    match kind {
+        // ASCII
+        0x5 => data
+            .get(pos..pos.checked_add(len)?)
+            .map(|b| String::from_utf8_lossy(b).into_owned()),
+        // UTF-16 big-endian
+        0x6 => {
+            let bytes = data.get(pos..pos.checked_add(len.checked_mul(2)?)?)?;
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16(&units).ok()
+        }
+        _ => None,
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `bplist00` con `objects` ya codificados; la raíz es el primero. Offsets y
+    /// referencias de 1 byte.
+    fn bplist_from(objects: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        for object in objects {
+            offsets.push(data.len() as u8);
+            data.extend(object);
+        }
+        let table_offset = data.len() as u64;
+        data.extend(&offsets);
+        data.extend([0; 6]);
+        data.extend([1, 1]);
+        data.extend((objects.len() as u64).to_be_bytes());
+        data.extend(0u64.to_be_bytes());
+        data.extend(table_offset.to_be_bytes());
+        data
+    }
+
+    /// Array de strings ASCII en la raíz, como las etiquetas de Finder.
+    fn bplist(strings: &[&str]) -> Vec<u8> {
+        let mut array = vec![0xA0 | strings.len() as u8];
+        array.extend(1..=strings.len() as u8);
+        let mut objects = vec![array];
+        for s in strings {
+            let mut object = vec![0x50 | s.len() as u8];
+            object.extend(s.as_bytes());
+            objects.push(object);
+        }
+        bplist_from(&objects)
+    }
+
+    /// Cabecera con longitud extendida de 8 bytes (`u64::MAX`) para el tipo `kind`.
+    fn huge_header(kind: u8) -> Vec<u8> {
+        let mut header = vec![(kind << 4) | 0x0F, 0x13];
+        header.extend(u64::MAX.to_be_bytes());
+        header
+    }
+
+    /// Sobrescribe el campo de 8 bytes del trailer que empieza en `at`.
+    fn set_trailer(data: &mut [u8], at: usize, value: u64) {
+        let start = data.len() - 32 + at;
+        data[start..start + 8].copy_from_slice(&value.to_be_bytes());
+    }
+
+    #[test]
+    fn decodes_finder_and_xdg_tags() {
+        let finder = bplist(&["Rojo\n6", "Trabajo"]);
+        assert_eq!(decode_tags(&finder), ["Rojo", "Trabajo"]);
+        assert_eq!(decode_tags(b"a, b ,,c"), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn truncated_bplist_is_rejected() {
+        let data = bplist(&["Rojo\n6", "Trabajo"]);
+        for len in 0..data.len() {
+            assert_eq!(bplist_strings(&data[..len]), None, "len {}", len);
+        }
+    }
+
+    #[test]
+    fn hostile_trailer_values_are_rejected() {
+        let valid = bplist(&["Rojo\n6", "Trabajo"]);
+
+        // Tabla de offsets u objeto raíz fuera del archivo (o del usize).
+        for (at, value) in [(24, u64::MAX), (24, u64::MAX / 2), (16, u64::MAX)] {
+            let mut data = valid.clone();
+            set_trailer(&mut data, at, value);
+            assert_eq!(bplist_strings(&data), None, "field {} = {}", at, value);
+        }
+
+        // Un número de objetos enorme solo amplía el rango válido de referencias.
+        let mut data = valid.clone();
+        set_trailer(&mut data, 8, u64::MAX);
+        assert_eq!(bplist_strings(&data).unwrap().len(), 2);
+
+        // Offsets de 255 bytes: la tabla ya no cabe.
+        let mut data = valid.clone();
+        let trailer = data.len() - 32;
+        data[trailer + 6] = 0xFF;
+        data[trailer + 7] = 0xFF;
+        assert_eq!(bplist_strings(&data), None);
+    }
+
+    #[test]
+    fn hostile_object_lengths_are_rejected() {
+        // Array raíz que dice tener `u64::MAX` elementos: no se reserva memoria para
+        // ellos y se para en la primera referencia que falta.
+        let mut array = huge_header(0xA);
+        array.push(1);
+        let data = bplist_from(&[array, b"\x54Rojo".to_vec()]);
+        assert_eq!(bplist_strings(&data), None);
+
+        // Strings ASCII y UTF-16 con longitudes que desbordan al sumar o multiplicar.
+        let mut half = vec![0x6F, 0x13];
+        half.extend((u64::MAX / 2).to_be_bytes());
+        for string in [huge_header(0x5), huge_header(0x6), half] {
+            let data = bplist_from(&[vec![0xA1, 1], string]);
+            assert_eq!(bplist_strings(&data), Some(Vec::new()));
+        }
+    }
+
+    #[test]
+    fn extracts_tag_terms() {
+        let (rest, tags) = extract_tag_terms("factura tag:rojo 2024 tag:");
+        assert_eq!(rest, "factura 2024 tag:");
+        assert_eq!(tags, ["rojo"]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Ayudas compartidas por los tests: directorios temporales y registros de prueba.
+
+use crate::db::{Database, Reader};
+use crate::types::{FileRecord, SearchResult};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Directorio único bajo el temporal del sistema; se borra con todo su contenido al
+/// salir de ámbito.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(label: &str) -> Self {
+        let id = NEXT_DIR.fetch_add(1, Ordering::Relaxed);
+        let name = format!("oxi-test-{}-{}-{}", std::process::id(), label, id);
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("create temp dir");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Ruta de `relative` dentro del directorio, como la guarda el índice.
+    pub fn join(&self, relative: &str) -> String {
+        self.0.join(relative).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Base de índice nueva en `dir` y un lector sobre ella.
+pub fn open_index(dir: &TempDir) -> (Database, Reader) {
+    let db_path = dir.path().join("index.db");
+    let db = Database::new(db_path.clone()).expect("open database");
+    let reader = Reader::open(&db_path).expect("open reader");
+    (db, reader)
+}
+
+/// Rutas del índice de `open_index` bajo `root`, relativas a él (con `/`) y ordenadas;
+/// la propia raíz es `""`.
+pub fn indexed_paths(dir: &TempDir, root: &str) -> Vec<String> {
+    let conn = rusqlite::Connection::open(dir.path().join("index.db")).expect("open index");
+    let mut stmt = conn.prepare("SELECT path FROM search_index").expect("prepare");
+    let mut paths: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .expect("query")
+        .map(|path| path.expect("row"))
+        .filter_map(|path| Some(path.strip_prefix(root)?.replace('\\', "/")))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Registro mínimo para `path`, modificado en `modified_time` (RFC3339 UTC).
+pub fn record(path: &str, is_dir: bool, modified_time: &str) -> FileRecord {
+    let path_ref = Path::new(path);
+    FileRecord {
+        path: path.to_string(),
+        name: path_ref
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string()),
+        extension: if is_dir {
+            None
+        } else {
+            path_ref.extension().map(|e| format!(".{}", e.to_string_lossy()))
+        },
+        file_size: if is_dir { None } else { Some(0) },
+        is_dir,
+        modified_time: modified_time.to_string(),
+        created_time: None,
+        last_indexed: chrono::Utc::now().to_rfc3339(),
+        path_lossy: false,
+        raw_path: None,
+        tags: None,
+        is_reparse: false,
+        link_count: 1,
+        file_id: None,
+        category: None,
+        interpreter: None,
+        mime: None,
+        mode: None,
+        owner_uid: None,
+        owner_gid: None,
+        content: None,
+    }
+}
+
+/// Resultado de búsqueda para `path` con el resto de campos por defecto.
+pub fn result(path: &str, is_dir: bool) -> SearchResult {
+    let record = record(path, is_dir, "2024-01-01T00:00:00+00:00");
+    SearchResult {
+        path: record.path,
+        name: record.name,
+        extension: record.extension,
+        file_size: record.file_size.map(|size| size as u64),
+        is_dir,
+        modified_time: record.modified_time,
+        created_time: None,
+        is_reparse: false,
+        link_count: 1,
+        mode: None,
+        owner_uid: None,
+        owner_gid: None,
+        score: 0.0,
+        child_count: None,
+        matched_in_path: false,
+    }
+}
@@ -12,6 +12,7 @@ pub struct FileEntry {
     pub is_dir: bool,
 }
 
+// Los tipos que cruzan IPC están espejados en `src/types.ts`: mantener ambos alineados.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub path: String,
@@ -20,16 +21,150 @@ pub struct SearchResult {
     pub file_size: Option<u64>,
     pub is_dir: bool,
     pub modified_time: String,
+    /// Fecha de creación (RFC3339 UTC), si el sistema de archivos la ofrece.
+    pub created_time: Option<String>,
+    /// Enlace simbólico, junction u otro punto de reparse.
+    pub is_reparse: bool,
+    /// Enlaces duros al archivo (1 si el sistema no lo informa).
+    pub link_count: u64,
+    /// `st_mode` en Unix; en Windows, atributos de solo lectura, oculto y de sistema.
+    pub mode: Option<u32>,
+    pub owner_uid: Option<u32>,
+    pub owner_gid: Option<u32>,
     pub score: f64,
+    /// Entradas directas del directorio (solo para resultados `is_dir`).
+    pub child_count: Option<u64>,
+    /// Con `search_in_path`, la consulta casó con la ruta pero no con el nombre.
+    pub matched_in_path: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SearchFilters {
     pub extensions: Option<Vec<String>>,
     pub min_size: Option<u64>,
     pub max_size: Option<u64>,
     pub min_date: Option<String>,
     pub max_date: Option<String>,
+    /// Coincidencia aproximada por similitud en lugar de subcadena exacta.
+    pub fuzzy: bool,
+    /// Cada término debe coincidir con un componente completo de la ruta, en orden.
+    pub component_match: bool,
+    /// Restringe la búsqueda a estas raíces indexadas (None = todas).
+    pub roots: Option<Vec<String>>,
+    /// Solo entradas con todas estas etiquetas (también vía `tag:` en la consulta).
+    pub tags: Option<Vec<String>>,
+    pub sort_by: SortBy,
+    /// Sentido de `sort_by` (`None`: ascendente por nombre y ruta, descendente por tamaño
+    /// y fecha). No se aplica a la relevancia ni a los órdenes por creación.
+    pub sort_dir: Option<SortDirection>,
+    /// Directorios antes que archivos, con cualquier orden.
+    pub dirs_first: bool,
+    /// Collation para los órdenes por nombre (incluido el desempate).
+    pub collation: NameCollation,
+    /// Diversifica: como mucho N resultados por extensión antes de completar la página.
+    pub max_per_extension: Option<usize>,
+    /// Descarta los resultados con score menor tras rankear (0.0..=1.0).
+    pub min_score: Option<f64>,
+    /// Oculta los resultados que casan con estos patrones (misma sintaxis que
+    /// `exclude_patterns`), sin tocar el índice.
+    pub hide_patterns: Vec<String>,
+    /// Solo archivos con más de un enlace duro.
+    pub multiple_links: bool,
+    /// Solo estas categorías (`image`, `document`, ...; ver `category`).
+    pub categories: Vec<String>,
+    /// Distingue mayúsculas en la búsqueda por subcadena (no aplica a `fuzzy` ni
+    /// `component_match`).
+    pub case_sensitive: bool,
+    /// La consulta debe aparecer en el nombre como palabra completa (ver
+    /// `ranking::contains_word`). Tampoco aplica a `fuzzy` ni `component_match`.
+    pub whole_word: bool,
+    /// Busca la subcadena en la ruta completa en vez de solo en el nombre (`/` y `\`
+    /// son intercambiables). Recorre la tabla entera: más lento que por nombre.
+    pub search_in_path: bool,
+    /// Solo scripts con estos intérpretes (`bash`, `python`; ver `interpreter`). Requiere
+    /// `index_interpreters`.
+    pub interpreters: Vec<String>,
+    /// Solo estos tipos MIME (`application/pdf`; `image` o `image/*` para todo el
+    /// grupo). Requiere `index_mime_types`.
+    pub mime_types: Vec<String>,
+    /// Solo entradas de este propietario (uid, solo Unix).
+    pub owner_uid: Option<u32>,
+    /// Solo entradas con todos estos bits en `mode` (`0o002`: escribible por todos;
+    /// en Windows, `0x2`: ocultos).
+    pub mode_mask: Option<u32>,
+}
+
+/// Orden de los resultados de `search_files`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    #[default]
+    Relevance,
+    CreatedDesc,
+    CreatedAsc,
+    Name,
+    Size,
+    Modified,
+    Path,
+}
+
+/// Sentido de los órdenes por nombre, tamaño, fecha de modificación y ruta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn sql(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+
+    /// `ordering` (ascendente) en este sentido.
+    pub fn apply(self, ordering: std::cmp::Ordering) -> std::cmp::Ordering {
+        match self {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// Cómo se comparan los nombres al ordenar (ver `collation`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameCollation {
+    #[default]
+    Binary,
+    Nocase,
+    Natural,
+}
+
+/// `PRAGMA synchronous` de la base del índice.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Sin esperar al disco. Solo durante un reíndice completo (`Database::set_bulk_mode`):
+    /// el resto del tiempo se usa `Normal`.
+    Off,
+    /// Con WAL, seguro ante cierres inesperados; un corte de luz puede perder lo último.
+    #[default]
+    Normal,
+    Full,
+}
+
+impl SyncMode {
+    pub fn pragma_value(self) -> &'static str {
+        match self {
+            SyncMode::Off => "OFF",
+            SyncMode::Normal => "NORMAL",
+            SyncMode::Full => "FULL",
+        }
+    }
 }
 
 impl Default for SearchFilters {
@@ -40,10 +175,39 @@ impl Default for SearchFilters {
             max_size: None,
             min_date: None,
             max_date: None,
+            fuzzy: false,
+            component_match: false,
+            roots: None,
+            tags: None,
+            sort_by: SortBy::Relevance,
+            sort_dir: None,
+            dirs_first: false,
+            collation: NameCollation::Binary,
+            max_per_extension: None,
+            min_score: None,
+            hide_patterns: Vec::new(),
+            multiple_links: false,
+            categories: Vec::new(),
+            case_sensitive: false,
+            whole_word: false,
+            search_in_path: false,
+            interpreters: Vec::new(),
+            mime_types: Vec::new(),
+            owner_uid: None,
+            mode_mask: None,
         }
     }
 }
 
+impl SearchFilters {
+    pub fn sort_direction(&self) -> SortDirection {
+        self.sort_dir.unwrap_or(match self.sort_by {
+            SortBy::Size | SortBy::Modified => SortDirection::Desc,
+            _ => SortDirection::Asc,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResults {
     pub query: String,
@@ -51,14 +215,330 @@ pub struct SearchResults {
     pub total: usize,
     pub page: usize,
     pub limit: usize,
+    /// La consulta se cortó por `search_timeout_ms`: los resultados pueden estar incompletos.
+    pub timed_out: bool,
+    /// Llegó otra búsqueda antes de terminar esta: sus resultados no son los vigentes.
+    pub is_stale: bool,
+}
+
+/// Formato de la ruta que copia `copy_path`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathStyle {
+    /// El del sistema en el que corre la app.
+    #[default]
+    Native,
+    /// Separadores `/` (`C:/a/b`, `//servidor/recurso`).
+    Posix,
+    /// Separadores `\` (`C:\a\b`, `\\servidor\recurso`).
+    Windows,
+}
+
+impl PathStyle {
+    /// `Native` según el sistema; los otros, tal cual.
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Native if cfg!(windows) => Self::Windows,
+            Self::Native => Self::Posix,
+            style => style,
+        }
+    }
+}
+
+/// Filtros de `refine_search` sobre los resultados de una búsqueda ya hecha.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Refinement {
+    /// Solo estas extensiones (`.pdf` o `pdf`, sin distinguir mayúsculas).
+    pub extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Límites de `modified_time` en RFC3339, como en `SearchFilters`.
+    pub min_date: Option<String>,
+    pub max_date: Option<String>,
+    /// `Some(true)`: solo directorios; `Some(false)`: solo archivos.
+    pub is_dir: Option<bool>,
+    /// Texto que además tiene que aparecer en el nombre (sin distinguir mayúsculas).
+    pub text: Option<String>,
+}
+
+/// Resultados por extensión dentro de un `refine_search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionFacet {
+    /// En minúsculas (`.pdf`); `None` sin extensión (también los directorios).
+    pub extension: Option<String>,
+    pub count: usize,
+}
+
+/// Respuesta de `refine_search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefinedResults {
+    #[serde(flatten)]
+    pub search: SearchResults,
+    /// Recuento por extensión con todos los filtros salvo el de extensiones.
+    pub facets: Vec<ExtensionFacet>,
+}
+
+/// Evento `search-result-batch` de `search_files_streaming`. Cada lote sale ordenado
+/// solo dentro de sí mismo (ver `search::stream_search`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResultBatch {
+    /// Época de la búsqueda: los lotes de una época superada se descartan.
+    pub epoch: u64,
+    pub query: String,
+    /// Posición del primer resultado del lote dentro del flujo.
+    pub offset: usize,
+    pub results: Vec<SearchResult>,
+}
+
+/// Evento `search-complete` (y respuesta) de `search_files_streaming`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchComplete {
+    pub epoch: u64,
+    pub query: String,
+    /// Resultados emitidos en total.
+    pub total: usize,
+    pub timed_out: bool,
+    /// Otra búsqueda cortó el flujo: `total` cuenta solo lo emitido hasta entonces.
+    pub is_stale: bool,
+}
+
+/// Raíz de un índice con sus exclusiones (`reindex_path` con `roots`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRoot {
+    pub path: String,
+    /// Solo para esta raíz, además de las globales.
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexingProgress {
     pub current_path: String,
     pub files_processed: usize,
+    /// Total estimado: recuento previo del recorrido (`precount_entries`) o, con la MFT,
+    /// el número de registros de la tabla (cota superior).
     pub total_files: Option<usize>,
     pub status: String,
+    /// Operación a la que pertenece el evento (ver `get_operations`).
+    pub operation_id: Option<u64>,
+    /// Totales de la ejecución en curso, según lo ya guardado.
+    pub inserted: usize,
+    pub updated: usize,
+    /// Sin cambios respecto al índice.
+    pub skipped: usize,
+}
+
+/// Archivos con el mismo contenido (`find_duplicates`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    /// `content_hash` común (ver `duplicates`).
+    pub hash: String,
+    /// Tamaño de cada copia.
+    pub file_size: u64,
+    pub paths: Vec<String>,
+    /// Bytes que se liberarían dejando una sola copia. Los enlaces duros a un mismo
+    /// archivo no ocupan más: cuentan como una copia.
+    pub wasted_bytes: u64,
+}
+
+/// Búsqueda guardada en el historial (`get_search_history`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub query: String,
+    pub filters: Option<SearchFilters>,
+    /// Resultados de la última vez que se buscó.
+    pub result_count: u64,
+    /// RFC3339, de la última vez que se buscó.
+    pub searched_at: String,
+}
+
+/// Búsqueda con nombre (`save_search`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub filters: SearchFilters,
+    /// RFC3339.
+    pub created_at: String,
+    /// RFC3339, de la última vez que se guardó.
+    pub updated_at: String,
+}
+
+/// Resultado de `save_search`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveSearchOutcome {
+    pub search: SavedSearch,
+    /// Ya había una búsqueda con ese nombre y se ha sustituido.
+    pub overwritten: bool,
+}
+
+/// Archivos indexados con un tipo MIME (`get_mime_summary`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MimeCount {
+    pub mime: String,
+    pub count: u64,
+}
+
+/// Entradas indexadas de una extensión (`get_extension_stats`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionStat {
+    /// En minúsculas (`.jpg`); `None` en los grupos sin extensión y de directorios.
+    pub extension: Option<String>,
+    /// Grupo de los directorios.
+    pub is_dir: bool,
+    pub count: u64,
+    /// Suma de los tamaños (0 en el grupo de directorios).
+    pub bytes: u64,
+}
+
+/// Directorio cuyos hijos directos ya están guardados (evento `directory-indexed`,
+/// que agrupa varios en cada emisión).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryIndexed {
+    pub path: String,
+    pub child_count: usize,
+}
+
+/// Límite de seguridad que detuvo un índice (evento `indexing-limit-reached`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum IndexLimit {
+    MaxFiles,
+    MaxDuration,
+    /// `index_max_depth` dejó directorios sin recorrer; la ejecución sí terminó.
+    MaxDepth,
+}
+
+/// Lo que un índice no pudo leer (evento `indexing-warnings` y `OperationInfo`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexingWarnings {
+    /// Entradas sin permiso de lectura; si son directorios, falta todo su contenido.
+    pub permission_denied: usize,
+    /// Otros errores del recorrido (rutas que desaparecen, bucles de enlaces...).
+    pub other_errors: usize,
+    /// Algunas de las rutas afectadas.
+    pub sample_paths: Vec<String>,
+    /// Unidades que se recorrieron (mucho más lento) porque no se pudo abrir su volumen
+    /// para leer la MFT.
+    pub mft_access_denied: Vec<String>,
+    /// El proceso no es administrador: ejecutarlo como tal permitiría leer la MFT.
+    pub requires_elevation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexLimitReached {
+    pub limit: IndexLimit,
+    /// Entradas guardadas antes de parar.
+    pub files: usize,
+}
+
+/// Qué hacer con las rutas que ya están en el índice al fusionar otra base (`merge_database`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// Conserva la entrada local.
+    Skip,
+    /// Sustituye la entrada local por la de la otra base.
+    Overwrite,
+    /// Se queda con la indexada más recientemente.
+    Newest,
+}
+
+/// Qué busca `find_empty`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyKind {
+    /// Archivos de 0 bytes.
+    Files,
+    /// Directorios sin ninguna entrada indexada debajo.
+    Directories,
+}
+
+/// Cambios aplicados al índice por el watcher (evento `watch-event`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchEvent {
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Resultado de `validate_indexing_path` para una raíz candidata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathValidation {
+    pub path: String,
+    /// Se puede añadir como raíz: existe, es un directorio y se puede leer.
+    pub valid: bool,
+    pub exists: bool,
+    pub is_dir: bool,
+    pub readable: bool,
+    /// Raíz de una unidad o del sistema de archivos (`C:\`, `/`).
+    pub is_drive: bool,
+    /// Se indexaría leyendo la MFT (Windows, NTFS y con permisos).
+    pub mft_available: bool,
+    /// Unidad sin MFT disponible porque el proceso no es administrador.
+    pub requires_elevation: bool,
+    /// Recurso de red (UNC o montaje NFS/SMB/...).
+    pub is_network: bool,
+    /// Entradas vistas en un recorrido de muestra acotado.
+    pub estimated_entries: usize,
+    /// El recorrido de muestra terminó: `estimated_entries` es el total real.
+    pub estimate_complete: bool,
+    /// Avisos para la UI; no impiden añadir la raíz.
+    pub warnings: Vec<String>,
+}
+
+/// Resultado de `preview_index`: lo que guardaría `reindex_path` con los mismos
+/// argumentos.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexPreview {
+    pub paths: Vec<String>,
+    pub entries: u64,
+    pub files: u64,
+    pub directories: u64,
+    /// Suma de los tamaños de los archivos.
+    pub total_bytes: u64,
+    /// Desglose por extensión, de más a menos bytes (solo las primeras).
+    pub extensions: Vec<ExtensionStats>,
+    /// Extensiones distintas, incluidas las que no caben en `extensions`.
+    pub extension_count: usize,
+    /// Duración del recorrido; el índice real tarda al menos esto (salvo con la MFT).
+    pub walk_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtensionStats {
+    /// Con el punto (`.rs`); `None` para los archivos sin extensión.
+    pub extension: Option<String>,
+    pub count: u64,
+    pub bytes: u64,
+}
+
+/// Estado de una raíz configurada en `get_coverage_report`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootCoverage {
+    pub path: String,
+    pub exists: bool,
+    pub readable: bool,
+    /// Inicio del último recorrido completo de la raíz, haya terminado o no.
+    pub last_started: Option<String>,
+    /// Inicio del último recorrido completo de la raíz que terminó.
+    pub last_completed: Option<String>,
+    /// El último recorrido no terminó (cancelado, límite, error o cierre de la app).
+    /// Con `is_indexing` puede ser el que está en curso.
+    pub incomplete: bool,
+    /// Entradas del índice bajo la raíz.
+    pub indexed_entries: u64,
+}
+
+/// Resultado de `get_coverage_report`: qué está indexado de cada raíz y qué no.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub roots: Vec<RootCoverage>,
+    pub last_full_index: Option<String>,
+    pub last_refresh: Option<String>,
+    pub is_indexing: bool,
 }
 
 // Registro interno para escritura en lote a SQLite (no expuesto a la UI).
@@ -70,7 +550,156 @@ pub struct FileRecord {
     pub file_size: Option<i64>,
     pub is_dir: bool,
     pub modified_time: String,
+    pub created_time: Option<String>,
     pub last_indexed: String,
+    /// La ruta original no era UTF-8 y `path` es una versión con pérdida.
+    pub path_lossy: bool,
+    /// Bytes originales de la ruta en base64 cuando `path_lossy` (ver `paths::encode_raw`).
+    pub raw_path: Option<String>,
+    /// Etiquetas (xattrs) en formato `,a,b,`, si se indexan.
+    pub tags: Option<String>,
+    /// Enlace simbólico (también seguido con `follow_symlinks`), junction u otro punto
+    /// de reparse.
+    pub is_reparse: bool,
+    /// Enlaces duros (`nlink` en Unix, contador del registro MFT en NTFS).
+    pub link_count: i64,
+    /// `dispositivo:inodo`, solo en archivos con más de un enlace duro: las rutas con el
+    /// mismo valor son los mismos datos.
+    pub file_id: Option<String>,
+    /// Categoría derivada de la extensión o de la firma (ver `category`).
+    pub category: Option<String>,
+    /// Intérprete del shebang, con `index_interpreters` (ver `interpreter`).
+    pub interpreter: Option<String>,
+    /// Tipo MIME según la base del sistema, con `index_mime_types` (ver `mime`).
+    pub mime: Option<String>,
+    /// Ver `SearchResult::mode`.
+    pub mode: Option<u32>,
+    /// Propietario y grupo (solo Unix).
+    pub owner_uid: Option<u32>,
+    pub owner_gid: Option<u32>,
+    /// Texto para `content_fts` con `index_content`: `Some("")` borra el que hubiera
+    /// (archivo no elegible o binario); `None` no toca el contenido indexado.
+    pub content: Option<String>,
+}
+
+/// Coincidencia de `search_content`: el resultado más un fragmento del texto.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentMatch {
+    #[serde(flatten)]
+    pub result: SearchResult,
+    /// Fragmento con los términos entre `\u{2}` y `\u{3}` (ver `content::SNIPPET_START`).
+    pub snippet: String,
+}
+
+/// Inicio de un archivo decodificado para el panel de vista previa (`preview_text`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextPreview {
+    /// Texto en UTF-8; vacío si `is_binary`.
+    pub text: String,
+    /// Codificación detectada (nombre WHATWG, p. ej. `windows-1252`).
+    pub encoding: Option<String>,
+    /// Parece binario: la UI muestra un marcador en vez del texto.
+    pub is_binary: bool,
+    /// El archivo es más largo que lo leído.
+    pub truncated: bool,
+}
+
+/// Detalle de una ruta (`get_file_details`): la fila del índice más lo que hay ahora en
+/// disco.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDetails {
+    pub path: String,
+    /// `None` si la ruta no está indexada.
+    pub indexed: Option<SearchResult>,
+    /// `None` si ya no existe en disco.
+    pub live: Option<LiveMetadata>,
+    /// Indexada pero ya no está en disco.
+    pub stale: bool,
+    /// El tamaño o la fecha de modificación en disco no son los indexados.
+    pub changed: bool,
+}
+
+/// Metadata leída al pedir el detalle. De un enlace simbólico, la del propio enlace
+/// (como la indexa el walker) y su destino.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveMetadata {
+    pub is_dir: bool,
+    pub file_size: u64,
+    pub modified_time: Option<String>,
+    pub created_time: Option<String>,
+    pub accessed_time: Option<String>,
+    /// `st_mode` en Unix; en Windows, atributos de solo lectura, oculto y de sistema.
+    pub mode: Option<u32>,
+    pub readonly: bool,
+    pub owner_uid: Option<u32>,
+    pub owner_gid: Option<u32>,
+    /// Enlaces duros al archivo (1 si el sistema no lo informa).
+    pub link_count: u64,
+    pub is_symlink: bool,
+    /// Destino del enlace tal como está escrito (puede ser relativo).
+    pub symlink_target: Option<String>,
+    /// El destino del enlace no existe.
+    pub dangling: bool,
+}
+
+/// Resultado de `verify_index`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// Entradas comprobadas (todas o la muestra).
+    pub checked: u64,
+    pub ok: u64,
+    /// Ya no existen en disco.
+    pub missing: u64,
+    /// Existen pero con otro tamaño o fecha de modificación (solo con `check_metadata`).
+    pub stale: u64,
+    /// No se pudo leer su metadata (sin permisos).
+    pub unreadable: u64,
+    /// Las primeras entradas con problemas (como mucho `verify::MAX_OFFENDERS`).
+    pub offenders: Vec<VerifyIssue>,
+    /// Entradas que faltaban y se borraron del índice (con `prune`).
+    pub pruned: usize,
+    /// Mensajes de `PRAGMA integrity_check`; vacío si la base está bien.
+    pub integrity_errors: Vec<String>,
+    /// Se canceló (`cancel_indexing`) antes de comprobarlo todo.
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIssue {
+    pub path: String,
+    pub kind: VerifyIssueKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyIssueKind {
+    Missing,
+    Stale,
+    Unreadable,
+}
+
+/// Operación larga en segundo plano (evento `operation-updated` y `get_operations`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationInfo {
+    pub id: u64,
+    /// `reindex`, `quick_refresh`, `auto_index`, `rebuild_fts`...
+    pub kind: String,
+    pub status: OperationStatus,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    /// Resumen al terminar (archivos indexados o error).
+    pub message: Option<String>,
+    /// Entradas que un índice no pudo leer.
+    pub warnings: Option<IndexingWarnings>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,14 +711,141 @@ pub struct IndexingStatus {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SearchConfig {
     pub indexing_paths: Vec<String>,
     pub exclude_patterns: Vec<String>,
+    /// Si no está vacía, `index_single_directory` solo acepta rutas dentro de estas.
+    pub allowed_index_roots: Vec<String>,
     pub max_results: usize,
     pub fuzzy_threshold: f64,
     pub cache_enabled: bool,
     pub cache_ttl_hours: u64,
     pub theme: String,
+    /// Máximo de búsquedas recientes guardadas (0 desactiva el historial).
+    pub history_max_entries: usize,
+    /// Días tras los cuales una búsqueda del historial expira (0 = nunca).
+    pub history_ttl_days: u64,
+    /// Búsquedas por segundo aceptadas por `search_files` (0 desactiva el límite).
+    pub search_rate_per_sec: f64,
+    /// Ráfaga máxima de búsquedas seguidas antes de aplicar el límite.
+    pub search_burst: u32,
+    /// Lee atributos extendidos como etiquetas al indexar (solo Unix).
+    pub index_tags: bool,
+    /// Atributos extendidos que se interpretan como etiquetas.
+    pub tag_attributes: Vec<String>,
+    /// Tras reindexar una ruta completa, borra lo que ya no está en disco.
+    pub prune_after_reindex: bool,
+    /// Permite `prefetch_results` (lectura anticipada de los primeros resultados).
+    pub prefetch_enabled: bool,
+    /// Bytes leídos del inicio de cada archivo al precargar.
+    pub prefetch_bytes_per_file: usize,
+    /// Límite de bytes leídos por llamada a `prefetch_results`.
+    pub prefetch_max_total_bytes: usize,
+    /// Cada cuántos minutos se hace un refresco rápido (`index_since`) en segundo plano (0 = nunca).
+    pub quick_refresh_minutes: u64,
+    /// Máximo de registros MFT leídos por unidad (0 = hasta el final de la MFT).
+    pub mft_max_records: usize,
+    /// Máximo de entradas por ejecución de índice (0 = sin límite).
+    pub index_max_files: usize,
+    /// Profundidad máxima bajo cada raíz (0 = sin límite). Con límite no se poda lo
+    /// que no aparezca ni se usan la MFT, Spotlight o locate.
+    pub index_max_depth: usize,
+    /// Duración máxima de una ejecución de índice en segundos (0 = sin límite).
+    pub index_max_duration_secs: u64,
+    /// Entradas por transacción al indexar. Lotes grandes van mejor en SSD; en discos
+    /// lentos o de red, uno menor deja ver antes el progreso en la base.
+    pub index_batch_size: usize,
+    /// Guarda lo acumulado al menos cada tantos segundos aunque el lote no esté lleno
+    /// (0 = solo al llenarse).
+    pub index_flush_interval_secs: u64,
+    /// Hilos del recorrido de directorios (0 = según los núcleos). Con 1 y
+    /// `index_max_files_per_sec` el índice molesta poco al resto del sistema.
+    pub index_threads: usize,
+    /// Entradas por segundo como mucho en los recorridos de directorios (0 = sin límite).
+    pub index_max_files_per_sec: f64,
+    /// Dirección de la API HTTP (feature `http-api`); solo local por defecto.
+    pub api_bind_address: String,
+    /// Puerto de la API HTTP (0 = elegir uno libre).
+    pub api_port: u16,
+    /// Token que la API HTTP exige en `Authorization: Bearer` (vacío = sin token).
+    pub api_token: String,
+    /// Tiempo máximo de la consulta SQL de una búsqueda en ms (0 = sin límite).
+    pub search_timeout_ms: u64,
+    /// Espera en ms antes de consultar: si entretanto llega otra búsqueda, esta no toca
+    /// la base (0 = sin espera).
+    pub search_debounce_ms: u64,
+    /// Las consultas de búsqueda más lentas que esto (ms) se registran en el log (0 = nunca).
+    pub slow_query_ms: u64,
+    /// Caracteres mínimos de una consulta; las más cortas devuelven vacío sin consultar.
+    pub min_query_length: usize,
+    /// Resultados por evento `search-result-batch` de `search_files_streaming`.
+    pub stream_batch_size: usize,
+    /// Indexa el texto de los archivos (solo extensiones de `content_extensions`).
+    pub index_content: bool,
+    pub content_extensions: Vec<String>,
+    /// Tamaño máximo de archivo cuyo contenido se indexa.
+    pub content_max_bytes: u64,
+    /// Calcula el tamaño recursivo de los directorios al indexar (más memoria y escrituras).
+    pub compute_dir_sizes: bool,
+    /// Un archivo con varios enlaces duros cuenta una sola vez en los tamaños de
+    /// directorio y, por defecto, en `get_extension_stats`.
+    pub dedupe_hardlinks: bool,
+    /// Indexa cada unidad en su propia base para escribir en paralelo (ver `shard`).
+    /// Cambiarlo requiere reindexar.
+    pub shard_by_drive: bool,
+    /// Cuenta las entradas antes de indexar para informar `total_files` en el progreso
+    /// (recorre el árbol dos veces).
+    pub precount_entries: bool,
+    /// Indexa archivos y carpetas ocultos (`.env`, `.config`).
+    pub index_hidden: bool,
+    /// Aplica las reglas de `.gitignore` (y las globales de git) al recorrer.
+    pub respect_gitignore: bool,
+    /// Aplica las reglas de los archivos `.ignore`.
+    pub respect_ignore_files: bool,
+    /// Lee el shebang de los scripts para buscar por intérprete (`interpreter:bash`).
+    pub index_interpreters: bool,
+    /// Tamaño máximo de un archivo para leer su shebang.
+    pub interpreter_max_bytes: u64,
+    /// Sigue los enlaces simbólicos al recorrer. Cada directorio se indexa una sola vez
+    /// aunque se llegue a él por varios caminos (así se cortan los ciclos).
+    pub follow_symlinks: bool,
+    /// En macOS, lista las rutas con Spotlight (`mdfind`) en vez de recorrer el disco
+    /// cuando el volumen lo tiene activado. Solo ve lo que Spotlight indexa, así que
+    /// esas ejecuciones no podan (`prune_after_reindex`).
+    pub use_spotlight: bool,
+    /// En Linux, parte de la base de `updatedb` (mlocate) cuando existe y se puede leer,
+    /// en vez de recorrer el disco. Refleja el último `updatedb`.
+    pub use_locate_db: bool,
+    /// Tras cargar la base de locate, recorre el disco para añadir lo posterior al
+    /// último `updatedb` y podar lo que ya no exista. Sin él no se poda.
+    pub locate_refresh_walk: bool,
+    /// Nivel de `synchronous` de la base. `Off` acelera los reíndices completos y vuelve
+    /// a `Normal` al terminar; la base no se queda nunca sin sincronizar.
+    pub db_synchronous: SyncMode,
+    /// Emite `directory-indexed` a medida que el recorrido termina cada directorio, para
+    /// que la UI los muestre durante el primer índice.
+    pub emit_directory_events: bool,
+    /// Guarda el tipo MIME de cada archivo según la base de asociaciones del sistema
+    /// (shared-mime-info, registro de Windows) para buscar con `mime:`.
+    pub index_mime_types: bool,
+    /// Tras indexar una unidad por la MFT, sigue su USN journal para aplicar
+    /// creaciones, renombrados y borrados sin volver a leer la MFT.
+    pub follow_usn_journal: bool,
+    /// Tras indexar, calcula el hash del contenido de los archivos que comparten tamaño
+    /// para `find_duplicates`.
+    pub hash_duplicates: bool,
+    /// En los recorridos completos, no vuelve a leer ni guardar los archivos cuyo mtime y
+    /// tamaño no cambiaron. Los cambios que no tocan el mtime (permisos, etiquetas) no se
+    /// ven hasta un recorrido sin este modo.
+    pub incremental_reindex: bool,
+    /// Archivos más grandes que esto (bytes) no se comparan.
+    pub duplicate_max_bytes: u64,
+    /// Emite `indexing-progress` cada tantas entradas (0 = sin límite por cantidad).
+    pub progress_every_files: u64,
+    /// Emite `indexing-progress` si pasaron estos ms desde el anterior (0 = sin límite
+    /// por tiempo). Con los dos a 0 se emite por cada entrada.
+    pub progress_interval_ms: u64,
 }
 
 impl Default for SearchConfig {
@@ -97,11 +853,264 @@ impl Default for SearchConfig {
         Self {
             indexing_paths: vec![],
             exclude_patterns: vec![],
+            allowed_index_roots: vec![],
             max_results: 1000,
             fuzzy_threshold: 0.7,
             cache_enabled: true,
             cache_ttl_hours: 1,
             theme: "dark".to_string(),
+            history_max_entries: 100,
+            history_ttl_days: 30,
+            search_rate_per_sec: 20.0,
+            search_burst: 20,
+            index_tags: false,
+            tag_attributes: crate::tags::default_tag_attributes(),
+            prune_after_reindex: true,
+            prefetch_enabled: false,
+            prefetch_bytes_per_file: 16 * 1024,
+            prefetch_max_total_bytes: 1024 * 1024,
+            quick_refresh_minutes: 0,
+            mft_max_records: 0,
+            index_max_files: 0,
+            index_max_depth: 0,
+            index_max_duration_secs: 0,
+            index_batch_size: 5_000,
+            index_flush_interval_secs: 5,
+            index_threads: 0,
+            index_max_files_per_sec: 0.0,
+            api_bind_address: "127.0.0.1".to_string(),
+            api_port: 7878,
+            api_token: String::new(),
+            search_timeout_ms: 2000,
+            search_debounce_ms: 0,
+            slow_query_ms: 500,
+            min_query_length: 2,
+            stream_batch_size: 200,
+            index_content: false,
+            content_extensions: crate::content::default_content_extensions(),
+            content_max_bytes: 1024 * 1024,
+            compute_dir_sizes: false,
+            dedupe_hardlinks: true,
+            shard_by_drive: false,
+            precount_entries: false,
+            index_hidden: false,
+            respect_gitignore: true,
+            respect_ignore_files: true,
+            index_interpreters: false,
+            interpreter_max_bytes: 1024 * 1024,
+            follow_symlinks: false,
+            use_spotlight: false,
+            use_locate_db: false,
+            locate_refresh_walk: true,
+            emit_directory_events: false,
+            db_synchronous: SyncMode::Normal,
+            index_mime_types: false,
+            follow_usn_journal: true,
+            hash_duplicates: false,
+            incremental_reindex: false,
+            duplicate_max_bytes: 4 * 1024 * 1024 * 1024,
+            progress_every_files: 0,
+            progress_interval_ms: 100,
+        }
+    }
+}
+
+impl SearchConfig {
+    pub fn search_timeout(&self) -> Option<std::time::Duration> {
+        (self.search_timeout_ms > 0).then(|| std::time::Duration::from_millis(self.search_timeout_ms))
+    }
+
+    pub fn search_debounce(&self) -> Option<std::time::Duration> {
+        (self.search_debounce_ms > 0).then(|| std::time::Duration::from_millis(self.search_debounce_ms))
+    }
+
+    /// Caducidad de la caché de búsquedas (`None` con `cache_ttl_hours` = 0: solo se
+    /// vacía al cambiar el índice).
+    pub fn cache_ttl(&self) -> Option<std::time::Duration> {
+        (self.cache_ttl_hours > 0).then(|| std::time::Duration::from_secs(self.cache_ttl_hours * 3600))
+    }
+
+    pub fn slow_query_threshold(&self) -> Option<std::time::Duration> {
+        (self.slow_query_ms > 0).then(|| std::time::Duration::from_millis(self.slow_query_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    // La UI declara los payloads a mano en `src/types.ts`: sus campos tienen que ser
+    // exactamente las claves que produce serde.
+    const TS_TYPES: &str = include_str!("../../src/types.ts");
+
+    /// Campos de `export interface <name>`, incluidos los heredados con `extends`.
+    fn ts_fields(name: &str) -> BTreeSet<String> {
+        let mut lines = TS_TYPES.lines().skip_while(|line| {
+            let rest = line.strip_prefix("export interface ").unwrap_or("");
+            !rest.starts_with(&format!("{name} ")) && rest != name
+        });
+        let header = lines.next().unwrap_or_else(|| panic!("{name} not in src/types.ts"));
+        let mut fields = match header.split(" extends ").nth(1) {
+            Some(parent) => ts_fields(parent.trim_end_matches('{').trim()),
+            None => BTreeSet::new(),
+        };
+        for line in lines.map(str::trim).take_while(|line| *line != "}") {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let (field, _) = line.split_once(':').expect("field without a type");
+            fields.insert(field.trim_end_matches('?').to_string());
+        }
+        fields
+    }
+
+    fn keys(value: impl Serialize) -> BTreeSet<String> {
+        match serde_json::to_value(value).unwrap() {
+            Value::Object(map) => map.into_iter().map(|(key, _)| key).collect(),
+            other => panic!("expected an object, got {other}"),
+        }
+    }
+
+    fn assert_mirrored(name: &str, value: impl Serialize) {
+        assert_eq!(keys(value), ts_fields(name), "{name} differs from src/types.ts");
+    }
+
+    fn result() -> SearchResult {
+        SearchResult {
+            path: "/a/b.txt".into(),
+            name: "b.txt".into(),
+            extension: Some(".txt".into()),
+            file_size: Some(1),
+            is_dir: false,
+            modified_time: "2024-01-01T00:00:00Z".into(),
+            created_time: None,
+            is_reparse: false,
+            link_count: 1,
+            mode: None,
+            owner_uid: None,
+            owner_gid: None,
+            score: 1.0,
+            child_count: None,
+            matched_in_path: false,
+        }
+    }
+
+    fn results() -> SearchResults {
+        SearchResults {
+            query: "b".into(),
+            results: vec![result()],
+            total: 1,
+            page: 0,
+            limit: 50,
+            timed_out: false,
+            is_stale: false,
+        }
+    }
+
+    fn live() -> LiveMetadata {
+        LiveMetadata {
+            is_dir: false,
+            file_size: 1,
+            modified_time: None,
+            created_time: None,
+            accessed_time: None,
+            mode: None,
+            readonly: false,
+            owner_uid: None,
+            owner_gid: None,
+            link_count: 1,
+            is_symlink: false,
+            symlink_target: None,
+            dangling: false,
         }
     }
+
+    #[test]
+    fn search_payloads_match_the_ui_types() {
+        assert_mirrored("SearchResult", result());
+        assert_mirrored("SearchFilters", SearchFilters::default());
+        assert_mirrored("SearchResults", results());
+        assert_mirrored("ContentMatch", ContentMatch { result: result(), snippet: String::new() });
+        assert_mirrored("RefinedResults", RefinedResults { search: results(), facets: vec![] });
+        assert_mirrored(
+            "SearchResultBatch",
+            SearchResultBatch { epoch: 1, query: "b".into(), offset: 0, results: vec![] },
+        );
+        assert_mirrored(
+            "SearchComplete",
+            SearchComplete {
+                epoch: 1,
+                query: "b".into(),
+                total: 0,
+                timed_out: false,
+                is_stale: false,
+            },
+        );
+        assert_mirrored("ExtensionFacet", ExtensionFacet { extension: None, count: 0 });
+    }
+
+    #[test]
+    fn detail_payloads_match_the_ui_types() {
+        assert_mirrored(
+            "TextPreview",
+            TextPreview { text: String::new(), encoding: None, is_binary: false, truncated: false },
+        );
+        assert_mirrored(
+            "FileDetails",
+            FileDetails {
+                path: "/a/b.txt".into(),
+                indexed: Some(result()),
+                live: Some(live()),
+                stale: false,
+                changed: false,
+            },
+        );
+        assert_mirrored("LiveMetadata", live());
+        assert_mirrored(
+            "ExtensionStat",
+            ExtensionStat { extension: None, is_dir: false, count: 0, bytes: 0 },
+        );
+    }
+
+    #[test]
+    fn indexing_payloads_match_the_ui_types() {
+        assert_mirrored(
+            "IndexingProgress",
+            IndexingProgress {
+                current_path: String::new(),
+                files_processed: 0,
+                total_files: None,
+                status: "indexing".into(),
+                operation_id: None,
+                inserted: 0,
+                updated: 0,
+                skipped: 0,
+            },
+        );
+        assert_mirrored(
+            "IndexingStatus",
+            IndexingStatus {
+                is_indexing: false,
+                last_indexed: None,
+                total_files: 0,
+                database_size: 0,
+            },
+        );
+        assert_mirrored("IndexingWarnings", IndexingWarnings::default());
+        assert_mirrored("VerifyReport", VerifyReport::default());
+        assert_mirrored(
+            "OperationInfo",
+            OperationInfo {
+                id: 1,
+                kind: "reindex".into(),
+                status: OperationStatus::Running,
+                started_at: String::new(),
+                finished_at: None,
+                message: None,
+                warnings: None,
+            },
+        );
+    }
 }
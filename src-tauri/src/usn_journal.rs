@@ -0,0 +1,485 @@
+//! Actualización incremental de una unidad NTFS ya indexada por la MFT a partir del
+//! USN Change Journal: en vez de volver a leer la MFT se leen los cambios que NTFS va
+//! anotando (creaciones, renombrados y borrados) y se aplican al índice.
+//!
+//! Las rutas siguen el formato de `MftIndexer` (`C:\nombre`) para que los borrados
+//! encuentren las entradas que creó. La posición leída se guarda en `meta` por unidad:
+//! al reiniciar se sigue desde ahí si el journal sigue siendo el mismo.
+
+use crate::category;
+use crate::db::Database;
+use crate::indexer::WINDOWS_ATTRIBUTE_MASK;
+use crate::mft_indexer::filetime_to_rfc3339;
+use crate::types::FileRecord;
+use chrono::Utc;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Clave de `meta` con `id_del_journal:siguiente_usn` de cada unidad.
+const META_USN_POSITION: &str = "usn_position:";
+
+/// Espera entre lecturas cuando ya no quedan cambios.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+const USN_REASON_FILE_CREATE: u32 = 0x0000_0100;
+const USN_REASON_FILE_DELETE: u32 = 0x0000_0200;
+const USN_REASON_RENAME_OLD_NAME: u32 = 0x0000_1000;
+const USN_REASON_RENAME_NEW_NAME: u32 = 0x0000_2000;
+/// Solo interesan los cambios de nombre: el resto no cambia la ruta indexada.
+const REASON_MASK: u32 =
+    USN_REASON_FILE_CREATE | USN_REASON_FILE_DELETE | USN_REASON_RENAME_OLD_NAME | USN_REASON_RENAME_NEW_NAME;
+
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+/// Unidades con un hilo de seguimiento activo.
+fn monitored() -> &'static Mutex<HashSet<char>> {
+    static MONITORED: OnceLock<Mutex<HashSet<char>>> = OnceLock::new();
+    MONITORED.get_or_init(Default::default)
+}
+
+/// Empieza a seguir el journal de `drive` en segundo plano, salvo que ya se esté
+/// siguiendo. Se llama tras un índice de la MFT completo de la unidad.
+pub fn start_monitor(drive: char, db: Arc<Mutex<Database>>) {
+    let drive = drive.to_ascii_uppercase();
+    if !monitored().lock().unwrap_or_else(|e| e.into_inner()).insert(drive) {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        if let Err(e) = monitor(drive, &db) {
+            warn!("Stopped following the USN journal of {}: {}", drive, e);
+        }
+        monitored().lock().unwrap_or_else(|e| e.into_inner()).remove(&drive);
+    });
+}
+
+fn monitor(drive: char, db: &Mutex<Database>) -> Result<(), Box<dyn std::error::Error>> {
+    let volume = sys::Volume::open(drive)?;
+    let journal = parse_journal_data(&volume.query()?)?;
+    let key = format!("{}{}", META_USN_POSITION, drive);
+    let mut next_usn = resume_position(drive, db, &key, &journal)?;
+    info!("Following USN journal of {} from USN {}", drive, next_usn);
+
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        let read = volume.read(next_usn, journal.id, REASON_MASK, &mut buffer)?;
+        let (last_usn, records) = parse_records(&buffer[..read])?;
+        if !records.is_empty() {
+            apply(drive, db, &records)?;
+        }
+
+        if last_usn == next_usn {
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        next_usn = last_usn;
+        lock(db)?.set_meta(&key, &format!("{}:{}", journal.id, next_usn))?;
+    }
+}
+
+/// USN desde el que seguir: el guardado si el journal es el mismo y aún lo contiene;
+/// si no, el actual (lo anterior lo cubre el índice de la MFT recién hecho).
+fn resume_position(
+    drive: char,
+    db: &Mutex<Database>,
+    key: &str,
+    journal: &JournalData,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let stored = lock(db)?.get_meta(key)?;
+    let resumed = stored.as_deref().and_then(|value| {
+        let (id, usn) = value.split_once(':')?;
+        let usn: i64 = usn.parse().ok()?;
+        (id.parse() == Ok(journal.id) && usn >= journal.lowest_valid_usn).then_some(usn)
+    });
+    if stored.is_some() && resumed.is_none() {
+        warn!(
+            "USN journal of {} was reset or overflowed; changes made while not running were missed",
+            drive
+        );
+    }
+    Ok(resumed.unwrap_or(journal.next_usn))
+}
+
+/// Aplica los registros en orden: un renombrado llega como nombre viejo (se borra) y
+/// nombre nuevo (se inserta), y un borrado gana a una creación del mismo registro.
+fn apply(drive: char, db: &Mutex<Database>, records: &[UsnRecord]) -> Result<(), Box<dyn std::error::Error>> {
+    let db_guard = lock(db)?;
+    let (mut updated, mut removed) = (0, 0);
+    for record in records {
+        let path = format!("{}:\\{}", drive, record.name);
+        match record.change() {
+            Some(Change::Remove) => removed += db_guard.delete_tree(&path)?,
+            Some(Change::Upsert) => {
+                db_guard.upsert_file(&record.file_record(path))?;
+                updated += 1;
+            }
+            None => {}
+        }
+    }
+    info!(
+        "USN journal of {}: {} entries updated, {} removed",
+        drive, updated, removed
+    );
+    Ok(())
+}
+
+fn lock(db: &Mutex<Database>) -> Result<std::sync::MutexGuard<'_, Database>, String> {
+    db.lock().map_err(|e| format!("Failed to lock database: {}", e))
+}
+
+/// Campos de `USN_JOURNAL_DATA_V0` que hacen falta.
+struct JournalData {
+    id: u64,
+    next_usn: i64,
+    lowest_valid_usn: i64,
+}
+
+/// `USN_JOURNAL_DATA_V0`: id en 0x00, siguiente USN en 0x10 y el menor válido en 0x18.
+fn parse_journal_data(data: &[u8]) -> io::Result<JournalData> {
+    Ok(JournalData {
+        id: read_u64(data, 0x00)?,
+        next_usn: read_u64(data, 0x10)? as i64,
+        lowest_valid_usn: read_u64(data, 0x18)? as i64,
+    })
+}
+
+/// Qué hace un registro con su ruta en el índice.
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    Remove,
+    Upsert,
+}
+
+/// Un cambio del journal con lo necesario para el índice.
+#[derive(Debug)]
+struct UsnRecord {
+    reason: u32,
+    attributes: u32,
+    /// FILETIME del cambio.
+    timestamp: u64,
+    name: String,
+}
+
+impl UsnRecord {
+    /// Un borrado gana a una creación del mismo registro.
+    fn change(&self) -> Option<Change> {
+        if self.reason & (USN_REASON_FILE_DELETE | USN_REASON_RENAME_OLD_NAME) != 0 {
+            Some(Change::Remove)
+        } else if self.reason & (USN_REASON_FILE_CREATE | USN_REASON_RENAME_NEW_NAME) != 0 {
+            Some(Change::Upsert)
+        } else {
+            None
+        }
+    }
+
+    /// Registro con lo que da el journal: sin tamaño (no se puede leer del disco con
+    /// las rutas de la MFT).
+    fn file_record(&self, path: String) -> FileRecord {
+        let is_dir = self.attributes & FILE_ATTRIBUTE_DIRECTORY != 0;
+        let extension = if is_dir {
+            None
+        } else {
+            Path::new(&self.name)
+                .extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+        };
+        let modified_time = filetime_to_rfc3339(self.timestamp);
+        let now = Utc::now().to_rfc3339();
+        FileRecord {
+            path,
+            name: self.name.clone(),
+            category: category::from_extension(extension.as_deref()).map(String::from),
+            extension,
+            file_size: None,
+            is_dir,
+            modified_time: modified_time.clone().unwrap_or_else(|| now.clone()),
+            created_time: modified_time.filter(|_| self.reason & USN_REASON_FILE_CREATE != 0),
+            last_indexed: now,
+            path_lossy: false,
+            raw_path: None,
+            tags: None,
+            is_reparse: self.attributes & FILE_ATTRIBUTE_REPARSE_POINT != 0,
+            link_count: 1,
+            file_id: None,
+            interpreter: None,
+            mime: None,
+            mode: Some(self.attributes & WINDOWS_ATTRIBUTE_MASK),
+            owner_uid: None,
+            owner_gid: None,
+            content: None,
+        }
+    }
+}
+
+/// Salida de `FSCTL_READ_USN_JOURNAL`: el USN desde el que seguir y los registros
+/// `USN_RECORD_V2`/`V3` que le siguen (los V3 llevan referencias de 128 bits).
+fn parse_records(buffer: &[u8]) -> io::Result<(i64, Vec<UsnRecord>)> {
+    let next_usn = read_u64(buffer, 0)? as i64;
+    let mut records = Vec::new();
+    let mut offset = 8;
+    while offset < buffer.len() {
+        let length = read_u32(buffer, offset)? as usize;
+        let record = buffer
+            .get(offset..offset + length)
+            .filter(|_| length > 0)
+            .ok_or_else(|| invalid_data("truncated USN record"))?;
+        records.push(parse_record(record)?);
+        // Los registros están alineados a 8 bytes.
+        offset += (length + 7) & !7;
+    }
+    Ok((next_usn, records))
+}
+
+/// Un `USN_RECORD_V2` o `V3` completo (`record` mide lo que dice su `RecordLength`).
+fn parse_record(record: &[u8]) -> io::Result<UsnRecord> {
+    // Tras las referencias, ambas versiones tienen los mismos campos.
+    let fields = match read_u16(record, 4)? {
+        2 => 0x18,
+        3 => 0x28,
+        version => {
+            return Err(invalid_data(&format!("unsupported USN record version {}", version)))
+        }
+    };
+    let name_length = read_u16(record, fields + 0x20)? as usize;
+    let name_offset = read_u16(record, fields + 0x22)? as usize;
+    let units: Vec<u16> = record
+        .get(name_offset..name_offset + name_length)
+        .ok_or_else(|| invalid_data("truncated USN record name"))?
+        .chunks_exact(2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+
+    Ok(UsnRecord {
+        timestamp: read_u64(record, fields + 0x08)?,
+        reason: read_u32(record, fields + 0x10)?,
+        attributes: read_u32(record, fields + 0x1C)?,
+        name: String::from_utf16_lossy(&units),
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> io::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid_data("truncated USN data"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    data.get(offset..offset + 4)
+        .and_then(|b| b.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(|| invalid_data("truncated USN data"))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> io::Result<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|b| b.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| invalid_data("truncated USN data"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+
+    const FSCTL_QUERY_USN_JOURNAL: u32 = 0x0009_00F4;
+    const FSCTL_READ_USN_JOURNAL: u32 = 0x0009_00BB;
+    /// Tamaño de `USN_JOURNAL_DATA_V0`.
+    const JOURNAL_DATA_SIZE: usize = 56;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn DeviceIoControl(
+            device: *mut c_void,
+            control_code: u32,
+            in_buffer: *const c_void,
+            in_size: u32,
+            out_buffer: *mut c_void,
+            out_size: u32,
+            bytes_returned: *mut u32,
+            overlapped: *mut c_void,
+        ) -> i32;
+    }
+
+    /// `READ_USN_JOURNAL_DATA_V0`.
+    #[repr(C)]
+    struct ReadJournalData {
+        start_usn: i64,
+        reason_mask: u32,
+        return_only_on_close: u32,
+        timeout: u64,
+        bytes_to_wait_for: u64,
+        usn_journal_id: u64,
+    }
+
+    pub struct Volume(File);
+
+    impl Volume {
+        /// Abre `\\.\X:` como hace `MftIndexer` (requiere administrador).
+        pub fn open(drive: char) -> io::Result<Self> {
+            File::open(format!(r"\\.\{}:", drive)).map(Self)
+        }
+
+        pub fn query(&self) -> io::Result<Vec<u8>> {
+            let mut data = vec![0u8; JOURNAL_DATA_SIZE];
+            let len = self.control(FSCTL_QUERY_USN_JOURNAL, std::ptr::null(), 0, &mut data)?;
+            data.truncate(len);
+            Ok(data)
+        }
+
+        /// Lee los cambios desde `start_usn` sin esperar a que haya nuevos.
+        pub fn read(&self, start_usn: i64, journal_id: u64, reason_mask: u32, buffer: &mut [u8]) -> io::Result<usize> {
+            let request = ReadJournalData {
+                start_usn,
+                reason_mask,
+                return_only_on_close: 0,
+                timeout: 0,
+                bytes_to_wait_for: 0,
+                usn_journal_id: journal_id,
+            };
+            self.control(
+                FSCTL_READ_USN_JOURNAL,
+                &request as *const ReadJournalData as *const c_void,
+                std::mem::size_of::<ReadJournalData>() as u32,
+                buffer,
+            )
+        }
+
+        fn control(&self, code: u32, input: *const c_void, input_size: u32, output: &mut [u8]) -> io::Result<usize> {
+            let mut returned = 0u32;
+            // SAFETY: los punteros apuntan a buffers vivos del tamaño indicado y la
+            // llamada es síncrona (sin OVERLAPPED).
+            let ok = unsafe {
+                DeviceIoControl(
+                    self.0.as_raw_handle() as *mut c_void,
+                    code,
+                    input,
+                    input_size,
+                    output.as_mut_ptr() as *mut c_void,
+                    output.len() as u32,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(returned as usize)
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod sys {
+    use std::io;
+
+    pub struct Volume;
+
+    impl Volume {
+        pub fn open(_drive: char) -> io::Result<Self> {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "USN journal is only available on Windows"))
+        }
+
+        pub fn query(&self) -> io::Result<Vec<u8>> {
+            unreachable!("Volume cannot be opened on this platform")
+        }
+
+        pub fn read(&self, _start_usn: i64, _journal_id: u64, _reason_mask: u32, _buffer: &mut [u8]) -> io::Result<usize> {
+            unreachable!("Volume cannot be opened on this platform")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `USN_RECORD_V2` con el nombre en 0x3C, alineado a 8 bytes.
+    fn record_v2(reason: u32, attributes: u32, name: &str) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let length = (0x3C + name.len() + 7) & !7;
+        let mut bytes = vec![0u8; length];
+        bytes[0..4].copy_from_slice(&(length as u32).to_le_bytes());
+        bytes[4..6].copy_from_slice(&2u16.to_le_bytes());
+        bytes[0x20..0x28].copy_from_slice(&132_000_000_000_000_000u64.to_le_bytes());
+        bytes[0x28..0x2C].copy_from_slice(&reason.to_le_bytes());
+        bytes[0x34..0x38].copy_from_slice(&attributes.to_le_bytes());
+        bytes[0x38..0x3A].copy_from_slice(&(name.len() as u16).to_le_bytes());
+        bytes[0x3A..0x3C].copy_from_slice(&0x3Cu16.to_le_bytes());
+        bytes[0x3C..0x3C + name.len()].copy_from_slice(&name);
+        bytes
+    }
+
+    #[test]
+    fn decodes_a_rename() {
+        let old = parse_record(&record_v2(USN_REASON_RENAME_OLD_NAME, 0, "viejo.txt")).unwrap();
+        let new = parse_record(&record_v2(USN_REASON_RENAME_NEW_NAME, 0x20, "nuevo.txt")).unwrap();
+
+        assert_eq!(old.name, "viejo.txt");
+        assert_eq!(old.change(), Some(Change::Remove));
+        assert_eq!(new.name, "nuevo.txt");
+        assert_eq!(new.change(), Some(Change::Upsert));
+        assert_eq!(new.timestamp, 132_000_000_000_000_000);
+
+        let file = new.file_record("C:\\nuevo.txt".to_string());
+        assert_eq!(file.extension.as_deref(), Some(".txt"));
+        assert!(!file.is_dir);
+        assert!(file.created_time.is_none());
+    }
+
+    #[test]
+    fn delete_wins_over_create() {
+        let reason = USN_REASON_FILE_CREATE | USN_REASON_FILE_DELETE;
+        let record = parse_record(&record_v2(reason, FILE_ATTRIBUTE_DIRECTORY, "tmp")).unwrap();
+        assert_eq!(record.change(), Some(Change::Remove));
+        assert!(record.file_record("C:\\tmp".to_string()).is_dir);
+
+        // Otros motivos (p. ej. escritura de datos) no cambian la ruta.
+        let write = parse_record(&record_v2(0x1, 0, "datos.bin")).unwrap();
+        assert_eq!(write.change(), None);
+    }
+
+    #[test]
+    fn reads_a_journal_buffer() {
+        let mut buffer = 77i64.to_le_bytes().to_vec();
+        buffer.extend(record_v2(USN_REASON_FILE_CREATE, 0, "á.txt"));
+        buffer.extend(record_v2(USN_REASON_FILE_DELETE, 0, "b"));
+
+        let (next_usn, records) = parse_records(&buffer).unwrap();
+        assert_eq!(next_usn, 77);
+        let names: Vec<_> = records.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(names, ["á.txt", "b"]);
+    }
+
+    #[test]
+    fn truncated_records_are_rejected() {
+        let record = record_v2(USN_REASON_FILE_CREATE, 0, "nombre-largo.txt");
+        // Sin el nombre completo o sin los campos fijos.
+        assert!(parse_record(&record[..0x40]).is_err());
+        assert!(parse_record(&record[..0x30]).is_err());
+
+        let mut version = record.clone();
+        version[4] = 4;
+        assert!(parse_record(&version).is_err());
+
+        // El `RecordLength` dice más de lo que queda en el búfer.
+        let mut buffer = 0i64.to_le_bytes().to_vec();
+        buffer.extend(&record[..record.len() - 8]);
+        assert!(parse_records(&buffer).is_err());
+        let mut zero = 0i64.to_le_bytes().to_vec();
+        zero.extend([0u8; 8]);
+        assert!(parse_records(&zero).is_err());
+    }
+}
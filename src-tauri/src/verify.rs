@@ -0,0 +1,122 @@
+//! Comprobación del índice contra el disco (`verify_index`): entradas que ya no
+//! existen, que cambiaron desde que se indexaron y la integridad de SQLite.
+
+use crate::db::{IndexedEntry, Reader};
+use crate::indexer::IndexControl;
+use crate::types::{VerifyIssue, VerifyIssueKind, VerifyReport};
+use chrono::{DateTime, Utc};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Entradas con problemas que se devuelven en el informe; los contadores son exactos.
+pub const MAX_OFFENDERS: usize = 1000;
+
+/// Comprueba las entradas (todas o `sample` al azar) y la integridad de la base. Para
+/// a mitad si se cancela el índice (`cancelled`). Devuelve además las rutas que
+/// faltan, para podarlas.
+pub fn verify(
+    reader: &Reader,
+    sample: Option<usize>,
+    check_metadata: bool,
+    control: &IndexControl,
+) -> rusqlite::Result<(VerifyReport, Vec<String>)> {
+    let mut report = VerifyReport {
+        integrity_errors: reader.integrity_check()?,
+        ..VerifyReport::default()
+    };
+    let mut missing = Vec::new();
+
+    reader.for_each_entry(sample, |path, entry| {
+        if control.should_stop() {
+            report.cancelled = true;
+            return false;
+        }
+        report.checked += 1;
+        let kind = match check(&path, &entry, check_metadata) {
+            None => {
+                report.ok += 1;
+                return true;
+            }
+            Some(kind) => kind,
+        };
+        match kind {
+            VerifyIssueKind::Missing => report.missing += 1,
+            VerifyIssueKind::Stale => report.stale += 1,
+            VerifyIssueKind::Unreadable => report.unreadable += 1,
+        }
+        if report.offenders.len() < MAX_OFFENDERS {
+            report.offenders.push(VerifyIssue {
+                path: path.clone(),
+                kind,
+            });
+        }
+        if kind == VerifyIssueKind::Missing {
+            missing.push(path);
+        }
+        true
+    })?;
+    Ok((report, missing))
+}
+
+/// Problema de una entrada, `None` si está al día. Mismo criterio que el índice
+/// incremental: mtime y tamaño para los archivos; a un directorio le basta existir.
+fn check(path: &str, entry: &IndexedEntry, check_metadata: bool) -> Option<VerifyIssueKind> {
+    let metadata = match std::fs::symlink_metadata(Path::new(path)) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Some(VerifyIssueKind::Missing),
+        Err(_) => return Some(VerifyIssueKind::Unreadable),
+    };
+    let (modified_time, file_size, is_dir) = entry;
+    if !check_metadata || *is_dir {
+        return None;
+    }
+    let modified = metadata.modified().ok().map(|m| DateTime::<Utc>::from(m).to_rfc3339());
+    let same = modified.as_ref() == Some(modified_time) && *file_size == Some(metadata.len() as i64);
+    (!same).then_some(VerifyIssueKind::Stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::Indexer;
+    use crate::test_support::{open_index, TempDir};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn deleted_and_changed_files_are_flagged() {
+        let dir = TempDir::new("verify");
+        let root = dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        for file in ["kept", "deleted", "changed"] {
+            std::fs::write(root.join(file), b"first").unwrap();
+        }
+        let (db, reader) = open_index(&dir);
+        let indexer = Indexer::new(Arc::new(Mutex::new(db)), Arc::new(IndexControl::new()));
+        let roots = vec![root.to_string_lossy().into_owned()];
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(indexer.index_multiple_paths(roots, Vec::new(), Arc::new(|_| {})))
+            .unwrap();
+
+        std::fs::remove_file(root.join("deleted")).unwrap();
+        std::fs::write(root.join("changed"), b"second version").unwrap();
+        let control = IndexControl::new();
+
+        let (report, missing) = verify(&reader, None, true, &control).unwrap();
+        assert_eq!(missing, [dir.join("root/deleted")]);
+        assert_eq!(
+            (report.checked, report.ok, report.missing, report.stale, report.unreadable),
+            (4, 2, 1, 1, 0)
+        );
+        let offenders: Vec<(String, VerifyIssueKind)> =
+            report.offenders.into_iter().map(|issue| (issue.path, issue.kind)).collect();
+        assert!(offenders.contains(&(dir.join("root/deleted"), VerifyIssueKind::Missing)));
+        assert!(offenders.contains(&(dir.join("root/changed"), VerifyIssueKind::Stale)));
+        assert!(report.integrity_errors.is_empty(), "{:?}", report.integrity_errors);
+
+        // Sin `check_metadata` solo cuenta que exista.
+        let (report, _) = verify(&reader, None, false, &control).unwrap();
+        assert_eq!((report.ok, report.missing, report.stale), (3, 1, 0));
+    }
+}
@@ -0,0 +1,290 @@
+use crate::db::Database;
+use crate::exclude::ExcludeMatcher;
+use crate::indexer::{IndexOptions, Indexer};
+use crate::shard::Shards;
+use crate::types::WatchEvent;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Silencio necesario tras el último evento para aplicar los cambios acumulados.
+const DEBOUNCE_WINDOW: Duration = Duration::from_millis(500);
+/// Con un flujo continuo de eventos, aplica igualmente como mucho cada este tiempo.
+const MAX_DEBOUNCE_DELAY: Duration = Duration::from_secs(5);
+
+/// Archivos de la propia base: la principal, sus shards y los `-wal`, `-shm` y
+/// `-journal` de cada una. Cada escritura en el índice los modifica, así que si se
+/// indexaran cada cambio provocaría otra escritura.
+struct IndexFiles {
+    /// Directorio de la base tal cual y resuelto (los eventos pueden traer cualquiera).
+    dirs: Vec<PathBuf>,
+    db_name: String,
+    shard_prefix: String,
+}
+
+impl IndexFiles {
+    fn new(main_path: &Path) -> Self {
+        let main_path = std::path::absolute(main_path).unwrap_or_else(|_| main_path.to_path_buf());
+        let dir = main_path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut dirs = vec![dir.clone()];
+        if let Ok(resolved) = std::fs::canonicalize(&dir) {
+            if resolved != dir {
+                dirs.push(resolved);
+            }
+        }
+        let name = |part: Option<&std::ffi::OsStr>| {
+            part.map(|p| p.to_string_lossy().into_owned()).unwrap_or_default()
+        };
+        Self {
+            dirs,
+            db_name: name(main_path.file_name()),
+            shard_prefix: format!("{}.shard-", name(main_path.file_stem())),
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        let in_dir = path
+            .parent()
+            .is_some_and(|parent| self.dirs.iter().any(|dir| dir == parent));
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        let is_main = name
+            .strip_prefix(self.db_name.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'));
+        in_dir && (is_main || name.starts_with(&self.shard_prefix))
+    }
+}
+
+/// Mantiene el índice al día con los cambios del sistema de archivos.
+pub struct IndexWatcher {
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl IndexWatcher {
+    pub fn new() -> Self {
+        Self {
+            watcher: Mutex::new(None),
+        }
+    }
+
+    /// Empieza a vigilar `paths` (reemplaza cualquier vigilancia anterior).
+    pub fn start(
+        &self,
+        db: Arc<Mutex<Database>>,
+        shards: Arc<Shards>,
+        paths: Vec<String>,
+        exclude_patterns: Vec<String>,
+        options: IndexOptions,
+        on_change: Arc<dyn Fn(WatchEvent) + Send + Sync>,
+    ) -> Result<(), notify::Error> {
+        let mut guard = self.watcher.lock().unwrap_or_else(|e| e.into_inner());
+        // Soltar el watcher anterior cierra su canal y termina su hilo.
+        *guard = None;
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res| {
+            let _ = tx.send(res);
+        })?;
+
+        for path in &paths {
+            match watcher.watch(Path::new(path), RecursiveMode::Recursive) {
+                Ok(()) => info!("Watching {}", path),
+                Err(e) => warn!("Failed to watch {}: {}", path, e),
+            }
+        }
+
+        let filter = EventFilter {
+            excludes: ExcludeMatcher::new(&exclude_patterns),
+            index_files: IndexFiles::new(shards.main_path()),
+        };
+        std::thread::spawn(move || process_events(rx, db, shards, filter, options, on_change));
+
+        *guard = Some(watcher);
+        Ok(())
+    }
+
+    /// Deja de vigilar. Devuelve `false` si no había vigilancia activa.
+    pub fn stop(&self) -> bool {
+        let mut guard = self.watcher.lock().unwrap_or_else(|e| e.into_inner());
+        let was_watching = guard.take().is_some();
+        if was_watching {
+            info!("Stopped watching filesystem");
+        }
+        was_watching
+    }
+}
+
+fn process_events(
+    rx: Receiver<notify::Result<Event>>,
+    db: Arc<Mutex<Database>>,
+    shards: Arc<Shards>,
+    filter: EventFilter,
+    options: IndexOptions,
+    on_change: Arc<dyn Fn(WatchEvent) + Send + Sync>,
+) {
+    let mut pending: HashSet<PathBuf> = HashSet::new();
+
+    // `recv` falla cuando se suelta el watcher: fin del hilo.
+    while let Ok(first) = rx.recv() {
+        collect(first, &filter, &mut pending);
+
+        let batch_start = Instant::now();
+        let mut disconnected = false;
+        while batch_start.elapsed() < MAX_DEBOUNCE_DELAY {
+            match rx.recv_timeout(DEBOUNCE_WINDOW) {
+                Ok(res) => collect(res, &filter, &mut pending),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    disconnected = true;
+                    break;
+                }
+            }
+        }
+
+        let event = apply(&db, &shards, &options, pending.drain());
+        if !event.updated.is_empty() || !event.removed.is_empty() {
+            on_change(event);
+        }
+
+        if disconnected {
+            break;
+        }
+    }
+}
+
+/// Qué eventos no llegan al índice.
+struct EventFilter {
+    excludes: ExcludeMatcher,
+    index_files: IndexFiles,
+}
+
+fn collect(res: notify::Result<Event>, filter: &EventFilter, pending: &mut HashSet<PathBuf>) {
+    match res {
+        // Abrir o leer no cambia nada, y el propio índice abre los archivos al
+        // enriquecerlos (etiquetas, intérprete, contenido).
+        Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+        Ok(event) => {
+            for path in event.paths {
+                if !filter.excludes.is_excluded(&path) && !filter.index_files.contains(&path) {
+                    pending.insert(path);
+                }
+            }
+        }
+        Err(e) => warn!("Watch error: {}", e),
+    }
+}
+
+/// Lleva al índice el estado actual en disco de cada ruta modificada.
+fn apply(
+    db: &Arc<Mutex<Database>>,
+    shards: &Shards,
+    options: &IndexOptions,
+    paths: impl Iterator<Item = PathBuf>,
+) -> WatchEvent {
+    let mut event = WatchEvent {
+        updated: Vec::new(),
+        removed: Vec::new(),
+    };
+
+    for path in paths {
+        let path_str = path.to_string_lossy().into_owned();
+        // Con `shard_by_drive`, la unidad ya indexada vive en su shard.
+        let target = if options.shard_by_drive {
+            shards.existing_writer(&path_str).unwrap_or_else(|e| {
+                warn!("Failed to open shard for {}: {}", path_str, e);
+                None
+            })
+        } else {
+            None
+        };
+        let target = target.as_ref().unwrap_or(db);
+        let db_guard = match target.lock() {
+            Ok(guard) => guard,
+            Err(e) => {
+                warn!("Failed to lock database for watch update: {}", e);
+                continue;
+            }
+        };
+
+        match std::fs::metadata(&path) {
+            Ok(metadata) => {
+                let mut record = Indexer::file_record(&path, &metadata);
+                options.enrich(&mut record, &path);
+                match db_guard.upsert_file(&record) {
+                    Ok(_) => event.updated.push(record.path),
+                    Err(e) => warn!("Failed to upsert {}: {}", record.path, e),
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                match db_guard.delete_tree(&path_str) {
+                    Ok(0) => {}
+                    Ok(_) => event.removed.push(path_str),
+                    Err(e) => warn!("Failed to remove {}: {}", path_str, e),
+                }
+            }
+            Err(e) => warn!("Failed to stat {}: {}", path.display(), e),
+        }
+    }
+
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, AccessMode, CreateKind, DataChange, ModifyKind};
+
+    fn filter(db_path: &Path) -> EventFilter {
+        EventFilter {
+            excludes: ExcludeMatcher::new(&[]),
+            index_files: IndexFiles::new(db_path),
+        }
+    }
+
+    fn collected(filter: &EventFilter, kind: EventKind, path: &Path) -> bool {
+        let mut pending = HashSet::new();
+        collect(Ok(Event::new(kind).add_path(path.to_path_buf())), filter, &mut pending);
+        pending.contains(path)
+    }
+
+    #[test]
+    fn ignores_the_index_database_and_its_siblings() {
+        let dir = std::env::temp_dir().join("oxi-watch");
+        let filter = filter(&dir.join("oxi-search.db"));
+        let modified = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+
+        for name in [
+            "oxi-search.db",
+            "oxi-search.db-wal",
+            "oxi-search.db-shm",
+            "oxi-search.db-journal",
+            "oxi-search.shard-c.db",
+            "oxi-search.shard-c.db-wal",
+        ] {
+            assert!(!collected(&filter, modified, &dir.join(name)), "{}", name);
+        }
+
+        // Mismo nombre en otro directorio, o un archivo vecino cualquiera: sí cuentan.
+        assert!(collected(&filter, modified, &dir.join("sub").join("oxi-search.db")));
+        assert!(collected(&filter, modified, &dir.join("oxi-search.dbx")));
+        assert!(collected(&filter, modified, &dir.join("notes.txt")));
+    }
+
+    #[test]
+    fn ignores_access_events() {
+        let dir = std::env::temp_dir().join("oxi-watch");
+        let filter = filter(&dir.join("oxi-search.db"));
+        let file = dir.join("notes.txt");
+
+        let opened = EventKind::Access(AccessKind::Open(AccessMode::Any));
+        let read = EventKind::Access(AccessKind::Read);
+        assert!(!collected(&filter, opened, &file));
+        assert!(!collected(&filter, read, &file));
+        assert!(collected(&filter, EventKind::Create(CreateKind::File), &file));
+    }
+}
@@ -1,7 +1,7 @@
 import { Search, X, XCircle, FileText } from "lucide-react";
 import { invoke } from "@tauri-apps/api/core";
 import { useState } from "react";
-import { SearchResult, SearchResults } from "../types";
+import { SearchResult, SearchResults, SearchFilters } from "../types";
 
 const SearchViewSimple = () => {
   const [query, setQuery] = useState("");
@@ -20,11 +20,11 @@ const SearchViewSimple = () => {
         query: searchQuery,
         filters: {
           extensions: null,
-          minSize: null,
-          maxSize: null,
-          minDate: null,
-          maxDate: null,
-        },
+          min_size: null,
+          max_size: null,
+          min_date: null,
+          max_date: null,
+        } satisfies SearchFilters,
         page: 0,
         limit: 50,
       });
@@ -10,7 +10,7 @@ import {
   X,
   Clock,
 } from "lucide-react";
-import { SearchResult, SearchResults, IndexingProgress } from "../types";
+import { SearchResult, SearchResults, SearchFilters, IndexingProgress } from "../types";
 
 function App() {
   const inputRef = useRef<HTMLInputElement>(null);
@@ -84,11 +84,11 @@ function App() {
         query: searchQuery,
         filters: {
           extensions: null,
-          minSize: null,
-          maxSize: null,
-          minDate: null,
-          maxDate: null,
-        },
+          min_size: null,
+          max_size: null,
+          min_date: null,
+          max_date: null,
+        } satisfies SearchFilters,
         page: 0,
         limit: 50,
       });
@@ -5,7 +5,57 @@ export interface SearchResult {
   file_size: number | null;
   is_dir: boolean;
   modified_time: string;
+  created_time: string | null;
+  is_reparse: boolean;
+  link_count: number;
+  mode: number | null;
+  owner_uid: number | null;
+  owner_gid: number | null;
   score: number;
+  child_count: number | null;
+  matched_in_path: boolean;
+}
+
+// Resultado de `search_content`: el fragmento marca los términos entre \u0002 y \u0003.
+export interface ContentMatch extends SearchResult {
+  snippet: string;
+}
+
+// Resultado de `preview_text`.
+export interface TextPreview {
+  text: string;
+  encoding: string | null;
+  is_binary: boolean;
+  truncated: boolean;
+}
+
+// Mismos nombres (snake_case) que `SearchFilters` en src-tauri/src/types.rs.
+export interface SearchFilters {
+  extensions?: string[] | null;
+  min_size?: number | null;
+  max_size?: number | null;
+  min_date?: string | null;
+  max_date?: string | null;
+  fuzzy?: boolean;
+  component_match?: boolean;
+  roots?: string[] | null;
+  tags?: string[] | null;
+  sort_by?: "relevance" | "created_desc" | "created_asc" | "name" | "size" | "modified" | "path";
+  sort_dir?: "asc" | "desc" | null;
+  dirs_first?: boolean;
+  collation?: "binary" | "nocase" | "natural";
+  max_per_extension?: number | null;
+  min_score?: number | null;
+  hide_patterns?: string[];
+  multiple_links?: boolean;
+  categories?: string[];
+  case_sensitive?: boolean;
+  whole_word?: boolean;
+  search_in_path?: boolean;
+  interpreters?: string[];
+  mime_types?: string[];
+  owner_uid?: number;
+  mode_mask?: number;
 }
 
 export interface SearchResults {
@@ -14,6 +64,85 @@ export interface SearchResults {
   total: number;
   page: number;
   limit: number;
+  timed_out: boolean;
+  is_stale: boolean;
+}
+
+// Formato de `copy_path`.
+export type PathStyle = "native" | "posix" | "windows";
+
+// Filtros de `refine_search`.
+export interface Refinement {
+  extensions?: string[];
+  min_size?: number | null;
+  max_size?: number | null;
+  min_date?: string | null;
+  max_date?: string | null;
+  is_dir?: boolean | null;
+  text?: string | null;
+}
+
+export interface ExtensionFacet {
+  extension: string | null;
+  count: number;
+}
+
+// Respuesta de `refine_search`.
+export interface RefinedResults extends SearchResults {
+  facets: ExtensionFacet[];
+}
+
+// Evento `search-result-batch` de `search_files_streaming`. Cada lote viene ordenado
+// (por relevancia o `sort_by`) solo dentro de sí mismo: para un orden global hay que
+// reordenar al recibirlos o usar `search_files`.
+export interface SearchResultBatch {
+  epoch: number;
+  query: string;
+  offset: number;
+  results: SearchResult[];
+}
+
+// Evento `search-complete` (y respuesta) de `search_files_streaming`.
+export interface SearchComplete {
+  epoch: number;
+  query: string;
+  total: number;
+  timed_out: boolean;
+  is_stale: boolean;
+}
+
+// Resultado de `get_file_details`.
+export interface FileDetails {
+  path: string;
+  indexed: SearchResult | null;
+  live: LiveMetadata | null;
+  stale: boolean;
+  changed: boolean;
+}
+
+export interface LiveMetadata {
+  is_dir: boolean;
+  file_size: number;
+  modified_time: string | null;
+  created_time: string | null;
+  accessed_time: string | null;
+  mode: number | null;
+  readonly: boolean;
+  owner_uid: number | null;
+  owner_gid: number | null;
+  link_count: number;
+  is_symlink: boolean;
+  symlink_target: string | null;
+  dangling: boolean;
+}
+
+// Resultado de `get_extension_stats`; `extension` null en los grupos sin extensión
+// y de directorios (`is_dir`).
+export interface ExtensionStat {
+  extension: string | null;
+  is_dir: boolean;
+  count: number;
+  bytes: number;
 }
 
 export interface IndexingProgress {
@@ -21,4 +150,184 @@ export interface IndexingProgress {
   files_processed: number;
   total_files: number | null;
   status: string;
-}
\ No newline at end of file
+  operation_id: number | null;
+  inserted: number;
+  updated: number;
+  skipped: number;
+}
+
+export interface IndexingStatus {
+  is_indexing: boolean;
+  last_indexed: string | null;
+  total_files: number;
+  database_size: number;
+}
+
+export type IndexLimit = "max_files" | "max_duration" | "max_depth";
+
+export interface IndexLimitReached {
+  limit: IndexLimit;
+  files: number;
+}
+
+// Evento `indexing-warnings`: lo que el índice no pudo leer.
+export interface IndexingWarnings {
+  permission_denied: number;
+  other_errors: number;
+  sample_paths: string[];
+  mft_access_denied: string[];
+  requires_elevation: boolean;
+}
+
+export type MergePolicy = "skip" | "overwrite" | "newest";
+
+export type EmptyKind = "files" | "directories";
+
+// Resultado de `validate_indexing_path`.
+export interface PathValidation {
+  path: string;
+  valid: boolean;
+  exists: boolean;
+  is_dir: boolean;
+  readable: boolean;
+  is_drive: boolean;
+  mft_available: boolean;
+  requires_elevation: boolean;
+  is_network: boolean;
+  estimated_entries: number;
+  estimate_complete: boolean;
+  warnings: string[];
+}
+
+// Raíz de `reindex_path` / `preview_index` con sus propias exclusiones.
+export interface IndexRoot {
+  path: string;
+  exclude_patterns?: string[];
+}
+
+// Error de cualquier comando (rechazo de `invoke`). `kind` es estable.
+export interface OxiError {
+  kind:
+    | "io"
+    | "database"
+    | "permission_denied"
+    | "not_found"
+    | "cancelled"
+    | "busy"
+    | "invalid_input"
+    | "rate_limited"
+    | "internal";
+  message: string;
+}
+
+// Resultado de `preview_index`.
+export interface IndexPreview {
+  paths: string[];
+  entries: number;
+  files: number;
+  directories: number;
+  total_bytes: number;
+  extensions: ExtensionStats[];
+  extension_count: number;
+  walk_ms: number;
+}
+
+export interface ExtensionStats {
+  extension: string | null;
+  count: number;
+  bytes: number;
+}
+
+// Elemento de `get_search_history`.
+export interface HistoryEntry {
+  id: number;
+  query: string;
+  filters: SearchFilters | null;
+  result_count: number;
+  searched_at: string;
+}
+
+// Búsqueda con nombre (`save_search`, `list_saved_searches`).
+export interface SavedSearch {
+  name: string;
+  query: string;
+  filters: SearchFilters;
+  created_at: string;
+  updated_at: string;
+}
+
+// Resultado de `save_search`.
+export interface SaveSearchOutcome {
+  search: SavedSearch;
+  overwritten: boolean;
+}
+
+// Elemento de `find_duplicates`.
+export interface DuplicateGroup {
+  hash: string;
+  file_size: number;
+  paths: string[];
+  wasted_bytes: number;
+}
+
+// Elemento de `get_mime_summary`.
+export interface MimeCount {
+  mime: string;
+  count: number;
+}
+
+// Carga del evento `directory-indexed` (llega una lista por emisión).
+export interface DirectoryIndexed {
+  path: string;
+  child_count: number;
+}
+
+// Resultado de `get_coverage_report`.
+export interface RootCoverage {
+  path: string;
+  exists: boolean;
+  readable: boolean;
+  last_started: string | null;
+  last_completed: string | null;
+  incomplete: boolean;
+  indexed_entries: number;
+}
+
+export interface CoverageReport {
+  roots: RootCoverage[];
+  last_full_index: string | null;
+  last_refresh: string | null;
+  is_indexing: boolean;
+}
+
+export interface WatchEvent {
+  updated: string[];
+  removed: string[];
+}
+
+export interface VerifyReport {
+  checked: number;
+  ok: number;
+  missing: number;
+  stale: number;
+  unreadable: number;
+  offenders: VerifyIssue[];
+  pruned: number;
+  integrity_errors: string[];
+  cancelled: boolean;
+}
+
+export interface VerifyIssue {
+  path: string;
+  kind: "missing" | "stale" | "unreadable";
+}
+
+export interface OperationInfo {
+  id: number;
+  kind: string;
+  status: "running" | "completed" | "cancelled" | "failed";
+  started_at: string;
+  finished_at: string | null;
+  message: string | null;
+  warnings: IndexingWarnings | null;
+}